@@ -0,0 +1,66 @@
+//! Build script: embeds build metadata for `--build-info`
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let git_sha = git(&["rev-parse", "--short=12", "HEAD"])
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Honor SOURCE_DATE_EPOCH for reproducible builds
+    let epoch_secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+
+    let target = std::env::var("TARGET").unwrap_or_else(|_| "unknown".to_string());
+
+    println!("cargo:rustc-env=OMNI_GIT_SHA={}", git_sha);
+    println!("cargo:rustc-env=OMNI_BUILD_DATE={}", format_date(epoch_secs));
+    println!("cargo:rustc-env=OMNI_TARGET={}", target);
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        // HEAD only changes on checkout; commits move the branch ref, which
+        // lives in its own file or, once packed, in packed-refs. Missing files
+        // are skipped since cargo would rerun on every build for them.
+        let head_ref = git(&["symbolic-ref", "-q", "HEAD"]);
+        let watched = ["HEAD", "packed-refs"].into_iter().chain(head_ref.as_deref());
+        for path in watched.map(|name| Path::new(&git_dir).join(name)) {
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+/// Run a git command, returning trimmed stdout on success
+fn git(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|out| !out.is_empty())
+}
+
+/// Format seconds since the Unix epoch as `YYYY-MM-DD` (UTC)
+fn format_date(epoch_secs: u64) -> String {
+    // Civil-from-days conversion (proleptic Gregorian calendar)
+    let days = (epoch_secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,240 @@
+//! Host-function access guard: denies and records ungranted privileged calls
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::agents::capabilities::{Capability, CapabilityManager};
+use crate::agents::event_protocol::{ErrorEvent, Event, EventType};
+use crate::agents::watchdog::{AgentWatchdog, Violation};
+use crate::oauth::consent::ConsentLedger;
+
+/// Ledger reason and error message prefix for denied host calls
+pub const UNAUTHORIZED_ACCESS: &str = "attempted unauthorized access";
+
+/// Privileged WASI imports and the capability each one requires
+const PRIVILEGED_IMPORTS: &[(&str, &str)] = &[
+    ("path_open", "files.read"),
+    ("path_readlink", "files.read"),
+    ("path_filestat_get", "files.read"),
+    ("fd_readdir", "files.read"),
+    ("path_create_directory", "files.write"),
+    ("path_remove_directory", "files.write"),
+    ("path_unlink_file", "files.write"),
+    ("path_rename", "files.write"),
+    ("path_symlink", "files.write"),
+    ("path_link", "files.write"),
+    ("path_filestat_set_times", "files.write"),
+    ("sock_accept", "network.listen"),
+    ("sock_recv", "network.connect"),
+    ("sock_send", "network.connect"),
+    ("sock_shutdown", "network.connect"),
+    ("environ_get", "env.read"),
+    ("environ_sizes_get", "env.read"),
+];
+
+/// Names of every privileged WASI import
+pub fn privileged_imports() -> impl Iterator<Item = &'static str> {
+    PRIVILEGED_IMPORTS.iter().map(|(func, _)| *func)
+}
+
+/// Capability required by a host import, if it is privileged
+pub fn required_capability(import: &str) -> Option<Capability> {
+    // Imports may be qualified with their module, e.g. "wasi_snapshot_preview1::path_open"
+    let name = import.rsplit("::").next().unwrap_or(import);
+    PRIVILEGED_IMPORTS
+        .iter()
+        .find(|(func, _)| *func == name)
+        .and_then(|(_, cap)| Capability::parse(cap).ok())
+}
+
+/// Checks one agent's privileged host calls against the grants it holds
+pub struct AccessGuard {
+    agent_id: String,
+    capabilities: Arc<CapabilityManager>,
+    ledger: Arc<ConsentLedger>,
+    watchdog: Option<Arc<AgentWatchdog>>,
+    /// Deny every `files.write` import, even when granted
+    read_only: bool,
+    events: Arc<RwLock<Vec<Event>>>,
+    denied: AtomicU64,
+    sequence: AtomicU64,
+}
+
+impl AccessGuard {
+    pub fn new(
+        agent_id: impl Into<String>,
+        capabilities: Arc<CapabilityManager>,
+        ledger: Arc<ConsentLedger>,
+    ) -> Self {
+        AccessGuard {
+            agent_id: agent_id.into(),
+            capabilities,
+            ledger,
+            watchdog: None,
+            read_only: false,
+            events: Arc::new(RwLock::new(Vec::new())),
+            denied: AtomicU64::new(0),
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Report denied calls to a watchdog that may auto-disable the agent
+    pub fn with_watchdog(mut self, watchdog: Arc<AgentWatchdog>) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Give the agent a read-only workspace: write imports are always denied
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    pub fn agent_id(&self) -> &str {
+        &self.agent_id
+    }
+
+    /// Check whether a host call may run; denied calls are logged as an
+    /// `ErrorEvent` and a ledger `Deny` entry
+    pub async fn check_host_call(&self, import: &str) -> bool {
+        let Some(capability) = required_capability(import) else {
+            return true;
+        };
+        let write_blocked = self.read_only && capability == Capability::new("files", "write");
+        if !write_blocked && self.capabilities.check_for(&self.agent_id, &capability).await {
+            return true;
+        }
+
+        let attempts = self.denied.fetch_add(1, Ordering::SeqCst) + 1;
+        tracing::warn!(
+            "Agent {} {}: {} via {} (attempt {})",
+            self.agent_id,
+            UNAUTHORIZED_ACCESS,
+            capability.to_string(),
+            import,
+            attempts
+        );
+
+        let event = Event::new(
+            EventType::Error(ErrorEvent {
+                code: "ACCESS_DENIED".to_string(),
+                message: format!(
+                    "{}: {} requires {}",
+                    UNAUTHORIZED_ACCESS,
+                    import,
+                    capability.to_string()
+                ),
+                hint: Some(if write_blocked {
+                    "Agent runs with a read-only workspace".to_string()
+                } else {
+                    format!("Grant {} to allow this call", capability.to_string())
+                }),
+            }),
+            self.agent_id.clone(),
+            self.sequence.fetch_add(1, Ordering::SeqCst),
+        );
+        self.events.write().await.push(event);
+
+        if let Err(e) = self
+            .ledger
+            .log_deny(
+                self.agent_id.clone(),
+                capability.to_string(),
+                UNAUTHORIZED_ACCESS.to_string(),
+            )
+            .await
+        {
+            tracing::warn!("Failed to record denied access for {}: {}", self.agent_id, e);
+        }
+
+        if let Some(watchdog) = &self.watchdog {
+            if let Err(e) = watchdog.record(&self.agent_id, Violation::DeniedAccess).await {
+                tracing::warn!("Failed to apply auto-disable policy to {}: {}", self.agent_id, e);
+            }
+        }
+
+        false
+    }
+
+    /// Number of denied calls so far
+    pub fn denied_attempts(&self) -> u64 {
+        self.denied.load(Ordering::SeqCst)
+    }
+
+    /// Take the error events recorded for denied calls
+    pub async fn drain_events(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.events.write().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oauth::consent::ConsentAction;
+
+    fn guard() -> (AccessGuard, Arc<CapabilityManager>, Arc<ConsentLedger>) {
+        let capabilities = Arc::new(CapabilityManager::new());
+        let ledger = Arc::new(ConsentLedger::new());
+        let guard = AccessGuard::new("agent1", capabilities.clone(), ledger.clone());
+        (guard, capabilities, ledger)
+    }
+
+    #[tokio::test]
+    async fn test_ungranted_file_import_is_denied_and_logged() {
+        let (guard, _, ledger) = guard();
+
+        assert!(!guard.check_host_call("wasi_snapshot_preview1::path_open").await);
+        assert_eq!(guard.denied_attempts(), 1);
+
+        let entries = ledger.get_for_agent("agent1").await;
+        assert_eq!(entries.len(), 1);
+        match &entries[0].action {
+            ConsentAction::Deny { capability, reason } => {
+                assert_eq!(capability, "files.read");
+                assert_eq!(reason, UNAUTHORIZED_ACCESS);
+            }
+            other => panic!("Unexpected ledger action: {:?}", other),
+        }
+
+        let events = guard.drain_events().await;
+        assert_eq!(events.len(), 1);
+        match &events[0].event_type {
+            EventType::Error(error) => assert_eq!(error.code, "ACCESS_DENIED"),
+            _ => panic!("Wrong event type"),
+        }
+
+        // The agent keeps running: unprivileged calls still go through
+        assert!(guard.check_host_call("fd_write").await);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_agent_cannot_write_despite_grant() {
+        let capabilities = Arc::new(CapabilityManager::new());
+        let ledger = Arc::new(ConsentLedger::new());
+        let guard = AccessGuard::new("auditor", capabilities.clone(), ledger.clone()).read_only();
+        capabilities.grant_to("auditor", Capability::new("files", "read"), None).await.unwrap();
+        capabilities.grant_to("auditor", Capability::new("files", "write"), None).await.unwrap();
+
+        assert!(guard.check_host_call("path_open").await);
+        assert!(!guard.check_host_call("path_unlink_file").await);
+        assert_eq!(guard.denied_attempts(), 1);
+        assert!(matches!(
+            &ledger.get_for_agent("auditor").await[0].action,
+            ConsentAction::Deny { capability, .. } if capability == "files.write"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_granted_import_is_allowed() {
+        let (guard, capabilities, ledger) = guard();
+        capabilities
+            .grant_to("agent1", Capability::new("files", "read"), None)
+            .await
+            .unwrap();
+
+        assert!(guard.check_host_call("path_open").await);
+        assert_eq!(guard.denied_attempts(), 0);
+        assert!(ledger.get_all().await.is_empty());
+    }
+}
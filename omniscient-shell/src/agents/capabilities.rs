@@ -1,10 +1,18 @@
 //! Capability-based security model
 
 use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
-use tokio::sync::RwLock;
+
+use crate::oauth::consent::ConsentLedger;
+use crate::state::SqliteStore;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 /// Capability identifier
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -36,37 +44,306 @@ impl Capability {
     pub fn to_string(&self) -> String {
         format!("{}.{}", self.scope, self.action)
     }
+
+    /// Whether the scope or action is `*`
+    pub fn is_wildcard(&self) -> bool {
+        self.scope == "*" || self.action == "*"
+    }
+
+    /// Whether holding this capability covers `requested`: a `*` scope or
+    /// action matches anything there, e.g. `files.*` covers `files.read` and
+    /// `*.*` covers everything. A `*` in `requested` is taken literally, so
+    /// only a grant at least as broad covers it.
+    pub fn matches(&self, requested: &Capability) -> bool {
+        let part = |held: &str, requested: &str| held == "*" || held == requested;
+        part(&self.scope, &requested.scope) && part(&self.action, &requested.action)
+    }
+
+    /// Whether this capability is in `known_capabilities`; a `*` action
+    /// stands for every action of a known scope
+    pub fn is_known(&self) -> bool {
+        known_capabilities()
+            .iter()
+            .any(|known| known.matches(self) || (self.action == "*" && known.scope == self.scope))
+    }
+
+    /// Describe the capability in plain language for consent prompts
+    ///
+    /// Unknown capabilities get a generic high-risk description.
+    pub fn describe(&self) -> CapabilityDescription {
+        let exact = CAPABILITY_DESCRIPTIONS
+            .iter()
+            .find(|(scope, action, _, _)| *scope == self.scope && *action == self.action);
+        let scope_wide = CAPABILITY_DESCRIPTIONS
+            .iter()
+            .find(|(scope, action, _, _)| *scope == self.scope && *action == "*");
+
+        match exact.or(scope_wide) {
+            Some((_, _, summary, risk)) => CapabilityDescription {
+                summary: summary.to_string(),
+                risk: *risk,
+            },
+            None => CapabilityDescription {
+                summary: format!(
+                    "Use the unrecognized permission \"{}\", which may grant broad access to your system",
+                    self.to_string()
+                ),
+                risk: RiskLevel::High,
+            },
+        }
+    }
+}
+
+/// Risk level shown alongside a capability in consent prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl RiskLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+        }
+    }
+}
+
+/// Human-readable explanation of a capability
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilityDescription {
+    pub summary: String,
+    pub risk: RiskLevel,
+}
+
+/// Every capability agents may request, with its description: (scope,
+/// action, summary, risk). `*` matches any action. Add new capabilities here.
+const CAPABILITY_DESCRIPTIONS: &[(&str, &str, &str, RiskLevel)] = &[
+    ("files", "read", "Read files in your workspace", RiskLevel::Low),
+    ("files", "write", "Create, modify, or delete files in your workspace", RiskLevel::Medium),
+    ("files", "exec", "Run programs from your workspace", RiskLevel::High),
+    ("network", "connect", "Access the network and connect to remote servers", RiskLevel::Medium),
+    ("network", "http", "Make HTTP requests to remote servers", RiskLevel::Medium),
+    ("network", "listen", "Accept incoming network connections", RiskLevel::High),
+    ("oauth", "*", "Act on your behalf with a connected account", RiskLevel::High),
+    ("process", "spawn", "Start other programs on your computer", RiskLevel::High),
+    ("env", "read", "Read environment variables, which may contain configuration", RiskLevel::Medium),
+    ("clipboard", "read", "Read the contents of your clipboard", RiskLevel::Medium),
+    ("clipboard", "write", "Replace the contents of your clipboard", RiskLevel::Low),
+    ("notifications", "send", "Show notifications", RiskLevel::Low),
+];
+
+/// Capabilities a manifest may request; `oauth.*` covers every provider
+pub fn known_capabilities() -> Vec<Capability> {
+    CAPABILITY_DESCRIPTIONS
+        .iter()
+        .map(|(scope, action, _, _)| Capability::new(*scope, *action))
+        .collect()
+}
+
+/// Narrows a grant to specific resources
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Constraint {
+    /// Paths at or below this directory
+    PathPrefix(PathBuf),
+    /// Paths matching a glob: `*` and `?` stay within one path segment, `**` spans segments
+    Glob(String),
+}
+
+impl Constraint {
+    /// Whether `resource` (a path) is covered by this constraint.
+    ///
+    /// The path is normalized first, so `..` can't climb out of the allowed prefix.
+    pub fn matches(&self, resource: &str) -> bool {
+        let Some(path) = normalize_path(Path::new(resource)) else {
+            return false;
+        };
+        match self {
+            Constraint::PathPrefix(prefix) => match normalize_path(prefix) {
+                Some(prefix) => path.starts_with(prefix),
+                None => false,
+            },
+            Constraint::Glob(pattern) => {
+                glob_matches(pattern.as_bytes(), path.to_string_lossy().as_bytes())
+            }
+        }
+    }
+
+    /// Directory that contains everything this constraint can match
+    pub fn base_dir(&self) -> PathBuf {
+        match self {
+            Constraint::PathPrefix(prefix) => prefix.clone(),
+            Constraint::Glob(pattern) => Path::new(pattern)
+                .components()
+                .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?']))
+                .collect(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Constraint::PathPrefix(prefix) => format!("under {}", prefix.display()),
+            Constraint::Glob(pattern) => format!("matching {}", pattern),
+        }
+    }
+}
+
+/// Resolve `.` and `..` lexically; None if the path climbs above its root
+fn normalize_path(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    Some(normalized)
+}
+
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len()).any(|i| glob_matches(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => {
+            let segment_end = text.iter().position(|c| *c == b'/').unwrap_or(text.len());
+            (0..=segment_end).any(|i| glob_matches(rest, &text[i..]))
+        }
+        [b'?', rest @ ..] => matches!(text, [c, tail @ ..] if *c != b'/' && glob_matches(rest, tail)),
+        [p, rest @ ..] => matches!(text, [c, tail @ ..] if c == p && glob_matches(rest, tail)),
+    }
+}
+
+/// Time source for grant expiry decisions
+pub trait Clock: Send + Sync {
+    /// Monotonic time, used for expiry decisions
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time, used for display only
+    fn wall_now(&self) -> SystemTime;
+}
+
+/// Clock backed by the operating system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
 }
 
 /// Capability grant with time bounds
+///
+/// Expiry is decided against a monotonic `Instant` captured at grant time,
+/// so wall-clock jumps neither expire grants early nor extend them.
+/// `granted_at` and `expires_at` are kept for display and audit only.
 #[derive(Debug, Clone)]
 pub struct CapabilityGrant {
+    /// Agent holding the grant
+    pub agent_id: String,
     pub capability: Capability,
+    /// Resources the grant is limited to; None covers the whole scope
+    pub constraint: Option<Constraint>,
     pub granted_at: SystemTime,
     pub expires_at: Option<SystemTime>,
     pub revoked: bool,
+    /// Uses allowed before the grant is exhausted; None is unlimited
+    pub max_uses: Option<u32>,
+    uses: u32,
+    /// Agents the grant was delegated through, root first; empty if granted directly
+    pub delegated_by: Vec<String>,
+    granted_instant: Instant,
+    duration: Option<Duration>,
+    /// Row the grant is saved in, for a persistent manager
+    row_id: Option<i64>,
 }
 
 impl CapabilityGrant {
     pub fn new(capability: Capability, duration: Option<Duration>) -> Self {
-        let granted_at = SystemTime::now();
+        Self::new_with_clock(capability, duration, &SystemClock)
+    }
+
+    /// Create a grant using the given clock
+    pub fn new_with_clock(capability: Capability, duration: Option<Duration>, clock: &dyn Clock) -> Self {
+        let granted_at = clock.wall_now();
         let expires_at = duration.map(|d| granted_at + d);
         
         CapabilityGrant {
+            agent_id: String::new(),
             capability,
+            constraint: None,
             granted_at,
             expires_at,
             revoked: false,
+            max_uses: None,
+            uses: 0,
+            delegated_by: Vec::new(),
+            granted_instant: clock.now(),
+            duration,
+            row_id: None,
         }
     }
 
+    /// Give the grant to `agent_id`
+    pub fn with_agent(mut self, agent_id: impl Into<String>) -> Self {
+        self.agent_id = agent_id.into();
+        self
+    }
+
+    /// Limit the grant to resources matching `constraint`
+    pub fn with_constraint(mut self, constraint: Constraint) -> Self {
+        self.constraint = Some(constraint);
+        self
+    }
+
+    /// Allow only `max_uses` uses, each recorded with `CapabilityManager::consume`
+    pub fn with_max_uses(mut self, max_uses: u32) -> Self {
+        self.max_uses = Some(max_uses);
+        self
+    }
+
+    /// Uses left, or None for an unlimited grant
+    pub fn remaining_uses(&self) -> Option<u32> {
+        self.max_uses.map(|max| max.saturating_sub(self.uses))
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining_uses() == Some(0)
+    }
+
+    /// Whether this grant covers `resource`
+    pub fn covers(&self, resource: &str) -> bool {
+        self.constraint.as_ref().is_none_or(|c| c.matches(resource))
+    }
+
     pub fn is_valid(&self) -> bool {
-        if self.revoked {
+        self.is_valid_with_clock(&SystemClock)
+    }
+
+    /// Check validity against the given clock
+    pub fn is_valid_with_clock(&self, clock: &dyn Clock) -> bool {
+        if self.revoked || self.is_exhausted() {
             return false;
         }
 
-        if let Some(expires_at) = self.expires_at {
-            if SystemTime::now() > expires_at {
+        if let Some(duration) = self.duration {
+            let elapsed = clock.now().saturating_duration_since(self.granted_instant);
+            if elapsed > duration {
                 return false;
             }
         }
@@ -74,62 +351,363 @@ impl CapabilityGrant {
         true
     }
 
+    /// Time left before expiry; None for a grant without a duration
+    fn remaining(&self) -> Option<Duration> {
+        self.duration.map(|duration| duration.saturating_sub(self.granted_instant.elapsed()))
+    }
+
     pub fn revoke(&mut self) {
         self.revoked = true;
     }
 }
 
-/// Capability manager (default deny)
+/// An active grant as listed for review, with its constraint described
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrantSummary {
+    pub agent_id: String,
+    pub capability: String,
+    pub expires_at: Option<SystemTime>,
+    pub constraint: Option<String>,
+}
+
+/// Capability manager (default deny). Grants made through the `*_to`
+/// methods belong to one agent and only count for that agent; the others
+/// make grants held by no agent.
 pub struct CapabilityManager {
     grants: Arc<RwLock<Vec<CapabilityGrant>>>,
+    /// When set, grants are also saved to its `capability_grants` table
+    db: Option<Arc<Mutex<Connection>>>,
+    /// Cancelled on drop to stop the tasks `start_gc` spawned
+    gc_stop: CancellationToken,
 }
 
 impl CapabilityManager {
     pub fn new() -> Self {
         CapabilityManager {
             grants: Arc::new(RwLock::new(Vec::new())),
+            db: None,
+            gc_stop: CancellationToken::new(),
         }
     }
 
+    /// Manager that saves grants to the `capability_grants` table of `conn`,
+    /// so they survive restarts. Starts with the saved grants that are
+    /// neither revoked nor expired.
+    pub async fn with_connection(conn: Arc<Mutex<Connection>>) -> Result<Self> {
+        let saved = load_grants(&*conn.lock().await, SystemTime::now())?;
+        tracing::debug!("Loaded {} saved capability grants", saved.len());
+        Ok(CapabilityManager {
+            grants: Arc::new(RwLock::new(saved)),
+            db: Some(conn),
+            gc_stop: CancellationToken::new(),
+        })
+    }
+
+    /// Manager that saves grants in `store`, starting with the ones saved
+    /// there that are still active
+    pub async fn new_persistent(store: Arc<SqliteStore>) -> Result<Self> {
+        CapabilityManager::with_connection(store.connection().await).await
+    }
+
+    /// Save a new grant, if grants are persisted
+    async fn save(&self, grant: &mut CapabilityGrant) -> Result<()> {
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        grant.row_id = Some(insert_grant(&*db.lock().await, grant)?);
+        Ok(())
+    }
+
+    /// Save how many times a limited grant has been used
+    async fn save_uses(&self, row_id: Option<i64>, uses: u32) -> Result<()> {
+        let (Some(db), Some(row_id)) = (&self.db, row_id) else {
+            return Ok(());
+        };
+        db.lock()
+            .await
+            .execute("UPDATE capability_grants SET uses = ?1 WHERE id = ?2", params![uses, row_id])?;
+        Ok(())
+    }
+
+    /// Mark the saved rows of revoked grants; the rows are kept as history
+    async fn save_revoked(&self, row_ids: &[i64]) -> Result<()> {
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        let conn = db.lock().await;
+        for row_id in row_ids {
+            conn.execute("UPDATE capability_grants SET revoked = 1 WHERE id = ?1", params![row_id])?;
+        }
+        Ok(())
+    }
+
     /// Grant a capability with optional duration
     pub async fn grant(&self, capability: Capability, duration: Option<Duration>) -> Result<()> {
-        let grant = CapabilityGrant::new(capability.clone(), duration);
+        self.grant_to("", capability, duration).await
+    }
+
+    /// Grant a capability to `agent_id` with optional duration
+    pub async fn grant_to(&self, agent_id: &str, capability: Capability, duration: Option<Duration>) -> Result<()> {
+        let mut grant = CapabilityGrant::new(capability.clone(), duration).with_agent(agent_id);
+        self.save(&mut grant).await?;
         let mut grants = self.grants.write().await;
         grants.push(grant);
         
-        tracing::info!("Granted capability: {}", capability.to_string());
+        tracing::info!("Granted capability to {}: {}", agent_id, capability.to_string());
+        Ok(())
+    }
+
+    /// Grant a capability limited to resources matching `constraint`
+    pub async fn grant_constrained(
+        &self,
+        capability: Capability,
+        constraint: Constraint,
+        duration: Option<Duration>,
+    ) -> Result<()> {
+        self.grant_constrained_to("", capability, constraint, duration).await
+    }
+
+    /// Grant a capability to `agent_id` limited to resources matching `constraint`
+    pub async fn grant_constrained_to(
+        &self,
+        agent_id: &str,
+        capability: Capability,
+        constraint: Constraint,
+        duration: Option<Duration>,
+    ) -> Result<()> {
+        let mut grant = CapabilityGrant::new(capability.clone(), duration)
+            .with_agent(agent_id)
+            .with_constraint(constraint.clone());
+        self.save(&mut grant).await?;
+        let mut grants = self.grants.write().await;
+        grants.push(grant);
+
+        tracing::info!("Granted capability to {}: {} {}", agent_id, capability.to_string(), constraint.describe());
         Ok(())
     }
 
-    /// Check if a capability is granted (default deny)
+    /// Grant a capability that can be used at most `max_uses` times
+    pub async fn grant_limited(
+        &self,
+        capability: Capability,
+        max_uses: u32,
+        duration: Option<Duration>,
+    ) -> Result<()> {
+        self.grant_limited_to("", capability, max_uses, duration).await
+    }
+
+    /// Grant a capability to `agent_id` that can be used at most `max_uses` times
+    pub async fn grant_limited_to(
+        &self,
+        agent_id: &str,
+        capability: Capability,
+        max_uses: u32,
+        duration: Option<Duration>,
+    ) -> Result<()> {
+        let mut grant = CapabilityGrant::new(capability.clone(), duration)
+            .with_agent(agent_id)
+            .with_max_uses(max_uses);
+        self.save(&mut grant).await?;
+        let mut grants = self.grants.write().await;
+        grants.push(grant);
+
+        tracing::info!("Granted capability to {}: {} for {} use(s)", agent_id, capability.to_string(), max_uses);
+        Ok(())
+    }
+
+    /// Record one use of a capability by `agent_id`, returning false if it
+    /// isn't granted to it.
+    ///
+    /// Unlimited grants are preferred; otherwise one use is taken from a
+    /// limited grant, and the ledger records when that grant runs out.
+    pub async fn consume(
+        &self,
+        capability: &Capability,
+        agent_id: &str,
+        ledger: &ConsentLedger,
+    ) -> Result<bool> {
+        let (row_id, uses, exhausted) = {
+            let mut grants = self.grants.write().await;
+            let usable = |grant: &CapabilityGrant| {
+                grant.agent_id == agent_id
+                    && grant.capability.matches(capability)
+                    && grant.constraint.is_none()
+                    && grant.is_valid()
+            };
+            if grants.iter().any(|grant| usable(grant) && grant.max_uses.is_none()) {
+                return Ok(true);
+            }
+            let Some(grant) = grants.iter_mut().find(|grant| usable(grant)) else {
+                return Ok(false);
+            };
+            grant.uses += 1;
+            (grant.row_id, grant.uses, grant.is_exhausted())
+        };
+        self.save_uses(row_id, uses).await?;
+
+        if exhausted {
+            tracing::info!("Capability {} exhausted after {} use(s)", capability.to_string(), uses);
+            ledger
+                .log_exhausted(agent_id.to_string(), capability.to_string(), uses)
+                .await?;
+        }
+        Ok(true)
+    }
+
+    /// Delegate a subset of `parent_id`'s valid grants to `child_id` in a
+    /// sub-agent's manager.
+    ///
+    /// Each child grant keeps the parent grant's constraint and expires no later
+    /// than it. Nothing is delegated if any capability isn't held by the parent;
+    /// limited-use grants can't be delegated.
+    pub async fn delegate(
+        &self,
+        parent_id: &str,
+        child_id: &str,
+        child: &CapabilityManager,
+        capabilities: &[Capability],
+        duration: Duration,
+        ledger: &ConsentLedger,
+    ) -> Result<()> {
+        let mut delegated: Vec<CapabilityGrant> = {
+            let grants = self.grants.read().await;
+            capabilities
+                .iter()
+                .map(|capability| {
+                    let held = grants
+                        .iter()
+                        .filter(|g| {
+                            g.agent_id == parent_id
+                                && g.capability.matches(capability)
+                                && g.is_valid()
+                                && g.max_uses.is_none()
+                        })
+                        .min_by_key(|g| g.constraint.is_some())
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "{} can't delegate {}: not held",
+                                parent_id,
+                                capability.to_string()
+                            )
+                        })?;
+                    let duration = held.remaining().map_or(duration, |left| left.min(duration));
+                    let mut grant = CapabilityGrant::new(capability.clone(), Some(duration)).with_agent(child_id);
+                    grant.constraint = held.constraint.clone();
+                    grant.delegated_by = held.delegated_by.clone();
+                    grant.delegated_by.push(parent_id.to_string());
+                    Ok(grant)
+                })
+                .collect::<Result<_>>()?
+        };
+
+        for grant in &delegated {
+            ledger
+                .log_delegate(
+                    child_id.to_string(),
+                    grant.capability.to_string(),
+                    grant.duration.map(|d| d.as_secs()),
+                    grant.delegated_by.clone(),
+                )
+                .await?;
+        }
+        for grant in &mut delegated {
+            child.save(grant).await?;
+        }
+        child.grants.write().await.extend(delegated);
+        Ok(())
+    }
+
+    /// Check if a capability is granted for its whole scope (default deny),
+    /// directly or through a wildcard grant. Constrained grants only count
+    /// through `check_resource`.
     pub async fn check(&self, capability: &Capability) -> bool {
+        self.check_for("", capability).await
+    }
+
+    /// Check if a capability is granted to `agent_id` for its whole scope,
+    /// as `check` does for grants held by no agent
+    pub async fn check_for(&self, agent_id: &str, capability: &Capability) -> bool {
         let grants = self.grants.read().await;
         
         grants.iter().any(|grant| {
-            grant.capability == *capability && grant.is_valid()
+            grant.agent_id == agent_id
+                && grant.capability.matches(capability)
+                && grant.constraint.is_none()
+                && grant.is_valid()
+        })
+    }
+
+    /// Check if a capability is granted for a specific resource (e.g. a path)
+    pub async fn check_resource(&self, capability: &Capability, resource: &str) -> bool {
+        self.check_resource_for("", capability, resource).await
+    }
+
+    /// Check if a capability is granted to `agent_id` for a specific
+    /// resource (e.g. a path)
+    pub async fn check_resource_for(&self, agent_id: &str, capability: &Capability, resource: &str) -> bool {
+        let grants = self.grants.read().await;
+
+        grants.iter().any(|grant| {
+            grant.agent_id == agent_id
+                && grant.capability.matches(capability)
+                && grant.covers(resource)
+                && grant.is_valid()
         })
     }
 
     /// Revoke a capability
     pub async fn revoke(&self, capability: &Capability) -> Result<()> {
+        self.revoke_from("", capability).await
+    }
+
+    /// Revoke a capability from `agent_id`
+    pub async fn revoke_from(&self, agent_id: &str, capability: &Capability) -> Result<()> {
         let mut grants = self.grants.write().await;
         
         let mut revoked = false;
+        let mut row_ids = Vec::new();
         for grant in grants.iter_mut() {
-            if grant.capability == *capability && !grant.revoked {
+            if grant.agent_id == agent_id && grant.capability == *capability && !grant.revoked {
                 grant.revoke();
                 revoked = true;
+                row_ids.extend(grant.row_id);
             }
         }
+        drop(grants);
+        self.save_revoked(&row_ids).await?;
 
         if revoked {
-            tracing::info!("Revoked capability: {}", capability.to_string());
+            tracing::info!("Revoked capability from {}: {}", agent_id, capability.to_string());
             Ok(())
         } else {
             anyhow::bail!("Capability not found or already revoked: {}", capability.to_string())
         }
     }
 
+    /// Revoke every grant that has an expiry, keeping permanent grants; returns how many
+    pub async fn revoke_short_lived(&self) -> usize {
+        let mut grants = self.grants.write().await;
+
+        let mut count = 0;
+        let mut row_ids = Vec::new();
+        for grant in grants.iter_mut() {
+            if grant.duration.is_some() && !grant.revoked {
+                grant.revoke();
+                count += 1;
+                row_ids.extend(grant.row_id);
+            }
+        }
+        drop(grants);
+        if let Err(e) = self.save_revoked(&row_ids).await {
+            tracing::warn!("Failed to save revoked capability grants: {}", e);
+        }
+
+        if count > 0 {
+            tracing::info!("Revoked {} short-lived capability grants", count);
+        }
+        count
+    }
+
     /// Get all active grants
     pub async fn active_grants(&self) -> Vec<CapabilityGrant> {
         let grants = self.grants.read().await;
@@ -139,10 +717,98 @@ impl CapabilityManager {
             .collect()
     }
 
-    /// Cleanup expired grants
+    /// Active grants held by `agent_id`
+    pub async fn grants_for(&self, agent_id: &str) -> Vec<CapabilityGrant> {
+        let grants = self.grants.read().await;
+        grants.iter()
+            .filter(|g| g.agent_id == agent_id && g.is_valid())
+            .cloned()
+            .collect()
+    }
+
+    /// Active grants of every agent, for review
+    pub async fn summaries(&self) -> Vec<GrantSummary> {
+        self.active_grants()
+            .await
+            .into_iter()
+            .map(|grant| GrantSummary {
+                capability: grant.capability.to_string(),
+                expires_at: grant.expires_at,
+                constraint: grant.constraint.as_ref().map(Constraint::describe),
+                agent_id: grant.agent_id,
+            })
+            .collect()
+    }
+
+    /// Revoke the grant a summary listed, matched by agent, capability and
+    /// described constraint, and record it in the ledger
+    pub async fn revoke_listed(
+        &self,
+        agent_id: &str,
+        capability: &str,
+        constraint: Option<&str>,
+        ledger: &ConsentLedger,
+    ) -> Result<()> {
+        let parsed = Capability::parse(capability)?;
+        let row_id = {
+            let mut grants = self.grants.write().await;
+            let grant = grants
+                .iter_mut()
+                .find(|g| {
+                    g.agent_id == agent_id
+                        && g.capability == parsed
+                        && g.is_valid()
+                        && g.constraint.as_ref().map(Constraint::describe).as_deref() == constraint
+                })
+                .ok_or_else(|| anyhow::anyhow!("No active grant of {} to {}", capability, agent_id))?;
+            grant.revoke();
+            grant.row_id
+        };
+        self.save_revoked(row_id.as_slice()).await?;
+        tracing::info!("Revoked capability from {} on review: {}", agent_id, capability);
+
+        ledger.log_revoke(agent_id.to_string(), capability.to_string()).await
+    }
+
+    /// Drop grants that have expired, been revoked or been used up
     pub async fn cleanup_expired(&self) {
-        let mut grants = self.grants.write().await;
-        grants.retain(|g| g.is_valid() || !g.revoked);
+        retain_valid(&self.grants).await;
+    }
+
+    /// Clean up expired grants every `interval` in a background task, which
+    /// stops when the manager is dropped
+    pub fn start_gc(&self, interval: Duration) -> JoinHandle<()> {
+        let grants = Arc::downgrade(&self.grants);
+        let stop = self.gc_stop.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.tick().await;
+            loop {
+                tokio::select! {
+                    _ = ticks.tick() => {}
+                    _ = stop.cancelled() => return,
+                }
+                let Some(grants) = grants.upgrade() else {
+                    return;
+                };
+                retain_valid(&grants).await;
+            }
+        })
+    }
+}
+
+impl Drop for CapabilityManager {
+    fn drop(&mut self) {
+        self.gc_stop.cancel();
+    }
+}
+
+async fn retain_valid(grants: &RwLock<Vec<CapabilityGrant>>) {
+    let mut grants = grants.write().await;
+    let before = grants.len();
+    grants.retain(|g| g.is_valid());
+    if grants.len() < before {
+        tracing::debug!("Cleaned up {} expired capability grants", before - grants.len());
     }
 }
 
@@ -152,9 +818,114 @@ impl Default for CapabilityManager {
     }
 }
 
+/// Milliseconds since the epoch, as grant times are saved
+fn epoch_ms(time: SystemTime) -> Result<i64> {
+    Ok(time.duration_since(UNIX_EPOCH)?.as_millis() as i64)
+}
+
+/// Save `grant` to the `capability_grants` table, returning its row
+fn insert_grant(conn: &Connection, grant: &CapabilityGrant) -> Result<i64> {
+    let constraint = grant.constraint.as_ref().map(serde_json::to_string).transpose()?;
+    conn.execute(
+        "INSERT INTO capability_grants
+             (agent_id, capability, path_constraint, granted_at, expires_at, max_uses, uses, delegated_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            grant.agent_id,
+            grant.capability.to_string(),
+            constraint,
+            epoch_ms(grant.granted_at)?,
+            grant.expires_at.map(epoch_ms).transpose()?,
+            grant.max_uses,
+            grant.uses,
+            serde_json::to_string(&grant.delegated_by)?,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Saved grants neither revoked, used up nor expired at `now`, oldest
+/// first. They expire when their saved expiry comes, however long the shell
+/// was closed.
+fn load_grants(conn: &Connection, now: SystemTime) -> Result<Vec<CapabilityGrant>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, agent_id, capability, path_constraint, granted_at, expires_at, max_uses, uses, delegated_by
+         FROM capability_grants
+         WHERE revoked = 0 AND (expires_at IS NULL OR expires_at > ?1) AND (max_uses IS NULL OR uses < max_uses)
+         ORDER BY id",
+    )?;
+    let rows = stmt.query_map(params![epoch_ms(now)?], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, Option<i64>>(5)?,
+            row.get::<_, Option<u32>>(6)?,
+            row.get::<_, u32>(7)?,
+            row.get::<_, String>(8)?,
+        ))
+    })?;
+
+    let mut grants = Vec::new();
+    for row in rows {
+        let (row_id, agent_id, capability, constraint, granted_ms, expires_ms, max_uses, uses, delegated_by) = row?;
+        let expires_at = expires_ms.map(|ms| UNIX_EPOCH + Duration::from_millis(ms as u64));
+        let remaining = expires_at.map(|expires| expires.duration_since(now).unwrap_or_default());
+
+        let mut grant = CapabilityGrant::new(Capability::parse(&capability)?, remaining).with_agent(agent_id);
+        grant.constraint = constraint.as_deref().map(serde_json::from_str).transpose()?;
+        grant.granted_at = UNIX_EPOCH + Duration::from_millis(granted_ms as u64);
+        grant.expires_at = expires_at;
+        grant.max_uses = max_uses;
+        grant.uses = uses;
+        grant.delegated_by = serde_json::from_str(&delegated_by)?;
+        grant.row_id = Some(row_id);
+        grants.push(grant);
+    }
+    Ok(grants)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::oauth::consent::ConsentAction;
+    use std::sync::Mutex;
+
+    /// Clock whose monotonic and wall-clock readings move independently
+    struct ManualClock {
+        instant: Mutex<Instant>,
+        wall: Mutex<SystemTime>,
+    }
+
+    impl ManualClock {
+        fn new() -> Self {
+            ManualClock {
+                instant: Mutex::new(Instant::now()),
+                wall: Mutex::new(SystemTime::now()),
+            }
+        }
+
+        fn advance(&self, d: Duration) {
+            *self.instant.lock().unwrap() += d;
+            *self.wall.lock().unwrap() += d;
+        }
+
+        fn set_wall(&self, t: SystemTime) {
+            *self.wall.lock().unwrap() = t;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.instant.lock().unwrap()
+        }
+
+        fn wall_now(&self) -> SystemTime {
+            *self.wall.lock().unwrap()
+        }
+    }
 
     #[test]
     fn test_capability_parsing() {
@@ -163,6 +934,92 @@ mod tests {
         assert_eq!(cap.action, "read");
     }
 
+    #[test]
+    fn test_wildcard_matching() {
+        let cap = |s: &str| Capability::parse(s).unwrap();
+
+        assert!(cap("files.*").matches(&cap("files.read")));
+        assert!(cap("files.*").matches(&cap("files.write")));
+        assert!(cap("*.*").matches(&cap("network.http")));
+        assert!(cap("*.read").matches(&cap("files.read")));
+        assert!(cap("files.read").matches(&cap("files.read")));
+
+        // Scopes and actions must still line up
+        assert!(!cap("files.*").matches(&cap("network.read")));
+        assert!(!cap("files.write").matches(&cap("files.read")));
+        assert!(!cap("*.read").matches(&cap("files.write")));
+
+        // A requested `*` is literal: only as broad a grant covers it
+        assert!(!cap("files.read").matches(&cap("files.*")));
+        assert!(cap("files.*").matches(&cap("files.*")));
+        assert!(!cap("files.*").matches(&cap("*.*")));
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_grant_checks_whole_scope() {
+        let manager = CapabilityManager::new();
+        manager.grant(Capability::new("files", "*"), None).await.unwrap();
+
+        assert!(manager.check(&Capability::new("files", "read")).await);
+        assert!(manager.check(&Capability::new("files", "delete")).await);
+        assert!(!manager.check(&Capability::new("network", "http")).await);
+
+        // Revoking one action leaves the wildcard grant in place
+        assert!(manager.revoke(&Capability::new("files", "read")).await.is_err());
+        manager.revoke(&Capability::new("files", "*")).await.unwrap();
+        assert!(!manager.check(&Capability::new("files", "read")).await);
+    }
+
+    #[test]
+    fn test_known_capabilities_registry() {
+        let known = known_capabilities();
+        assert!(known.contains(&Capability::new("files", "read")));
+        assert!(known.contains(&Capability::new("oauth", "*")));
+
+        let cap = |s: &str| Capability::parse(s).unwrap();
+        assert!(cap("files.read").is_known());
+        assert!(cap("files.*").is_known());
+        assert!(cap("oauth.github").is_known());
+        assert!(!cap("file.raed").is_known());
+        assert!(!cap("files.raed").is_known());
+        assert!(!cap("telepathy.read").is_known());
+        assert!(!cap("*.*").is_known());
+    }
+
+    #[test]
+    fn test_describe_known_capability() {
+        let desc = Capability::new("network", "connect").describe();
+        assert!(desc.summary.contains("network"));
+        assert_eq!(desc.risk, RiskLevel::Medium);
+
+        let desc = Capability::new("oauth", "github").describe();
+        assert_eq!(desc.risk, RiskLevel::High);
+    }
+
+    #[test]
+    fn test_describe_unknown_capability() {
+        let desc = Capability::new("telepathy", "read").describe();
+        assert!(desc.summary.contains("telepathy.read"));
+        assert_eq!(desc.risk, RiskLevel::High);
+    }
+
+    #[tokio::test]
+    async fn test_gc_drops_expired_grants_until_manager_dropped() {
+        let manager = CapabilityManager::new();
+        manager.grant(Capability::new("files", "read"), None).await.unwrap();
+        manager.grant(Capability::new("network", "http"), Some(Duration::from_millis(20))).await.unwrap();
+        manager.grant(Capability::new("env", "read"), None).await.unwrap();
+        manager.revoke(&Capability::new("env", "read")).await.unwrap();
+
+        let gc = manager.start_gc(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let kept: Vec<String> = manager.grants.read().await.iter().map(|g| g.capability.to_string()).collect();
+        assert_eq!(kept, vec!["files.read".to_string()]);
+
+        drop(manager);
+        tokio::time::timeout(Duration::from_secs(1), gc).await.unwrap().unwrap();
+    }
+
     #[tokio::test]
     async fn test_default_deny() {
         let manager = CapabilityManager::new();
@@ -181,6 +1038,39 @@ mod tests {
         assert!(manager.check(&cap).await);
     }
 
+    #[tokio::test]
+    async fn test_grants_are_scoped_to_their_agent() {
+        let manager = CapabilityManager::new();
+        let read = Capability::new("files", "read");
+        manager.grant_to("indexer", read.clone(), None).await.unwrap();
+
+        assert!(manager.check_for("indexer", &read).await);
+        assert!(!manager.check_for("crawler", &read).await);
+        assert!(!manager.check(&read).await);
+        assert!(manager.revoke_from("crawler", &read).await.is_err());
+        assert_eq!(manager.grants_for("crawler").await.len(), 0);
+        assert_eq!(manager.grants_for("indexer").await.len(), 1);
+
+        // A grant held by no agent counts for none of them
+        manager.grant(read.clone(), None).await.unwrap();
+        assert!(manager.check(&read).await);
+        assert!(!manager.check_for("crawler", &read).await);
+    }
+
+    #[tokio::test]
+    async fn test_constrained_grants_are_scoped_to_their_agent() {
+        let manager = CapabilityManager::new();
+        let write = Capability::new("files", "write");
+        manager
+            .grant_constrained_to("indexer", write.clone(), Constraint::PathPrefix("/project".into()), None)
+            .await
+            .unwrap();
+
+        assert!(manager.check_resource_for("indexer", &write, "/project/a").await);
+        assert!(!manager.check_resource_for("crawler", &write, "/project/a").await);
+        assert!(!manager.check_resource(&write, "/project/a").await);
+    }
+
     #[tokio::test]
     async fn test_revoke() {
         let manager = CapabilityManager::new();
@@ -210,4 +1100,304 @@ mod tests {
         // Should be expired
         assert!(!manager.check(&cap).await);
     }
+
+    #[test]
+    fn test_wall_clock_jump_does_not_change_validity() {
+        let clock = ManualClock::new();
+        let cap = Capability::new("files", "read");
+        let grant = CapabilityGrant::new_with_clock(cap, Some(Duration::from_secs(60)), &clock);
+
+        clock.advance(Duration::from_secs(30));
+        assert!(grant.is_valid_with_clock(&clock));
+
+        // Wall clock jumps back a day: grant is neither expired nor extended
+        clock.set_wall(grant.granted_at - Duration::from_secs(24 * 3600));
+        assert!(grant.is_valid_with_clock(&clock));
+
+        clock.advance(Duration::from_secs(31));
+        assert!(!grant.is_valid_with_clock(&clock));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_short_lived_keeps_permanent_grants() {
+        let manager = CapabilityManager::new();
+        let read = Capability::new("files", "read");
+        let net = Capability::new("network", "connect");
+        manager.grant(read.clone(), None).await.unwrap();
+        manager.grant(net.clone(), Some(Duration::from_secs(300))).await.unwrap();
+
+        assert_eq!(manager.revoke_short_lived().await, 1);
+        assert!(manager.check(&read).await);
+        assert!(!manager.check(&net).await);
+    }
+
+    #[tokio::test]
+    async fn test_path_constrained_grant() {
+        let manager = CapabilityManager::new();
+        let read = Capability::new("files", "read");
+        manager
+            .grant_constrained(read.clone(), Constraint::PathPrefix("/project".into()), None)
+            .await
+            .unwrap();
+
+        assert!(manager.check_resource(&read, "/project/a").await);
+        assert!(manager.check_resource(&read, "/project").await);
+        assert!(!manager.check_resource(&read, "/etc/passwd").await);
+        assert!(!manager.check_resource(&read, "/project/../etc/passwd").await);
+        assert!(!manager.check_resource(&read, "/projectx/a").await);
+        // A constrained grant doesn't grant the whole scope
+        assert!(!manager.check(&read).await);
+    }
+
+    #[test]
+    fn test_glob_constraint() {
+        let glob = Constraint::Glob("/project/**/*.rs".to_string());
+        assert!(glob.matches("/project/main.rs"));
+        assert!(glob.matches("/project/src/agents/mod.rs"));
+        assert!(!glob.matches("/project/README.md"));
+        assert!(!glob.matches("/other/main.rs"));
+        assert_eq!(glob.base_dir(), PathBuf::from("/project"));
+
+        let single = Constraint::Glob("/project/*.toml".to_string());
+        assert!(single.matches("/project/Cargo.toml"));
+        assert!(!single.matches("/project/sub/Cargo.toml"));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_listed_logs_to_ledger() {
+        let manager = CapabilityManager::new();
+        let ledger = ConsentLedger::new();
+        let read = Capability::new("files", "read");
+        manager.grant_to("indexer", read.clone(), None).await.unwrap();
+        manager
+            .grant_constrained_to("indexer", read.clone(), Constraint::PathPrefix("/project".into()), None)
+            .await
+            .unwrap();
+        manager.grant_to("crawler", read.clone(), None).await.unwrap();
+
+        // Each summary names the agent holding the grant
+        let summaries = manager.summaries().await;
+        let agents: Vec<&str> = summaries.iter().map(|s| s.agent_id.as_str()).collect();
+        assert_eq!(agents, ["indexer", "indexer", "crawler"]);
+
+        let constrained = summaries.iter().find(|s| s.constraint.is_some()).unwrap();
+        manager
+            .revoke_listed(&constrained.agent_id, &constrained.capability, constrained.constraint.as_deref(), &ledger)
+            .await
+            .unwrap();
+
+        // Only the selected grant is revoked
+        assert!(manager.check_for("indexer", &read).await);
+        assert!(manager.check_for("crawler", &read).await);
+        assert_eq!(manager.summaries().await.len(), 2);
+        assert_eq!(ledger.get_for_agent("indexer").await.len(), 1);
+        assert!(ledger.get_for_agent("crawler").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_single_use_grant_is_exhausted() {
+        let manager = CapabilityManager::new();
+        let ledger = ConsentLedger::new();
+        let http = Capability::new("network", "connect");
+        manager.grant_limited_to("fetcher", http.clone(), 1, None).await.unwrap();
+
+        assert!(manager.check_for("fetcher", &http).await);
+        assert!(manager.consume(&http, "fetcher", &ledger).await.unwrap());
+        assert!(!manager.check_for("fetcher", &http).await);
+        assert!(!manager.consume(&http, "fetcher", &ledger).await.unwrap());
+
+        let entries = ledger.get_for_agent("fetcher").await;
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(
+            &entries[0].action,
+            ConsentAction::Exhausted { capability, uses: 1 } if capability == "network.connect"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_grant_is_used_before_limited_one() {
+        let manager = CapabilityManager::new();
+        let ledger = ConsentLedger::new();
+        let read = Capability::new("files", "read");
+        manager.grant_limited_to("indexer", read.clone(), 2, None).await.unwrap();
+        manager.grant_to("indexer", read.clone(), None).await.unwrap();
+
+        for _ in 0..3 {
+            assert!(manager.consume(&read, "indexer", &ledger).await.unwrap());
+        }
+        let limited = manager.active_grants().await.into_iter().find(|g| g.max_uses.is_some());
+        assert_eq!(limited.unwrap().remaining_uses(), Some(2));
+        assert!(ledger.get_all().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delegate_subset_to_sub_agent() {
+        let parent = CapabilityManager::new();
+        let child = CapabilityManager::new();
+        let ledger = ConsentLedger::new();
+        let read = Capability::new("files", "read");
+        let net = Capability::new("network", "connect");
+        parent
+            .grant_constrained_to("planner", read.clone(), Constraint::PathPrefix("/project".into()), None)
+            .await
+            .unwrap();
+        parent.grant_to("planner", net.clone(), Some(Duration::from_secs(60))).await.unwrap();
+
+        parent
+            .delegate(
+                "planner",
+                "worker",
+                &child,
+                &[read.clone(), net.clone()],
+                Duration::from_secs(3600),
+                &ledger,
+            )
+            .await
+            .unwrap();
+
+        assert!(child.check_resource_for("worker", &read, "/project/src/main.rs").await);
+        assert!(!child.check_resource_for("worker", &read, "/etc/passwd").await);
+        let net_grant = child.active_grants().await.into_iter().find(|g| g.capability == net).unwrap();
+        assert!(net_grant.remaining().unwrap() <= Duration::from_secs(60));
+        assert_eq!(net_grant.delegated_by, ["planner"]);
+
+        // Delegating onwards extends the chain
+        let grandchild = CapabilityManager::new();
+        child
+            .delegate("worker", "helper", &grandchild, std::slice::from_ref(&net), Duration::from_secs(10), &ledger)
+            .await
+            .unwrap();
+        let entries = ledger.get_for_agent("helper").await;
+        assert!(matches!(
+            &entries[0].action,
+            ConsentAction::Delegate { chain, .. } if chain == &["planner", "worker"]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_over_delegation_is_rejected() {
+        let parent = CapabilityManager::new();
+        let child = CapabilityManager::new();
+        let ledger = ConsentLedger::new();
+        let read = Capability::new("files", "read");
+        let write = Capability::new("files", "write");
+        parent.grant_to("planner", read.clone(), None).await.unwrap();
+
+        let err = parent
+            .delegate(
+                "planner",
+                "worker",
+                &child,
+                &[read.clone(), write],
+                Duration::from_secs(60),
+                &ledger,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("files.write"));
+        assert!(!child.check_for("worker", &read).await);
+        assert!(ledger.get_all().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_capability_grants_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+
+        let store = Arc::new(SqliteStore::new(&path).unwrap());
+        let manager = CapabilityManager::new_persistent(store.clone()).await.unwrap();
+        manager.grant(Capability::new("files", "read"), None).await.unwrap();
+        manager.grant(Capability::new("network", "http"), Some(Duration::from_secs(60))).await.unwrap();
+        manager
+            .grant_constrained(Capability::new("files", "write"), Constraint::PathPrefix("/project/docs".into()), None)
+            .await
+            .unwrap();
+        manager.grant_limited(Capability::new("process", "spawn"), 1, None).await.unwrap();
+        manager.revoke(&Capability::new("network", "http")).await.unwrap();
+        {
+            // Expired while the shell was closed
+            let conn = store.connection().await;
+            let conn = conn.lock().await;
+            conn.execute(
+                "INSERT INTO capability_grants (capability, granted_at, expires_at) VALUES ('env.read', 1000, 2000)",
+                [],
+            )
+            .unwrap();
+        }
+        drop(manager);
+        drop(store);
+
+        let store = Arc::new(SqliteStore::new(&path).unwrap());
+        let manager = CapabilityManager::new_persistent(store.clone()).await.unwrap();
+        assert!(manager.check(&Capability::new("files", "read")).await);
+        assert!(!manager.check(&Capability::new("network", "http")).await);
+        assert!(manager.check_resource(&Capability::new("files", "write"), "/project/docs/a.md").await);
+        assert!(!manager.check_resource(&Capability::new("files", "write"), "/project/src/a.rs").await);
+        assert!(manager.check(&Capability::new("process", "spawn")).await);
+        assert!(!manager.check(&Capability::new("env", "read")).await);
+        assert_eq!(manager.active_grants().await.len(), 3);
+
+        // Revoking marks the row instead of deleting it
+        manager.revoke(&Capability::new("files", "read")).await.unwrap();
+        let conn = store.connection().await;
+        let conn = conn.lock().await;
+        let revoked: i64 = conn
+            .query_row("SELECT COUNT(*) FROM capability_grants WHERE revoked = 1", [], |row| row.get(0))
+            .unwrap();
+        let total: i64 = conn.query_row("SELECT COUNT(*) FROM capability_grants", [], |row| row.get(0)).unwrap();
+        assert_eq!((revoked, total), (2, 5));
+    }
+
+    #[tokio::test]
+    async fn test_limited_grant_uses_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+        let ledger = ConsentLedger::new();
+        let http = Capability::new("network", "http");
+
+        let store = Arc::new(SqliteStore::new(&path).unwrap());
+        let manager = CapabilityManager::new_persistent(store.clone()).await.unwrap();
+        manager.grant_limited_to("fetcher", http.clone(), 2, None).await.unwrap();
+        assert!(manager.consume(&http, "fetcher", &ledger).await.unwrap());
+        drop(manager);
+
+        // One use left after a restart, and none after the next
+        let manager = CapabilityManager::new_persistent(store.clone()).await.unwrap();
+        let grant = manager.grants_for("fetcher").await.pop().unwrap();
+        assert_eq!(grant.remaining_uses(), Some(1));
+        assert!(manager.consume(&http, "fetcher", &ledger).await.unwrap());
+        drop(manager);
+
+        let manager = CapabilityManager::new_persistent(store).await.unwrap();
+        assert!(!manager.check_for("fetcher", &http).await);
+        assert!(!manager.consume(&http, "fetcher", &ledger).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_delegated_grants_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+        let ledger = ConsentLedger::new();
+        let read = Capability::new("files", "read");
+
+        let parent = CapabilityManager::new();
+        parent
+            .grant_constrained_to("planner", read.clone(), Constraint::PathPrefix("/project".into()), None)
+            .await
+            .unwrap();
+        let store = Arc::new(SqliteStore::new(&path).unwrap());
+        let child = CapabilityManager::new_persistent(store.clone()).await.unwrap();
+        parent
+            .delegate("planner", "worker", &child, std::slice::from_ref(&read), Duration::from_secs(3600), &ledger)
+            .await
+            .unwrap();
+        drop(child);
+
+        let child = CapabilityManager::new_persistent(store).await.unwrap();
+        assert!(child.check_resource_for("worker", &read, "/project/src/main.rs").await);
+        assert!(!child.check_resource_for("worker", &read, "/etc/passwd").await);
+        let grant = child.grants_for("worker").await.pop().unwrap();
+        assert_eq!(grant.delegated_by, ["planner"]);
+        assert!(grant.remaining().unwrap() <= Duration::from_secs(3600));
+    }
 }
@@ -0,0 +1,189 @@
+//! Outstanding consent requests, matched to grants and denials by request id
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+
+use crate::agents::event_protocol::{Event, EventType};
+use crate::oauth::consent::{ConsentAction, ConsentLedger};
+
+/// A consent request waiting for the user's answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingConsent {
+    pub request_id: String,
+    pub agent_id: String,
+    pub capability: String,
+    pub reason: String,
+    pub duration_s: Option<u64>,
+}
+
+/// Consent requests awaiting an answer, oldest first
+pub struct ConsentQueue {
+    pending: Mutex<Vec<PendingConsent>>,
+    sequence: AtomicU64,
+}
+
+impl ConsentQueue {
+    pub fn new() -> Self {
+        ConsentQueue {
+            pending: Mutex::new(Vec::new()),
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Queue a request from `agent_id` under a fresh request id; returns the
+    /// id and the event announcing it. A request matching one still pending
+    /// from the same agent reuses that request's id.
+    pub async fn request(
+        &self,
+        agent_id: &str,
+        capability: &str,
+        reason: &str,
+        duration_s: Option<u64>,
+    ) -> (String, Event) {
+        let mut pending = self.pending.lock().await;
+        let request_id = match pending
+            .iter()
+            .find(|p| p.agent_id == agent_id && p.capability == capability)
+        {
+            Some(existing) => existing.request_id.clone(),
+            None => {
+                let request_id = uuid::Uuid::new_v4().to_string();
+                pending.push(PendingConsent {
+                    request_id: request_id.clone(),
+                    agent_id: agent_id.to_string(),
+                    capability: capability.to_string(),
+                    reason: reason.to_string(),
+                    duration_s,
+                });
+                request_id
+            }
+        };
+
+        let event = Event::consent_request(
+            agent_id,
+            request_id.clone(),
+            capability,
+            reason,
+            duration_s,
+            self.sequence.fetch_add(1, Ordering::SeqCst),
+        );
+        (request_id, event)
+    }
+
+    /// Requests still awaiting an answer
+    pub async fn pending(&self) -> Vec<PendingConsent> {
+        self.pending.lock().await.clone()
+    }
+
+    /// Resolve the pending request answered by a grant or deny `event` and
+    /// record the answer in `ledger`. Returns None if no pending request has
+    /// the event's request id.
+    pub async fn resolve(&self, event: &Event, ledger: &ConsentLedger) -> Result<Option<PendingConsent>> {
+        let (request_id, capability) = match &event.event_type {
+            EventType::ConsentGrant(grant) => (&grant.request_id, &grant.capability),
+            EventType::ConsentDeny(deny) => (&deny.request_id, &deny.capability),
+            _ => anyhow::bail!("Not a consent answer: {:?}", event.event_type),
+        };
+
+        let resolved = {
+            let mut pending = self.pending.lock().await;
+            let Some(index) = pending.iter().position(|p| &p.request_id == request_id) else {
+                tracing::debug!("No pending consent request {}", request_id);
+                return Ok(None);
+            };
+            if &pending[index].capability != capability {
+                anyhow::bail!(
+                    "Consent request {} is for {}, not {}",
+                    request_id,
+                    pending[index].capability,
+                    capability
+                );
+            }
+            pending.remove(index)
+        };
+
+        let action = match &event.event_type {
+            EventType::ConsentDeny(deny) => ConsentAction::Deny {
+                capability: resolved.capability.clone(),
+                reason: deny.reason.clone(),
+            },
+            _ => ConsentAction::Grant {
+                capability: resolved.capability.clone(),
+                duration_s: resolved.duration_s,
+            },
+        };
+        ledger
+            .log_response(resolved.agent_id.clone(), resolved.request_id.clone(), action)
+            .await?;
+        Ok(Some(resolved))
+    }
+}
+
+impl Default for ConsentQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_grant_resolves_only_matching_request() {
+        let queue = ConsentQueue::new();
+        let ledger = ConsentLedger::new();
+        let (first, event) = queue.request("agent1", "files.read", "read notes", Some(60)).await;
+        assert!(matches!(
+            &event.event_type,
+            EventType::ConsentRequest(request) if request.request_id == first
+        ));
+        let (second, _) = queue.request("agent1", "net.http", "fetch docs", None).await;
+        assert_ne!(first, second);
+
+        let grant = Event::consent_grant("agent1", second.clone(), "net.http", None, 0);
+        let resolved = queue.resolve(&grant, &ledger).await.unwrap().unwrap();
+        assert_eq!(resolved.request_id, second);
+
+        let pending = queue.pending().await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].request_id, first);
+
+        let entries = ledger.get_all().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].request_id.as_deref(), Some(second.as_str()));
+        assert!(matches!(
+            &entries[0].action,
+            ConsentAction::Grant { capability, duration_s: None } if capability == "net.http"
+        ));
+
+        // Answering the same request twice is a no-op
+        assert!(queue.resolve(&grant, &ledger).await.unwrap().is_none());
+        assert_eq!(ledger.get_all().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_deny_and_mismatched_answers() {
+        let queue = ConsentQueue::new();
+        let ledger = ConsentLedger::new();
+        let (id, _) = queue.request("agent1", "files.read", "read notes", None).await;
+        // Re-asking while pending keeps the same id
+        assert_eq!(queue.request("agent1", "files.read", "again", None).await.0, id);
+
+        let unknown = Event::consent_grant("agent1", "no-such-request", "files.read", None, 0);
+        assert!(queue.resolve(&unknown, &ledger).await.unwrap().is_none());
+
+        let wrong_cap = Event::consent_grant("agent1", id.clone(), "net.http", None, 0);
+        assert!(queue.resolve(&wrong_cap, &ledger).await.is_err());
+        assert_eq!(queue.pending().await.len(), 1);
+
+        let deny = Event::consent_deny("agent1", id.clone(), "files.read", "not now", 0);
+        queue.resolve(&deny, &ledger).await.unwrap().unwrap();
+        assert!(queue.pending().await.is_empty());
+
+        let entries = ledger.get_all().await;
+        assert_eq!(entries[0].request_id.as_deref(), Some(id.as_str()));
+        assert!(matches!(&entries[0].action, ConsentAction::Deny { reason, .. } if reason == "not now"));
+    }
+}
@@ -3,6 +3,8 @@
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
 
+use crate::workspace::artifacts::ArtifactKind;
+
 /// Event protocol version
 pub const PROTOCOL_VERSION: &str = "0.1";
 
@@ -24,6 +26,7 @@ pub enum EventType {
     Artifact(ArtifactEvent),
     ConsentRequest(ConsentRequestEvent),
     ConsentGrant(ConsentGrantEvent),
+    ConsentDeny(ConsentDenyEvent),
     ConsentRevoke(ConsentRevokeEvent),
     Error(ErrorEvent),
     StateUpdate(StateUpdateEvent),
@@ -49,7 +52,7 @@ pub struct OutputEvent {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArtifactEvent {
     pub id: String,
-    pub kind: String,  // "diff", "log", "preview", "code"
+    pub kind: ArtifactKind,
     pub path: String,
     pub preview_hint: Option<String>,
 }
@@ -57,6 +60,9 @@ pub struct ArtifactEvent {
 /// Consent request event: agent requests capability
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsentRequestEvent {
+    /// Assigned by the runtime; grants and denials echo it back
+    #[serde(default)]
+    pub request_id: String,
     pub capability: String,
     pub reason: String,
     pub duration_s: Option<u64>,
@@ -65,10 +71,20 @@ pub struct ConsentRequestEvent {
 /// Consent grant event: user grants capability
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsentGrantEvent {
+    #[serde(default)]
+    pub request_id: String,
     pub capability: String,
     pub expires_at: Option<SystemTime>,
 }
 
+/// Consent deny event: user refuses a capability request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentDenyEvent {
+    pub request_id: String,
+    pub capability: String,
+    pub reason: String,
+}
+
 /// Consent revoke event: capability revoked
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsentRevokeEvent {
@@ -144,6 +160,62 @@ impl Event {
         )
     }
 
+    pub fn consent_request(
+        agent_id: impl Into<String>,
+        request_id: impl Into<String>,
+        capability: impl Into<String>,
+        reason: impl Into<String>,
+        duration_s: Option<u64>,
+        sequence: u64,
+    ) -> Self {
+        Event::new(
+            EventType::ConsentRequest(ConsentRequestEvent {
+                request_id: request_id.into(),
+                capability: capability.into(),
+                reason: reason.into(),
+                duration_s,
+            }),
+            agent_id,
+            sequence,
+        )
+    }
+
+    pub fn consent_grant(
+        agent_id: impl Into<String>,
+        request_id: impl Into<String>,
+        capability: impl Into<String>,
+        expires_at: Option<SystemTime>,
+        sequence: u64,
+    ) -> Self {
+        Event::new(
+            EventType::ConsentGrant(ConsentGrantEvent {
+                request_id: request_id.into(),
+                capability: capability.into(),
+                expires_at,
+            }),
+            agent_id,
+            sequence,
+        )
+    }
+
+    pub fn consent_deny(
+        agent_id: impl Into<String>,
+        request_id: impl Into<String>,
+        capability: impl Into<String>,
+        reason: impl Into<String>,
+        sequence: u64,
+    ) -> Self {
+        Event::new(
+            EventType::ConsentDeny(ConsentDenyEvent {
+                request_id: request_id.into(),
+                capability: capability.into(),
+                reason: reason.into(),
+            }),
+            agent_id,
+            sequence,
+        )
+    }
+
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
     }
@@ -0,0 +1,257 @@
+//! Live fan-out of agent events, with an NDJSON sink for headless consumers
+//! and recordings that can be replayed without re-running the agent
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::agents::event_protocol::Event;
+
+/// Events buffered per subscriber before slow readers start missing some
+pub const DEFAULT_STREAM_CAPACITY: usize = 1024;
+
+/// Content type of agent output that isn't an event frame
+const OUTPUT_CONTENT_TYPE: &str = "text/plain";
+
+/// Broadcasts every event as it happens to any number of subscribers
+#[derive(Clone)]
+pub struct EventStream {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventStream {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        EventStream { sender }
+    }
+
+    /// Send an event to current subscribers; events with no subscribers are dropped
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventStream {
+    fn default() -> Self {
+        Self::new(DEFAULT_STREAM_CAPACITY)
+    }
+}
+
+/// Turns the output of one agent run into numbered events
+pub struct Emitter {
+    agent_id: String,
+    events: UnboundedSender<Event>,
+    next_chunk: u64,
+    /// 0 is the input event, sent before the agent starts
+    next_sequence: u64,
+}
+
+impl Emitter {
+    pub fn new(agent_id: String, events: UnboundedSender<Event>) -> Self {
+        Emitter { agent_id, events, next_chunk: 0, next_sequence: 1 }
+    }
+
+    pub fn output(&mut self, data: Vec<u8>, complete: bool) {
+        let chunk = Event::output(self.agent_id.clone(), self.next_chunk, OUTPUT_CONTENT_TYPE, data, complete, 0);
+        self.next_chunk += 1;
+        self.send(chunk);
+    }
+
+    /// Send `event` as the agent's next, whatever agent and sequence it claims
+    pub fn send(&mut self, mut event: Event) {
+        event.agent_id = self.agent_id.clone();
+        event.sequence = self.next_sequence;
+        self.next_sequence += 1;
+        // The receiver is gone once the run has been stopped
+        let _ = self.events.send(event);
+    }
+
+    /// Send one line of the agent's stdout as it arrives: an event frame as
+    /// it is, anything else as output
+    pub fn line(&mut self, line: &[u8]) {
+        match frame(line) {
+            Some(frame) => self.send(frame),
+            None => self.output(line.to_vec(), false),
+        }
+    }
+
+    /// Send the agent's whole stdout: event frames as they are, the lines
+    /// between them as output, ending with a complete chunk
+    pub fn stdout(&mut self, stdout: &[u8]) {
+        let mut text = Vec::new();
+        for line in stdout.split_inclusive(|&b| b == b'\n') {
+            match frame(line) {
+                Some(frame) => {
+                    if !text.is_empty() {
+                        self.output(std::mem::take(&mut text), false);
+                    }
+                    self.send(frame);
+                }
+                None => text.extend_from_slice(line),
+            }
+        }
+        self.output(text, true);
+    }
+}
+
+/// The event a line of agent output holds, if it is an event frame
+fn frame(line: &[u8]) -> Option<Event> {
+    std::str::from_utf8(line).ok().and_then(|line| Event::from_json(line.trim()).ok())
+}
+
+/// Write each received event as one JSON line, flushing per line, until the
+/// stream closes; returns the number of events written
+pub async fn write_ndjson<W: Write>(
+    events: &mut broadcast::Receiver<Event>,
+    out: &mut W,
+) -> Result<u64> {
+    let mut written = 0;
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                writeln!(out, "{}", event.to_json()?)?;
+                out.flush()?;
+                written += 1;
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("Event stream reader fell behind; {} events skipped", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(written),
+        }
+    }
+}
+
+/// Writes an agent run's events to an NDJSON recording
+pub struct EventRecorder {
+    out: BufWriter<File>,
+    recorded: u64,
+}
+
+impl EventRecorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create recording: {}", path.display()))?;
+        Ok(EventRecorder {
+            out: BufWriter::new(file),
+            recorded: 0,
+        })
+    }
+
+    pub fn record(&mut self, event: &Event) -> Result<()> {
+        writeln!(self.out, "{}", event.to_json()?)?;
+        self.recorded += 1;
+        Ok(())
+    }
+
+    /// Flush the recording; returns the number of events recorded
+    pub fn finish(mut self) -> Result<u64> {
+        self.out.flush()?;
+        Ok(self.recorded)
+    }
+}
+
+/// Read the events of a recording, in order
+pub fn load_recording(path: &Path) -> Result<Vec<Event>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open recording: {}", path.display()))?;
+    let mut events = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = Event::from_json(&line)
+            .with_context(|| format!("Bad event on line {} of {}", number + 1, path.display()))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// Publish a recording to `stream` as if the agent were running, so the UI
+/// and ledger see the same sequence; returns the number of events replayed
+pub fn replay(path: &Path, stream: &EventStream) -> Result<u64> {
+    let events = load_recording(path)?;
+    let count = events.len() as u64;
+    for event in events {
+        stream.publish(event);
+    }
+    tracing::info!("Replayed {} events from {}", count, path.display());
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_ndjson_one_line_per_event() {
+        let stream = EventStream::default();
+        let mut events = stream.subscribe();
+
+        stream.publish(Event::input("agent1", "hello".to_string(), 0));
+        stream.publish(Event::error("agent1", "E1", "boom", 1));
+        drop(stream);
+
+        let mut out = Vec::new();
+        assert_eq!(write_ndjson(&mut events, &mut out).await.unwrap(), 2);
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(Event::from_json(lines[1]).unwrap().sequence, 1);
+    }
+
+    #[tokio::test]
+    async fn test_recording_replays_identical_events() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("run.ndjson");
+        let original = vec![
+            Event::input("agent1", "hello".to_string(), 0),
+            Event::error("agent1", "E1", "boom", 1),
+            Event::input("agent1", "again".to_string(), 2),
+        ];
+
+        let mut recorder = EventRecorder::create(&path).unwrap();
+        for event in &original {
+            recorder.record(event).unwrap();
+        }
+        assert_eq!(recorder.finish().unwrap(), 3);
+
+        let stream = EventStream::default();
+        let mut events = stream.subscribe();
+        assert_eq!(replay(&path, &stream).unwrap(), 3);
+
+        for expected in &original {
+            let replayed = events.recv().await.unwrap();
+            assert_eq!(replayed.to_json().unwrap(), expected.to_json().unwrap());
+        }
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_stdout_frames_become_events() {
+        use crate::agents::event_protocol::EventType;
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut emitter = Emitter::new("agent1".to_string(), sender);
+        emitter.output(b"partial".to_vec(), false);
+
+        let frame = Event::error("spoofed", "not_found", "no such file", 9).to_json().unwrap();
+        emitter.stdout(format!("working\n{}\ndone", frame).as_bytes());
+
+        let events: Vec<Event> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert_eq!(events.len(), 4);
+        assert!(events.iter().all(|e| e.agent_id == "agent1"));
+        assert_eq!(events.iter().map(|e| e.sequence).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(matches!(&events[1].event_type, EventType::Output(o) if o.data == b"working\n" && !o.complete));
+        assert!(matches!(&events[2].event_type, EventType::Error(e) if e.code == "not_found"));
+        assert!(matches!(&events[3].event_type, EventType::Output(o) if o.data == b"done" && o.complete));
+    }
+}
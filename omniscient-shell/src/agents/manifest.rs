@@ -1,10 +1,29 @@
 //! Agent manifest schema v0.1
 
 use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::fs;
 
+use crate::agents::capabilities::{known_capabilities, Capability};
+use crate::utils::config::AgentsConfig;
+
+/// File name of a manifest inside an agent directory
+pub const MANIFEST_FILE: &str = "manifest.toml";
+
+/// Detached signature of the manifest, beside it in the agent directory
+pub const SIGNATURE_FILE: &str = "manifest.toml.sig";
+
+/// Memory limits outside this range are flagged by `lint`
+const MEM_LINT_RANGE: (u64, u64) = (16 << 20, 8 << 30);
+/// CPU limits (millicores) outside this range are flagged by `lint`
+const CPU_LINT_RANGE: (u32, u32) = (50, 4000);
+
 /// Agent manifest (schema v0.1)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Manifest {
@@ -16,8 +35,46 @@ pub struct Manifest {
     pub capabilities: Vec<String>,
     #[serde(default)]
     pub oauth_scopes: Vec<String>,
+    /// Artifact kinds the agent emits, e.g. ["diff", "preview"]
+    #[serde(default)]
+    pub produces: Vec<String>,
+    /// Analysis-only agent: the workspace is mounted read-only
+    #[serde(default)]
+    pub read_only_workspace: bool,
+    /// URL the agent was installed from, matched against trusted URL sources
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Hex sha256 of the entry file, so a signature covers the code it runs
+    #[serde(default)]
+    pub entry_sha256: Option<String>,
     pub resources: ResourceLimits,
     pub ui: UiHints,
+    /// Text the manifest was loaded from, which its signature covers
+    #[serde(skip)]
+    pub raw: String,
+}
+
+/// Advisory finding from `Manifest::lint`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// Manifest field the warning is about, e.g. `resources.mem`
+    pub field: String,
+    pub message: String,
+}
+
+impl LintWarning {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        LintWarning {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -33,6 +90,39 @@ pub struct ResourceLimits {
     pub mem: String,  // e.g., "512Mi"
 }
 
+impl ResourceLimits {
+    /// Memory limit in bytes, from e.g. "512Mi", "1Gi", "500M" or "1048576"
+    pub fn parse_mem(&self) -> Result<u64> {
+        let mem = self.mem.trim();
+        let split = mem.find(|c: char| !c.is_ascii_digit()).unwrap_or(mem.len());
+        let (number, unit) = mem.split_at(split);
+        let multiplier: u64 = match unit {
+            "" => 1,
+            "Ki" => 1 << 10,
+            "Mi" => 1 << 20,
+            "Gi" => 1 << 30,
+            "K" => 1_000,
+            "M" => 1_000_000,
+            "G" => 1_000_000_000,
+            _ => anyhow::bail!("Invalid memory limit: {}", self.mem),
+        };
+        let number: u64 = number
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid memory limit: {}", self.mem))?;
+        Ok(number * multiplier)
+    }
+
+    /// CPU limit in millicores, from e.g. "500m" or "2"
+    pub fn parse_cpu(&self) -> Result<u32> {
+        let cpu = self.cpu.trim();
+        let parsed = match cpu.strip_suffix('m') {
+            Some(millis) => millis.parse::<u32>().ok(),
+            None => cpu.parse::<f32>().ok().map(|cores| (cores * 1000.0) as u32),
+        };
+        parsed.ok_or_else(|| anyhow::anyhow!("Invalid CPU limit: {}", self.cpu))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiHints {
     pub hints: Vec<String>,  // e.g., ["streaming", "diff", "preview"]
@@ -44,13 +134,51 @@ impl Manifest {
         let contents = fs::read_to_string(path)
             .with_context(|| format!("Failed to read manifest: {}", path.display()))?;
 
-        let manifest: Manifest = toml::from_str(&contents)
+        let mut manifest: Manifest = toml::from_str(&contents)
             .with_context(|| format!("Failed to parse manifest: {}", path.display()))?;
+        manifest.raw = contents;
 
         manifest.validate()?;
         Ok(manifest)
     }
 
+    /// Check `sig`, the base64 ed25519 signature from `manifest.toml.sig`,
+    /// against the text the manifest was loaded from. Succeeds if any of
+    /// `pubkeys` (base64 public keys) made it.
+    pub fn verify_signature(&self, sig: &str, pubkeys: &[String]) -> Result<()> {
+        let sig = BASE64
+            .decode(sig.trim())
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| anyhow::anyhow!("Signature of {} is not a base64 ed25519 signature", self.name))?;
+
+        for key in pubkeys {
+            let bytes = BASE64.decode(key.trim()).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok());
+            let Some(key) = bytes.and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok()) else {
+                tracing::warn!("Skipping trusted key {}: not a base64 ed25519 public key", key);
+                continue;
+            };
+            if key.verify_strict(self.raw.as_bytes(), &sig).is_ok() {
+                return Ok(());
+            }
+        }
+        anyhow::bail!("Manifest of {} is not signed by a trusted key", self.name)
+    }
+
+    /// Check the entry file under `base_dir` against `entry_sha256`
+    pub fn verify_entry(&self, base_dir: &Path) -> Result<()> {
+        let Some(expected) = &self.entry_sha256 else {
+            anyhow::bail!("Manifest of {} has no entry_sha256 to check its entry against", self.name);
+        };
+        let path = self.entry_path(base_dir);
+        let entry = fs::read(&path).with_context(|| format!("Failed to read agent entry {}", path.display()))?;
+        let actual: String = Sha256::digest(&entry).iter().map(|b| format!("{:02x}", b)).collect();
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            anyhow::bail!("Entry {} of {} does not match the entry_sha256 in its manifest", path.display(), self.name);
+        }
+        Ok(())
+    }
+
     /// Validate manifest
     pub fn validate(&self) -> Result<()> {
         // Check schema version
@@ -66,14 +194,89 @@ impl Manifest {
             anyhow::bail!("Manifest entry point cannot be empty");
         }
 
-        // Validate capabilities format
+        // Catch typos, which would otherwise never be granted
+        let unknown: Vec<&str> = self
+            .capabilities
+            .iter()
+            .filter(|cap| !Capability::parse(cap).is_ok_and(|cap| cap.is_known()))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            let mut scopes: Vec<String> = known_capabilities().into_iter().map(|cap| cap.scope).collect();
+            scopes.dedup();
+            anyhow::bail!(
+                "Unrecognized capabilities: {}. Capabilities are written scope.action, with scope one of: {}",
+                unknown.join(", "),
+                scopes.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Check for likely mistakes that `validate` accepts; warnings only
+    pub fn lint(&self) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+
         for cap in &self.capabilities {
-            if !cap.contains('.') && !cap.contains(':') {
-                tracing::warn!("Capability '{}' may not follow standard format", cap);
+            match Capability::parse(cap) {
+                Ok(capability) if capability.is_wildcard() => warnings.push(LintWarning::new(
+                    "capabilities",
+                    format!("'{}' is a wildcard; list only the actions the agent needs", cap),
+                )),
+                Ok(capability) if !capability.is_known() => warnings.push(LintWarning::new(
+                    "capabilities",
+                    format!("'{}' is not a recognized capability", cap),
+                )),
+                Ok(_) => {}
+                Err(_) => warnings.push(LintWarning::new(
+                    "capabilities",
+                    format!("'{}' should be written as scope.action, e.g. files.read", cap),
+                )),
             }
         }
 
-        Ok(())
+        for kind in &self.produces {
+            if !self.ui.hints.contains(kind) {
+                warnings.push(LintWarning::new(
+                    "ui.hints",
+                    format!("agent produces '{}' but has no '{}' UI hint", kind, kind),
+                ));
+            }
+        }
+
+        match self.resources.parse_mem() {
+            Ok(bytes) if bytes < MEM_LINT_RANGE.0 => warnings.push(LintWarning::new(
+                "resources.mem",
+                format!("{} is suspiciously low; the agent may be killed at startup", self.resources.mem),
+            )),
+            Ok(bytes) if bytes > MEM_LINT_RANGE.1 => warnings.push(LintWarning::new(
+                "resources.mem",
+                format!("{} is suspiciously high for an agent", self.resources.mem),
+            )),
+            Ok(_) => {}
+            Err(e) => warnings.push(LintWarning::new("resources.mem", e.to_string())),
+        }
+
+        match self.resources.parse_cpu() {
+            Ok(millis) if millis < CPU_LINT_RANGE.0 => warnings.push(LintWarning::new(
+                "resources.cpu",
+                format!("{} is suspiciously low; the agent will be heavily throttled", self.resources.cpu),
+            )),
+            Ok(millis) if millis > CPU_LINT_RANGE.1 => warnings.push(LintWarning::new(
+                "resources.cpu",
+                format!("{} is suspiciously high for an agent", self.resources.cpu),
+            )),
+            Ok(_) => {}
+            Err(e) => warnings.push(LintWarning::new("resources.cpu", e.to_string())),
+        }
+
+        warnings
+    }
+
+    /// Load and lint the manifest in an agent directory (`omni:agent lint <dir>`)
+    pub fn lint_dir(dir: &Path) -> Result<Vec<LintWarning>> {
+        Ok(Self::load(&dir.join(MANIFEST_FILE))?.lint())
     }
 
     /// Get the full path to the entry point
@@ -81,6 +284,11 @@ impl Manifest {
         base_dir.join(&self.entry)
     }
 
+    /// Whether the agent gets a read-only workspace, from its manifest or `agents.read_only`
+    pub fn workspace_read_only(&self, config: &AgentsConfig) -> bool {
+        self.read_only_workspace || config.read_only.contains(&self.name)
+    }
+
     /// Check if the agent requires native execution
     pub fn requires_native(&self) -> bool {
         self.sandbox == SandboxMode::Native
@@ -128,6 +336,9 @@ hints = ["streaming"]
             sandbox: SandboxMode::Wasm,
             capabilities: vec!["files.read".to_string()],
             oauth_scopes: vec![],
+            produces: vec![],
+            read_only_workspace: false,
+            source: None,
             resources: ResourceLimits {
                 cpu: "500m".to_string(),
                 mem: "512Mi".to_string(),
@@ -135,11 +346,42 @@ hints = ["streaming"]
             ui: UiHints {
                 hints: vec!["streaming".to_string()],
             },
+            entry_sha256: None,
+            raw: String::new(),
         };
 
         assert!(manifest.validate().is_ok());
     }
 
+    #[test]
+    fn test_validate_rejects_unknown_capabilities() {
+        let mut manifest: Manifest = toml::from_str(
+            r#"
+schema_version = "0.1"
+name = "Typo"
+version = "0.1.0"
+entry = "agent.wasm"
+sandbox = "wasm"
+capabilities = ["files.read", "file.raed", "network", "oauth.github", "files.*"]
+
+[resources]
+cpu = "500m"
+mem = "512Mi"
+
+[ui]
+hints = []
+"#,
+        )
+        .unwrap();
+
+        let err = manifest.validate().unwrap_err().to_string();
+        assert!(err.contains("Unrecognized capabilities: file.raed, network."), "{}", err);
+        assert!(err.contains("files, network, oauth"), "{}", err);
+
+        manifest.capabilities.retain(|cap| cap.contains('.') && cap != "file.raed");
+        assert!(manifest.validate().is_ok());
+    }
+
     #[test]
     fn test_invalid_schema_version() {
         let mut manifest = Manifest {
@@ -150,13 +392,123 @@ hints = ["streaming"]
             sandbox: SandboxMode::Wasm,
             capabilities: vec![],
             oauth_scopes: vec![],
+            produces: vec![],
+            read_only_workspace: false,
+            source: None,
             resources: ResourceLimits {
                 cpu: "500m".to_string(),
                 mem: "512Mi".to_string(),
             },
             ui: UiHints { hints: vec![] },
+            entry_sha256: None,
+            raw: String::new(),
         };
 
         assert!(manifest.validate().is_err());
     }
+
+    #[test]
+    fn test_resource_limit_parsing() {
+        let limits = ResourceLimits {
+            cpu: "500m".to_string(),
+            mem: "512Mi".to_string(),
+        };
+        assert_eq!(limits.parse_mem().unwrap(), 512 * 1024 * 1024);
+        assert_eq!(limits.parse_cpu().unwrap(), 500);
+
+        let limits = ResourceLimits {
+            cpu: "1.5".to_string(),
+            mem: "2G".to_string(),
+        };
+        assert_eq!(limits.parse_mem().unwrap(), 2_000_000_000);
+        assert_eq!(limits.parse_cpu().unwrap(), 1500);
+
+        let limits = ResourceLimits {
+            cpu: "2".to_string(),
+            mem: "1Gi".to_string(),
+        };
+        assert_eq!(limits.parse_mem().unwrap(), 1 << 30);
+        assert_eq!(limits.parse_cpu().unwrap(), 2000);
+
+        let bad = ResourceLimits {
+            cpu: "lots".to_string(),
+            mem: "512Xi".to_string(),
+        };
+        assert!(bad.parse_mem().is_err());
+        assert!(bad.parse_cpu().is_err());
+    }
+
+    #[test]
+    fn test_lint_flags_wildcards_and_huge_limits() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+schema_version = "0.1"
+name = "Greedy"
+version = "0.1.0"
+entry = "agent.wasm"
+sandbox = "wasm"
+capabilities = ["files.*", "files.read", "telepathy.read"]
+produces = ["diff"]
+
+[resources]
+cpu = "500m"
+mem = "64Gi"
+
+[ui]
+hints = ["streaming"]
+"#,
+        )
+        .unwrap();
+        assert!(manifest.validate().unwrap_err().to_string().contains("telepathy.read"));
+
+        let warnings = manifest.lint();
+        let fields: Vec<&str> = warnings.iter().map(|w| w.field.as_str()).collect();
+        assert_eq!(fields, ["capabilities", "capabilities", "ui.hints", "resources.mem"]);
+        assert!(warnings[0].message.contains("files.*"));
+        assert!(warnings[0].message.contains("wildcard"));
+        assert!(warnings[1].message.contains("telepathy.read"));
+        assert!(warnings[3].to_string().contains("64Gi is suspiciously high"));
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(
+            br#"schema_version = "0.1"
+name = "Signed"
+version = "0.1.0"
+entry = "agent.wasm"
+sandbox = "wasm"
+capabilities = ["files.read"]
+produces = ["text"]
+
+[resources]
+cpu = "500m"
+mem = "256Mi"
+
+[ui]
+hints = []
+"#,
+        )
+        .unwrap();
+        let manifest = Manifest::load(file.path()).unwrap();
+
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let public = BASE64.encode(key.verifying_key().as_bytes());
+        let other = BASE64.encode(SigningKey::from_bytes(&[9u8; 32]).verifying_key().as_bytes());
+        let sig = format!("{}\n", BASE64.encode(key.sign(manifest.raw.as_bytes()).to_bytes()));
+
+        assert!(manifest.verify_signature(&sig, &[other.clone(), public.clone()]).is_ok());
+        assert!(manifest.verify_signature(&sig, &[other]).is_err());
+        assert!(manifest.verify_signature("not a signature", std::slice::from_ref(&public)).is_err());
+        assert!(manifest.verify_signature(&sig, &["short".to_string()]).is_err());
+        // A malformed key is skipped rather than failing the check
+        assert!(manifest.verify_signature(&sig, &["short".to_string(), public.clone()]).is_ok());
+
+        let mut tampered = manifest.clone();
+        tampered.raw = tampered.raw.replace("files.read", "files.write");
+        assert!(tampered.verify_signature(&sig, &[public]).is_err());
+    }
 }
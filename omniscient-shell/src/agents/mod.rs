@@ -6,10 +6,18 @@ pub mod manifest;
 pub mod wasm_host;
 pub mod native_runner;
 pub mod event_protocol;
+pub mod event_stream;
 pub mod capabilities;
+pub mod access_guard;
+pub mod consent_queue;
+pub mod watchdog;
 
-pub use runtime::AgentRuntime;
-pub use registry::AgentRegistry;
+pub use runtime::{AgentRuntime, AgentStatus, ExecutionResult};
+pub use registry::{AgentRegistry, CapabilityChange};
 pub use manifest::Manifest;
-pub use capabilities::{Capability, CapabilityManager};
+pub use capabilities::{Capability, CapabilityDescription, CapabilityManager, RiskLevel};
 pub use event_protocol::Event;
+pub use event_stream::{EventRecorder, EventStream};
+pub use access_guard::AccessGuard;
+pub use consent_queue::{ConsentQueue, PendingConsent};
+pub use watchdog::{AgentWatchdog, AutoDisablePolicy, Violation};
@@ -1,22 +1,279 @@
 //! Native agent subprocess runner with OS-level isolation
 
 use anyhow::Result;
-use std::path::Path;
+use async_trait::async_trait;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Child, Stdio};
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::process::Command as TokioCommand;
 
+use crate::agents::capabilities::{Capability, CapabilityGrant};
+use crate::agents::manifest::ResourceLimits;
+#[cfg(target_os = "linux")]
+use crate::platform::cgroup::AgentCgroup;
+#[cfg(windows)]
+use crate::shell::job_object::Job;
+use crate::shell::process_supervision::SupervisedProcess;
+
+/// Buffer between a blocking child pipe and its async side
+const PIPE_BRIDGE_BYTES: usize = 8192;
+
+/// How often a std child is checked for exit while waiting
+const EXIT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// Async writer for a child's stdin
+pub type ChildWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Async reader for a child's stdout or stderr
+pub type ChildReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// A spawned native agent, whichever process API started it
+pub enum ProcessHandle {
+    Std(Child),
+    Tokio(tokio::process::Child),
+    /// A child in its own job object; dropping the handle closes the job,
+    /// killing the child and every process it started
+    #[cfg(windows)]
+    Job(Child, Job),
+    /// A child under sandbox-exec; its profile is removed with the handle
+    #[cfg(target_os = "macos")]
+    Sandboxed(Child, tempfile::TempPath),
+    /// A child in its own cgroup, removed once the child has exited
+    #[cfg(target_os = "linux")]
+    Limited(tokio::process::Child, AgentCgroup),
+}
+
+impl ProcessHandle {
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            ProcessHandle::Std(child) => Some(child.id()),
+            #[cfg(windows)]
+            ProcessHandle::Job(child, _) => Some(child.id()),
+            #[cfg(target_os = "macos")]
+            ProcessHandle::Sandboxed(child, _) => Some(child.id()),
+            ProcessHandle::Tokio(child) => child.id(),
+            #[cfg(target_os = "linux")]
+            ProcessHandle::Limited(child, _) => child.id(),
+        }
+    }
+
+    /// Ask the process to exit: closes its stdin and, on Unix, sends SIGTERM
+    pub fn terminate(&mut self) -> Result<()> {
+        match self {
+            ProcessHandle::Std(child) => drop(child.stdin.take()),
+            #[cfg(windows)]
+            ProcessHandle::Job(child, _) => drop(child.stdin.take()),
+            #[cfg(target_os = "macos")]
+            ProcessHandle::Sandboxed(child, _) => drop(child.stdin.take()),
+            ProcessHandle::Tokio(child) => drop(child.stdin.take()),
+            #[cfg(target_os = "linux")]
+            ProcessHandle::Limited(child, _) => drop(child.stdin.take()),
+        }
+
+        #[cfg(unix)]
+        if let Some(pid) = self.id() {
+            use nix::sys::signal::{kill, Signal};
+            use nix::unistd::Pid;
+            kill(Pid::from_raw(pid as i32), Signal::SIGTERM)?;
+        }
+        Ok(())
+    }
+
+    /// Force the process to exit; for a job, every process in it
+    pub fn kill(&mut self) -> Result<()> {
+        match self {
+            ProcessHandle::Std(child) => child.kill()?,
+            #[cfg(windows)]
+            ProcessHandle::Job(_, job) => job.terminate()?,
+            #[cfg(target_os = "macos")]
+            ProcessHandle::Sandboxed(child, _) => child.kill()?,
+            ProcessHandle::Tokio(child) => child.start_kill()?,
+            #[cfg(target_os = "linux")]
+            ProcessHandle::Limited(child, _) => child.start_kill()?,
+        }
+        Ok(())
+    }
+
+    /// Wait for the process to exit
+    pub async fn wait(&mut self) -> Result<std::process::ExitStatus> {
+        match self {
+            ProcessHandle::Std(child) => wait_std(child).await,
+            #[cfg(windows)]
+            ProcessHandle::Job(child, _) => wait_std(child).await,
+            #[cfg(target_os = "macos")]
+            ProcessHandle::Sandboxed(child, _) => wait_std(child).await,
+            ProcessHandle::Tokio(child) => Ok(child.wait().await?),
+            #[cfg(target_os = "linux")]
+            ProcessHandle::Limited(child, cgroup) => {
+                let status = child.wait().await?;
+                cgroup.remove();
+                Ok(status)
+            }
+        }
+    }
+
+    /// Take the child's stdin; None if already taken or not piped.
+    ///
+    /// Dropping the writer closes the pipe.
+    pub fn take_stdin(&mut self) -> Option<ChildWriter> {
+        match self {
+            ProcessHandle::Std(child) => child.stdin.take().map(bridge_writer),
+            #[cfg(windows)]
+            ProcessHandle::Job(child, _) => child.stdin.take().map(bridge_writer),
+            #[cfg(target_os = "macos")]
+            ProcessHandle::Sandboxed(child, _) => child.stdin.take().map(bridge_writer),
+            ProcessHandle::Tokio(child) => child.stdin.take().map(|s| Box::new(s) as ChildWriter),
+            #[cfg(target_os = "linux")]
+            ProcessHandle::Limited(child, _) => child.stdin.take().map(|s| Box::new(s) as ChildWriter),
+        }
+    }
+
+    /// Take the child's stdout; None if already taken or not piped
+    pub fn take_stdout(&mut self) -> Option<ChildReader> {
+        match self {
+            ProcessHandle::Std(child) => child.stdout.take().map(bridge_reader),
+            #[cfg(windows)]
+            ProcessHandle::Job(child, _) => child.stdout.take().map(bridge_reader),
+            #[cfg(target_os = "macos")]
+            ProcessHandle::Sandboxed(child, _) => child.stdout.take().map(bridge_reader),
+            ProcessHandle::Tokio(child) => child.stdout.take().map(|s| Box::new(s) as ChildReader),
+            #[cfg(target_os = "linux")]
+            ProcessHandle::Limited(child, _) => child.stdout.take().map(|s| Box::new(s) as ChildReader),
+        }
+    }
+
+    /// Take the child's stderr; None if already taken or not piped
+    pub fn take_stderr(&mut self) -> Option<ChildReader> {
+        match self {
+            ProcessHandle::Std(child) => child.stderr.take().map(bridge_reader),
+            #[cfg(windows)]
+            ProcessHandle::Job(child, _) => child.stderr.take().map(bridge_reader),
+            #[cfg(target_os = "macos")]
+            ProcessHandle::Sandboxed(child, _) => child.stderr.take().map(bridge_reader),
+            ProcessHandle::Tokio(child) => child.stderr.take().map(|s| Box::new(s) as ChildReader),
+            #[cfg(target_os = "linux")]
+            ProcessHandle::Limited(child, _) => child.stderr.take().map(|s| Box::new(s) as ChildReader),
+        }
+    }
+}
+
+/// Poll a std child until it exits
+async fn wait_std(child: &mut Child) -> Result<std::process::ExitStatus> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+    }
+}
+
+/// Lets a `Supervisor` restart native agents that crash
+#[async_trait]
+impl SupervisedProcess for ProcessHandle {
+    async fn wait(&mut self) -> Result<std::process::ExitStatus> {
+        ProcessHandle::wait(self).await
+    }
+
+    fn kill(&mut self) -> Result<()> {
+        ProcessHandle::kill(self)
+    }
+
+    fn terminate(&mut self) -> Result<()> {
+        ProcessHandle::terminate(self)
+    }
+
+    fn id(&self) -> Option<u32> {
+        ProcessHandle::id(self)
+    }
+}
+
+/// Expose a blocking pipe writer as async, copying on a blocking thread
+fn bridge_writer<W: Write + Send + 'static>(mut pipe: W) -> ChildWriter {
+    let (writer, mut incoming) = tokio::io::duplex(PIPE_BRIDGE_BYTES);
+    let runtime = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || {
+        let mut buf = [0u8; PIPE_BRIDGE_BYTES];
+        loop {
+            let n = match runtime.block_on(incoming.read(&mut buf)) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            if pipe.write_all(&buf[..n]).and_then(|_| pipe.flush()).is_err() {
+                break;
+            }
+        }
+    });
+    Box::new(writer)
+}
+
+/// Expose a blocking pipe reader as async, copying on a blocking thread
+fn bridge_reader<R: Read + Send + 'static>(mut pipe: R) -> ChildReader {
+    let (reader, mut outgoing) = tokio::io::duplex(PIPE_BRIDGE_BYTES);
+    let runtime = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || {
+        let mut buf = [0u8; PIPE_BRIDGE_BYTES];
+        loop {
+            let n = match pipe.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            if runtime.block_on(outgoing.write_all(&buf[..n])).is_err() {
+                break;
+            }
+        }
+    });
+    Box::new(reader)
+}
+
+#[derive(Clone)]
 pub struct NativeRunner {
     // Process isolation configuration
+    /// Workspace the agent may read but not modify
+    read_only_root: Option<PathBuf>,
+    /// CPU and memory limits from the agent's manifest
+    limits: Option<ResourceLimits>,
+    /// Workspace that unconstrained `files.*` grants cover
+    workspace: Option<PathBuf>,
+    /// Grants the sandbox opens up filesystem and network access for
+    grants: Vec<CapabilityGrant>,
 }
 
 impl NativeRunner {
     pub fn new() -> Self {
-        NativeRunner {}
+        NativeRunner { read_only_root: None, limits: None, workspace: None, grants: Vec::new() }
+    }
+
+    /// Sandbox spawned agents to `workspace` and what `grants` allow. Only
+    /// enforced on macOS so far.
+    pub fn with_grants(mut self, workspace: impl Into<PathBuf>, grants: Vec<CapabilityGrant>) -> Self {
+        self.workspace = Some(workspace.into());
+        self.grants = grants;
+        self
+    }
+
+    /// Mount `root` read-only for spawned agents. Spawning fails rather than
+    /// running the agent unprotected when the platform can't enforce it.
+    pub fn with_read_only_workspace(mut self, root: impl Into<PathBuf>) -> Self {
+        self.read_only_root = Some(root.into());
+        self
+    }
+
+    /// Hold spawned agents to `limits`. They are enforced with a cgroup on
+    /// Linux and (memory only) a job object on Windows; elsewhere, or when no
+    /// cgroup can be set up, they are advisory only.
+    pub fn with_resource_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = Some(limits);
+        self
     }
 
     /// Run a native agent with OS-level isolation
-    pub async fn spawn(&self, executable: &Path, args: &[String]) -> Result<Child> {
+    pub async fn spawn(&self, executable: &Path, args: &[String]) -> Result<ProcessHandle> {
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        if self.limits.is_some() {
+            tracing::info!("Resource limits for {} are advisory only on this platform", executable.display());
+        }
+
         #[cfg(target_os = "windows")]
         {
             // Use Job Objects for isolation on Windows
@@ -42,39 +299,111 @@ impl NativeRunner {
     }
 
     #[cfg(target_os = "windows")]
-    fn spawn_windows(&self, executable: &Path, args: &[String]) -> Result<Child> {
+    fn spawn_windows(&self, executable: &Path, args: &[String]) -> Result<ProcessHandle> {
         // Windows Job Objects implementation
-        let child = Command::new(executable)
+        if self.read_only_root.is_some() {
+            anyhow::bail!("Read-only workspaces are not supported for native agents on Windows");
+        }
+        use crate::shell::job_object::resume_process;
+        use std::os::windows::io::AsRawHandle;
+        use std::os::windows::process::CommandExt;
+        use windows::Win32::System::Threading::CREATE_SUSPENDED;
+
+        let memory_limit = match &self.limits {
+            Some(limits) => {
+                tracing::info!("CPU limit for {} is advisory only on Windows", executable.display());
+                Some(limits.parse_mem()?)
+            }
+            None => None,
+        };
+        let job = Job::new(memory_limit)?;
+        // Suspended until it is in the job, so nothing it starts escapes the limits
+        let mut child = Command::new(executable)
             .args(args)
+            .creation_flags(CREATE_SUSPENDED.0)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()?;
-        
+        if let Err(e) = job.assign(child.as_raw_handle()) {
+            let _ = child.kill();
+            return Err(e.context("Failed to place native agent in a job object"));
+        }
+        if let Err(e) = resume_process(child.id()) {
+            let _ = child.kill();
+            return Err(e.context("Failed to start native agent"));
+        }
+
         tracing::info!("Spawned native agent on Windows with PID: {:?}", child.id());
-        Ok(child)
+        Ok(ProcessHandle::Job(child, job))
     }
 
     #[cfg(target_os = "linux")]
-    async fn spawn_linux(&self, executable: &Path, args: &[String]) -> Result<Child> {
+    async fn spawn_linux(&self, executable: &Path, args: &[String]) -> Result<ProcessHandle> {
         // Linux cgroups implementation
-        let child = TokioCommand::new(executable)
+        let mut command = match &self.read_only_root {
+            // bubblewrap keeps the host filesystem visible but remounts the workspace read-only
+            Some(root) => {
+                let mut command = TokioCommand::new("bwrap");
+                command
+                    .args(["--dev-bind", "/", "/", "--ro-bind"])
+                    .arg(root)
+                    .arg(root)
+                    .arg("--")
+                    .arg(executable);
+                command
+            }
+            None => TokioCommand::new(executable),
+        };
+        // The child joins the cgroup before it execs, so nothing it
+        // allocates or starts escapes the limits
+        let cgroup = self.limits.as_ref().and_then(|limits| match agent_cgroup(executable, limits) {
+            Ok(cgroup) => Some(cgroup),
+            Err(e) => {
+                tracing::warn!("Resource limits for {} are advisory only: {:#}", executable.display(), e);
+                None
+            }
+        });
+        let cgroup = cgroup.map(|(cgroup, mut procs)| {
+            // SAFETY: the hook only makes a write(2) to an already open file,
+            // which is async-signal-safe
+            unsafe {
+                command.pre_exec(move || procs.write_all(b"0"));
+            }
+            cgroup
+        });
+        let child = command
             .args(args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .spawn()?;
+            .spawn()
+            .map_err(|e| match (&self.read_only_root, e.kind()) {
+                (Some(_), std::io::ErrorKind::NotFound) => anyhow::anyhow!(
+                    "bubblewrap (bwrap) is required to run agents with a read-only workspace"
+                ),
+                _ => e.into(),
+            })?;
         
         tracing::info!("Spawned native agent on Linux with PID: {:?}", child.id());
-        Ok(child)
+        match (cgroup, &self.limits) {
+            (Some(cgroup), Some(limits)) => {
+                tracing::info!("Limited native agent {:?} to {} CPU and {} memory", child.id(), limits.cpu, limits.mem);
+                Ok(ProcessHandle::Limited(child, cgroup))
+            }
+            _ => Ok(ProcessHandle::Tokio(child)),
+        }
     }
 
     #[cfg(target_os = "macos")]
-    fn spawn_macos(&self, executable: &Path, args: &[String]) -> Result<Child> {
+    fn spawn_macos(&self, executable: &Path, args: &[String]) -> Result<ProcessHandle> {
         // macOS sandbox-exec implementation
+        let workspace = self.workspace.as_deref().or(self.read_only_root.as_deref());
+        let profile = sandbox_profile(executable, workspace, self.read_only_root.is_some(), &self.grants);
+        let profile_path = write_profile(&profile)?;
         let child = Command::new("sandbox-exec")
             .arg("-f")
-            .arg("/dev/null")  // Sandbox profile (to be implemented)
+            .arg(&*profile_path)
             .arg(executable)
             .args(args)
             .stdin(Stdio::piped())
@@ -83,8 +412,85 @@ impl NativeRunner {
             .spawn()?;
         
         tracing::info!("Spawned native agent on macOS with PID: {:?}", child.id());
-        Ok(child)
+        Ok(ProcessHandle::Sandboxed(child, profile_path))
+    }
+}
+
+/// Create a cgroup enforcing `limits` for an agent; returns it with its
+/// `cgroup.procs`, which the agent writes to join it
+#[cfg(target_os = "linux")]
+fn agent_cgroup(executable: &Path, limits: &ResourceLimits) -> Result<(AgentCgroup, std::fs::File)> {
+    let name = executable.file_name().unwrap_or_default().to_string_lossy();
+    let cgroup = AgentCgroup::create(&name, limits.parse_cpu()?, limits.parse_mem()?)?;
+    let procs = cgroup.procs()?;
+    Ok((cgroup, procs))
+}
+
+/// sandbox-exec profile that denies everything except running `executable`
+/// and what `grants` allow. Unconstrained `files.*` grants cover `workspace`;
+/// constrained ones the directory of their constraint. Nothing under a
+/// `read_only` workspace is writable, whatever is granted: a closing deny
+/// rule overrides grants on the workspace or a directory above it.
+#[cfg(any(target_os = "macos", test))]
+fn sandbox_profile(executable: &Path, workspace: Option<&Path>, read_only: bool, grants: &[CapabilityGrant]) -> String {
+    let quote = |path: &Path| {
+        let path = path.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+        format!("\"{}\"", path)
+    };
+    let granted = |scope: &'static str, action: &'static str| {
+        let requested = Capability::new(scope, action);
+        grants
+            .iter()
+            .filter(move |grant| grant.is_valid() && grant.capability.matches(&requested))
+    };
+    let paths = |action: &'static str| -> Vec<PathBuf> {
+        granted("files", action)
+            .filter_map(|grant| match &grant.constraint {
+                Some(constraint) => Some(constraint.base_dir()),
+                None => workspace.map(Path::to_path_buf),
+            })
+            .filter(|path| !(read_only && action == "write" && workspace.is_some_and(|ws| path.starts_with(ws))))
+            .collect()
+    };
+
+    let mut rules = vec![
+        "(version 1)".to_string(),
+        "(deny default)".to_string(),
+        // System libraries and frameworks every process needs to start
+        "(import \"system.sb\")".to_string(),
+        format!("(allow process-exec (literal {}))", quote(executable)),
+        format!("(allow file-read* (literal {}))", quote(executable)),
+    ];
+    if granted("process", "spawn").next().is_some() {
+        rules.push("(allow process-fork process-exec)".to_string());
+    }
+    let writable = paths("write");
+    for (operation, dirs) in [("file-read*", [paths("read"), writable.clone()].concat()), ("file-write*", writable)] {
+        if !dirs.is_empty() {
+            let subpaths: Vec<String> = dirs.iter().map(|dir| format!("(subpath {})", quote(dir))).collect();
+            rules.push(format!("(allow {} {})", operation, subpaths.join(" ")));
+        }
     }
+    if granted("network", "connect").chain(granted("network", "http")).next().is_some() {
+        rules.push("(allow network-outbound)".to_string());
+    }
+    if granted("network", "listen").next().is_some() {
+        rules.push("(allow network-inbound network-bind)".to_string());
+    }
+    // The last matching rule wins, so this has to come after every allow
+    if let Some(ws) = workspace.filter(|_| read_only) {
+        rules.push(format!("(deny file-write* (subpath {}))", quote(ws)));
+    }
+    rules.join("\n")
+}
+
+/// Write `profile` to a new temp file for `sandbox-exec -f`, removed when
+/// the returned path is dropped
+#[cfg(target_os = "macos")]
+fn write_profile(profile: &str) -> Result<tempfile::TempPath> {
+    let mut file = tempfile::Builder::new().prefix("omniscient-agent-").suffix(".sb").tempfile()?;
+    file.write_all(profile.as_bytes())?;
+    Ok(file.into_temp_path())
 }
 
 impl Default for NativeRunner {
@@ -103,4 +509,90 @@ mod tests {
         // Basic construction test
         assert!(true);
     }
+
+    #[test]
+    fn test_sandbox_profile_allows_only_granted_access() {
+        use crate::agents::capabilities::{Capability, Constraint};
+
+        let agent = Path::new("/opt/agents/lint");
+        let workspace = Path::new("/Users/me/my \"project\"");
+        let grants = vec![
+            CapabilityGrant::new(Capability::new("files", "read"), None),
+            CapabilityGrant::new(Capability::new("files", "write"), None)
+                .with_constraint(Constraint::Glob("/tmp/lint/**/*.json".to_string())),
+            CapabilityGrant::new(Capability::new("network", "connect"), None),
+        ];
+
+        let profile = sandbox_profile(agent, Some(workspace), false, &grants);
+        assert!(profile.starts_with("(version 1)\n(deny default)"));
+        assert!(profile.contains("(allow process-exec (literal \"/opt/agents/lint\"))"));
+        assert!(profile.contains("(allow file-read* (literal \"/opt/agents/lint\"))"));
+        assert!(profile.contains(
+            "(allow file-read* (subpath \"/Users/me/my \\\"project\\\"\") (subpath \"/tmp/lint\"))"
+        ));
+        assert!(profile.contains("(allow file-write* (subpath \"/tmp/lint\"))"));
+        assert!(profile.contains("(allow network-outbound)"));
+        assert!(!profile.contains("network-inbound"));
+        assert!(!profile.contains("process-fork"));
+
+        // A read-only workspace stays unwritable even with files.write granted
+        let grants = vec![CapabilityGrant::new(Capability::new("files", "*"), None)];
+        let profile = sandbox_profile(agent, Some(workspace), true, &grants);
+        assert!(profile.contains("(allow file-read* (subpath"));
+        assert!(!profile.contains("(allow file-write*"));
+        assert!(profile.ends_with("(deny file-write* (subpath \"/Users/me/my \\\"project\\\"\"))"));
+
+        // So does one whose parent directory is writable
+        let grants = vec![CapabilityGrant::new(Capability::new("files", "write"), None)
+            .with_constraint(Constraint::Glob("/Users/me/**".to_string()))];
+        let profile = sandbox_profile(agent, Some(workspace), true, &grants);
+        let allow = profile.find("(allow file-write* (subpath \"/Users/me\"))").unwrap();
+        let deny = profile.find("(deny file-write* (subpath \"/Users/me/my").unwrap();
+        assert!(allow < deny);
+        assert!(!sandbox_profile(agent, None, false, &[]).contains("file-read* (subpath"));
+
+        let grants = vec![CapabilityGrant::new(Capability::new("network", "http"), None)];
+        assert!(sandbox_profile(agent, None, false, &grants).contains("(allow network-outbound)"));
+    }
+
+    async fn echo_through(mut handle: ProcessHandle) -> String {
+        let mut stdin = handle.take_stdin().unwrap();
+        let mut stdout = handle.take_stdout().unwrap();
+        assert!(handle.take_stdin().is_none());
+
+        stdin.write_all(b"{\"type\":\"input\"}\n").await.unwrap();
+        stdin.flush().await.unwrap();
+        drop(stdin);
+
+        let mut echoed = String::new();
+        stdout.read_to_string(&mut echoed).await.unwrap();
+        echoed
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stdin_round_trip() {
+        let runner = NativeRunner::new();
+        let handle = runner.spawn(Path::new("cat"), &[]).await.unwrap();
+        assert!(handle.id().is_some());
+        assert_eq!(echo_through(handle).await, "{\"type\":\"input\"}\n");
+
+        // Std children get the same async access
+        let child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        assert_eq!(echo_through(ProcessHandle::Std(child)).await, "{\"type\":\"input\"}\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_agent_with_limits_runs_without_cgroup_access() {
+        // Whether or not a cgroup can be created here, the agent still runs
+        let limits = ResourceLimits { cpu: "500m".to_string(), mem: "512Mi".to_string() };
+        let runner = NativeRunner::new().with_resource_limits(limits);
+        let handle = runner.spawn(Path::new("cat"), &[]).await.unwrap();
+        assert_eq!(echo_through(handle).await, "{\"type\":\"input\"}\n");
+    }
 }
@@ -6,7 +6,8 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::agents::manifest::{Manifest, SandboxMode};
+use crate::agents::manifest::{Manifest, SandboxMode, MANIFEST_FILE, SIGNATURE_FILE};
+use crate::utils::config::AgentsConfig;
 
 /// Agent information
 #[derive(Debug, Clone)]
@@ -14,22 +15,87 @@ pub struct AgentInfo {
     pub manifest: Manifest,
     pub base_dir: PathBuf,
     pub enabled: bool,
+    /// Why the agent was disabled by policy; cleared only by an explicit re-enable
+    pub disabled_reason: Option<String>,
 }
 
+/// Capabilities an updated agent requests beyond its previous manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilityChange {
+    pub agent: String,
+    pub previous_version: String,
+    pub version: String,
+    pub added: Vec<String>,
+}
+
+/// Alerts the user to a capability change, e.g. by queueing consent requests
+pub type CapabilityChangeHandler = Arc<dyn Fn(&CapabilityChange) + Send + Sync>;
+
 /// Agent registry
 pub struct AgentRegistry {
     agents: Arc<RwLock<HashMap<String, AgentInfo>>>,
+    /// Strict mode: `discover` only registers agents from these sources
+    trusted_sources: Option<Vec<String>>,
+    /// When set, only agents whose manifest one of these keys signed register
+    signing_keys: Option<Vec<String>>,
+    capability_change: Option<CapabilityChangeHandler>,
 }
 
 impl AgentRegistry {
     pub fn new() -> Self {
         AgentRegistry {
             agents: Arc::new(RwLock::new(HashMap::new())),
+            trusted_sources: None,
+            signing_keys: None,
+            capability_change: None,
+        }
+    }
+
+    /// Registry honouring `agents.trusted_sources` when `agents.strict_sources`
+    /// is set, and `agents.trusted_keys` when `agents.require_signed_agents` is
+    pub fn from_config(config: &AgentsConfig) -> Self {
+        let mut registry = Self::new();
+        if config.strict_sources {
+            registry = registry.with_trusted_sources(config.trusted_sources.clone());
+        }
+        if config.require_signed_agents {
+            registry = registry.with_required_signatures(config.trusted_keys.clone());
         }
+        registry
+    }
+
+    /// Strict mode: discover agents only from `sources`, which are
+    /// directories or install URL prefixes
+    pub fn with_trusted_sources(mut self, sources: Vec<String>) -> Self {
+        self.trusted_sources = Some(sources);
+        self
+    }
+
+    /// Register only agents with a `manifest.toml.sig` made by one of `keys`
+    /// (base64 ed25519 public keys)
+    pub fn with_required_signatures(mut self, keys: Vec<String>) -> Self {
+        self.signing_keys = Some(keys);
+        self
+    }
+
+    /// Called when a re-registered agent requests capabilities its previous
+    /// manifest didn't
+    pub fn on_capability_change<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&CapabilityChange) + Send + Sync + 'static,
+    {
+        self.capability_change = Some(Arc::new(handler));
+        self
     }
 
-    /// Register an agent from a directory
-    pub async fn register(&self, agent_dir: &Path) -> Result<()> {
+    /// Register an agent from a directory. Native agents are rejected
+    /// unless `config.native_allowed` names them, and agents are enabled
+    /// only if `config.enabled` names them or is empty.
+    ///
+    /// Returns the capabilities it requests that its previously registered
+    /// manifest didn't; these need the user's consent, while existing grants
+    /// are kept.
+    pub async fn register(&self, agent_dir: &Path, config: &AgentsConfig) -> Result<Vec<String>> {
         let manifest_path = agent_dir.join("manifest.toml");
         
         if !manifest_path.exists() {
@@ -39,17 +105,78 @@ impl AgentRegistry {
         let manifest = Manifest::load(&manifest_path)
             .with_context(|| format!("Failed to load agent manifest from {}", agent_dir.display()))?;
 
+        if manifest.sandbox == SandboxMode::Native && !config.native_allowed.contains(&manifest.name) {
+            tracing::warn!(
+                "Policy rejected native agent {} in {}: not in agents.native_allowed",
+                manifest.name,
+                agent_dir.display()
+            );
+            anyhow::bail!("Native agent {} is not in agents.native_allowed", manifest.name);
+        }
+        let listed = config.enabled.is_empty() || config.enabled.contains(&manifest.name);
+
+        if let Some(keys) = &self.signing_keys {
+            let sig_path = agent_dir.join(SIGNATURE_FILE);
+            let sig = std::fs::read_to_string(&sig_path)
+                .with_context(|| format!("Agent {} is not signed: no {}", manifest.name, sig_path.display()))?;
+            manifest
+                .verify_signature(&sig, keys)
+                .with_context(|| format!("Refusing to register agent from {}", agent_dir.display()))?;
+        }
+        // The signature covers the entry through its hash
+        if self.signing_keys.is_some() || manifest.entry_sha256.is_some() {
+            manifest
+                .verify_entry(agent_dir)
+                .with_context(|| format!("Refusing to register agent from {}", agent_dir.display()))?;
+        }
+
+        let mut agents = self.agents.write().await;
+
+        // Re-registering (e.g. on rediscovery) must not re-enable a policy-disabled agent
+        let disabled_reason = agents
+            .get(&manifest.name)
+            .and_then(|existing| existing.disabled_reason.clone());
+
+        let change = agents.get(&manifest.name).and_then(|existing| {
+            let added: Vec<String> = manifest
+                .capabilities
+                .iter()
+                .filter(|cap| !existing.manifest.capabilities.contains(cap))
+                .cloned()
+                .collect();
+            (!added.is_empty()).then(|| CapabilityChange {
+                agent: manifest.name.clone(),
+                previous_version: existing.manifest.version.clone(),
+                version: manifest.version.clone(),
+                added,
+            })
+        });
+
         let agent_info = AgentInfo {
             manifest: manifest.clone(),
             base_dir: agent_dir.to_path_buf(),
-            enabled: true,
+            enabled: listed && disabled_reason.is_none(),
+            disabled_reason,
         };
 
-        let mut agents = self.agents.write().await;
         agents.insert(manifest.name.clone(), agent_info);
 
+        drop(agents);
+
         tracing::info!("Registered agent: {} v{}", manifest.name, manifest.version);
-        Ok(())
+        let Some(change) = change else {
+            return Ok(Vec::new());
+        };
+        tracing::warn!(
+            "Agent {} v{} requests new capabilities: {}",
+            change.agent,
+            change.version,
+            change.added.join(", ")
+        );
+        if let Some(handler) = &self.capability_change {
+            handler(&change);
+        }
+        Ok(change.added)
     }
 
     /// Get an agent by name
@@ -64,19 +191,34 @@ impl AgentRegistry {
         agents.values().cloned().collect()
     }
 
-    /// Enable or disable an agent
+    /// Enable or disable an agent; enabling clears any policy disable
     pub async fn set_enabled(&self, name: &str, enabled: bool) -> Result<()> {
         let mut agents = self.agents.write().await;
         if let Some(info) = agents.get_mut(name) {
             info.enabled = enabled;
+            if enabled {
+                info.disabled_reason = None;
+            }
+            Ok(())
+        } else {
+            anyhow::bail!("Agent not found: {}", name)
+        }
+    }
+
+    /// Disable an agent by policy, recording why
+    pub async fn disable_with_reason(&self, name: &str, reason: impl Into<String>) -> Result<()> {
+        let mut agents = self.agents.write().await;
+        if let Some(info) = agents.get_mut(name) {
+            info.enabled = false;
+            info.disabled_reason = Some(reason.into());
             Ok(())
         } else {
             anyhow::bail!("Agent not found: {}", name)
         }
     }
 
-    /// Discover agents from a directory
-    pub async fn discover(&self, agents_dir: &Path) -> Result<()> {
+    /// Discover agents from a directory, registering each under `config`
+    pub async fn discover(&self, agents_dir: &Path, config: &AgentsConfig) -> Result<()> {
         if !agents_dir.exists() {
             tracing::warn!("Agents directory does not exist: {}", agents_dir.display());
             return Ok(());
@@ -92,8 +234,14 @@ impl AgentRegistry {
             if path.is_dir() {
                 let manifest_path = path.join("manifest.toml");
                 if manifest_path.exists() {
-                    match self.register(&path).await {
-                        Ok(()) => {},
+                    if let Some(sources) = &self.trusted_sources {
+                        if !is_trusted(sources, &path) {
+                            tracing::warn!("Ignoring agent in {}: not from a trusted source", path.display());
+                            continue;
+                        }
+                    }
+                    match self.register(&path, config).await {
+                        Ok(_) => {},
                         Err(e) => {
                             tracing::warn!("Failed to register agent in {}: {}", path.display(), e);
                         }
@@ -115,6 +263,31 @@ impl AgentRegistry {
     }
 }
 
+/// Whether the agent in `agent_dir` lives under a trusted directory or was
+/// installed from a trusted URL
+fn is_trusted(sources: &[String], agent_dir: &Path) -> bool {
+    let dir = agent_dir.canonicalize().unwrap_or_else(|_| agent_dir.to_path_buf());
+    let installed_from = || {
+        Manifest::load(&agent_dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|manifest| manifest.source)
+    };
+
+    sources.iter().any(|source| {
+        if source.contains("://") {
+            installed_from().is_some_and(|url| url.starts_with(source.as_str()))
+        } else {
+            let trusted = match source.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+                None => Some(PathBuf::from(source)),
+            };
+            trusted
+                .and_then(|trusted| trusted.canonicalize().ok())
+                .is_some_and(|trusted| dir.starts_with(trusted))
+        }
+    })
+}
+
 impl Default for AgentRegistry {
     fn default() -> Self {
         Self::new()
@@ -124,6 +297,7 @@ impl Default for AgentRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::config::Config;
 
     #[tokio::test]
     async fn test_registry_basic() {
@@ -131,4 +305,176 @@ mod tests {
         let agents = registry.list().await;
         assert_eq!(agents.len(), 0);
     }
+
+    fn write_agent(dir: &Path, name: &str, source: Option<&str>) {
+        write_manifest(dir, name, "0.1.0", &[], source);
+    }
+
+    fn write_manifest(dir: &Path, name: &str, version: &str, capabilities: &[&str], source: Option<&str>) {
+        std::fs::create_dir_all(dir).unwrap();
+        let source = source.map(|url| format!("source = \"{}\"\n", url)).unwrap_or_default();
+        let capabilities = capabilities.iter().map(|cap| format!("\"{}\"", cap)).collect::<Vec<_>>().join(", ");
+        std::fs::write(
+            dir.join(MANIFEST_FILE),
+            format!(
+                r#"schema_version = "0.1"
+name = "{}"
+version = "{}"
+entry = "agent.wasm"
+sandbox = "wasm"
+capabilities = [{}]
+{}
+[resources]
+cpu = "500m"
+mem = "256Mi"
+
+[ui]
+hints = []
+"#,
+                name, version, capabilities, source
+            ),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_strict_discovery_skips_untrusted_agents() {
+        let root = tempfile::tempdir().unwrap();
+        let trusted = root.path().join("trusted");
+        let agents = root.path().join("agents");
+        std::fs::create_dir_all(&trusted).unwrap();
+        std::fs::create_dir_all(&agents).unwrap();
+        write_agent(&trusted.join("inside"), "inside", None);
+        write_agent(&agents.join("outside"), "outside", None);
+        write_agent(&agents.join("fetched"), "fetched", Some("https://agents.example.com/fetched"));
+
+        let registry = AgentRegistry::new().with_trusted_sources(vec![
+            trusted.display().to_string(),
+            "https://agents.example.com/".to_string(),
+        ]);
+        let config = Config::default().agents;
+        registry.discover(&trusted, &config).await.unwrap();
+        registry.discover(&agents, &config).await.unwrap();
+
+        assert!(registry.get("inside").await.is_some());
+        assert!(registry.get("fetched").await.is_some());
+        assert!(registry.get("outside").await.is_none());
+
+        // Without strict mode every agent is discovered
+        let open = AgentRegistry::new();
+        open.discover(&agents, &config).await.unwrap();
+        assert!(open.get("outside").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_update_flags_newly_requested_capabilities() {
+        let config = Config::default().agents;
+        let dir = tempfile::tempdir().unwrap();
+        let changes = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = changes.clone();
+        let registry = AgentRegistry::new().on_capability_change(move |change| seen.lock().unwrap().push(change.clone()));
+
+        write_manifest(dir.path(), "notes", "1.0.0", &["files.read"], None);
+        assert!(registry.register(dir.path(), &config).await.unwrap().is_empty());
+
+        write_manifest(dir.path(), "notes", "2.0.0", &["files.read", "network.connect"], None);
+        assert_eq!(registry.register(dir.path(), &config).await.unwrap(), vec!["network.connect"]);
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![CapabilityChange {
+                agent: "notes".to_string(),
+                previous_version: "1.0.0".to_string(),
+                version: "2.0.0".to_string(),
+                added: vec!["network.connect".to_string()],
+            }]
+        );
+
+        // Re-registering the same manifest, or dropping a capability, flags nothing
+        assert!(registry.register(dir.path(), &config).await.unwrap().is_empty());
+        write_manifest(dir.path(), "notes", "2.1.0", &["network.connect"], None);
+        assert!(registry.register(dir.path(), &config).await.unwrap().is_empty());
+        assert_eq!(changes.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_agents_rejected_when_signatures_required() {
+        let config = Config::default().agents;
+        use base64::engine::general_purpose::STANDARD as BASE64;
+        use base64::Engine;
+        use ed25519_dalek::{Signer, SigningKey};
+        use sha2::{Digest, Sha256};
+
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let registry =
+            AgentRegistry::new().with_required_signatures(vec![BASE64.encode(key.verifying_key().as_bytes())]);
+        let dir = tempfile::tempdir().unwrap();
+        write_manifest(dir.path(), "notes", "1.0.0", &["files.read"], None);
+
+        let err = registry.register(dir.path(), &config).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("not signed"));
+
+        let sign = || {
+            let raw = std::fs::read(dir.path().join(MANIFEST_FILE)).unwrap();
+            std::fs::write(dir.path().join(SIGNATURE_FILE), BASE64.encode(key.sign(&raw).to_bytes())).unwrap();
+        };
+        // A signed manifest must pin its entry
+        sign();
+        let err = registry.register(dir.path(), &config).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("no entry_sha256"));
+
+        let module = b"(module)";
+        std::fs::write(dir.path().join("agent.wasm"), module).unwrap();
+        let hash: String = Sha256::digest(module).iter().map(|b| format!("{:02x}", b)).collect();
+        let manifest = std::fs::read_to_string(dir.path().join(MANIFEST_FILE)).unwrap();
+        let pinned = manifest.replace("sandbox = \"wasm\"", &format!("sandbox = \"wasm\"\nentry_sha256 = \"{}\"", hash));
+        std::fs::write(dir.path().join(MANIFEST_FILE), pinned).unwrap();
+        sign();
+        registry.register(dir.path(), &config).await.unwrap();
+        assert!(registry.get("notes").await.is_some());
+
+        // Swapping the entry breaks the chain even though the manifest still verifies
+        std::fs::write(dir.path().join("agent.wasm"), b"(module (memory 1))").unwrap();
+        let err = registry.register(dir.path(), &config).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("does not match the entry_sha256"));
+    }
+
+    #[tokio::test]
+    async fn test_registration_applies_enabled_and_native_allowed() {
+        let agents = tempfile::tempdir().unwrap();
+        write_agent(&agents.path().join("listed"), "listed", None);
+        write_agent(&agents.path().join("unlisted"), "unlisted", None);
+        for name in ["trusted-native", "rogue-native"] {
+            let dir = agents.path().join(name);
+            write_agent(&dir, name, None);
+            let manifest = std::fs::read_to_string(dir.join(MANIFEST_FILE)).unwrap();
+            std::fs::write(dir.join(MANIFEST_FILE), manifest.replace("sandbox = \"wasm\"", "sandbox = \"native\""))
+                .unwrap();
+        }
+
+        let config = AgentsConfig {
+            enabled: vec!["listed".to_string(), "trusted-native".to_string()],
+            native_allowed: vec!["trusted-native".to_string()],
+            ..Config::default().agents
+        };
+        let registry = AgentRegistry::new();
+        registry.discover(agents.path(), &config).await.unwrap();
+
+        assert!(registry.get("listed").await.unwrap().enabled);
+        assert!(!registry.get("unlisted").await.unwrap().enabled);
+        assert!(registry.get("trusted-native").await.unwrap().enabled);
+        assert!(registry.get("rogue-native").await.is_none());
+
+        // Registering directly applies the same policy
+        let direct = AgentRegistry::new();
+        let err = direct.register(&agents.path().join("rogue-native"), &config).await.unwrap_err();
+        assert!(err.to_string().contains("not in agents.native_allowed"));
+        direct.register(&agents.path().join("unlisted"), &config).await.unwrap();
+        assert!(!direct.get("unlisted").await.unwrap().enabled);
+
+        // An empty enabled list enables everything
+        let open = AgentRegistry::new();
+        open.discover(agents.path(), &Config::default().agents).await.unwrap();
+        assert!(open.get("unlisted").await.unwrap().enabled);
+        assert!(open.get("rogue-native").await.is_none());
+    }
 }
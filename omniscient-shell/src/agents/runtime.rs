@@ -1,19 +1,165 @@
 //! Agent runtime orchestration
 
 use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
 
-use crate::agents::manifest::Manifest;
-use crate::agents::capabilities::CapabilityManager;
-use crate::agents::event_protocol::Event;
-use crate::agents::wasm_host::WasmHost;
-use crate::agents::native_runner::NativeRunner;
+use crate::agents::access_guard::AccessGuard;
+use crate::agents::manifest::{Manifest, ResourceLimits};
+use crate::agents::capabilities::{Capability, CapabilityManager};
+use crate::agents::consent_queue::{ConsentQueue, PendingConsent};
+use crate::agents::event_protocol::{Event, EventType};
+use crate::utils::errors::{OmniError, RecoveryAction};
+use crate::agents::event_stream::{Emitter, EventRecorder, EventStream};
+use crate::agents::wasm_host::{preopens_for, WasmHost, WasmRun, FUEL_PER_MILLICORE};
+use crate::agents::native_runner::{ChildReader, NativeRunner, ProcessHandle};
+use crate::agents::registry::AgentInfo;
+use crate::agents::watchdog::{AgentWatchdog, Violation};
+use crate::oauth::consent::ConsentLedger;
+use crate::platform::process::sample_process;
+use crate::shell::process_supervision::{ProcessState, RestartPolicy, Supervisor};
+use crate::utils::config::{AgentsConfig, Config};
+
+/// An agent that must be stopped when the shell exits
+enum RunningAgent {
+    Native {
+        handle: ProcessHandle,
+        /// Time and CPU total of the previous usage sample
+        last_sample: Option<(Instant, Duration)>,
+    },
+    Wasm {
+        /// Interrupt flag the WASM host checks between guest calls
+        interrupt: Arc<AtomicBool>,
+        fuel_consumed: u64,
+    },
+    /// A native agent run by the supervisor, which restarts it if it crashes
+    Supervised {
+        /// Time and CPU total of the previous usage sample
+        last_sample: Option<(Instant, Duration)>,
+    },
+}
+
+/// Resource usage of a running agent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceUsage {
+    /// Resident memory of a native agent
+    pub memory_bytes: u64,
+    /// Total CPU time of a native agent
+    pub cpu_time: Duration,
+    /// CPU use since the previous sample, in percent of one core
+    pub cpu_percent: f32,
+    /// Fuel a WASM agent has consumed; None for native agents
+    pub fuel_consumed: Option<u64>,
+}
+
+impl ResourceUsage {
+    /// Whether usage is above the manifest's memory or CPU limit
+    pub fn exceeds(&self, limits: &ResourceLimits) -> bool {
+        let over_memory = limits
+            .parse_mem()
+            .is_ok_and(|limit| self.memory_bytes > limit);
+        let over_cpu = limits
+            .parse_cpu()
+            .is_ok_and(|limit| self.cpu_percent * 10.0 > limit as f32);
+        over_memory || over_cpu
+    }
+}
+
+/// Bytes that have flowed to and from an agent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoStats {
+    /// Prompt bytes of every input event
+    pub input_bytes: u64,
+    /// Data bytes of every output event
+    pub output_bytes: u64,
+    /// Output of the current (or last) run, which the output cap applies to
+    pub run_output_bytes: u64,
+}
+
+impl IoStats {
+    /// Count `event` if it is input or output; returns the bytes counted
+    fn count(&mut self, event: &Event) -> u64 {
+        match &event.event_type {
+            EventType::Input(input) => {
+                let bytes = input.prompt.len() as u64;
+                self.input_bytes += bytes;
+                bytes
+            }
+            EventType::Output(output) => {
+                let bytes = output.data.len() as u64;
+                self.output_bytes += bytes;
+                self.run_output_bytes += bytes;
+                bytes
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// How an agent run ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentStatus {
+    /// Ran to completion with every capability it asked for
+    Completed,
+    /// Ran to completion, but without capabilities that weren't granted
+    Restricted,
+    /// Failed or was stopped, e.g. by the output cap
+    Failed(String),
+}
+
+/// Everything a caller or the UI needs to know about one agent run
+#[derive(Debug, Clone)]
+pub struct ExecutionResult {
+    /// Events published during the run
+    pub events: Vec<Event>,
+    pub status: AgentStatus,
+    pub duration: Duration,
+    /// Capabilities the manifest asks for that weren't granted; a consent
+    /// request was raised for each
+    pub denied_capabilities: Vec<String>,
+    /// Usage sampled as the run ended; None if it couldn't be
+    pub usage: Option<ResourceUsage>,
+}
+
+/// Time running agents get to exit when the shell quits
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Agents stopped by `AgentRuntime::shutdown`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Exited (or were interrupted) within the grace period
+    pub stopped: Vec<String>,
+    /// Killed after the grace period ran out
+    pub killed: Vec<String>,
+}
 
 /// Agent runtime for executing agents
 pub struct AgentRuntime {
     capability_manager: Arc<CapabilityManager>,
     wasm_host: Arc<WasmHost>,
     native_runner: Arc<NativeRunner>,
+    /// Restarts native agents that crash
+    supervisor: Supervisor,
+    events: EventStream,
+    running: Mutex<HashMap<String, RunningAgent>>,
+    ledger: Arc<ConsentLedger>,
+    consents: Arc<ConsentQueue>,
+    io: Mutex<HashMap<String, IoStats>>,
+    /// Output one run may produce before the agent is stopped; 0 is unlimited
+    output_cap: u64,
+    /// Exposed to WASM agents with an unconstrained file grant
+    workspace_root: PathBuf,
+    /// Policy from `[agents]`, e.g. which agents get a read-only workspace
+    config: AgentsConfig,
+    /// Told about denied host calls and crashes; may auto-disable the agent
+    watchdog: Option<Arc<AgentWatchdog>>,
 }
 
 impl AgentRuntime {
@@ -26,50 +172,507 @@ impl AgentRuntime {
             capability_manager,
             wasm_host,
             native_runner,
+            supervisor: Supervisor::new(RestartPolicy::default()),
+            events: EventStream::default(),
+            running: Mutex::new(HashMap::new()),
+            ledger: Arc::new(ConsentLedger::new()),
+            consents: Arc::new(ConsentQueue::new()),
+            io: Mutex::new(HashMap::new()),
+            output_cap: 0,
+            workspace_root: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            config: Config::default().agents,
+            watchdog: None,
         })
     }
 
-    /// Execute an agent
-    pub async fn execute(&self, manifest: &Manifest, input: &str) -> Result<Vec<Event>> {
+    /// Apply `[agents]`: its output cap, restart policy and read-only list
+    pub fn with_agents_config(self, config: &AgentsConfig) -> Self {
+        let mut runtime = self
+            .with_output_cap(config.max_output_bytes)
+            .with_restart_policy(RestartPolicy::from_config(&config.restart));
+        runtime.config = config.clone();
+        runtime
+    }
+
+    /// Stop an agent whose run outputs more than `bytes`; 0 (the default)
+    /// means no limit. Usually `agents.max_output_bytes`.
+    pub fn with_output_cap(mut self, bytes: u64) -> Self {
+        self.output_cap = bytes;
+        self
+    }
+
+    /// Restart crashed native agents under `policy`, usually from `agents.restart`
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.supervisor = Supervisor::new(policy);
+        self
+    }
+
+    /// Expose `root` rather than the current directory as the workspace
+    pub fn with_workspace(mut self, root: impl Into<PathBuf>) -> Self {
+        self.workspace_root = root.into();
+        self
+    }
+
+    /// Check grants against `manager`, e.g. one persisted with
+    /// `CapabilityManager::new_persistent`, rather than a private one
+    pub fn with_capability_manager(mut self, manager: Arc<CapabilityManager>) -> Self {
+        self.capability_manager = manager;
+        self
+    }
+
+    /// Record shutdowns in `ledger` rather than a private one
+    pub fn with_ledger(mut self, ledger: Arc<ConsentLedger>) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    /// Report every agent's denied host calls and crashes to `watchdog`
+    pub fn with_watchdog(mut self, watchdog: Arc<AgentWatchdog>) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Track a native agent process so shutdown can stop it
+    pub async fn track_process(&self, agent_id: impl Into<String>, handle: ProcessHandle) {
+        let agent_id = agent_id.into();
+        self.begin_run(&agent_id).await;
+        self.running
+            .lock()
+            .await
+            .insert(agent_id, RunningAgent::Native { handle, last_sample: None });
+    }
+
+    /// Track a WASM agent; the returned flag is set when it must stop
+    pub async fn track_wasm(&self, agent_id: impl Into<String>) -> Arc<AtomicBool> {
+        let agent_id = agent_id.into();
+        self.begin_run(&agent_id).await;
+        let interrupt = Arc::new(AtomicBool::new(false));
+        self.running
+            .lock()
+            .await
+            .insert(
+                agent_id,
+                RunningAgent::Wasm { interrupt: interrupt.clone(), fuel_consumed: 0 },
+            );
+        interrupt
+    }
+
+    /// Add fuel a WASM agent consumed in a guest call
+    pub async fn record_fuel(&self, agent_id: &str, fuel: u64) {
+        if let Some(RunningAgent::Wasm { fuel_consumed, .. }) = self.running.lock().await.get_mut(agent_id) {
+            *fuel_consumed += fuel;
+        }
+    }
+
+    /// Sample the resource usage of a running agent; None if it isn't running
+    pub async fn agent_usage(&self, agent_id: &str) -> Option<ResourceUsage> {
+        let mut running = self.running.lock().await;
+        match running.get_mut(agent_id)? {
+            RunningAgent::Native { handle, last_sample } => sample_native(agent_id, handle.id(), last_sample),
+            RunningAgent::Supervised { last_sample } => {
+                let status = self.supervisor.status().await.into_iter().find(|s| s.name == agent_id)?;
+                sample_native(agent_id, status.pid.filter(|_| status.state == ProcessState::Running), last_sample)
+            }
+            RunningAgent::Wasm { fuel_consumed, .. } => Some(ResourceUsage {
+                fuel_consumed: Some(*fuel_consumed),
+                ..ResourceUsage::default()
+            }),
+        }
+    }
+
+    /// Bytes sent to and received from an agent so far
+    pub async fn io_stats(&self, agent_id: &str) -> IoStats {
+        self.io.lock().await.get(agent_id).copied().unwrap_or_default()
+    }
+
+    /// Start counting a new run of `agent_id` against the output cap
+    async fn begin_run(&self, agent_id: &str) {
+        self.io.lock().await.entry(agent_id.to_string()).or_default().run_output_bytes = 0;
+    }
+
+    /// Count an event streamed to or from a running agent and publish it.
+    ///
+    /// If the output pushes the run past the output cap, the agent is
+    /// stopped, an error event published in place of the output, and an
+    /// `OmniError::Agent` returned.
+    pub async fn publish(&self, event: Event) -> Result<()> {
+        let run_output = {
+            let mut io = self.io.lock().await;
+            let stats = io.entry(event.agent_id.clone()).or_default();
+            stats.count(&event);
+            stats.run_output_bytes
+        };
+        if self.output_cap == 0 || run_output <= self.output_cap {
+            self.events.publish(event);
+            return Ok(());
+        }
+
+        let agent_id = event.agent_id;
+        tracing::warn!(
+            "Agent {} produced {} bytes of output, over the {} byte cap; stopping it",
+            agent_id,
+            run_output,
+            self.output_cap
+        );
+        self.stop(&agent_id).await;
+        let message = format!("Output cap of {} bytes exceeded", self.output_cap);
+        self.events
+            .publish(Event::error(agent_id.clone(), "output_cap", message.clone(), event.sequence));
+        Err(OmniError::agent(
+            format!("Agent {} stopped: {}", agent_id, message),
+            Some("Raise agents.max_output_bytes, or set it to 0 to remove the cap".to_string()),
+            RecoveryAction::None,
+        )
+        .into())
+    }
+
+    /// Kill or interrupt one running agent
+    async fn stop(&self, agent_id: &str) {
+        let Some(agent) = self.running.lock().await.remove(agent_id) else {
+            return;
+        };
+        match agent {
+            RunningAgent::Native { mut handle, .. } => {
+                if let Err(e) = handle.kill() {
+                    tracing::debug!("Failed to kill agent {}: {}", agent_id, e);
+                }
+                let _ = handle.wait().await;
+            }
+            RunningAgent::Wasm { interrupt, .. } => interrupt.store(true, Ordering::SeqCst),
+            RunningAgent::Supervised { .. } => self.supervisor.stop(agent_id).await,
+        }
+    }
+
+    /// IDs of agents currently tracked as running
+    pub async fn running_agents(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.running.lock().await.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Stop every running agent.
+    ///
+    /// Native agents, supervised or not, are asked to exit and get `grace`
+    /// to do so before being killed; WASM instances are interrupted. Each
+    /// stop is recorded in the ledger.
+    pub async fn shutdown(&self, grace: Duration) -> Result<ShutdownReport> {
+        let running: Vec<(String, RunningAgent)> = self.running.lock().await.drain().collect();
+        let mut report = ShutdownReport::default();
+        if running.is_empty() {
+            return Ok(report);
+        }
+        tracing::info!("Stopping {} running agents", running.len());
+
+        let mut processes = Vec::new();
+        let mut supervised = Vec::new();
+        for (agent_id, agent) in running {
+            match agent {
+                RunningAgent::Native { mut handle, .. } => {
+                    if let Err(e) = handle.terminate() {
+                        tracing::debug!("Failed to signal agent {}: {}", agent_id, e);
+                    }
+                    processes.push((agent_id, handle));
+                }
+                RunningAgent::Wasm { interrupt, .. } => {
+                    interrupt.store(true, Ordering::SeqCst);
+                    report.stopped.push(agent_id);
+                }
+                RunningAgent::Supervised { .. } => supervised.push(agent_id),
+            }
+        }
+
+        let deadline = Instant::now() + grace;
+        let native = async {
+            let mut stopped = Vec::new();
+            let mut killed = Vec::new();
+            for (agent_id, mut handle) in processes {
+                match tokio::time::timeout_at(deadline, handle.wait()).await {
+                    Ok(_) => stopped.push(agent_id),
+                    Err(_) => {
+                        tracing::warn!("Agent {} did not exit within {:?}; killing it", agent_id, grace);
+                        if let Err(e) = handle.kill() {
+                            tracing::debug!("Failed to kill agent {}: {}", agent_id, e);
+                        }
+                        let _ = handle.wait().await;
+                        killed.push(agent_id);
+                    }
+                }
+            }
+            (stopped, killed)
+        };
+        let ((stopped, killed), supervised_killed) =
+            tokio::join!(native, self.supervisor.stop_within(&supervised, grace));
+        report.stopped.extend(stopped);
+        report.killed.extend(killed);
+        for agent_id in supervised {
+            if supervised_killed.contains(&agent_id) {
+                tracing::warn!("Agent {} did not exit within {:?}; killed it", agent_id, grace);
+                report.killed.push(agent_id);
+            } else {
+                report.stopped.push(agent_id);
+            }
+        }
+
+        for agent_id in &report.stopped {
+            self.ledger.log_shutdown(agent_id.clone(), false).await?;
+        }
+        for agent_id in &report.killed {
+            self.ledger.log_shutdown(agent_id.clone(), true).await?;
+        }
+        Ok(report)
+    }
+
+    /// Execute a registered agent, reporting how the run ended alongside its
+    /// events. The entry runs from the directory the registry loaded (and
+    /// verified) it from.
+    pub async fn execute(&self, agent: &AgentInfo, input: &str) -> Result<ExecutionResult> {
+        let manifest = &agent.manifest;
+        let started = Instant::now();
+        let mut denied_capabilities = Vec::new();
+
         // Check capabilities
         for cap_str in &manifest.capabilities {
             let cap = crate::agents::capabilities::Capability::parse(cap_str)?;
-            if !self.capability_manager.check(&cap).await {
-                tracing::warn!("Capability not granted: {}", cap_str);
-                // In a real implementation, this would request consent
+            if !self.capability_manager.check_for(&manifest.name, &cap).await {
+                let description = cap.describe();
+                tracing::warn!(
+                    "Capability not granted: {} ({}; {} risk)",
+                    cap_str,
+                    description.summary,
+                    description.risk.label()
+                );
+                self.request_consent(&manifest.name, cap_str, &description.summary, None).await;
+                denied_capabilities.push(cap_str.clone());
             }
         }
 
-        // Execute based on sandbox mode
-        if manifest.requires_native() {
-            self.execute_native(manifest, input).await
-        } else {
-            self.execute_wasm(manifest, input).await
+        // Execute based on sandbox mode, publishing events as they arrive
+        self.begin_run(&manifest.name).await;
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let run = async {
+            if manifest.requires_native() {
+                self.execute_native(agent, input, sender).await
+            } else {
+                self.execute_wasm(agent, input, sender).await
+            }
+        };
+        let publish = async {
+            let mut events = Vec::new();
+            while let Some(event) = receiver.recv().await {
+                if let Err(e) = self.publish(event.clone()).await {
+                    return (events, Some(e.to_string()));
+                }
+                events.push(event);
+            }
+            (events, None)
+        };
+        let (run, (events, publish_failure)) = tokio::join!(run, publish);
+        let usage = self.agent_usage(&manifest.name).await;
+        self.running.lock().await.remove(&manifest.name);
+        let failure = publish_failure.or_else(|| run.err().map(|e| e.to_string()));
+
+        let status = match failure {
+            Some(reason) => {
+                tracing::warn!("Agent {} failed: {}", manifest.name, reason);
+                AgentStatus::Failed(reason)
+            }
+            None if denied_capabilities.is_empty() => AgentStatus::Completed,
+            None => AgentStatus::Restricted,
+        };
+        Ok(ExecutionResult {
+            events,
+            status,
+            duration: started.elapsed(),
+            denied_capabilities,
+            usage,
+        })
+    }
+
+    /// Execute an agent, returning only its events; a failed run is an error
+    pub async fn execute_events(&self, agent: &AgentInfo, input: &str) -> Result<Vec<Event>> {
+        let result = self.execute(agent, input).await?;
+        match result.status {
+            AgentStatus::Failed(reason) => Err(anyhow::anyhow!(reason)),
+            _ => Ok(result.events),
         }
     }
 
-    async fn execute_wasm(&self, manifest: &Manifest, input: &str) -> Result<Vec<Event>> {
+    /// Execute an agent and record its events to `recording` for later replay
+    pub async fn execute_recorded(
+        &self,
+        agent: &AgentInfo,
+        input: &str,
+        recording: &Path,
+    ) -> Result<Vec<Event>> {
+        let mut recorder = EventRecorder::create(recording)?;
+        let events = self.execute_events(agent, input).await?;
+        for event in &events {
+            recorder.record(event)?;
+        }
+        let count = recorder.finish()?;
+        tracing::info!("Recorded {} events from {} to {}", count, agent.manifest.name, recording.display());
+        Ok(events)
+    }
+
+    async fn execute_wasm(&self, agent: &AgentInfo, input: &str, events: UnboundedSender<Event>) -> Result<()> {
+        let manifest = &agent.manifest;
         tracing::info!("Executing WASM agent: {}", manifest.name);
-        
-        // Placeholder - real implementation would:
-        // 1. Load WASM module
-        // 2. Setup WASI context with capability restrictions
-        // 3. Execute with input
-        // 4. Stream output events
-        
-        Ok(vec![Event::input("wasm-agent", input.to_string(), 0)])
+
+        let grants = self.capability_manager.grants_for(&manifest.name).await;
+        verify_entry(agent)?;
+        let run = WasmRun {
+            agent_id: manifest.name.clone(),
+            module: manifest.entry_path(&agent.base_dir),
+            input: input.to_string(),
+            preopens: preopens_for(&grants, &self.workspace_root, manifest.workspace_read_only(&self.config)),
+            memory_bytes: manifest.resources.parse_mem()?,
+            fuel: manifest.resources.parse_cpu()? as u64 * FUEL_PER_MILLICORE,
+            interrupt: self.track_wasm(&manifest.name).await,
+            guard: Arc::new(self.access_guard(manifest)),
+        };
+        let _ = events.send(Event::input(manifest.name.clone(), input.to_string(), 0));
+
+        let host = self.wasm_host.clone();
+        let fuel = tokio::task::spawn_blocking(move || host.run(run, events)).await??;
+        self.record_fuel(&manifest.name, fuel).await;
+        Ok(())
     }
 
-    async fn execute_native(&self, manifest: &Manifest, input: &str) -> Result<Vec<Event>> {
+    async fn execute_native(&self, agent: &AgentInfo, input: &str, events: UnboundedSender<Event>) -> Result<()> {
+        let manifest = &agent.manifest;
         tracing::info!("Executing native agent: {}", manifest.name);
-        
-        // Placeholder - real implementation would:
-        // 1. Spawn isolated subprocess
-        // 2. Setup IPC channels
-        // 3. Send input via stdin
-        // 4. Stream output events from stdout
-        
-        Ok(vec![Event::input("native-agent", input.to_string(), 0)])
+
+        let grants = self.capability_manager.grants_for(&manifest.name).await;
+        let mut runner = (*self.native_runner)
+            .clone()
+            .with_resource_limits(manifest.resources.clone())
+            .with_grants(&self.workspace_root, grants);
+        if manifest.workspace_read_only(&self.config) {
+            runner = runner.with_read_only_workspace(&self.workspace_root);
+        }
+        let executable = manifest.entry_path(&agent.base_dir);
+
+        // The agent reads its input as one event line and answers on stdout.
+        // A restarted agent gets the same input; its output continues the run's.
+        let input = Event::input(manifest.name.clone(), input.to_string(), 0);
+        let input_line = format!("{}\n", input.to_json()?);
+        let _ = events.send(input);
+        let emitter = Arc::new(std::sync::Mutex::new(Emitter::new(manifest.name.clone(), events)));
+        let readers = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let spawn = {
+            let (emitter, readers) = (emitter.clone(), readers.clone());
+            move || {
+                let (runner, executable, input_line) = (runner.clone(), executable.clone(), input_line.clone());
+                let (emitter, readers) = (emitter.clone(), readers.clone());
+                async move {
+                    let mut handle = runner.spawn(&executable, &[]).await?;
+                    if let Some(mut stdin) = handle.take_stdin() {
+                        // An agent that exits without reading its input is not a failed start
+                        let _ = stdin.write_all(input_line.as_bytes()).await;
+                    }
+                    if let Some(stdout) = handle.take_stdout() {
+                        readers.lock().unwrap().push(tokio::spawn(forward_stdout(stdout, emitter)));
+                    }
+                    Ok(handle)
+                }
+            }
+        };
+        verify_entry(agent)?;
+        self.running
+            .lock()
+            .await
+            .insert(manifest.name.clone(), RunningAgent::Supervised { last_sample: None });
+        self.supervisor.supervise(&manifest.name, spawn).await;
+        let state = self.supervisor.wait(&manifest.name).await;
+
+        let readers: Vec<_> = readers.lock().unwrap().drain(..).collect();
+        for reader in readers {
+            let _ = reader.await;
+        }
+        emitter.lock().unwrap().output(Vec::new(), true);
+        self.report_crashes(&manifest.name).await;
+
+        if !self.running.lock().await.contains_key(&manifest.name) {
+            anyhow::bail!("Native agent {} was stopped", manifest.name);
+        }
+        match state {
+            Some(ProcessState::Dead) => anyhow::bail!("Native agent {} kept crashing and was given up on", manifest.name),
+            _ => Ok(()),
+        }
+    }
+
+    /// Tell the watchdog how often a supervised agent crashed during its
+    /// run: once before each restart, and once more if it was given up on
+    async fn report_crashes(&self, agent_id: &str) {
+        let Some(watchdog) = &self.watchdog else {
+            return;
+        };
+        let Some(status) = self.supervisor.status().await.into_iter().find(|s| s.name == agent_id) else {
+            return;
+        };
+        let crashes = status.restarts + u32::from(status.state == ProcessState::Dead);
+        for _ in 0..crashes {
+            if let Err(e) = watchdog.record(agent_id, Violation::Crash).await {
+                tracing::warn!("Failed to record crash of agent {}: {}", agent_id, e);
+            }
+        }
+    }
+
+    /// Guard checking `manifest`'s agent's privileged calls against its grants
+    fn access_guard(&self, manifest: &Manifest) -> AccessGuard {
+        let mut guard = AccessGuard::new(&manifest.name, self.capability_manager.clone(), self.ledger.clone());
+        if let Some(watchdog) = &self.watchdog {
+            guard = guard.with_watchdog(watchdog.clone());
+        }
+        if manifest.workspace_read_only(&self.config) {
+            guard.read_only()
+        } else {
+            guard
+        }
+    }
+
+    /// Live stream of every event agents produce
+    pub fn event_stream(&self) -> EventStream {
+        self.events.clone()
+    }
+
+    /// Ask the user for `capability` on behalf of `agent_id`; publishes a
+    /// consent request event and returns its request id
+    pub async fn request_consent(
+        &self,
+        agent_id: &str,
+        capability: &str,
+        reason: &str,
+        duration_s: Option<u64>,
+    ) -> String {
+        let (request_id, event) = self.consents.request(agent_id, capability, reason, duration_s).await;
+        self.events.publish(event);
+        request_id
+    }
+
+    /// Apply a grant or deny event to the pending request it answers,
+    /// granting the capability to the requesting agent on approval; None if
+    /// nothing was pending
+    pub async fn answer_consent(&self, answer: Event) -> Result<Option<PendingConsent>> {
+        let resolved = self.consents.resolve(&answer, &self.ledger).await?;
+        if let (Some(pending), EventType::ConsentGrant(_)) = (&resolved, &answer.event_type) {
+            self.capability_manager
+                .grant_to(
+                    &pending.agent_id,
+                    Capability::parse(&pending.capability)?,
+                    pending.duration_s.map(Duration::from_secs),
+                )
+                .await?;
+        }
+        if resolved.is_some() {
+            self.events.publish(answer);
+        }
+        Ok(resolved)
+    }
+
+    pub fn consent_queue(&self) -> Arc<ConsentQueue> {
+        self.consents.clone()
     }
 
     pub fn capability_manager(&self) -> Arc<CapabilityManager> {
@@ -77,6 +680,58 @@ impl AgentRuntime {
     }
 }
 
+/// Check a pinned entry against its `entry_sha256` again right before it
+/// runs: registration checked it, but the file may have changed since
+fn verify_entry(agent: &AgentInfo) -> Result<()> {
+    match &agent.manifest.entry_sha256 {
+        Some(_) => agent.manifest.verify_entry(&agent.base_dir),
+        None => Ok(()),
+    }
+}
+
+/// Usage of native agent process `pid`, with CPU use since `last_sample`
+fn sample_native(agent_id: &str, pid: Option<u32>, last_sample: &mut Option<(Instant, Duration)>) -> Option<ResourceUsage> {
+    let sample = match sample_process(pid?) {
+        Ok(sample) => sample,
+        Err(e) => {
+            tracing::debug!("Failed to sample agent {}: {}", agent_id, e);
+            return None;
+        }
+    };
+
+    let now = Instant::now();
+    let cpu_percent = match last_sample.replace((now, sample.cpu_time)) {
+        Some((then, cpu_then)) if now > then => {
+            let cpu = sample.cpu_time.saturating_sub(cpu_then).as_secs_f32();
+            cpu / (now - then).as_secs_f32() * 100.0
+        }
+        _ => 0.0,
+    };
+
+    Some(ResourceUsage {
+        memory_bytes: sample.memory_bytes,
+        cpu_time: sample.cpu_time,
+        cpu_percent,
+        fuel_consumed: None,
+    })
+}
+
+/// Send each line a native agent writes to stdout through `emitter`
+async fn forward_stdout(stdout: ChildReader, emitter: Arc<std::sync::Mutex<Emitter>>) {
+    let mut stdout = BufReader::new(stdout);
+    let mut line = Vec::new();
+    while stdout.read_until(b'\n', &mut line).await.is_ok_and(|n| n > 0) {
+        emitter.lock().unwrap().line(&line);
+        line.clear();
+    }
+}
+
+/// Agents directory under `~/.omniscient`
+pub fn default_agents_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".omniscient").join("agents")
+}
+
 impl Default for AgentRuntime {
     fn default() -> Self {
         Self::new().expect("Failed to create AgentRuntime")
@@ -92,4 +747,427 @@ mod tests {
         let runtime = AgentRuntime::new();
         assert!(runtime.is_ok());
     }
+
+    /// Emits its input back as one output chunk
+    const ECHO_WAT: &str = r#"
+(module
+  (import "omni" "emit" (func $emit (param i32 i32)))
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) i32.const 1024)
+  (func (export "run") (param $ptr i32) (param $len i32) (result i32)
+    (call $emit (local.get $ptr) (local.get $len))
+    i32.const 0))
+"#;
+
+    /// A WASM agent registered from `dir`, whose module, `wat`, is saved there
+    fn wat_agent(dir: &Path, name: &str, wat: &str) -> AgentInfo {
+        let entry = dir.join(format!("{}.wat", name));
+        std::fs::write(&entry, wat).unwrap();
+        let manifest = toml::from_str(&format!(
+            r#"
+schema_version = "0.1"
+name = "{}"
+version = "0.1.0"
+entry = "{}"
+sandbox = "wasm"
+capabilities = []
+oauth_scopes = []
+
+[resources]
+cpu = "500m"
+mem = "512Mi"
+
+[ui]
+hints = []
+"#,
+            name,
+            entry.display()
+        ))
+        .unwrap();
+        AgentInfo {
+            manifest,
+            base_dir: dir.to_path_buf(),
+            enabled: true,
+            disabled_reason: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agents_listed_read_only_cannot_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let auditor = wat_agent(dir.path(), "auditor", ECHO_WAT);
+        let editor = wat_agent(dir.path(), "editor", ECHO_WAT);
+        let config = AgentsConfig { read_only: vec!["auditor".to_string()], ..Config::default().agents };
+        let runtime = AgentRuntime::new().unwrap().with_agents_config(&config);
+        for agent in ["auditor", "editor"] {
+            runtime.capability_manager().grant_to(agent, Capability::new("files", "write"), None).await.unwrap();
+        }
+
+        assert!(!runtime.access_guard(&auditor.manifest).check_host_call("path_unlink_file").await);
+        assert!(runtime.access_guard(&editor.manifest).check_host_call("path_unlink_file").await);
+    }
+
+    #[tokio::test]
+    async fn test_agent_run_streams_ndjson() {
+        use crate::agents::event_stream::write_ndjson;
+
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = wat_agent(dir.path(), "echo", ECHO_WAT);
+
+        let runtime = AgentRuntime::new().unwrap();
+        let mut events = runtime.event_stream().subscribe();
+        let returned = runtime.execute_events(&manifest, "hi").await.unwrap();
+        drop(runtime);
+
+        let mut out = Vec::new();
+        let written = write_ndjson(&mut events, &mut out).await.unwrap();
+        assert_eq!(written as usize, returned.len());
+
+        let text = String::from_utf8(out).unwrap();
+        for line in text.lines() {
+            let event = Event::from_json(line).unwrap();
+            assert_eq!(event.agent_id, "echo");
+        }
+
+        // The input, the emitted chunk, then the (empty) final chunk
+        assert_eq!(returned.len(), 3);
+        assert!(matches!(&returned[1].event_type, EventType::Output(o) if o.data == b"hi" && !o.complete));
+        assert!(matches!(&returned[2].event_type, EventType::Output(o) if o.complete));
+        let sequences: Vec<u64> = returned.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_wasm_run_records_fuel() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = wat_agent(dir.path(), "echo", ECHO_WAT);
+        let runtime = AgentRuntime::new().unwrap();
+
+        let result = runtime.execute(&manifest, "hi").await.unwrap();
+        let fuel = result.usage.unwrap().fuel_consumed.unwrap();
+        assert!(fuel > 0);
+        assert!(fuel < 500 * FUEL_PER_MILLICORE);
+        assert!(runtime.running_agents().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wasm_agent_limited_by_manifest_resources() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = AgentRuntime::new().unwrap();
+
+        let mut spinner = wat_agent(
+            dir.path(),
+            "spinner",
+            r#"(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) i32.const 0)
+  (func (export "run") (param i32 i32) (result i32) (loop br 0) i32.const 0))"#,
+        );
+        spinner.manifest.resources.cpu = "1m".to_string();
+        let result = runtime.execute(&spinner, "").await.unwrap();
+        assert!(matches!(&result.status, AgentStatus::Failed(reason) if reason.contains("ran out of fuel")));
+        assert!(runtime.running_agents().await.is_empty());
+
+        // 64 pages is 4MiB, more than the agent may use
+        let hungry = wat_agent(dir.path(), "hungry", &ECHO_WAT.replace("(memory (export \"memory\") 1)", "(memory (export \"memory\") 64)"));
+        let mut small = hungry.clone();
+        small.manifest.resources.mem = "1Mi".to_string();
+        assert!(matches!(runtime.execute(&small, "").await.unwrap().status, AgentStatus::Failed(_)));
+        assert_eq!(runtime.execute(&hungry, "").await.unwrap().status, AgentStatus::Completed);
+    }
+
+    /// A native agent running `script`, registered from `agents_dir/<name>`
+    #[cfg(unix)]
+    fn script_agent(agents_dir: &Path, name: &str, script: &str) -> AgentInfo {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = agents_dir.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let entry = dir.join("run.sh");
+        std::fs::write(&entry, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&entry, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let mut agent = wat_agent(agents_dir, name, ECHO_WAT);
+        agent.manifest.sandbox = crate::agents::manifest::SandboxMode::Native;
+        agent.manifest.entry = "run.sh".to_string();
+        agent.base_dir = dir;
+        agent
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_native_agent_runs_supervised_with_manifest_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = RestartPolicy {
+            max_retries: 2,
+            backoff: Duration::from_millis(5),
+            min_uptime: Duration::ZERO,
+            max_fast_crashes: 3,
+        };
+        let runtime = AgentRuntime::new().unwrap().with_restart_policy(policy);
+
+        let greeter = script_agent(dir.path(), "greeter", "read input; echo hello; echo \"$input\" | grep -q '\"prompt\":\"hi\"'");
+        let result = runtime.execute(&greeter, "hi").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert_eq!(result.events.len(), 3);
+        assert!(matches!(&result.events[1].event_type, EventType::Output(o) if o.data == b"hello\n"));
+        assert!(matches!(&result.events[2].event_type, EventType::Output(o) if o.complete));
+
+        // A crashing agent is restarted with the same input until given up on
+        let failing = script_agent(dir.path(), "failing", "echo attempt; exit 3");
+        let result = runtime.execute(&failing, "").await.unwrap();
+        assert!(matches!(&result.status, AgentStatus::Failed(reason) if reason.contains("given up on")));
+        let attempts = result
+            .events
+            .iter()
+            .filter(|e| matches!(&e.event_type, EventType::Output(o) if o.data == b"attempt\n"))
+            .count();
+        assert_eq!(attempts, 3);
+        assert!(runtime.running_agents().await.is_empty());
+    }
+
+    /// A registry holding `agent`, whose manifest is saved to its directory
+    async fn registry_with(agent: &AgentInfo) -> Arc<crate::agents::AgentRegistry> {
+        std::fs::write(agent.base_dir.join("manifest.toml"), toml::to_string(&agent.manifest).unwrap()).unwrap();
+        let config = AgentsConfig { native_allowed: vec![agent.manifest.name.clone()], ..Config::default().agents };
+        let registry = Arc::new(crate::agents::AgentRegistry::new());
+        registry.register(&agent.base_dir, &config).await.unwrap();
+        registry
+    }
+
+    fn watchdog(registry: Arc<crate::agents::AgentRegistry>, max_denied: u32, max_crashes: u32) -> Arc<AgentWatchdog> {
+        let policy = crate::agents::AutoDisablePolicy { max_denied, max_crashes, window: Duration::from_secs(600) };
+        Arc::new(AgentWatchdog::new(policy, registry, Arc::new(ConsentLedger::new()), None))
+    }
+
+    #[tokio::test]
+    async fn test_denied_host_calls_reach_the_watchdog() {
+        let dir = tempfile::tempdir().unwrap();
+        let agent = wat_agent(dir.path(), "prober", ECHO_WAT);
+        let registry = registry_with(&agent).await;
+        let runtime = AgentRuntime::new().unwrap().with_watchdog(watchdog(registry.clone(), 2, 0));
+
+        let guard = runtime.access_guard(&agent.manifest);
+        assert!(!guard.check_host_call("path_unlink_file").await);
+        assert!(registry.get("prober").await.unwrap().enabled);
+        assert!(!guard.check_host_call("path_unlink_file").await);
+        assert!(!registry.get("prober").await.unwrap().enabled);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_supervised_crashes_disable_the_agent() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = RestartPolicy {
+            max_retries: 2,
+            backoff: Duration::from_millis(5),
+            min_uptime: Duration::ZERO,
+            max_fast_crashes: 3,
+        };
+        let failing = script_agent(dir.path(), "failing", "exit 3");
+        let registry = registry_with(&failing).await;
+        let runtime = AgentRuntime::new()
+            .unwrap()
+            .with_restart_policy(policy)
+            .with_watchdog(watchdog(registry.clone(), 0, 3));
+
+        // Started three times, crashing each time
+        runtime.execute(&failing, "").await.unwrap();
+        let info = registry.get("failing").await.unwrap();
+        assert!(!info.enabled);
+        assert!(info.disabled_reason.unwrap().contains("3 crashes"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_entry_runs_from_registered_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = AgentRuntime::new().unwrap();
+
+        // Registered from a directory not named after the agent, next to one that is
+        let mut greeter = script_agent(dir.path(), "greeter-v2", "echo registered");
+        greeter.manifest.name = "greeter".to_string();
+        script_agent(dir.path(), "greeter", "echo impostor");
+
+        let result = runtime.execute(&greeter, "").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert!(matches!(&result.events[1].event_type, EventType::Output(o) if o.data == b"registered\n"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_entry_changed_after_registration_is_not_run() {
+        use sha2::{Digest, Sha256};
+
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = AgentRuntime::new().unwrap();
+        let mut pinned = script_agent(dir.path(), "pinned", "echo original");
+        let entry = pinned.manifest.entry_path(&pinned.base_dir);
+        let hash: String = Sha256::digest(std::fs::read(&entry).unwrap()).iter().map(|b| format!("{:02x}", b)).collect();
+        pinned.manifest.entry_sha256 = Some(hash);
+
+        let result = runtime.execute(&pinned, "").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+
+        std::fs::write(&entry, "#!/bin/sh\necho swapped\n").unwrap();
+        let result = runtime.execute(&pinned, "").await.unwrap();
+        assert!(matches!(&result.status, AgentStatus::Failed(reason) if reason.contains("does not match the entry_sha256")));
+        assert!(!result.events.iter().any(|e| matches!(&e.event_type, EventType::Output(o) if o.data == b"swapped\n")));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_stops_agents_within_grace() {
+        use crate::oauth::consent::ConsentAction;
+        use std::process::{Command, Stdio};
+        use tokio::io::AsyncReadExt;
+
+        let ledger = Arc::new(ConsentLedger::new());
+        let runtime = AgentRuntime::new().unwrap().with_ledger(ledger.clone());
+
+        let spawn = |script: &str| {
+            let child = Command::new("sh")
+                .args(["-c", script])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .unwrap();
+            ProcessHandle::Std(child)
+        };
+        runtime.track_process("sleeper", spawn("exec sleep 30")).await;
+
+        // Ignores SIGTERM, so it has to be killed; wait until the trap is set
+        let mut stubborn = spawn("trap '' TERM; echo ready; exec sleep 30");
+        let mut ready = [0u8; 6];
+        stubborn.take_stdout().unwrap().read_exact(&mut ready).await.unwrap();
+        runtime.track_process("stubborn", stubborn).await;
+        let interrupt = runtime.track_wasm("wasm-agent").await;
+        assert_eq!(runtime.running_agents().await.len(), 3);
+
+        let started = std::time::Instant::now();
+        let report = runtime.shutdown(Duration::from_millis(500)).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let mut stopped = report.stopped.clone();
+        stopped.sort();
+        assert_eq!(stopped, vec!["sleeper".to_string(), "wasm-agent".to_string()]);
+        assert_eq!(report.killed, vec!["stubborn".to_string()]);
+        assert!(interrupt.load(Ordering::SeqCst));
+        assert!(runtime.running_agents().await.is_empty());
+
+        assert!(matches!(
+            ledger.get_for_agent("stubborn").await[0].action,
+            ConsentAction::Shutdown { forced: true }
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_asks_supervised_agents_to_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ready, termed) = (dir.path().join("ready"), dir.path().join("termed"));
+        let script = format!(
+            "trap 'touch {}; exit 0' TERM; touch {}; while :; do sleep 0.05; done",
+            termed.display(),
+            ready.display()
+        );
+        let agent = script_agent(dir.path(), "polite", &script);
+        let runtime = AgentRuntime::new().unwrap();
+
+        let stop = async {
+            while !ready.exists() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            runtime.shutdown(Duration::from_secs(5)).await.unwrap()
+        };
+        let (result, report) = tokio::join!(runtime.execute(&agent, ""), stop);
+
+        assert_eq!(report.stopped, vec!["polite".to_string()]);
+        assert!(report.killed.is_empty());
+        assert!(termed.exists());
+        assert!(matches!(result.unwrap().status, AgentStatus::Failed(reason) if reason.contains("was stopped")));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_native_agent_reports_memory() {
+        let runtime = AgentRuntime::new().unwrap();
+        let handle = NativeRunner::new()
+            .spawn(std::path::Path::new("sleep"), &["30".to_string()])
+            .await
+            .unwrap();
+        runtime.track_process("sleeper", handle).await;
+
+        let usage = runtime.agent_usage("sleeper").await.unwrap();
+        assert!(usage.memory_bytes > 0);
+        assert_eq!(usage.fuel_consumed, None);
+
+        let limits = ResourceLimits { cpu: "500m".to_string(), mem: "512Mi".to_string() };
+        assert!(!usage.exceeds(&limits));
+        let heavy = ResourceUsage { memory_bytes: 600 << 20, ..usage.clone() };
+        assert!(heavy.exceeds(&limits));
+        assert!(runtime.agent_usage("missing").await.is_none());
+
+        runtime.track_wasm("wasm-agent").await;
+        runtime.record_fuel("wasm-agent", 1200).await;
+        let usage = runtime.agent_usage("wasm-agent").await.unwrap();
+        assert_eq!(usage.fuel_consumed, Some(1200));
+
+        runtime.shutdown(Duration::from_secs(1)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_output_past_cap_stops_agent() {
+        let runtime = AgentRuntime::new().unwrap().with_output_cap(10);
+        let mut events = runtime.event_stream().subscribe();
+        let interrupt = runtime.track_wasm("chatty").await;
+
+        runtime.publish(Event::input("chatty", "hello".to_string(), 0)).await.unwrap();
+        for sequence in 1..=2 {
+            let chunk = Event::output("chatty", sequence, "text/plain", b"abcd".to_vec(), false, sequence);
+            runtime.publish(chunk).await.unwrap();
+        }
+        let over = Event::output("chatty", 3, "text/plain", b"efgh".to_vec(), true, 3);
+        let err = runtime.publish(over).await.unwrap_err();
+        assert!(err.to_string().contains("Output cap of 10 bytes exceeded"));
+        assert!(interrupt.load(Ordering::SeqCst));
+        assert!(runtime.running_agents().await.is_empty());
+
+        let stats = runtime.io_stats("chatty").await;
+        assert_eq!(stats, IoStats { input_bytes: 5, output_bytes: 12, run_output_bytes: 12 });
+        assert_eq!(runtime.io_stats("quiet").await, IoStats::default());
+
+        // The output over the cap is replaced by an error event
+        let received: Vec<Event> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        assert_eq!(received.len(), 4);
+        assert!(matches!(&received[3].event_type, EventType::Error(e) if e.code == "output_cap"));
+
+        // A new run starts from zero against the cap, totals keep counting
+        runtime.track_wasm("chatty").await;
+        let chunk = Event::output("chatty", 0, "text/plain", b"abcd".to_vec(), true, 0);
+        runtime.publish(chunk).await.unwrap();
+        let stats = runtime.io_stats("chatty").await;
+        assert_eq!((stats.output_bytes, stats.run_output_bytes), (16, 4));
+    }
+
+    #[tokio::test]
+    async fn test_ungranted_capability_reported_in_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manifest = wat_agent(dir.path(), "fetcher", ECHO_WAT);
+        manifest.manifest.capabilities = vec!["files.read".to_string(), "network.http".to_string()];
+
+        let runtime = AgentRuntime::new().unwrap();
+        let capabilities = runtime.capability_manager();
+        capabilities.grant_to("fetcher", Capability::parse("files.read").unwrap(), None).await.unwrap();
+
+        let result = runtime.execute(&manifest, "fetch it").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Restricted);
+        assert_eq!(result.denied_capabilities, vec!["network.http".to_string()]);
+        assert_eq!(result.events.len(), 3);
+        assert_eq!(runtime.consent_queue().pending().await.len(), 1);
+
+        capabilities.grant_to("fetcher", Capability::parse("network.http").unwrap(), None).await.unwrap();
+        let result = runtime.execute(&manifest, "fetch it").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert!(result.denied_capabilities.is_empty());
+    }
 }
@@ -1,12 +1,116 @@
 //! WASM agent runtime host
+//!
+//! A WASM agent is a core module exporting `memory`, `alloc(len) -> ptr`
+//! and `run(ptr, len) -> status`. The host copies the input into the buffer
+//! `alloc` returns and calls `run`; a status other than 0 fails the run.
+//! Output chunks the guest passes to the `omni.emit(ptr, len)` import are
+//! streamed as they arrive. Stdout is published when `run` returns: lines
+//! holding a JSON event frame as that event, the rest as output.
+//!
+//! Privileged WASI imports go through the agent's `AccessGuard`; a denied
+//! call returns `ERRNO_NOTCAPABLE` to the guest and is logged.
 
 use anyhow::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
 
+use crate::agents::access_guard::AccessGuard;
 #[cfg(feature = "wasm")]
-use wasmtime::{Engine, Module, Store, Instance, Linker};
+use crate::agents::access_guard::privileged_imports;
+use crate::agents::capabilities::{Capability, CapabilityGrant};
+use crate::agents::event_protocol::Event;
 #[cfg(feature = "wasm")]
-use wasmtime_wasi::{WasiCtx, WasiCtxBuilder};
+use crate::agents::event_stream::Emitter;
+
+/// WASI errno returned to the guest when a host call is denied (`ENOTCAPABLE`)
+pub const ERRNO_NOTCAPABLE: i32 = 76;
+
+/// Module the WASI preview1 imports are linked under
+#[cfg(feature = "wasm")]
+const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+/// Fuel a guest gets per millicore of its CPU limit; one unit is roughly
+/// one WASM instruction
+pub const FUEL_PER_MILLICORE: u64 = 1_000_000;
+
+#[cfg(feature = "wasm")]
+use anyhow::Context;
+#[cfg(feature = "wasm")]
+use std::sync::atomic::Ordering;
+#[cfg(feature = "wasm")]
+use tokio::runtime::Handle;
+#[cfg(feature = "wasm")]
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap, Val};
+#[cfg(feature = "wasm")]
+use wasmtime_wasi::pipe::MemoryOutputPipe;
+#[cfg(feature = "wasm")]
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+#[cfg(feature = "wasm")]
+use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder};
+
+/// A host directory exposed to the guest filesystem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preopen {
+    pub host_path: PathBuf,
+    pub writable: bool,
+}
+
+/// Translate file grants into the directories the sandbox preopens.
+///
+/// Constrained grants expose only their allowed directory; an unconstrained
+/// grant exposes the whole workspace. Nothing is exposed without a grant, and
+/// nothing is writable for a `read_only` agent.
+pub fn preopens_for(grants: &[CapabilityGrant], workspace_root: &Path, read_only: bool) -> Vec<Preopen> {
+    let mut preopens: Vec<Preopen> = Vec::new();
+    let read = Capability::new("files", "read");
+    let write = Capability::new("files", "write");
+    for grant in grants.iter().filter(|g| g.is_valid()) {
+        let writable = if grant.capability.matches(&write) {
+            !read_only
+        } else if grant.capability.matches(&read) {
+            false
+        } else {
+            continue;
+        };
+        let host_path = match &grant.constraint {
+            Some(constraint) => constraint.base_dir(),
+            None => workspace_root.to_path_buf(),
+        };
+        match preopens.iter_mut().find(|p| p.host_path == host_path) {
+            Some(existing) => existing.writable |= writable,
+            None => preopens.push(Preopen { host_path, writable }),
+        }
+    }
+    preopens
+}
+
+/// One call of a WASM agent's `run` export
+pub struct WasmRun {
+    pub agent_id: String,
+    /// Module file, binary or text format
+    pub module: PathBuf,
+    pub input: String,
+    pub preopens: Vec<Preopen>,
+    /// Memory the guest may grow to, from `resources.mem`
+    pub memory_bytes: u64,
+    /// Fuel the guest may consume, from `resources.cpu`
+    pub fuel: u64,
+    /// Stops the guest at its next emitted chunk once set
+    pub interrupt: Arc<AtomicBool>,
+    /// Checks the guest's privileged WASI calls against its grants
+    pub guard: Arc<AccessGuard>,
+}
+
+/// Store data of a running guest
+#[cfg(feature = "wasm")]
+struct Guest {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+    emitter: Emitter,
+    interrupt: Arc<AtomicBool>,
+}
 
 pub struct WasmHost {
     #[cfg(feature = "wasm")]
@@ -17,7 +121,9 @@ impl WasmHost {
     pub fn new() -> Result<Self> {
         #[cfg(feature = "wasm")]
         {
-            let engine = Engine::default();
+            let mut config = Config::new();
+            config.consume_fuel(true);
+            let engine = Engine::new(&config)?;
             Ok(WasmHost { engine })
         }
         #[cfg(not(feature = "wasm"))]
@@ -26,30 +132,138 @@ impl WasmHost {
         }
     }
 
-    pub async fn load_module(&self, _path: &Path) -> Result<()> {
+    /// Run a guest to completion, sending its output to `events` as it
+    /// goes; returns the fuel it consumed. Blocks, so call it from a
+    /// blocking task.
+    pub fn run(&self, run: WasmRun, events: UnboundedSender<Event>) -> Result<u64> {
         #[cfg(feature = "wasm")]
         {
-            // Load and instantiate WASM module
-            tracing::info!("Loading WASM module");
-            Ok(())
+            // Guard checks are async; this thread blocks on them
+            let runtime = Handle::try_current().context("WASM agents must run on the tokio runtime")?;
+            let module = Module::from_file(&self.engine, &run.module)
+                .with_context(|| format!("Failed to load WASM module {}", run.module.display()))?;
+
+            // Stdout is buffered until the run ends, up to the memory limit
+            let stdout = MemoryOutputPipe::new(run.memory_bytes as usize);
+            let mut wasi = WasiCtxBuilder::new();
+            wasi.stdout(stdout.clone()).args(&[&run.agent_id]);
+            for preopen in &run.preopens {
+                let (dir_perms, file_perms) = if preopen.writable {
+                    (DirPerms::READ | DirPerms::MUTATE, FilePerms::READ | FilePerms::WRITE)
+                } else {
+                    (DirPerms::READ, FilePerms::READ)
+                };
+                let guest_path = preopen.host_path.to_string_lossy();
+                wasi.preopened_dir(&preopen.host_path, guest_path, dir_perms, file_perms)
+                    .with_context(|| format!("Failed to preopen {}", preopen.host_path.display()))?;
+            }
+
+            let guest = Guest {
+                wasi: wasi.build_p1(),
+                limits: StoreLimitsBuilder::new().memory_size(run.memory_bytes as usize).build(),
+                emitter: Emitter::new(run.agent_id.clone(), events),
+                interrupt: run.interrupt,
+            };
+            let mut store = Store::new(&self.engine, guest);
+            store.limiter(|guest| &mut guest.limits);
+            store.set_fuel(run.fuel)?;
+
+            let mut linker: Linker<Guest> = Linker::new(&self.engine);
+            preview1::add_to_linker_sync(&mut linker, |guest: &mut Guest| &mut guest.wasi)?;
+            guard_imports(&mut linker, &mut store, &run.guard, &runtime)?;
+            linker.func_wrap("omni", "emit", emit)?;
+
+            let instance = linker.instantiate(&mut store, &module)?;
+            let memory = instance
+                .get_memory(&mut store, "memory")
+                .context("WASM agent must export its memory")?;
+            let alloc = instance
+                .get_typed_func::<i32, i32>(&mut store, "alloc")
+                .context("WASM agent must export alloc(len) -> ptr")?;
+            let entry = instance
+                .get_typed_func::<(i32, i32), i32>(&mut store, "run")
+                .context("WASM agent must export run(ptr, len) -> status")?;
+
+            let input = run.input.as_bytes();
+            let status = alloc
+                .call(&mut store, input.len() as i32)
+                .and_then(|ptr| {
+                    memory.write(&mut store, ptr as u32 as usize, input)?;
+                    entry.call(&mut store, (ptr, input.len() as i32))
+                });
+            let fuel_consumed = run.fuel - store.get_fuel().unwrap_or(0);
+
+            let status = match status {
+                Ok(status) => status,
+                Err(e) => match e.downcast_ref::<I32Exit>() {
+                    Some(exit) => exit.0,
+                    None if e.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel) => {
+                        anyhow::bail!("WASM agent {} ran out of fuel; raise resources.cpu in its manifest", run.agent_id)
+                    }
+                    None => return Err(e.context(format!("WASM agent {} trapped", run.agent_id))),
+                },
+            };
+            for denied in runtime.block_on(run.guard.drain_events()) {
+                store.data_mut().emitter.send(denied);
+            }
+            store.data_mut().emitter.stdout(&stdout.contents());
+            if status != 0 {
+                anyhow::bail!("WASM agent {} exited with status {}", run.agent_id, status);
+            }
+            Ok(fuel_consumed)
         }
         #[cfg(not(feature = "wasm"))]
         {
+            let _ = (run, events);
             anyhow::bail!("WASM support not compiled in")
         }
     }
+}
 
-    pub async fn invoke(&self, _input: &str) -> Result<String> {
-        #[cfg(feature = "wasm")]
-        {
-            // Invoke WASM function
-            Ok("WASM response".to_string())
-        }
-        #[cfg(not(feature = "wasm"))]
-        {
-            anyhow::bail!("WASM support not compiled in")
-        }
+/// Replace each privileged WASI import in `linker` with one that asks
+/// `guard` first. Denied calls return `ERRNO_NOTCAPABLE` to the guest
+/// instead of trapping, so the agent keeps running.
+#[cfg(feature = "wasm")]
+fn guard_imports(linker: &mut Linker<Guest>, store: &mut Store<Guest>, guard: &Arc<AccessGuard>, runtime: &Handle) -> Result<()> {
+    linker.allow_shadowing(true);
+    for import in privileged_imports() {
+        let Some(wasi) = linker.get(&mut *store, WASI_MODULE, import).and_then(Extern::into_func) else {
+            continue;
+        };
+        let guard = guard.clone();
+        let runtime = runtime.clone();
+        linker.func_new(WASI_MODULE, import, wasi.ty(&*store), move |mut caller, params, results| {
+            if runtime.block_on(guard.check_host_call(import)) {
+                wasi.call(&mut caller, params, results)
+            } else {
+                results[0] = Val::I32(ERRNO_NOTCAPABLE);
+                Ok(())
+            }
+        })?;
+    }
+    linker.allow_shadowing(false);
+    Ok(())
+}
+
+/// `omni.emit(ptr, len)`: stream `len` bytes of guest memory at `ptr` as an
+/// output chunk
+#[cfg(feature = "wasm")]
+fn emit(mut caller: Caller<'_, Guest>, ptr: i32, len: i32) -> Result<()> {
+    if caller.data().interrupt.load(Ordering::SeqCst) {
+        anyhow::bail!("Agent was stopped");
     }
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .context("WASM agent must export its memory")?;
+    let start = ptr as u32 as usize;
+    let data = memory
+        .data(&caller)
+        .get(start..start + len as u32 as usize)
+        .context("Emitted chunk is outside guest memory")?
+        .to_vec();
+    caller.data_mut().emitter.output(data, false);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -69,4 +283,94 @@ mod tests {
             assert!(host.is_err());
         }
     }
+
+    #[cfg(feature = "wasm")]
+    #[tokio::test]
+    async fn test_denied_import_returns_errno() {
+        use crate::agents::capabilities::CapabilityManager;
+        use crate::agents::event_protocol::EventType;
+        use crate::oauth::consent::ConsentLedger;
+
+        // Returns what path_open returned as its status
+        let dir = tempfile::tempdir().unwrap();
+        let module = dir.path().join("opener.wat");
+        std::fs::write(
+            &module,
+            r#"(module
+  (import "wasi_snapshot_preview1" "path_open"
+    (func $open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) i32.const 1024)
+  (func (export "run") (param i32 i32) (result i32)
+    (call $open (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0)
+      (i64.const 0) (i64.const 0) (i32.const 0) (i32.const 2048))))"#,
+        )
+        .unwrap();
+
+        let host = Arc::new(WasmHost::new().unwrap());
+        let capabilities = Arc::new(CapabilityManager::new());
+        let ledger = Arc::new(ConsentLedger::new());
+        let guard = Arc::new(AccessGuard::new("opener", capabilities.clone(), ledger.clone()));
+        let run = |host: Arc<WasmHost>| {
+            let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+            let run = WasmRun {
+                agent_id: "opener".to_string(),
+                module: module.clone(),
+                input: String::new(),
+                preopens: Vec::new(),
+                memory_bytes: 1 << 20,
+                fuel: 1_000_000,
+                interrupt: Arc::new(AtomicBool::new(false)),
+                guard: guard.clone(),
+            };
+            let result = tokio::task::spawn_blocking(move || host.run(run, sender));
+            (result, receiver)
+        };
+
+        let (result, mut receiver) = run(host.clone());
+        let err = result.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains(&format!("status {}", ERRNO_NOTCAPABLE)));
+        assert_eq!(guard.denied_attempts(), 1);
+        assert_eq!(ledger.get_for_agent("opener").await.len(), 1);
+        let events: Vec<Event> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert!(matches!(&events[0].event_type, EventType::Error(e) if e.code == "ACCESS_DENIED"));
+
+        // Once granted the call reaches WASI, which has no fd 3 to open from
+        capabilities.grant_to("opener", Capability::new("files", "read"), None).await.unwrap();
+        let (result, _) = run(host);
+        let status = result.await.unwrap().err().map(|e| e.to_string()).unwrap_or_default();
+        assert!(!status.contains(&format!("status {}", ERRNO_NOTCAPABLE)));
+        assert_eq!(guard.denied_attempts(), 1);
+    }
+
+    #[test]
+    fn test_preopens_follow_path_constraints() {
+        use crate::agents::capabilities::{Capability, Constraint};
+
+        let grants = vec![
+            CapabilityGrant::new(Capability::new("files", "read"), None)
+                .with_constraint(Constraint::PathPrefix("/project/docs".into())),
+            CapabilityGrant::new(Capability::new("files", "write"), None)
+                .with_constraint(Constraint::Glob("/project/docs/**/*.md".to_string())),
+            CapabilityGrant::new(Capability::new("network", "http"), None),
+        ];
+
+        let preopens = preopens_for(&grants, Path::new("/project"), false);
+        assert_eq!(
+            preopens,
+            vec![Preopen { host_path: PathBuf::from("/project/docs"), writable: true }]
+        );
+
+        let unconstrained = vec![CapabilityGrant::new(Capability::new("files", "read"), None)];
+        assert_eq!(
+            preopens_for(&unconstrained, Path::new("/project"), false),
+            vec![Preopen { host_path: PathBuf::from("/project"), writable: false }]
+        );
+
+        // Read-only agents get the same directories without write access
+        assert_eq!(
+            preopens_for(&grants, Path::new("/project"), true),
+            vec![Preopen { host_path: PathBuf::from("/project/docs"), writable: false }]
+        );
+    }
 }
@@ -0,0 +1,245 @@
+//! Auto-disable policy for agents that keep hitting denied access or crashing
+
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::agents::registry::AgentRegistry;
+use crate::notifications::{Notifier, Priority};
+use crate::oauth::consent::ConsentLedger;
+use crate::utils::config::AutoDisableConfig;
+
+/// Thresholds for auto-disabling an agent; a threshold of 0 never trips
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoDisablePolicy {
+    pub max_denied: u32,
+    pub max_crashes: u32,
+    pub window: Duration,
+}
+
+impl AutoDisablePolicy {
+    pub fn from_config(config: &AutoDisableConfig) -> Self {
+        AutoDisablePolicy {
+            max_denied: config.max_denied,
+            max_crashes: config.max_crashes,
+            window: Duration::from_secs(config.window_minutes as u64 * 60),
+        }
+    }
+}
+
+impl Default for AutoDisablePolicy {
+    fn default() -> Self {
+        Self::from_config(&AutoDisableConfig::default())
+    }
+}
+
+/// Kind of misbehavior counted against an agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    DeniedAccess,
+    Crash,
+}
+
+#[derive(Default)]
+struct ViolationLog {
+    denied: VecDeque<Instant>,
+    crashes: VecDeque<Instant>,
+}
+
+/// Counts violations per agent and disables agents that cross a threshold
+pub struct AgentWatchdog {
+    policy: AutoDisablePolicy,
+    registry: Arc<AgentRegistry>,
+    ledger: Arc<ConsentLedger>,
+    notifier: Option<Arc<Notifier>>,
+    violations: Mutex<HashMap<String, ViolationLog>>,
+}
+
+impl AgentWatchdog {
+    pub fn new(
+        policy: AutoDisablePolicy,
+        registry: Arc<AgentRegistry>,
+        ledger: Arc<ConsentLedger>,
+        notifier: Option<Arc<Notifier>>,
+    ) -> Self {
+        AgentWatchdog {
+            policy,
+            registry,
+            ledger,
+            notifier,
+            violations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a violation; returns true if it caused the agent to be disabled
+    pub async fn record(&self, agent_id: &str, violation: Violation) -> Result<bool> {
+        let now = Instant::now();
+        let (count, limit) = {
+            let mut violations = self.violations.lock().await;
+            let log = violations.entry(agent_id.to_string()).or_default();
+            let (times, limit) = match violation {
+                Violation::DeniedAccess => (&mut log.denied, self.policy.max_denied),
+                Violation::Crash => (&mut log.crashes, self.policy.max_crashes),
+            };
+            times.push_back(now);
+            while times
+                .front()
+                .is_some_and(|t| now.duration_since(*t) > self.policy.window)
+            {
+                times.pop_front();
+            }
+            (times.len() as u32, limit)
+        };
+
+        if limit == 0 || count < limit {
+            return Ok(false);
+        }
+
+        let already_disabled = self
+            .registry
+            .get(agent_id)
+            .await
+            .map(|info| !info.enabled)
+            .unwrap_or(true);
+        if already_disabled {
+            return Ok(false);
+        }
+
+        let reason = match violation {
+            Violation::DeniedAccess => format!(
+                "{} denied access attempts within {} minutes",
+                count,
+                self.policy.window.as_secs() / 60
+            ),
+            Violation::Crash => format!(
+                "{} crashes within {} minutes",
+                count,
+                self.policy.window.as_secs() / 60
+            ),
+        };
+        self.disable(agent_id, &reason).await?;
+        Ok(true)
+    }
+
+    /// Forget recorded violations, e.g. after the user re-enables the agent
+    pub async fn reset(&self, agent_id: &str) {
+        self.violations.lock().await.remove(agent_id);
+    }
+
+    async fn disable(&self, agent_id: &str, reason: &str) -> Result<()> {
+        tracing::warn!("Auto-disabling agent {}: {}", agent_id, reason);
+        self.registry.disable_with_reason(agent_id, reason).await?;
+        self.ledger
+            .log_disable(agent_id.to_string(), reason.to_string())
+            .await?;
+        self.reset(agent_id).await;
+
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(
+                format!("Agent {} disabled", agent_id),
+                format!("{}. Re-enable it with agent:enable.", reason),
+                Priority::Warning,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oauth::consent::ConsentAction;
+    use crate::utils::config::Config;
+    use tempfile::TempDir;
+
+    async fn registry_with_agent(dir: &TempDir) -> Arc<AgentRegistry> {
+        std::fs::write(
+            dir.path().join("manifest.toml"),
+            r#"
+schema_version = "0.1"
+name = "noisy"
+version = "0.1.0"
+entry = "agent.wasm"
+sandbox = "wasm"
+capabilities = []
+oauth_scopes = []
+
+[resources]
+cpu = "500m"
+mem = "512Mi"
+
+[ui]
+hints = []
+"#,
+        )
+        .unwrap();
+        let registry = Arc::new(AgentRegistry::new());
+        registry.register(dir.path(), &Config::default().agents).await.unwrap();
+        registry
+    }
+
+    fn policy(max_denied: u32, max_crashes: u32) -> AutoDisablePolicy {
+        AutoDisablePolicy {
+            max_denied,
+            max_crashes,
+            window: Duration::from_secs(600),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nth_denied_attempt_disables_agent() {
+        let dir = TempDir::new().unwrap();
+        let registry = registry_with_agent(&dir).await;
+        let ledger = Arc::new(ConsentLedger::new());
+        let watchdog = AgentWatchdog::new(policy(3, 0), registry.clone(), ledger.clone(), None);
+
+        for _ in 0..2 {
+            assert!(!watchdog.record("noisy", Violation::DeniedAccess).await.unwrap());
+            assert!(registry.get("noisy").await.unwrap().enabled);
+        }
+        assert!(watchdog.record("noisy", Violation::DeniedAccess).await.unwrap());
+
+        let info = registry.get("noisy").await.unwrap();
+        assert!(!info.enabled);
+        assert!(info.disabled_reason.is_some());
+        assert!(matches!(
+            ledger.get_for_agent("noisy").await[0].action,
+            ConsentAction::Disable { .. }
+        ));
+
+        // Rediscovery keeps it disabled; only an explicit enable brings it back
+        registry.register(dir.path(), &Config::default().agents).await.unwrap();
+        assert!(!registry.get("noisy").await.unwrap().enabled);
+        registry.set_enabled("noisy", true).await.unwrap();
+        assert!(registry.get("noisy").await.unwrap().enabled);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_crashes_outside_window_do_not_count() {
+        let dir = TempDir::new().unwrap();
+        let registry = registry_with_agent(&dir).await;
+        let watchdog = AgentWatchdog::new(
+            policy(0, 2),
+            registry.clone(),
+            Arc::new(ConsentLedger::new()),
+            None,
+        );
+
+        assert!(!watchdog.record("noisy", Violation::Crash).await.unwrap());
+        tokio::time::advance(Duration::from_secs(601)).await;
+        assert!(!watchdog.record("noisy", Violation::Crash).await.unwrap());
+        assert!(registry.get("noisy").await.unwrap().enabled);
+
+        assert!(watchdog.record("noisy", Violation::Crash).await.unwrap());
+        assert!(!registry.get("noisy").await.unwrap().enabled);
+
+        // Denied access never trips with a zero threshold
+        registry.set_enabled("noisy", true).await.unwrap();
+        for _ in 0..10 {
+            assert!(!watchdog.record("noisy", Violation::DeniedAccess).await.unwrap());
+        }
+    }
+}
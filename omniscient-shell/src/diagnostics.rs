@@ -0,0 +1,302 @@
+//! Environment self-check behind `omni:doctor` and `--doctor`
+//!
+//! Each check reports pass/warn/fail with a hint on how to fix it, so a
+//! missing pwsh or keychain shows up as a clear line instead of a cryptic
+//! failure later on.
+
+use std::fmt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::graphics::kitty_backend::KittyBackend;
+use crate::graphics::BackendType;
+use crate::state::sqlite::state_db_path;
+use crate::tui::theme::Theme;
+use crate::utils::config::{parse_config, Config, VaultConfig};
+use crate::utils::telemetry::TelemetryCollector;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// How to fix a warning or failure
+    pub hint: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        CheckResult { name, status: CheckStatus::Pass, detail: detail.into(), hint: None }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        CheckResult { name, status: CheckStatus::Warn, detail: detail.into(), hint: Some(hint.into()) }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        CheckResult { name, status: CheckStatus::Fail, detail: detail.into(), hint: Some(hint.into()) }
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self.status {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        };
+        write!(f, "[{}] {}: {}", status, self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n       {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// PowerShell executables tried in order
+pub fn powershell_candidates() -> Vec<String> {
+    let mut candidates = vec!["pwsh".to_string()];
+    if cfg!(windows) {
+        candidates.push("powershell".to_string());
+    }
+    candidates
+}
+
+/// Run every check against the config at `config_path`, negotiating a
+/// graphics backend as startup would; needs no terminal
+pub async fn run(config_path: &Path) -> Vec<CheckResult> {
+    let (config_check, config) = check_config(config_path);
+    let telemetry = TelemetryCollector::default();
+    let graphics = match crate::graphics::negotiate_backend(&config.graphics, &telemetry).await {
+        Ok(backend) => check_graphics(wanted_backend(&config), backend.backend_type()),
+        Err(e) => CheckResult::fail(
+            "Graphics",
+            format!("No graphics backend could be initialized: {}", e),
+            "Set graphics.preferred = \"overlay\" for plain terminal rendering",
+        ),
+    };
+    collect(config_check, &config, graphics)
+}
+
+/// Every check, for a session that already negotiated `backend`
+pub fn run_with_backend(config_path: &Path, backend: BackendType) -> Vec<CheckResult> {
+    let (config_check, config) = check_config(config_path);
+    let graphics = check_graphics(wanted_backend(&config), backend);
+    collect(config_check, &config, graphics)
+}
+
+/// Backend the config asks for: the pinned one, else the preferred one
+fn wanted_backend(config: &Config) -> &str {
+    config.graphics.pin.as_deref().unwrap_or(&config.graphics.preferred)
+}
+
+fn collect(config_check: CheckResult, config: &Config, graphics: CheckResult) -> Vec<CheckResult> {
+    vec![
+        check_powershell(&powershell_candidates()),
+        graphics,
+        check_vault(&config.vault),
+        config_check,
+        check_state_db(&state_db_path(&config.state)),
+    ]
+}
+
+/// The first of `candidates` that runs, with its version
+pub fn check_powershell(candidates: &[String]) -> CheckResult {
+    for candidate in candidates {
+        let output = Command::new(candidate)
+            .args(["-NoProfile", "-Command", "$PSVersionTable.PSVersion.ToString()"])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        if let Some(output) = output.ok().filter(|output| output.status.success()) {
+            let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            return CheckResult::pass("PowerShell", format!("{} {}", candidate, version));
+        }
+    }
+    CheckResult::fail(
+        "PowerShell",
+        format!("None of {} could be run", candidates.join(", ")),
+        "Install PowerShell 7+ and make sure `pwsh` is on PATH: https://aka.ms/powershell",
+    )
+}
+
+/// Whether the backend startup would pick is the configured one and the
+/// terminal looks able to show it
+pub fn check_graphics(preferred: &str, chosen: BackendType) -> CheckResult {
+    let name = match chosen {
+        BackendType::Notcurses => "notcurses",
+        BackendType::Kitty => "kitty",
+        BackendType::Overlay => "overlay",
+    };
+    if chosen == BackendType::Kitty && !KittyBackend::detect_kitty_support() {
+        return CheckResult::warn(
+            "Graphics",
+            "Using kitty, but this terminal doesn't advertise the kitty graphics protocol",
+            "Use kitty, WezTerm or Ghostty for inline images, or set graphics.preferred = \"overlay\"",
+        );
+    }
+    if name != preferred {
+        return CheckResult::warn(
+            "Graphics",
+            format!("Preferred backend '{}' is unavailable; using {}", preferred, name),
+            format!(
+                "Set graphics.preferred = \"{}\", or build with the '{}' feature if it isn't compiled in",
+                name, preferred
+            ),
+        );
+    }
+    CheckResult::pass("Graphics", format!("Using {}", name))
+}
+
+/// Whether the configured vault backend can be reached
+pub fn check_vault(config: &VaultConfig) -> CheckResult {
+    match config.backend.as_str() {
+        "os_keychain" => {
+            // Looking up a label that doesn't exist still talks to the keychain
+            let probe = keyring::Entry::new("omniscient-shell", "omni-doctor").and_then(|entry| entry.get_password());
+            match probe {
+                Ok(_) | Err(keyring::Error::NoEntry) => CheckResult::pass("Vault", "OS keychain is reachable"),
+                Err(e) => CheckResult::fail(
+                    "Vault",
+                    format!("OS keychain is unavailable: {}", e),
+                    "Start the system keychain service (e.g. gnome-keyring), or set vault.backend = \"encrypted_sqlite\"",
+                ),
+            }
+        }
+        "encrypted_sqlite" => CheckResult::pass("Vault", "Encrypted SQLite vault, unlocked with a passphrase"),
+        other => CheckResult::fail(
+            "Vault",
+            format!("Unknown vault backend '{}'", other),
+            "Set vault.backend to \"os_keychain\" or \"encrypted_sqlite\"",
+        ),
+    }
+}
+
+/// Parse the config without creating it; returns the config to check the
+/// rest against (defaults when it can't be used)
+pub fn check_config(path: &Path) -> (CheckResult, Config) {
+    if !path.exists() {
+        let check = CheckResult::warn(
+            "Config",
+            format!("{} doesn't exist; defaults are used", path.display()),
+            "It is created with the defaults on first launch; edit it with config:edit",
+        );
+        return (check, Config::default());
+    }
+    let parsed = std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|contents| parse_config(&contents));
+    let config = match parsed {
+        Ok(config) => config,
+        Err(e) => {
+            let check = CheckResult::fail(
+                "Config",
+                format!("{} is invalid: {}", path.display(), e),
+                "Fix the file or run config:edit; compare with config.example.toml",
+            );
+            return (check, Config::default());
+        }
+    };
+
+    let problem = Theme::from_config(&config.theme)
+        .validate_accessibility(config.theme.strict_contrast)
+        .err()
+        .map(|e| e.to_string());
+    let check = match problem {
+        Some(problem) => CheckResult::fail("Config", problem, format!("Fix {} or run config:edit", path.display())),
+        None => CheckResult::pass("Config", format!("{} is valid", path.display())),
+    };
+    (check, config)
+}
+
+/// Whether the state database can be read. It is opened read-only, so
+/// checking never creates, migrates or locks it.
+pub fn check_state_db(path: &Path) -> CheckResult {
+    let dir = path.parent().unwrap_or(path);
+    if !path.exists() {
+        return CheckResult::warn(
+            "Database",
+            format!("{} doesn't exist yet", path.display()),
+            format!("It is created on first launch; make sure {} is writable", dir.display()),
+        );
+    }
+    let result = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|conn| conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)));
+    match result {
+        Ok(_) if std::fs::metadata(path).is_ok_and(|m| m.permissions().readonly()) => CheckResult::fail(
+            "Database",
+            format!("{} is read-only", path.display()),
+            format!("Make {} writable", path.display()),
+        ),
+        Ok(_) => CheckResult::pass("Database", format!("{} is readable", path.display())),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if matches!(e.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) =>
+        {
+            CheckResult::warn(
+                "Database",
+                format!("{} is in use by another instance", path.display()),
+                "Close the other instance before starting this one",
+            )
+        }
+        Err(e) => CheckResult::fail(
+            "Database",
+            format!("Can't read {}: {}", path.display(), e),
+            format!("Move {} aside to start with a fresh database", path.display()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_pwsh_fails_with_hint() {
+        let check = check_powershell(&["omni-no-such-pwsh".to_string()]);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.hint.as_deref().unwrap().contains("Install PowerShell"));
+        assert!(check.to_string().starts_with("[FAIL] PowerShell: None of omni-no-such-pwsh"));
+    }
+
+    #[test]
+    fn test_config_and_database_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let (check, _) = check_config(&path);
+        assert_eq!(check.status, CheckStatus::Warn);
+        // Checking doesn't create the file
+        assert!(!path.exists());
+
+        std::fs::write(&path, "version = ").unwrap();
+        assert_eq!(check_config(&path).0.status, CheckStatus::Fail);
+        Config::default().save(&path).unwrap();
+        assert_eq!(check_config(&path).0.status, CheckStatus::Pass);
+
+        // Checking doesn't create the database either
+        let db = dir.path().join("state.db");
+        assert_eq!(check_state_db(&db).status, CheckStatus::Warn);
+        assert!(!db.exists());
+        drop(crate::state::SqliteStore::new(&db).unwrap());
+        assert_eq!(check_state_db(&db).status, CheckStatus::Pass);
+        let vault = VaultConfig { backend: "floppy".to_string(), ..Config::default().vault };
+        assert_eq!(check_vault(&vault).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_graphics_fallback_warns() {
+        assert_eq!(check_graphics("overlay", BackendType::Overlay).status, CheckStatus::Pass);
+        let check = check_graphics("notcurses", BackendType::Overlay);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.detail.contains("'notcurses' is unavailable"));
+    }
+}
@@ -2,6 +2,8 @@
 
 use anyhow::Result;
 
+use crate::graphics::image::RgbaImage;
+
 /// Backend type identifier
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BackendType {
@@ -37,7 +39,7 @@ impl Default for Capabilities {
 }
 
 /// Screen region for rendering
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Region {
     pub x: u16,
     pub y: u16,
@@ -45,6 +47,110 @@ pub struct Region {
     pub height: u16,
 }
 
+impl Region {
+    /// Clip the region to a terminal of `cols` x `rows` cells; None if fully off screen
+    pub fn clipped_to(&self, cols: u16, rows: u16) -> Option<Region> {
+        if self.x >= cols || self.y >= rows {
+            return None;
+        }
+        Some(Region {
+            x: self.x,
+            y: self.y,
+            width: self.width.min(cols - self.x),
+            height: self.height.min(rows - self.y),
+        })
+    }
+}
+
+/// Pixel size of one text cell for a terminal of `cols` x `rows` cells
+/// spanning `pixel_w` x `pixel_h` pixels; None when the terminal doesn't
+/// report pixel dimensions
+pub fn cell_size(cols: u16, rows: u16, pixel_w: u16, pixel_h: u16) -> Option<(u16, u16)> {
+    if cols == 0 || rows == 0 || pixel_w == 0 || pixel_h == 0 {
+        return None;
+    }
+    Some((pixel_w / cols, pixel_h / rows)).filter(|&(w, h)| w > 0 && h > 0)
+}
+
+/// Cell size of the current terminal, queried via `TIOCGWINSZ` or equivalent
+pub fn query_cell_size() -> Option<(u16, u16)> {
+    let size = crossterm::terminal::window_size().ok()?;
+    cell_size(size.columns, size.rows, size.width, size.height)
+}
+
+/// An image drawn on screen, kept so it can be redrawn after a resize
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub region: Region,
+    pub image: RgbaImage,
+}
+
+/// Capabilities, cell size and live placements of a backend that draws
+/// images into regions
+#[derive(Debug, Clone)]
+pub struct PlacementState {
+    pub capabilities: Capabilities,
+    /// Cell size in pixels, from the terminal's reported window size
+    pub cell_size: Option<(u16, u16)>,
+    placements: Vec<Placement>,
+}
+
+impl PlacementState {
+    pub fn new(capabilities: Capabilities) -> Self {
+        PlacementState { capabilities, cell_size: None, placements: Vec::new() }
+    }
+
+    /// Record `image` as drawn at `region`, replacing what was there
+    pub fn place(&mut self, region: &Region, image: &RgbaImage) {
+        self.remove(region);
+        self.placements.push(Placement { region: region.clone(), image: image.clone() });
+    }
+
+    /// Forget the placement at `region`
+    pub fn remove(&mut self, region: &Region) {
+        self.placements.retain(|p| p.region != *region);
+    }
+
+    #[cfg(test)]
+    pub fn placements(&self) -> &[Placement] {
+        &self.placements
+    }
+}
+
+/// Backends that track their placements in a `PlacementState`
+pub trait PlacementBackend: GraphicsBackend {
+    fn placement_state(&mut self) -> &mut PlacementState;
+}
+
+/// Shared `resize` for placement backends: record the new cell size and pixel
+/// limits, clear every placement, and redraw those still on screen clipped to
+/// a terminal of `cols` x `rows` cells
+pub fn rescale(
+    backend: &mut impl PlacementBackend,
+    cols: u16,
+    rows: u16,
+    pixel_w: u16,
+    pixel_h: u16,
+) -> Result<()> {
+    let state = backend.placement_state();
+    state.cell_size = cell_size(cols, rows, pixel_w, pixel_h);
+    if pixel_w > 0 && pixel_h > 0 {
+        state.capabilities.max_width = pixel_w as u32;
+        state.capabilities.max_height = pixel_h as u32;
+    }
+
+    let placements = std::mem::take(&mut state.placements);
+    for placement in &placements {
+        backend.clear_region(&placement.region)?;
+    }
+    for placement in placements {
+        if let Some(region) = placement.region.clipped_to(cols, rows) {
+            backend.render_image(&region, &placement.image)?;
+        }
+    }
+    Ok(())
+}
+
 /// Graphics backend trait
 pub trait GraphicsBackend: Send {
     /// Get backend type
@@ -57,10 +163,10 @@ pub trait GraphicsBackend: Send {
     fn capabilities(&self) -> Capabilities;
 
     /// Render an image in the specified region
-    fn render_image(&mut self, region: &Region, image_data: &[u8]) -> Result<()>;
+    fn render_image(&mut self, region: &Region, image: &RgbaImage) -> Result<()>;
 
     /// Render a video frame in the specified region
-    fn render_video_frame(&mut self, region: &Region, frame_data: &[u8]) -> Result<()>;
+    fn render_video_frame(&mut self, region: &Region, frame: &RgbaImage) -> Result<()>;
 
     /// Clear the specified region
     fn clear_region(&mut self, region: &Region) -> Result<()>;
@@ -70,4 +176,98 @@ pub trait GraphicsBackend: Send {
 
     /// Benchmark the backend (returns effective resolution score)
     fn benchmark(&mut self) -> Result<f32>;
+
+    /// Pixel width and height of one text cell, if known
+    fn cell_pixel_size(&self) -> Option<(u16, u16)> {
+        None
+    }
+
+    /// React to a terminal size change (cells and pixels); no-op by default
+    fn resize(&mut self, _cols: u16, _rows: u16, _pixel_w: u16, _pixel_h: u16) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records what it draws and clears
+    struct RecordingBackend {
+        state: PlacementState,
+        drawn: Vec<(Region, u32)>,
+        cleared: Vec<Region>,
+    }
+
+    impl GraphicsBackend for RecordingBackend {
+        fn backend_type(&self) -> BackendType {
+            BackendType::Kitty
+        }
+
+        fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            self.state.capabilities.clone()
+        }
+
+        fn render_image(&mut self, region: &Region, image: &RgbaImage) -> Result<()> {
+            self.state.place(region, image);
+            self.drawn.push((region.clone(), image.width()));
+            Ok(())
+        }
+
+        fn render_video_frame(&mut self, _region: &Region, _frame: &RgbaImage) -> Result<()> {
+            Ok(())
+        }
+
+        fn clear_region(&mut self, region: &Region) -> Result<()> {
+            self.state.remove(region);
+            self.cleared.push(region.clone());
+            Ok(())
+        }
+
+        fn supports_resolution(&self, _width: u32, _height: u32) -> bool {
+            true
+        }
+
+        fn benchmark(&mut self) -> Result<f32> {
+            Ok(1.0)
+        }
+
+        fn resize(&mut self, cols: u16, rows: u16, pixel_w: u16, pixel_h: u16) -> Result<()> {
+            rescale(self, cols, rows, pixel_w, pixel_h)
+        }
+    }
+
+    impl PlacementBackend for RecordingBackend {
+        fn placement_state(&mut self) -> &mut PlacementState {
+            &mut self.state
+        }
+    }
+
+    #[test]
+    fn test_resize_redraws_placements_at_new_size() {
+        let mut backend = RecordingBackend {
+            state: PlacementState::new(Capabilities::default()),
+            drawn: Vec::new(),
+            cleared: Vec::new(),
+        };
+        let straddling = Region { x: 60, y: 5, width: 40, height: 10 };
+        let offscreen = Region { x: 100, y: 0, width: 10, height: 10 };
+        backend.render_image(&straddling, &RgbaImage::filled(3, 1, [0; 4]).unwrap()).unwrap();
+        backend.render_image(&offscreen, &RgbaImage::filled(5, 1, [0; 4]).unwrap()).unwrap();
+        backend.drawn.clear();
+
+        backend.resize(80, 24, 800, 480).unwrap();
+
+        let clipped = Region { x: 60, y: 5, width: 20, height: 10 };
+        assert_eq!(backend.cleared, vec![straddling, offscreen]);
+        assert_eq!(backend.drawn, vec![(clipped.clone(), 3)]);
+        assert_eq!(backend.state.placements().len(), 1);
+        assert_eq!(backend.state.placements()[0].region, clipped);
+        assert_eq!(backend.state.cell_size, Some((10, 20)));
+        assert_eq!(backend.capabilities().max_width, 800);
+    }
 }
@@ -0,0 +1,177 @@
+//! Decoded image handed from the media pipeline to graphics backends
+//!
+//! Pixels are always 8-bit RGBA, row-major with no padding, so a backend
+//! never has to guess what format a buffer is in.
+
+use anyhow::Result;
+use std::time::Duration;
+
+/// Bytes per RGBA pixel
+pub const BYTES_PER_PIXEL: usize = 4;
+
+/// One frame of an animation, shown for `delay` before the next
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub pixels: Vec<u8>,
+    pub delay: Duration,
+}
+
+/// RGBA image, optionally animated; every frame has the image's dimensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    /// Frames after the first (`pixels`); empty for a still image
+    frames: Vec<Frame>,
+}
+
+/// Length of an RGBA buffer of `width` x `height` pixels; None on overflow
+fn buffer_len(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize)?.checked_mul(BYTES_PER_PIXEL)
+}
+
+fn check_len(width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    match buffer_len(width, height) {
+        Some(expected) if expected == pixels.len() => Ok(()),
+        Some(expected) => anyhow::bail!(
+            "{}x{} RGBA image needs {} bytes, got {}",
+            width,
+            height,
+            expected,
+            pixels.len()
+        ),
+        None => anyhow::bail!("{}x{} image is too large", width, height),
+    }
+}
+
+impl RgbaImage {
+    /// Still image from `pixels`, which must hold exactly `width` x `height` RGBA pixels
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self> {
+        check_len(width, height, &pixels)?;
+        Ok(RgbaImage { width, height, pixels, frames: Vec::new() })
+    }
+
+    /// Image of a single color
+    pub fn filled(width: u32, height: u32, rgba: [u8; 4]) -> Result<Self> {
+        let pixels = buffer_len(width, height)
+            .map(|len| rgba.repeat(len / BYTES_PER_PIXEL))
+            .ok_or_else(|| anyhow::anyhow!("{}x{} image is too large", width, height))?;
+        Self::new(width, height, pixels)
+    }
+
+    /// Append an animation frame of the same size
+    pub fn with_frame(mut self, pixels: Vec<u8>, delay: Duration) -> Result<Self> {
+        check_len(self.width, self.height, &pixels)?;
+        self.frames.push(Frame { pixels, delay });
+        Ok(self)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Pixels of the first frame
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Frames after the first
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn is_animated(&self) -> bool {
+        !self.frames.is_empty()
+    }
+
+    /// Number of frames, counting the first
+    pub fn frame_count(&self) -> usize {
+        1 + self.frames.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::backend::{BackendType, Capabilities, GraphicsBackend, Region};
+
+    /// Checks every buffer it's given against the image's dimensions
+    #[derive(Default)]
+    struct ValidatingBackend {
+        frames_seen: usize,
+    }
+
+    impl ValidatingBackend {
+        fn validate(&mut self, image: &RgbaImage) -> Result<()> {
+            let expected = image.width() as usize * image.height() as usize * BYTES_PER_PIXEL;
+            anyhow::ensure!(image.pixels().len() == expected, "first frame has the wrong size");
+            for frame in image.frames() {
+                anyhow::ensure!(frame.pixels.len() == expected, "animation frame has the wrong size");
+            }
+            self.frames_seen += image.frame_count();
+            Ok(())
+        }
+    }
+
+    impl GraphicsBackend for ValidatingBackend {
+        fn backend_type(&self) -> BackendType {
+            BackendType::Overlay
+        }
+        fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+        fn render_image(&mut self, _: &Region, image: &RgbaImage) -> Result<()> {
+            self.validate(image)
+        }
+        fn render_video_frame(&mut self, _: &Region, frame: &RgbaImage) -> Result<()> {
+            self.validate(frame)
+        }
+        fn clear_region(&mut self, _: &Region) -> Result<()> {
+            Ok(())
+        }
+        fn supports_resolution(&self, _: u32, _: u32) -> bool {
+            true
+        }
+        fn benchmark(&mut self) -> Result<f32> {
+            Ok(1.0)
+        }
+    }
+
+    #[test]
+    fn test_buffer_must_match_dimensions() {
+        let image = RgbaImage::new(2, 3, vec![0; 24]).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 3));
+        assert!(!image.is_animated());
+
+        let err = RgbaImage::new(2, 3, vec![0; 23]).unwrap_err();
+        assert_eq!(err.to_string(), "2x3 RGBA image needs 24 bytes, got 23");
+        assert!(RgbaImage::new(u32::MAX, u32::MAX, Vec::new()).is_err());
+
+        let red = RgbaImage::filled(2, 2, [255, 0, 0, 255]).unwrap();
+        assert_eq!(&red.pixels()[4..8], &[255, 0, 0, 255]);
+        assert!(red.clone().with_frame(vec![0; 12], Duration::from_millis(40)).is_err());
+    }
+
+    #[test]
+    fn test_backend_receives_consistent_frames() {
+        let image = RgbaImage::filled(4, 2, [0, 0, 0, 255])
+            .unwrap()
+            .with_frame(vec![255; 32], Duration::from_millis(40))
+            .unwrap();
+        assert_eq!(image.frame_count(), 2);
+
+        let region = Region { x: 0, y: 0, width: 4, height: 1 };
+        let mut backend = ValidatingBackend::default();
+        backend.render_image(&region, &image).unwrap();
+        backend.render_video_frame(&region, &RgbaImage::filled(1, 1, [0; 4]).unwrap()).unwrap();
+        assert_eq!(backend.frames_seen, 3);
+    }
+}
@@ -1,17 +1,20 @@
 //! Kitty graphics protocol backend implementation
 
 use anyhow::Result;
-use crate::graphics::backend::{GraphicsBackend, BackendType, Capabilities, Region};
+use crate::graphics::backend::{
+    query_cell_size, rescale, GraphicsBackend, BackendType, Capabilities, PlacementBackend, PlacementState, Region,
+};
+use crate::graphics::image::RgbaImage;
 
 pub struct KittyBackend {
-    capabilities: Capabilities,
+    state: PlacementState,
     initialized: bool,
 }
 
 impl KittyBackend {
     pub fn new() -> Result<Self> {
         Ok(KittyBackend {
-            capabilities: Capabilities {
+            state: PlacementState::new(Capabilities {
                 max_width: 1920,
                 max_height: 1080,
                 color_depth: 24,
@@ -19,12 +22,12 @@ impl KittyBackend {
                 supports_animation: true,
                 effective_resolution: 8.0,
                 latency_ms: 15.0,
-            },
+            }),
             initialized: false,
         })
     }
 
-    fn detect_kitty_support() -> bool {
+    pub fn detect_kitty_support() -> bool {
         // Check for Kitty terminal via environment variables
         std::env::var("TERM").map(|t| t.contains("kitty")).unwrap_or(false)
             || std::env::var("KITTY_WINDOW_ID").is_ok()
@@ -41,35 +44,78 @@ impl GraphicsBackend for KittyBackend {
             tracing::warn!("Kitty terminal not detected, but initializing anyway");
         }
         tracing::info!("Initializing Kitty graphics protocol backend");
+        self.state.cell_size = query_cell_size();
         self.initialized = true;
         Ok(())
     }
 
     fn capabilities(&self) -> Capabilities {
-        self.capabilities.clone()
+        self.state.capabilities.clone()
     }
 
-    fn render_image(&mut self, region: &Region, _image_data: &[u8]) -> Result<()> {
-        tracing::debug!("Rendering image at {:?} using Kitty protocol", region);
+    fn render_image(&mut self, region: &Region, image: &RgbaImage) -> Result<()> {
+        self.state.place(region, image);
+        tracing::debug!("Rendering {}x{} image at {:?} using Kitty protocol", image.width(), image.height(), region);
         // Real implementation would use Kitty graphics escape codes
         Ok(())
     }
 
-    fn render_video_frame(&mut self, region: &Region, _frame_data: &[u8]) -> Result<()> {
-        tracing::debug!("Rendering video frame at {:?} using Kitty protocol", region);
+    fn render_video_frame(&mut self, region: &Region, frame: &RgbaImage) -> Result<()> {
+        tracing::debug!("Rendering {}x{} video frame at {:?} using Kitty protocol", frame.width(), frame.height(), region);
         Ok(())
     }
 
     fn clear_region(&mut self, region: &Region) -> Result<()> {
+        self.state.remove(region);
         tracing::debug!("Clearing region {:?}", region);
         Ok(())
     }
 
     fn supports_resolution(&self, width: u32, height: u32) -> bool {
-        width <= self.capabilities.max_width && height <= self.capabilities.max_height
+        width <= self.state.capabilities.max_width && height <= self.state.capabilities.max_height
     }
 
     fn benchmark(&mut self) -> Result<f32> {
         Ok(8.0)
     }
+
+    fn cell_pixel_size(&self) -> Option<(u16, u16)> {
+        self.state.cell_size
+    }
+
+    fn resize(&mut self, cols: u16, rows: u16, pixel_w: u16, pixel_h: u16) -> Result<()> {
+        tracing::debug!("Kitty backend resized to {}x{} cells ({}x{} px)", cols, rows, pixel_w, pixel_h);
+        rescale(self, cols, rows, pixel_w, pixel_h)
+    }
+}
+
+impl PlacementBackend for KittyBackend {
+    fn placement_state(&mut self) -> &mut PlacementState {
+        &mut self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_clips_and_drops_placements() {
+        let mut backend = KittyBackend::new().unwrap();
+        let inside = Region { x: 0, y: 0, width: 40, height: 10 };
+        let straddling = Region { x: 60, y: 5, width: 40, height: 10 };
+        let offscreen = Region { x: 100, y: 0, width: 10, height: 10 };
+        let image = RgbaImage::filled(1, 1, [0; 4]).unwrap();
+        for region in [&inside, &straddling, &offscreen] {
+            backend.render_image(region, &image).unwrap();
+        }
+
+        backend.resize(80, 24, 800, 480).unwrap();
+
+        let regions: Vec<&Region> = backend.state.placements().iter().map(|p| &p.region).collect();
+        assert_eq!(regions, vec![&inside, &Region { x: 60, y: 5, width: 20, height: 10 }]);
+        assert!(backend.supports_resolution(800, 480));
+        assert_eq!(backend.cell_pixel_size(), Some((10, 20)));
+        assert!(!backend.supports_resolution(1920, 1080));
+    }
 }
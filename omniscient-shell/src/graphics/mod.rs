@@ -4,29 +4,87 @@ pub mod backend;
 pub mod notcurses_backend;
 pub mod kitty_backend;
 pub mod overlay_backend;
+pub mod image;
 
 use anyhow::Result;
+use std::collections::HashMap;
 use crate::utils::config::GraphicsConfig;
+use crate::utils::errors::{OmniError, RecoveryAction};
+use crate::utils::telemetry::TelemetryCollector;
 pub use backend::{GraphicsBackend, BackendType, Capabilities, Region};
+pub use image::{Frame, RgbaImage};
+
+/// Telemetry event recorded when the preferred backend can't be used
+pub const FALLBACK_EVENT: &str = "graphics.fallback";
 
 /// Negotiate and initialize the best available graphics backend
-pub fn negotiate_backend(config: &GraphicsConfig) -> Result<Box<dyn GraphicsBackend>> {
+pub async fn negotiate_backend(
+    config: &GraphicsConfig,
+    telemetry: &TelemetryCollector,
+) -> Result<Box<dyn GraphicsBackend>> {
+    negotiate_with(config, telemetry, try_backend).await
+}
+
+async fn negotiate_with<F>(
+    config: &GraphicsConfig,
+    telemetry: &TelemetryCollector,
+    mut try_backend: F,
+) -> Result<Box<dyn GraphicsBackend>>
+where
+    F: FnMut(&str, &GraphicsConfig) -> Result<Box<dyn GraphicsBackend>>,
+{
+    if let Some(pinned) = &config.pin {
+        return try_backend(pinned, config).map_err(|e| {
+            OmniError::graphics(
+                format!("Pinned backend '{}' failed to initialize: {}", pinned, e),
+                Some("Pin a backend this terminal supports, or remove graphics.pin to negotiate one".to_string()),
+                RecoveryAction::None,
+            )
+            .into()
+        });
+    }
+
     let mut backends_to_try = vec![config.preferred.as_str()];
     backends_to_try.extend(config.fallback.iter().map(|s| s.as_str()));
 
+    let mut failed = Vec::new();
+    let mut chosen = None;
     for backend_name in backends_to_try {
         match try_backend(backend_name, config) {
-            Ok(backend) => return Ok(backend),
+            Ok(backend) => {
+                chosen = Some((backend_name, backend));
+                break;
+            }
             Err(e) => {
                 tracing::warn!("Failed to initialize {} backend: {}", backend_name, e);
-                continue;
+                failed.push(backend_name);
             }
         }
     }
 
-    // Final fallback to overlay
-    tracing::warn!("All preferred backends failed, falling back to overlay");
-    try_backend("overlay", config)
+    let (name, backend) = match chosen {
+        Some(chosen) => chosen,
+        None => {
+            // Final fallback to overlay
+            tracing::warn!("All preferred backends failed, falling back to overlay");
+            ("overlay", try_backend("overlay", config)?)
+        }
+    };
+
+    if !failed.is_empty() {
+        record_fallback(telemetry, &failed, name).await;
+    }
+    Ok(backend)
+}
+
+async fn record_fallback(telemetry: &TelemetryCollector, failed: &[&str], chosen: &str) {
+    let metadata = HashMap::from([
+        ("failed".to_string(), failed.join(",")),
+        ("chosen".to_string(), chosen.to_string()),
+    ]);
+    if let Err(e) = telemetry.record_event(FALLBACK_EVENT, None, metadata, true).await {
+        tracing::debug!("Failed to record backend fallback: {}", e);
+    }
 }
 
 fn try_backend(name: &str, config: &GraphicsConfig) -> Result<Box<dyn GraphicsBackend>> {
@@ -34,6 +92,10 @@ fn try_backend(name: &str, config: &GraphicsConfig) -> Result<Box<dyn GraphicsBa
         "notcurses" => {
             #[cfg(feature = "notcurses")]
             {
+                // Fail fast where no pixel blitter is plausible; init stays authoritative
+                if !notcurses_backend::pixel_blitter_plausible() {
+                    anyhow::bail!("Terminal has no pixel blitter for notcurses");
+                }
                 Ok(Box::new(notcurses_backend::NotcursesBackend::new()?))
             }
             #[cfg(not(feature = "notcurses"))]
@@ -56,3 +118,104 @@ fn try_backend(name: &str, config: &GraphicsConfig) -> Result<Box<dyn GraphicsBa
         _ => anyhow::bail!("Unknown graphics backend: {}", name),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::telemetry::TelemetryConfig;
+
+    struct MockBackend(BackendType);
+
+    impl GraphicsBackend for MockBackend {
+        fn backend_type(&self) -> BackendType {
+            self.0
+        }
+
+        fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        fn render_image(&mut self, _region: &Region, _image: &RgbaImage) -> Result<()> {
+            Ok(())
+        }
+
+        fn render_video_frame(&mut self, _region: &Region, _frame: &RgbaImage) -> Result<()> {
+            Ok(())
+        }
+
+        fn clear_region(&mut self, _region: &Region) -> Result<()> {
+            Ok(())
+        }
+
+        fn supports_resolution(&self, _width: u32, _height: u32) -> bool {
+            true
+        }
+
+        fn benchmark(&mut self) -> Result<f32> {
+            Ok(1.0)
+        }
+    }
+
+    /// Only kitty initializes
+    fn kitty_only(name: &str, _config: &GraphicsConfig) -> Result<Box<dyn GraphicsBackend>> {
+        match name {
+            "kitty" => Ok(Box::new(MockBackend(BackendType::Kitty))),
+            other => anyhow::bail!("{} unavailable", other),
+        }
+    }
+
+    fn opted_in() -> TelemetryCollector {
+        TelemetryCollector::new(TelemetryConfig { enabled: true, ..TelemetryConfig::default() })
+    }
+
+    #[tokio::test]
+    async fn test_fallback_records_telemetry() {
+        let config = crate::utils::config::Config::default().graphics;
+        let telemetry = opted_in();
+
+        let backend = negotiate_with(&config, &telemetry, kitty_only).await.unwrap();
+        assert_eq!(backend.backend_type(), BackendType::Kitty);
+
+        let events = telemetry.events().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, FALLBACK_EVENT);
+        assert_eq!(events[0].metadata["failed"], "notcurses");
+        assert_eq!(events[0].metadata["chosen"], "kitty");
+    }
+
+    #[tokio::test]
+    async fn test_preferred_backend_records_nothing() {
+        let mut config = crate::utils::config::Config::default().graphics;
+        config.preferred = "kitty".to_string();
+        let telemetry = opted_in();
+
+        negotiate_with(&config, &telemetry, kitty_only).await.unwrap();
+        assert!(telemetry.events().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_backend_never_falls_back() {
+        let mut config = crate::utils::config::Config::default().graphics;
+        config.pin = Some("notcurses".to_string());
+        let telemetry = opted_in();
+
+        let mut tried = Vec::new();
+        let result = negotiate_with(&config, &telemetry, |name, config| {
+            tried.push(name.to_string());
+            kitty_only(name, config)
+        })
+        .await;
+        let err = result.err().unwrap();
+        assert!(err.to_string().contains("Pinned backend 'notcurses'"));
+        assert_eq!(tried, vec!["notcurses"]);
+        assert!(telemetry.events().await.is_empty());
+
+        config.pin = Some("kitty".to_string());
+        let backend = negotiate_with(&config, &telemetry, kitty_only).await.unwrap();
+        assert_eq!(backend.backend_type(), BackendType::Kitty);
+    }
+}
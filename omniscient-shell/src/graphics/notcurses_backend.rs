@@ -1,17 +1,48 @@
 //! Notcurses graphics backend implementation
 
 use anyhow::Result;
-use crate::graphics::backend::{GraphicsBackend, BackendType, Capabilities, Region};
+use crate::graphics::backend::{
+    rescale, GraphicsBackend, BackendType, Capabilities, PlacementBackend, PlacementState, Region,
+};
+use crate::graphics::image::RgbaImage;
+
+/// `TERM` / `TERM_PROGRAM` fragments of terminals with a pixel protocol
+/// (kitty graphics, sixel or iTerm2 images) that notcurses can blit with
+const PIXEL_TERMINALS: &[&str] = &[
+    "kitty", "wezterm", "foot", "mlterm", "contour", "ghostty", "iterm", "konsole", "mintty",
+];
+
+/// Variables set by pixel-capable terminals even when `TERM` is generic
+const PIXEL_TERMINAL_VARS: &[&str] = &["KITTY_WINDOW_ID", "WEZTERM_PANE", "KONSOLE_VERSION"];
+
+/// Cheap environment check for whether a pixel blitter could work here.
+///
+/// Lets negotiation skip notcurses on plain terminals without paying for a
+/// full init; a `true` here still has to be confirmed by `init`.
+#[cfg_attr(not(feature = "notcurses"), allow(dead_code))]
+pub fn pixel_blitter_plausible() -> bool {
+    pixel_blitter_plausible_with(|name| std::env::var(name).ok())
+}
+
+fn pixel_blitter_plausible_with(var: impl Fn(&str) -> Option<String>) -> bool {
+    if PIXEL_TERMINAL_VARS.iter().any(|name| var(name).is_some()) {
+        return true;
+    }
+    ["TERM", "TERM_PROGRAM"].iter().filter_map(|name| var(name)).any(|value| {
+        let value = value.to_lowercase();
+        PIXEL_TERMINALS.iter().any(|term| value.contains(term))
+    })
+}
 
 pub struct NotcursesBackend {
-    capabilities: Capabilities,
+    state: PlacementState,
     initialized: bool,
 }
 
 impl NotcursesBackend {
     pub fn new() -> Result<Self> {
         Ok(NotcursesBackend {
-            capabilities: Capabilities::default(),
+            state: PlacementState::new(Capabilities::default()),
             initialized: false,
         })
     }
@@ -28,6 +59,7 @@ impl GraphicsBackend for NotcursesBackend {
             // Initialize Notcurses
             // This would use the notcurses crate to initialize
             tracing::info!("Initializing Notcurses backend");
+            self.state.cell_size = crate::graphics::backend::query_cell_size();
             self.initialized = true;
             Ok(())
         }
@@ -38,30 +70,79 @@ impl GraphicsBackend for NotcursesBackend {
     }
 
     fn capabilities(&self) -> Capabilities {
-        self.capabilities.clone()
+        self.state.capabilities.clone()
     }
 
-    fn render_image(&mut self, region: &Region, _image_data: &[u8]) -> Result<()> {
-        tracing::debug!("Rendering image at {:?}", region);
+    fn render_image(&mut self, region: &Region, image: &RgbaImage) -> Result<()> {
+        self.state.place(region, image);
+        tracing::debug!("Rendering {}x{} image at {:?}", image.width(), image.height(), region);
         Ok(())
     }
 
-    fn render_video_frame(&mut self, region: &Region, _frame_data: &[u8]) -> Result<()> {
-        tracing::debug!("Rendering video frame at {:?}", region);
+    fn render_video_frame(&mut self, region: &Region, frame: &RgbaImage) -> Result<()> {
+        tracing::debug!("Rendering {}x{} video frame at {:?}", frame.width(), frame.height(), region);
         Ok(())
     }
 
     fn clear_region(&mut self, region: &Region) -> Result<()> {
+        self.state.remove(region);
         tracing::debug!("Clearing region {:?}", region);
         Ok(())
     }
 
     fn supports_resolution(&self, width: u32, height: u32) -> bool {
-        width <= self.capabilities.max_width && height <= self.capabilities.max_height
+        width <= self.state.capabilities.max_width && height <= self.state.capabilities.max_height
     }
 
     fn benchmark(&mut self) -> Result<f32> {
         // Simplified benchmark - real implementation would measure actual performance
         Ok(10.0)
     }
+
+    fn cell_pixel_size(&self) -> Option<(u16, u16)> {
+        self.state.cell_size
+    }
+
+    fn resize(&mut self, cols: u16, rows: u16, pixel_w: u16, pixel_h: u16) -> Result<()> {
+        tracing::debug!("Notcurses backend resized to {}x{} cells ({}x{} px)", cols, rows, pixel_w, pixel_h);
+        rescale(self, cols, rows, pixel_w, pixel_h)
+    }
+}
+
+impl PlacementBackend for NotcursesBackend {
+    fn placement_state(&mut self) -> &mut PlacementState {
+        &mut self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> =
+            vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_plain_terminal_has_no_pixel_blitter() {
+        assert!(!pixel_blitter_plausible_with(env(&[("TERM", "xterm-256color")])));
+        assert!(!pixel_blitter_plausible_with(env(&[("TERM", "dumb")])));
+        assert!(!pixel_blitter_plausible_with(env(&[])));
+    }
+
+    #[test]
+    fn test_pixel_terminals_are_plausible() {
+        assert!(pixel_blitter_plausible_with(env(&[("TERM", "xterm-kitty")])));
+        assert!(pixel_blitter_plausible_with(env(&[
+            ("TERM", "xterm-256color"),
+            ("TERM_PROGRAM", "WezTerm"),
+        ])));
+        assert!(pixel_blitter_plausible_with(env(&[
+            ("TERM", "screen-256color"),
+            ("KITTY_WINDOW_ID", "1"),
+        ])));
+    }
 }
@@ -2,6 +2,7 @@
 
 use anyhow::Result;
 use crate::graphics::backend::{GraphicsBackend, BackendType, Capabilities, Region};
+use crate::graphics::image::RgbaImage;
 
 pub struct OverlayBackend {
     capabilities: Capabilities,
@@ -40,14 +41,14 @@ impl GraphicsBackend for OverlayBackend {
         self.capabilities.clone()
     }
 
-    fn render_image(&mut self, region: &Region, _image_data: &[u8]) -> Result<()> {
-        tracing::debug!("Rendering ASCII art placeholder at {:?}", region);
+    fn render_image(&mut self, region: &Region, image: &RgbaImage) -> Result<()> {
+        tracing::debug!("Rendering {}x{} image as ASCII art at {:?}", image.width(), image.height(), region);
         // Real implementation would convert image to ASCII art
         Ok(())
     }
 
-    fn render_video_frame(&mut self, region: &Region, _frame_data: &[u8]) -> Result<()> {
-        tracing::debug!("Rendering video frame as ASCII at {:?}", region);
+    fn render_video_frame(&mut self, region: &Region, frame: &RgbaImage) -> Result<()> {
+        tracing::debug!("Rendering {}x{} video frame as ASCII at {:?}", frame.width(), frame.height(), region);
         Ok(())
     }
 
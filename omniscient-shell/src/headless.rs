@@ -0,0 +1,152 @@
+//! Headless agent runs behind `--agent`, for scripts and orchestrators
+//!
+//! With `--stream-events` every event the runtime publishes is written to
+//! stdout as one JSON line as it happens; otherwise only the agent's output
+//! is printed once the run ends.
+
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::agents::capabilities::CapabilityManager;
+use crate::agents::event_protocol::EventType;
+use crate::agents::event_stream::write_ndjson;
+use crate::agents::{AgentRegistry, AgentRuntime, AgentStatus, AgentWatchdog, AutoDisablePolicy};
+use crate::oauth::consent::ConsentLedger;
+use crate::state::sqlite::{state_db_path, SqliteStore};
+use crate::utils::config::Config;
+
+/// Run the agent `name` from `agents_dir` on `input`, writing its events
+/// (`stream_events`) or its output to `out`; returns how the run ended.
+/// Capabilities are checked against the grants saved in the state database,
+/// and denials and crashes are counted against `agents.auto_disable`.
+pub async fn run_agent(
+    config: &Config,
+    agents_dir: &Path,
+    name: &str,
+    input: &str,
+    stream_events: bool,
+    out: &mut impl Write,
+) -> Result<AgentStatus> {
+    let registry = Arc::new(AgentRegistry::from_config(&config.agents));
+    registry.discover(agents_dir, &config.agents).await?;
+    let info = registry
+        .get(name)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Agent {} not found in {}", name, agents_dir.display()))?;
+    if !info.enabled {
+        let reason = info.disabled_reason.unwrap_or_else(|| "not in agents.enabled".to_string());
+        anyhow::bail!("Agent {} is disabled: {}", name, reason);
+    }
+
+    let store = Arc::new(SqliteStore::new(&state_db_path(&config.state))?);
+    let capabilities = CapabilityManager::new_persistent(store.clone()).await?;
+    let ledger = Arc::new(ConsentLedger::new_persistent(store));
+    let watchdog = AgentWatchdog::new(
+        AutoDisablePolicy::from_config(&config.agents.auto_disable),
+        registry.clone(),
+        ledger.clone(),
+        None,
+    );
+    let runtime = AgentRuntime::new()?
+        .with_capability_manager(Arc::new(capabilities))
+        .with_ledger(ledger)
+        .with_watchdog(Arc::new(watchdog))
+        .with_agents_config(&config.agents);
+    let mut events = runtime.event_stream().subscribe();
+    let run = async move {
+        let result = runtime.execute(&info, input).await;
+        // Closes the event stream, ending the writer
+        drop(runtime);
+        result
+    };
+
+    let result = if stream_events {
+        let (result, written) = tokio::join!(run, write_ndjson(&mut events, out));
+        written?;
+        result?
+    } else {
+        drop(events);
+        let result = run.await?;
+        for event in &result.events {
+            if let EventType::Output(output) = &event.event_type {
+                out.write_all(&output.data)?;
+            }
+        }
+        out.flush()?;
+        result
+    };
+    Ok(result.status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::Event;
+
+    const ECHO_WAT: &str = r#"
+(module
+  (import "omni" "emit" (func $emit (param i32 i32)))
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) i32.const 1024)
+  (func (export "run") (param $ptr i32) (param $len i32) (result i32)
+    (call $emit (local.get $ptr) (local.get $len))
+    i32.const 0))
+"#;
+
+    fn echo_agent(agents_dir: &Path) {
+        let dir = agents_dir.join("echo");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("echo.wat"), ECHO_WAT).unwrap();
+        std::fs::write(
+            dir.join("manifest.toml"),
+            r#"
+schema_version = "0.1"
+name = "echo"
+version = "0.1.0"
+entry = "echo.wat"
+sandbox = "wasm"
+capabilities = []
+oauth_scopes = []
+
+[resources]
+cpu = "500m"
+mem = "512Mi"
+
+[ui]
+hints = []
+"#,
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stream_events_writes_ndjson() {
+        let dir = tempfile::tempdir().unwrap();
+        echo_agent(dir.path());
+        let mut config = Config::default();
+        config.state.db_path = Some(dir.path().join("state.db").to_string_lossy().into_owned());
+
+        let mut out = Vec::new();
+        let status = run_agent(&config, dir.path(), "echo", "hi", true, &mut out).await.unwrap();
+        assert_eq!(status, AgentStatus::Completed);
+
+        // The input, the emitted chunk, then the final chunk, one per line
+        let events: Vec<Event> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| Event::from_json(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|e| e.agent_id == "echo"));
+        assert!(matches!(&events[1].event_type, EventType::Output(o) if o.data == b"hi"));
+
+        let mut out = Vec::new();
+        run_agent(&config, dir.path(), "echo", "hi", false, &mut out).await.unwrap();
+        assert_eq!(out, b"hi");
+
+        let err = run_agent(&config, dir.path(), "missing", "", true, &mut Vec::new()).await.unwrap_err();
+        assert!(err.to_string().contains("not found"));
+    }
+}
@@ -3,6 +3,9 @@
 //! Phase 1: Core + TUI implementation
 
 use anyhow::Result;
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{info, warn};
 use tracing_subscriber;
 
@@ -10,53 +13,438 @@ mod utils;
 mod shell;
 mod tui;
 mod graphics;
+mod diagnostics;
+mod headless;
+mod agents;
+mod media;
+mod notifications;
+mod oauth;
+mod platform;
+mod state;
+mod workspace;
 
-use crate::utils::config::{Config, load_config};
+use crate::agents::runtime::{default_agents_dir, SHUTDOWN_GRACE};
+use crate::agents::{AgentRuntime, AgentStatus, CapabilityManager};
+use crate::oauth::consent::ConsentLedger;
+use crate::oauth::vault::{TokenVault, PASSPHRASE_ENV};
+use crate::state::artifact_index::ArtifactIndex;
+use crate::state::sqlite::{state_db_path, SqliteStore};
+use crate::utils::config::{Config, ThemeConfig, load_config_from, GRAPHICS_BACKENDS};
+use crate::utils::profiles::{Profiles, DEFAULT_PROFILE};
+use crate::shell::history::{default_history_path, History};
+use crate::tui::capability_review::GrantRow;
 use crate::tui::dashboard::Dashboard;
+use crate::tui::theme::resolve_theme_config;
+use crate::utils::build_info::build_info;
+use crate::utils::idle::IdleAction;
+use crate::utils::telemetry::TelemetryCollector;
+use crate::workspace::selection::workspace_hash;
+use crate::workspace::{RetentionPolicy, RetentionSweeper};
+
+/// Command-line arguments
+#[derive(Parser, Debug)]
+#[command(name = "omniscient-shell", version, about)]
+struct Cli {
+    /// Print git SHA, build date, target and enabled features, then exit
+    #[arg(long)]
+    build_info: bool,
+
+    /// Check PowerShell, graphics, vault, config and database, print the results and exit
+    #[arg(long)]
+    doctor: bool,
+
+    /// Config file to use instead of ~/.omniscient/config.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Config profile from ~/.omniscient/profiles/<NAME>.toml; remembered for later launches
+    #[arg(long, value_name = "NAME", conflicts_with = "config")]
+    profile: Option<String>,
+
+    /// Theme for this session: a built-in name or a theme file; the saved config is unchanged
+    #[arg(long, value_name = "NAME|PATH")]
+    theme: Option<String>,
+
+    /// Use only this graphics backend, skipping negotiation; fails instead of falling back
+    #[arg(long, value_name = "NAME", value_parser = clap::builder::PossibleValuesParser::new(GRAPHICS_BACKENDS))]
+    graphics: Option<String>,
+
+    /// Run this agent from ~/.omniscient/agents without the dashboard, printing its output
+    #[arg(long, value_name = "NAME")]
+    agent: Option<String>,
+
+    /// Input for the --agent run
+    #[arg(long, value_name = "TEXT", requires = "agent", default_value = "")]
+    input: String,
+
+    /// With --agent, write every event to stdout as a JSON line as it happens
+    #[arg(long, requires = "agent")]
+    stream_events: bool,
+}
+
+/// Config file named by `--config` or `--profile`, else the active profile's
+fn config_path(cli: &Cli, profiles: &Profiles) -> Result<PathBuf> {
+    if let Some(path) = &cli.config {
+        return Ok(path.clone());
+    }
+    let Some(name) = &cli.profile else {
+        return Ok(profiles.active_path());
+    };
+
+    let path = profiles.path(name)?;
+    if name != DEFAULT_PROFILE && !path.exists() {
+        anyhow::bail!("Profile '{}' not found; create {} first", name, path.display());
+    }
+    info!("Using profile '{}'", name);
+    Ok(path)
+}
+
+/// Make `--profile` the active profile for later launches, if it isn't already
+fn remember_profile(cli: &Cli, profiles: &Profiles) -> Result<()> {
+    match &cli.profile {
+        Some(name) if *name != profiles.active() => profiles.set_active(name),
+        _ => Ok(()),
+    }
+}
+
+/// Read the vault passphrase from the terminal without echoing it
+fn prompt_passphrase() -> Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use std::io::{IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("No terminal to ask for the vault passphrase; set {}", PASSPHRASE_ENV);
+    }
+    eprint!("Vault passphrase: ");
+    std::io::stderr().flush()?;
+
+    crossterm::terminal::enable_raw_mode()?;
+    let mut pass = String::new();
+    let read = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e.into()),
+        };
+        match key.code {
+            KeyCode::Enter => break Ok(()),
+            KeyCode::Esc => break Err(anyhow::anyhow!("Vault unlock cancelled")),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                break Err(anyhow::anyhow!("Vault unlock cancelled"))
+            }
+            KeyCode::Backspace => {
+                pass.pop();
+            }
+            KeyCode::Char(c) => pass.push(c),
+            _ => {}
+        }
+    };
+    crossterm::terminal::disable_raw_mode()?;
+    eprintln!();
+    read.map(|()| pass)
+}
+
+/// Load the config at `path` and apply `--theme` and `--graphics`
+fn resolve_config(cli: &Cli, path: &Path) -> Result<Config> {
+    let mut config = if cli.config.is_some() || cli.profile.is_some() {
+        load_config_from(path)?
+    } else {
+        match load_config_from(path) {
+            Ok(cfg) => {
+                info!("Configuration loaded successfully");
+                cfg
+            }
+            Err(e) => {
+                warn!("Failed to load config, using defaults: {}", e);
+                Config::default()
+            }
+        }
+    };
+
+    if let Some(spec) = &cli.theme {
+        let theme = resolve_theme_config(spec)?;
+        info!("Using theme '{}' from --theme", theme.name);
+        config.theme = ThemeConfig {
+            strict_contrast: config.theme.strict_contrast,
+            ..theme
+        };
+    }
+    if let Some(backend) = &cli.graphics {
+        config.graphics.pin = Some(backend.clone());
+    }
+    Ok(config)
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt()
+    let cli = Cli::parse();
+
+    if cli.build_info {
+        println!("{}", build_info());
+        return Ok(());
+    }
+
+    if cli.doctor {
+        let config_path = config_path(&cli, &Profiles::default())?;
+        let results = diagnostics::run(&config_path).await;
+        for result in &results {
+            println!("{}", result);
+        }
+        if results.iter().any(|r| r.status == diagnostics::CheckStatus::Fail) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Initialize logging; headless runs keep stdout for the agent
+    let logging = tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::from_default_env()
                 .add_directive(tracing::Level::INFO.into())
-        )
-        .init();
+        );
+    if cli.agent.is_some() {
+        logging.with_writer(std::io::stderr).init();
+    } else {
+        logging.init();
+    }
 
     info!("Omniscient Shell v0.1.0 starting...");
 
     // Load configuration
-    let config = match load_config() {
-        Ok(cfg) => {
-            info!("Configuration loaded successfully");
-            cfg
-        }
-        Err(e) => {
-            warn!("Failed to load config, using defaults: {}", e);
-            Config::default()
-        }
-    };
+    let profiles = Profiles::default();
+    let config_path = config_path(&cli, &profiles)?;
+    let config = resolve_config(&cli, &config_path)?;
 
-    // Validate schema version
-    if config.version != "0.1" {
-        anyhow::bail!("Unsupported config version: {}. Expected 0.1. Please update your config file.", config.version);
+    if let Some(name) = &cli.agent {
+        let status = headless::run_agent(
+            &config,
+            &default_agents_dir(),
+            name,
+            &cli.input,
+            cli.stream_events,
+            &mut std::io::stdout(),
+        )
+        .await?;
+        if let AgentStatus::Failed(reason) = status {
+            tracing::error!("Agent {} failed: {}", name, reason);
+            std::process::exit(1);
+        }
+        return Ok(());
     }
 
+    // Only an interactive session switches the active profile
+    remember_profile(&cli, &profiles)?;
+
     // Initialize graphics backend
-    let graphics_backend = graphics::negotiate_backend(&config.graphics)?;
+    let telemetry = Arc::new(TelemetryCollector::new(config.telemetry.clone()));
+    let _flusher = telemetry.spawn_flusher().await;
+    let graphics_backend = graphics::negotiate_backend(&config.graphics, &telemetry).await?;
     info!("Graphics backend selected: {:?}", graphics_backend.backend_type());
 
     // Initialize PowerShell integration
-    let mut shell_integration = shell::PowerShellIntegration::new()?;
+    let history_path = config.shell.history_file.as_ref().map(PathBuf::from).unwrap_or_else(default_history_path);
+    let history = History::load(&history_path, config.shell.history_size).unwrap_or_else(|e| {
+        warn!("{:#}; history won't be saved this session", e);
+        History::new(config.shell.history_size)
+    });
+    let shell_integration = shell::PowerShellIntegration::new()?.with_history(history);
     info!("PowerShell integration initialized");
 
+    // Agents run against the grants and consent trail saved in the state database
+    let store = Arc::new(SqliteStore::new(&state_db_path(&config.state))?);
+    let capabilities = Arc::new(CapabilityManager::new_persistent(store.clone()).await?);
+    let ledger = Arc::new(ConsentLedger::new_persistent(store.clone()));
+    let runtime = match AgentRuntime::new() {
+        Ok(runtime) => Some(Arc::new(
+            runtime
+                .with_capability_manager(capabilities.clone())
+                .with_ledger(ledger.clone())
+                .with_agents_config(&config.agents),
+        )),
+        Err(e) => {
+            warn!("Agents are unavailable this session: {}", e);
+            None
+        }
+    };
+
+    // Prune the configured workspace's artifacts in the background
+    let sweeper = match &config.workspace.root {
+        Some(root) => match RetentionPolicy::from_config(&config.retention) {
+            Ok(policy) => RetentionSweeper::for_workspace(
+                policy,
+                Arc::new(ArtifactIndex::new(store.clone())),
+                workspace_hash(Path::new(root)),
+                config.retention.sweep_interval_minutes,
+                Some(telemetry.clone()),
+            ),
+            Err(e) => {
+                warn!("Retention sweeps are off this session: {:#}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let vault = match TokenVault::from_config(&config.vault) {
+        Ok(vault) => Some(Arc::new(vault)),
+        Err(e) => {
+            warn!("Vault is unavailable this session: {}", e);
+            None
+        }
+    };
+    // An encrypted vault starts locked; unlock it before the dashboard takes over the terminal
+    if let Some(vault) = &vault {
+        if let Err(e) = vault.unlock_at_startup(prompt_passphrase).await {
+            warn!("Vault stays locked this session: {}", e);
+        }
+    }
+    // Only an encrypted vault has keys to rotate
+    let rotatable = vault.clone().filter(|_| config.vault.backend == "encrypted_sqlite");
+
     // Create and run dashboard
+    let workspace_root = config.workspace.root.clone();
     let mut dashboard = Dashboard::new(config, graphics_backend, shell_integration)?;
+    // The dashboard re-themes if the workspace's own config changes the theme
+    if let Some(root) = workspace_root {
+        if let Err(e) = dashboard.workspace().select(&root).await {
+            warn!("Starting without a workspace: {}", e);
+        }
+    }
+    dashboard.set_config_path(config_path);
+    dashboard.set_profiles(profiles);
+    let (idle_vault, idle_capabilities) = (vault.clone(), capabilities.clone());
+    dashboard.on_idle(move |actions| {
+        let (vault, capabilities, actions) = (idle_vault.clone(), idle_capabilities.clone(), actions.to_vec());
+        tokio::spawn(async move {
+            for action in actions {
+                match action {
+                    IdleAction::LockVault => {
+                        if let Some(vault) = &vault {
+                            vault.lock().await;
+                        }
+                    }
+                    IdleAction::RevokeShortLived => {
+                        capabilities.revoke_short_lived().await;
+                    }
+                    // The dashboard dims itself
+                    IdleAction::DimUi => {}
+                }
+            }
+        });
+    });
+    if let Some(vault) = rotatable {
+        dashboard.on_vault_rotate(vault.rotate_handler());
+    }
+    let (listed, revoking) = (capabilities.clone(), capabilities.clone());
+    dashboard.on_capability_review(
+        move || {
+            let listed = listed.clone();
+            async move { listed.summaries().await.into_iter().map(GrantRow::from).collect() }
+        },
+        move |request| {
+            let (capabilities, ledger, request) = (revoking.clone(), ledger.clone(), request.clone());
+            tokio::spawn(async move {
+                let constraint = request.constraint.as_deref();
+                if let Err(e) = capabilities
+                    .revoke_listed(&request.agent_id, &request.capability, constraint, &ledger)
+                    .await
+                {
+                    warn!("Failed to revoke {} from {}: {}", request.capability, request.agent_id, e);
+                }
+            });
+        },
+    );
+    dashboard.on_shutdown(move || async move {
+        if let Some(runtime) = runtime {
+            if let Err(e) = runtime.shutdown(SHUTDOWN_GRACE).await {
+                warn!("Failed to stop running agents: {}", e);
+            }
+        }
+        if let Some(sweeper) = sweeper {
+            sweeper.shutdown().await;
+        }
+    });
     info!("Dashboard initialized, starting main loop...");
     
     dashboard.run().await?;
 
+    if let Err(e) = telemetry.flush().await {
+        warn!("{}", e);
+    }
     info!("Omniscient Shell shutting down");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_flag_overrides_config_theme() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        let mut saved = Config::default();
+        saved.theme.name = "Mine".to_string();
+        saved.theme.strict_contrast = true;
+        saved.save(&config_path).unwrap();
+
+        let theme_path = dir.path().join("paper.toml");
+        std::fs::write(
+            &theme_path,
+            "name = \"Paper\"\nbackground = \"#ffffff\"\nforeground = \"#1f2328\"\naccent = \"#0969da\"\n",
+        )
+        .unwrap();
+
+        let cli = Cli::parse_from([
+            "omniscient-shell",
+            "--config",
+            config_path.to_str().unwrap(),
+            "--theme",
+            theme_path.to_str().unwrap(),
+        ]);
+        let config = resolve_config(&cli, &config_path).unwrap();
+        assert_eq!(config.theme.name, "Paper");
+        assert_eq!(config.theme.background, "#ffffff");
+        assert!(config.theme.strict_contrast);
+
+        let cli = Cli::parse_from(["omniscient-shell", "--config", config_path.to_str().unwrap()]);
+        assert_eq!(resolve_config(&cli, &config_path).unwrap().theme.name, "Mine");
+
+        let cli = Cli::parse_from(["omniscient-shell", "--config", config_path.to_str().unwrap(), "--graphics", "kitty"]);
+        assert_eq!(resolve_config(&cli, &config_path).unwrap().graphics.pin.as_deref(), Some("kitty"));
+        assert!(Cli::try_parse_from(["omniscient-shell", "--graphics", "sixel"]).is_err());
+    }
+
+    #[test]
+    fn test_profile_flag_loads_profile_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let profiles = Profiles::new(dir.path());
+        let mut work = Config::default();
+        work.theme.name = "Work".to_string();
+        work.save(&profiles.dir().join("work.toml")).unwrap();
+
+        let cli = Cli::parse_from(["omniscient-shell", "--profile", "work"]);
+        let path = config_path(&cli, &profiles).unwrap();
+        assert_eq!(path, profiles.dir().join("work.toml"));
+        assert_eq!(resolve_config(&cli, &path).unwrap().theme.name, "Work");
+
+        // Resolving the path alone, as --doctor does, leaves the active profile alone
+        let active = dir.path().join("active_profile");
+        assert!(!active.exists());
+
+        // Once remembered, the next launch without --profile stays on it
+        remember_profile(&cli, &profiles).unwrap();
+        let cli = Cli::parse_from(["omniscient-shell"]);
+        assert_eq!(config_path(&cli, &profiles).unwrap(), profiles.dir().join("work.toml"));
+
+        // Remembering the profile already active doesn't rewrite the file
+        let modified = std::fs::metadata(&active).unwrap().modified().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        remember_profile(&Cli::parse_from(["omniscient-shell", "--profile", "work"]), &profiles).unwrap();
+        assert_eq!(std::fs::metadata(&active).unwrap().modified().unwrap(), modified);
+
+        let cli = Cli::parse_from(["omniscient-shell", "--profile", "missing"]);
+        assert!(config_path(&cli, &profiles).is_err());
+        assert!(Cli::try_parse_from(["omniscient-shell", "--profile", "work", "--config", "x.toml"]).is_err());
+    }
+}
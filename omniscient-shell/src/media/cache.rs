@@ -1,11 +1,14 @@
 //! Media cache management
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::utils::config::MediaConfig;
+
 /// Media cache entry
 #[derive(Debug, Clone)]
 pub struct CacheEntry {
@@ -14,20 +17,72 @@ pub struct CacheEntry {
     pub last_accessed: std::time::SystemTime,
 }
 
+/// Cache usage statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheStats {
+    pub entry_count: usize,
+    pub total_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// Fraction of lookups that hit (0.0 when there were no lookups)
+    pub hit_rate: f64,
+    pub evictions: u64,
+}
+
+/// Called with the key of each entry pruned from the cache
+pub type EvictCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
 /// Media cache with intelligent pruning
 pub struct MediaCache {
     entries: Arc<RwLock<HashMap<String, CacheEntry>>>,
+    dir: PathBuf,
     max_size_mb: u64,
+    on_evict: Option<EvictCallback>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
 }
 
 impl MediaCache {
     pub fn new(max_size_mb: u64) -> Self {
         MediaCache {
             entries: Arc::new(RwLock::new(HashMap::new())),
+            dir: default_media_cache_dir(),
             max_size_mb,
+            on_evict: None,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
         }
     }
 
+    /// Create the cache from the media config, creating its directory
+    pub fn from_config(config: &MediaConfig) -> Result<Self> {
+        let dir = media_cache_dir(config);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create media cache directory {}", dir.display()))?;
+
+        let mut cache = Self::new(config.max_size_mb);
+        cache.dir = dir;
+        Ok(cache)
+    }
+
+    /// Directory cached files are written to
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn max_size_mb(&self) -> u64 {
+        self.max_size_mb
+    }
+
+    /// Be told when pruning evicts an entry, e.g. to regenerate a displayed thumbnail.
+    ///
+    /// The callback runs after the cache lock is released, so it may use the cache.
+    pub fn on_evict(&mut self, callback: impl Fn(&str) + Send + Sync + 'static) {
+        self.on_evict = Some(Arc::new(callback));
+    }
+
     /// Add entry to cache
     pub async fn add(&self, key: String, path: PathBuf, size_bytes: u64) -> Result<()> {
         let entry = CacheEntry {
@@ -36,11 +91,19 @@ impl MediaCache {
             last_accessed: std::time::SystemTime::now(),
         };
 
-        let mut entries = self.entries.write().await;
-        entries.insert(key, entry);
+        let evicted = {
+            let mut entries = self.entries.write().await;
+            entries.insert(key, entry);
+
+            // Check if pruning needed
+            self.prune_if_needed(&mut entries).await?
+        };
 
-        // Check if pruning needed
-        self.prune_if_needed(&mut entries).await?;
+        if let Some(on_evict) = &self.on_evict {
+            for key in &evicted {
+                on_evict(key);
+            }
+        }
 
         Ok(())
     }
@@ -50,14 +113,49 @@ impl MediaCache {
         let mut entries = self.entries.write().await;
         if let Some(entry) = entries.get_mut(key) {
             entry.last_accessed = std::time::SystemTime::now();
+            self.hits.fetch_add(1, Ordering::Relaxed);
             Some(entry.path.clone())
         } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
             None
         }
     }
 
-    /// Prune cache if needed (LRU)
-    async fn prune_if_needed(&self, entries: &mut HashMap<String, CacheEntry>) -> Result<()> {
+    /// Drop every entry generated from `path`, whatever its mtime, deleting
+    /// the cached files; returns how many were dropped
+    pub async fn invalidate_path(&self, path: &Path) -> usize {
+        let prefix = format!("{}@", path.display());
+        let mut entries = self.entries.write().await;
+        let stale: Vec<String> = entries.keys().filter(|key| key.starts_with(&prefix)).cloned().collect();
+        for key in &stale {
+            if let Some(entry) = entries.remove(key) {
+                let _ = std::fs::remove_file(&entry.path);
+                tracing::debug!("Invalidated cache entry: {}", key);
+            }
+        }
+        stale.len()
+    }
+
+    /// Get cache statistics
+    pub async fn stats(&self) -> CacheStats {
+        let entries = self.entries.read().await;
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+
+        CacheStats {
+            entry_count: entries.len(),
+            total_bytes: entries.values().map(|e| e.size_bytes).sum(),
+            hits,
+            misses,
+            hit_rate: if lookups > 0 { hits as f64 / lookups as f64 } else { 0.0 },
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Prune cache if needed (LRU), returning the evicted keys
+    async fn prune_if_needed(&self, entries: &mut HashMap<String, CacheEntry>) -> Result<Vec<String>> {
+        let mut evicted = Vec::new();
         let total_size: u64 = entries.values().map(|e| e.size_bytes).sum();
         let max_size_bytes = self.max_size_mb * 1024 * 1024;
 
@@ -66,28 +164,36 @@ impl MediaCache {
                 total_size / (1024 * 1024), self.max_size_mb);
 
             // Sort by last accessed (LRU)
-            let mut sorted: Vec<_> = entries.iter().collect();
-            sorted.sort_by_key(|(_, entry)| entry.last_accessed);
+            let mut sorted: Vec<(String, std::time::SystemTime)> = entries
+                .iter()
+                .map(|(key, entry)| (key.clone(), entry.last_accessed))
+                .collect();
+            sorted.sort_by_key(|(_, last_accessed)| *last_accessed);
 
             // Remove oldest entries until under limit
             let mut current_size = total_size;
-            for (key, entry) in sorted.iter() {
+            for (key, _) in sorted {
                 if current_size <= max_size_bytes {
                     break;
                 }
 
+                let Some(entry) = entries.remove(&key) else {
+                    continue;
+                };
+
                 // Delete file
                 if let Err(e) = std::fs::remove_file(&entry.path) {
                     tracing::warn!("Failed to delete cached file {}: {}", entry.path.display(), e);
                 }
 
-                entries.remove(*key);
                 current_size -= entry.size_bytes;
+                self.evictions.fetch_add(1, Ordering::Relaxed);
                 tracing::debug!("Pruned cache entry: {}", key);
+                evicted.push(key);
             }
         }
 
-        Ok(())
+        Ok(evicted)
     }
 
     /// Clear entire cache
@@ -104,6 +210,34 @@ impl MediaCache {
     }
 }
 
+/// Cache key for the preview of `path` as it is now: the path plus its
+/// modification time, so an edited file never hits the old preview
+pub fn preview_key(path: &Path) -> Result<String> {
+    let modified = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .with_context(|| format!("Failed to read modification time of {}", path.display()))?;
+    let nanos = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    Ok(format!("{}@{}", path.display(), nanos))
+}
+
+/// Default media cache directory (`~/.omniscient/media-cache`)
+pub fn default_media_cache_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".omniscient").join("media-cache")
+}
+
+/// The configured media cache directory, or the default
+pub fn media_cache_dir(config: &MediaConfig) -> PathBuf {
+    config
+        .cache_dir
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(default_media_cache_dir)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,4 +255,115 @@ mod tests {
         let path = cache.get("test").await;
         assert!(path.is_some());
     }
+
+    #[tokio::test]
+    async fn test_stats_hit_rate() {
+        let cache = MediaCache::new(100);
+        assert_eq!(cache.get("test").await, None);
+
+        cache.add("test".to_string(), PathBuf::from("/tmp/test.jpg"), 1024).await.unwrap();
+        assert!(cache.get("test").await.is_some());
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.entry_count, 1);
+        assert_eq!(stats.total_bytes, 1024);
+        assert_eq!(stats.hit_rate, 0.5);
+        assert_eq!(stats.evictions, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stats_evictions() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = MediaCache::new(1); // 1 MB
+        let half_mb = 512 * 1024;
+
+        for name in ["a", "b", "c"] {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, b"cached").unwrap();
+            cache.add(name.to_string(), path, half_mb).await.unwrap();
+        }
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.entry_count, 2);
+        assert_eq!(stats.total_bytes, 2 * half_mb);
+    }
+
+    #[tokio::test]
+    async fn test_on_evict_called_for_each_pruned_key() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut cache = MediaCache::new(1); // 1 MB
+        let evicted = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = evicted.clone();
+        cache.on_evict(move |key| seen.lock().unwrap().push(key.to_string()));
+
+        let quarter_mb = 256 * 1024;
+        for name in ["a", "b", "c", "d"] {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, b"cached").unwrap();
+            cache.add(name.to_string(), path, quarter_mb).await.unwrap();
+            // Distinct access times keep the LRU order deterministic
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert!(evicted.lock().unwrap().is_empty());
+
+        // A large entry forces out the two oldest
+        let path = temp_dir.path().join("big");
+        std::fs::write(&path, b"cached").unwrap();
+        cache.add("big".to_string(), path, 2 * quarter_mb).await.unwrap();
+
+        assert_eq!(*evicted.lock().unwrap(), vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(cache.stats().await.evictions, 2);
+    }
+
+    #[tokio::test]
+    async fn test_from_config_honors_dir_and_size() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path().join("media");
+        let config = MediaConfig {
+            cache_dir: Some(dir.to_string_lossy().into_owned()),
+            max_size_mb: 1,
+            ..MediaConfig::default()
+        };
+
+        let cache = MediaCache::from_config(&config).unwrap();
+        assert_eq!(cache.dir(), dir);
+        assert!(dir.is_dir());
+        assert_eq!(cache.max_size_mb(), 1);
+
+        // The 1 MB limit applies
+        for name in ["a", "b", "c"] {
+            let path = dir.join(name);
+            std::fs::write(&path, b"cached").unwrap();
+            cache.add(name.to_string(), path, 512 * 1024).await.unwrap();
+        }
+        assert_eq!(cache.stats().await.evictions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_path_drops_every_version() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = MediaCache::new(100);
+        let source = temp_dir.path().join("clip.mp4");
+        std::fs::write(&source, b"v1").unwrap();
+
+        let key = preview_key(&source).unwrap();
+        assert!(key.starts_with(&format!("{}@", source.display())));
+        let cached = temp_dir.path().join("preview.png");
+        std::fs::write(&cached, b"png").unwrap();
+        cache.add(key.clone(), cached.clone(), 3).await.unwrap();
+        cache.add("other.mp4@1".to_string(), PathBuf::from("/tmp/other.png"), 3).await.unwrap();
+
+        assert_eq!(cache.invalidate_path(&source).await, 1);
+        assert_eq!(cache.get(&key).await, None);
+        assert!(!cached.exists());
+        assert!(cache.get("other.mp4@1").await.is_some());
+    }
+
+    #[test]
+    fn test_default_cache_dir() {
+        let config = MediaConfig::default();
+        assert_eq!(config.max_size_mb, 512);
+        assert!(media_cache_dir(&config).ends_with(".omniscient/media-cache"));
+    }
 }
@@ -3,6 +3,8 @@
 use anyhow::Result;
 use std::path::Path;
 
+use crate::graphics::image::RgbaImage;
+
 /// FFmpeg wrapper for media processing
 pub struct FFmpegProcessor {
     // Configuration
@@ -33,6 +35,36 @@ impl FFmpegProcessor {
         Ok(())
     }
 
+    /// Decode the frame at `timestamp_s`, scaled to `width` x `height`, as
+    /// RGBA ready for a graphics backend
+    pub async fn decode_frame(&self, _input: &Path, _timestamp_s: f64, width: u32, height: u32) -> Result<RgbaImage> {
+        #[cfg(feature = "media")]
+        {
+            // Real implementation would decode with ffmpeg-next, converting to RGBA
+            tracing::info!("Decoding frame (stub)");
+            RgbaImage::filled(width, height, [0, 0, 0, 255])
+        }
+        #[cfg(not(feature = "media"))]
+        {
+            let _ = (width, height);
+            anyhow::bail!("Media support not compiled in. Enable the 'media' feature.")
+        }
+    }
+
+    /// Probe media duration in seconds
+    pub async fn probe_duration(&self, _input: &Path) -> Result<f64> {
+        #[cfg(feature = "media")]
+        {
+            // Real implementation would read the container duration via ffmpeg-next
+            tracing::info!("Probing duration (stub)");
+            Ok(0.0)
+        }
+        #[cfg(not(feature = "media"))]
+        {
+            anyhow::bail!("Media support not compiled in. Enable the 'media' feature.")
+        }
+    }
+
     /// Generate waveform
     pub async fn generate_waveform(&self, _input: &Path, _output: &Path) -> Result<()> {
         tracing::info!("Generating waveform (stub)");
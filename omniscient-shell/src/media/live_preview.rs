@@ -0,0 +1,127 @@
+//! Preview pane contents that follow the previewed file
+//!
+//! Previews are cached under `preview_key` (path + mtime). When the workspace
+//! watcher reports the file modified, every cached version is invalidated and
+//! the preview regenerated, so the pane never shows a stale thumbnail.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::media::cache::{preview_key, MediaCache};
+use crate::media::preview::PreviewAdapter;
+use crate::workspace::watcher::WorkspaceWatcher;
+
+/// The file shown in the preview pane and its current preview
+pub struct LivePreview {
+    cache: Arc<MediaCache>,
+    adapter: Arc<PreviewAdapter>,
+    watcher: WorkspaceWatcher,
+    shown: Option<PathBuf>,
+    data: Option<Vec<u8>>,
+}
+
+impl LivePreview {
+    pub fn new(cache: Arc<MediaCache>, adapter: Arc<PreviewAdapter>, watcher: WorkspaceWatcher) -> Self {
+        LivePreview {
+            cache,
+            adapter,
+            watcher,
+            shown: None,
+            data: None,
+        }
+    }
+
+    /// File currently previewed
+    pub fn shown(&self) -> Option<&Path> {
+        self.shown.as_deref()
+    }
+
+    /// Preview of the shown file, if one was generated
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+
+    /// Preview `path`, from the cache when it hasn't changed, and watch it
+    pub async fn show(&mut self, path: &Path) -> Result<()> {
+        if let Some(previous) = self.shown.take() {
+            self.watcher.unwatch(&previous);
+        }
+        self.watcher.watch(path);
+        self.shown = Some(path.to_path_buf());
+        self.data = None;
+        self.data = Some(self.load(path).await?);
+        Ok(())
+    }
+
+    /// Regenerate the preview if the shown file was modified; true when the
+    /// preview changed. Call on each UI tick.
+    pub async fn refresh(&mut self) -> Result<bool> {
+        let Some(path) = self.shown.clone() else {
+            return Ok(false);
+        };
+        if !self.watcher.poll().contains(&path) {
+            return Ok(false);
+        }
+
+        let dropped = self.cache.invalidate_path(&path).await;
+        tracing::debug!("{} changed, invalidated {} cached previews", path.display(), dropped);
+        self.data = None;
+        self.data = Some(self.load(&path).await?);
+        Ok(true)
+    }
+
+    async fn load(&self, path: &Path) -> Result<Vec<u8>> {
+        let key = preview_key(path)?;
+        if let Some(cached) = self.cache.get(&key).await {
+            match tokio::fs::read(&cached).await {
+                Ok(data) => return Ok(data),
+                Err(e) => tracing::debug!("Cached preview {} unreadable: {}", cached.display(), e),
+            }
+        }
+
+        let data = self.adapter.generate_preview(path).await?;
+        tokio::fs::create_dir_all(self.cache.dir()).await?;
+        let file = self.cache.dir().join(format!("preview-{}", uuid::Uuid::new_v4()));
+        tokio::fs::write(&file, &data).await?;
+        self.cache.add(key, file, data.len() as u64).await?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::MediaConfig;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_modified_file_invalidates_cached_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = MediaConfig {
+            cache_dir: Some(dir.path().join("cache").to_string_lossy().into_owned()),
+            ..MediaConfig::default()
+        };
+        let cache = Arc::new(MediaCache::from_config(&config).unwrap());
+        let adapter = Arc::new(PreviewAdapter::from_config(&config));
+        let mut preview = LivePreview::new(cache.clone(), adapter, WorkspaceWatcher::new(Duration::ZERO));
+
+        let source = dir.path().join("render.png");
+        std::fs::write(&source, b"first render").unwrap();
+        preview.show(&source).await.unwrap();
+        assert_eq!(preview.data(), Some(&b"[image] render.png"[..]));
+        let old_key = preview_key(&source).unwrap();
+        assert!(!preview.refresh().await.unwrap());
+
+        // Make sure the mtime moves even on coarse-grained filesystems
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(&source, b"second render, larger").unwrap();
+        assert!(preview.refresh().await.unwrap());
+
+        let new_key = preview_key(&source).unwrap();
+        assert_ne!(old_key, new_key);
+        assert_eq!(cache.get(&old_key).await, None);
+        assert!(cache.get(&new_key).await.is_some());
+        assert_eq!(cache.stats().await.entry_count, 1);
+    }
+}
@@ -3,7 +3,9 @@
 pub mod ffmpeg;
 pub mod cache;
 pub mod preview;
+pub mod live_preview;
 
 pub use ffmpeg::FFmpegProcessor;
-pub use cache::MediaCache;
-pub use preview::PreviewAdapter;
+pub use cache::{MediaCache, CacheStats, EvictCallback, preview_key};
+pub use preview::{PreviewAdapter, PreviewStrategy};
+pub use live_preview::LivePreview;
@@ -1,23 +1,132 @@
 //! Media preview adapters
 
 use anyhow::Result;
-use std::path::Path;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+use crate::graphics::backend::{GraphicsBackend, Region};
+use crate::media::cache::media_cache_dir;
+use crate::media::ffmpeg::FFmpegProcessor;
+use crate::utils::config::MediaConfig;
+
+/// Preview generation strategies, tried in order until one succeeds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewStrategy {
+    /// Thumbnail embedded in the container (cover art, poster frame)
+    EmbeddedThumbnail,
+    /// Frame from the middle of the media
+    MidpointFrame,
+    /// First decodable frame
+    FirstFrame,
+    /// Typed placeholder; always succeeds
+    Placeholder,
+}
+
+impl PreviewStrategy {
+    /// Fallback chain used by `generate_preview`
+    pub const CHAIN: [PreviewStrategy; 4] = [
+        PreviewStrategy::EmbeddedThumbnail,
+        PreviewStrategy::MidpointFrame,
+        PreviewStrategy::FirstFrame,
+        PreviewStrategy::Placeholder,
+    ];
+}
 
 /// Preview adapter for media files
 pub struct PreviewAdapter {
-    // Configuration
+    /// Bounds concurrent preview generations; extra requests queue for a permit
+    permits: Arc<Semaphore>,
+    concurrency: usize,
+    /// Where FFmpeg writes generated previews
+    output_dir: PathBuf,
 }
 
 impl PreviewAdapter {
+    /// Create an adapter allowing one concurrent generation per CPU
     pub fn new() -> Self {
-        PreviewAdapter {}
+        Self::with_concurrency(default_concurrency())
+    }
+
+    /// Create an adapter allowing at most `limit` concurrent generations
+    pub fn with_concurrency(limit: usize) -> Self {
+        let concurrency = limit.max(1);
+        PreviewAdapter {
+            permits: Arc::new(Semaphore::new(concurrency)),
+            concurrency,
+            output_dir: std::env::temp_dir(),
+        }
+    }
+
+    /// Create an adapter from the media config, writing previews to the media cache directory
+    pub fn from_config(config: &MediaConfig) -> Self {
+        let mut adapter = match config.preview_concurrency {
+            Some(limit) => Self::with_concurrency(limit),
+            None => Self::new(),
+        };
+        adapter.output_dir = media_cache_dir(config);
+        adapter
+    }
+
+    /// Directory generated previews are written to
+    pub fn output_dir(&self) -> &Path {
+        &self.output_dir
+    }
+
+    /// Maximum number of concurrent generations
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Generate preview for file, falling back through `PreviewStrategy::CHAIN`
+    pub async fn generate_preview(&self, input: &Path) -> Result<Vec<u8>> {
+        let ffmpeg = FFmpegProcessor::new();
+        let (_strategy, data) = self
+            .generate_with_fallback(input, |strategy, input| {
+                run_strategy(&ffmpeg, strategy, input, &self.output_dir)
+            })
+            .await?;
+        Ok(data)
+    }
+
+    /// Try each strategy in order and return the first success
+    pub async fn generate_with_fallback<'a, F, Fut>(
+        &self,
+        input: &'a Path,
+        mut generator: F,
+    ) -> Result<(PreviewStrategy, Vec<u8>)>
+    where
+        F: FnMut(PreviewStrategy, &'a Path) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>>>,
+    {
+        let _permit = self.permits.acquire().await?;
+
+        let mut last_error = None;
+        for strategy in PreviewStrategy::CHAIN {
+            match generator(strategy, input).await {
+                Ok(data) => {
+                    tracing::info!("Generated preview for {} using {:?}", input.display(), strategy);
+                    return Ok((strategy, data));
+                }
+                Err(e) => {
+                    tracing::debug!("Preview strategy {:?} failed for {}: {}", strategy, input.display(), e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No preview strategies available")))
     }
 
-    /// Generate preview for file
-    pub async fn generate_preview(&self, _input: &Path) -> Result<Vec<u8>> {
-        // Stub implementation
-        tracing::info!("Generating preview (stub)");
-        Ok(vec![])
+    /// Run a preview generator once a concurrency permit is available
+    pub async fn generate_with<'a, F, Fut>(&self, input: &'a Path, generator: F) -> Result<Vec<u8>>
+    where
+        F: FnOnce(&'a Path) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>>>,
+    {
+        let _permit = self.permits.acquire().await?;
+        generator(input).await
     }
 
     /// Check if file type is supported
@@ -35,3 +144,222 @@ impl Default for PreviewAdapter {
         Self::new()
     }
 }
+
+/// Pixel rectangle on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Pixel area covered by `region`; None if the backend doesn't know its cell size
+pub fn region_pixels(backend: &dyn GraphicsBackend, region: &Region) -> Option<PixelRect> {
+    let (cell_w, cell_h) = backend.cell_pixel_size()?;
+    let (cell_w, cell_h) = (cell_w as u32, cell_h as u32);
+    Some(PixelRect {
+        x: region.x as u32 * cell_w,
+        y: region.y as u32 * cell_h,
+        width: region.width as u32 * cell_w,
+        height: region.height as u32 * cell_h,
+    })
+}
+
+/// Smallest whole-cell region at the origin of `bounds` that holds an
+/// `image_w` x `image_h` pixel preview, clamped to `bounds`
+pub fn snap_to_cells(
+    backend: &dyn GraphicsBackend,
+    image_w: u32,
+    image_h: u32,
+    bounds: &Region,
+) -> Option<Region> {
+    let (cell_w, cell_h) = backend.cell_pixel_size()?;
+    let cols = image_w.div_ceil(cell_w as u32).min(bounds.width as u32) as u16;
+    let rows = image_h.div_ceil(cell_h as u32).min(bounds.height as u32) as u16;
+    Some(Region { x: bounds.x, y: bounds.y, width: cols, height: rows })
+}
+
+/// Run a single strategy using FFmpeg, reading back the generated image
+async fn run_strategy(
+    ffmpeg: &FFmpegProcessor,
+    strategy: PreviewStrategy,
+    input: &Path,
+    output_dir: &Path,
+) -> Result<Vec<u8>> {
+    if strategy == PreviewStrategy::Placeholder {
+        return Ok(placeholder(input));
+    }
+
+    tokio::fs::create_dir_all(output_dir).await?;
+    let output = output_dir.join(format!("omni-preview-{}.png", uuid::Uuid::new_v4()));
+    match strategy {
+        PreviewStrategy::EmbeddedThumbnail => ffmpeg.generate_thumbnail(input, &output, 320, 180).await?,
+        PreviewStrategy::MidpointFrame => {
+            let duration = ffmpeg.probe_duration(input).await?;
+            ffmpeg.extract_frame(input, &output, duration / 2.0).await?
+        }
+        PreviewStrategy::FirstFrame => ffmpeg.extract_frame(input, &output, 0.0).await?,
+        PreviewStrategy::Placeholder => unreachable!(),
+    }
+
+    let data = tokio::fs::read(&output).await;
+    let _ = tokio::fs::remove_file(&output).await;
+    Ok(data?)
+}
+
+/// Text placeholder naming the media type, e.g. `[video] clip.mp4`
+fn placeholder(input: &Path) -> Vec<u8> {
+    let kind = match input.extension().and_then(|e| e.to_str()) {
+        Some("mp4") | Some("webm") => "video",
+        Some("jpg") | Some("png") | Some("gif") => "image",
+        _ => "file",
+    };
+    let name = input.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    format!("[{}] {}", kind, name).into_bytes()
+}
+
+fn default_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::image::RgbaImage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let adapter = Arc::new(PreviewAdapter::with_concurrency(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let mut tasks = Vec::new();
+        for _ in 0..6 {
+            let adapter = adapter.clone();
+            let running = running.clone();
+            let peak = peak.clone();
+            tasks.push(tokio::spawn(async move {
+                adapter
+                    .generate_with(Path::new("clip.mp4"), |_| async {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok(vec![])
+                    })
+                    .await
+            }));
+        }
+
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_to_next_strategy() {
+        let adapter = PreviewAdapter::with_concurrency(1);
+        let mut attempts = Vec::new();
+
+        let (strategy, data) = adapter
+            .generate_with_fallback(Path::new("clip.mp4"), |strategy, _| {
+                attempts.push(strategy);
+                async move {
+                    match strategy {
+                        PreviewStrategy::EmbeddedThumbnail => anyhow::bail!("no embedded thumbnail"),
+                        _ => Ok(b"frame".to_vec()),
+                    }
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(strategy, PreviewStrategy::MidpointFrame);
+        assert_eq!(data, b"frame");
+        assert_eq!(attempts, vec![PreviewStrategy::EmbeddedThumbnail, PreviewStrategy::MidpointFrame]);
+    }
+
+    #[tokio::test]
+    async fn test_placeholder_when_media_unreadable() {
+        let adapter = PreviewAdapter::with_concurrency(1);
+        let data = adapter.generate_preview(Path::new("/nonexistent/clip.mp4")).await.unwrap();
+        assert_eq!(data, b"[video] clip.mp4");
+    }
+
+    struct FixedCells(Option<(u16, u16)>);
+
+    impl GraphicsBackend for FixedCells {
+        fn backend_type(&self) -> crate::graphics::backend::BackendType {
+            crate::graphics::backend::BackendType::Overlay
+        }
+        fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn capabilities(&self) -> crate::graphics::backend::Capabilities {
+            Default::default()
+        }
+        fn render_image(&mut self, _: &Region, _: &RgbaImage) -> Result<()> {
+            Ok(())
+        }
+        fn render_video_frame(&mut self, _: &Region, _: &RgbaImage) -> Result<()> {
+            Ok(())
+        }
+        fn clear_region(&mut self, _: &Region) -> Result<()> {
+            Ok(())
+        }
+        fn supports_resolution(&self, _: u32, _: u32) -> bool {
+            true
+        }
+        fn benchmark(&mut self) -> Result<f32> {
+            Ok(1.0)
+        }
+        fn cell_pixel_size(&self) -> Option<(u16, u16)> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_regions_snap_to_cells() {
+        let backend = FixedCells(Some((10, 20)));
+        let bounds = Region { x: 2, y: 3, width: 40, height: 10 };
+
+        assert_eq!(
+            region_pixels(&backend, &bounds),
+            Some(PixelRect { x: 20, y: 60, width: 400, height: 200 })
+        );
+        // 320x180 needs 32 columns and 9 (rounded up) rows
+        assert_eq!(
+            snap_to_cells(&backend, 320, 180, &bounds),
+            Some(Region { x: 2, y: 3, width: 32, height: 9 })
+        );
+        // Oversized images are clamped to the bounds
+        assert_eq!(
+            snap_to_cells(&backend, 1920, 1080, &bounds),
+            Some(Region { x: 2, y: 3, width: 40, height: 10 })
+        );
+
+        let unknown = FixedCells(None);
+        assert_eq!(region_pixels(&unknown, &bounds), None);
+        assert_eq!(snap_to_cells(&unknown, 320, 180, &bounds), None);
+    }
+
+    #[test]
+    fn test_concurrency_from_config() {
+        let config = MediaConfig {
+            preview_concurrency: Some(3),
+            cache_dir: Some("/srv/media-cache".to_string()),
+            ..MediaConfig::default()
+        };
+        let adapter = PreviewAdapter::from_config(&config);
+        assert_eq!(adapter.concurrency(), 3);
+        assert_eq!(adapter.output_dir(), Path::new("/srv/media-cache"));
+        assert!(PreviewAdapter::new().concurrency() >= 1);
+    }
+}
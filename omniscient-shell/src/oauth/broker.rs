@@ -2,16 +2,34 @@
 
 use anyhow::Result;
 use oauth2::{
-    AuthUrl, ClientId, DeviceAuthorizationUrl, Scope, TokenUrl,
-    basic::BasicClient,
+    AuthUrl, AuthorizationCode, ClientId, CsrfToken, DeviceAuthorizationUrl, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, RefreshToken, RequestTokenError, Scope, TokenResponse, TokenUrl,
+    basic::{BasicClient, BasicErrorResponseType, BasicTokenResponse},
     reqwest::async_http_client,
-    DeviceAuthorizationResponse,
+    StandardDeviceAuthorizationResponse,
 };
+use oauth2::url::Url;
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
 
+use crate::oauth::consent::ConsentLedger;
+use crate::oauth::loopback::{LoopbackServer, PortRange, RedirectParams, DEFAULT_BIND_ADDR};
+use crate::oauth::pending::{PendingAuthStore, DEFAULT_PENDING_TTL};
 use crate::oauth::vault::TokenVault;
+use crate::tui::device_code::DeviceCodePrompt;
+use crate::utils::errors::{OmniError, RecoveryAction};
+
+/// Shows a device-code prompt to the user, e.g. as a card with a QR code
+pub type DevicePromptHandler = Arc<dyn Fn(&DeviceCodePrompt) + Send + Sync>;
+
+/// Sends the user to a PKCE authorization URL; the default opens the browser
+pub type AuthorizeUrlHandler = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Agent id on the consent ledger entries the broker writes; it acts for the user
+pub const BROKER_AGENT_ID: &str = "oauth-broker";
 
 /// OAuth provider configuration
 #[derive(Debug, Clone)]
@@ -21,6 +39,15 @@ pub struct ProviderConfig {
     pub token_url: String,
     pub device_auth_url: Option<String>,
     pub scopes: Vec<String>,
+    /// Fixed loopback redirect URI registered with the provider, e.g.
+    /// `http://127.0.0.1:8400/callback`; its port and path are listened on
+    pub redirect_uri: Option<String>,
+    /// Ports for the redirect listener when `redirect_uri` is unset;
+    /// overrides the broker's range
+    pub redirect_ports: Option<PortRange>,
+    /// RFC 7009 token revocation endpoint; without one, `revoke` can only
+    /// forget the token locally
+    pub revocation_url: Option<String>,
 }
 
 /// OAuth token handle (not the actual token)
@@ -29,12 +56,28 @@ pub struct TokenHandle {
     pub id: String,
     pub provider: String,
     pub scopes: Vec<String>,
+    /// When the access token expires, if the provider said
+    pub expires_at: Option<SystemTime>,
+}
+
+impl TokenHandle {
+    /// Whether the access token expires within `leeway` from now and should
+    /// be refreshed; false when the provider gave no expiry
+    pub fn needs_refresh(&self, leeway: Duration) -> bool {
+        self.expires_at.is_some_and(|at| at <= SystemTime::now() + leeway)
+    }
 }
 
 /// OAuth broker
 pub struct OAuthBroker {
     vault: Arc<TokenVault>,
     providers: Arc<RwLock<HashMap<String, ProviderConfig>>>,
+    pending: Arc<PendingAuthStore>,
+    redirect_bind: IpAddr,
+    redirect_ports: Option<PortRange>,
+    device_prompt: Option<DevicePromptHandler>,
+    authorize_url: Option<AuthorizeUrlHandler>,
+    ledger: Option<Arc<ConsentLedger>>,
 }
 
 impl OAuthBroker {
@@ -42,9 +85,61 @@ impl OAuthBroker {
         OAuthBroker {
             vault,
             providers: Arc::new(RwLock::new(HashMap::new())),
+            pending: Arc::new(PendingAuthStore::new()),
+            redirect_bind: DEFAULT_BIND_ADDR,
+            redirect_ports: None,
+            device_prompt: None,
+            authorize_url: None,
+            ledger: None,
         }
     }
 
+    /// Record token revocations in `ledger`
+    pub fn with_consent_ledger(mut self, ledger: Arc<ConsentLedger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// Restrict the PKCE redirect listener to `ports` (from `oauth.redirect_ports`)
+    pub fn with_redirect_ports(mut self, ports: Option<PortRange>) -> Self {
+        self.redirect_ports = ports;
+        self
+    }
+
+    /// Bind the PKCE redirect listener to `addr` instead of 127.0.0.1
+    pub fn with_redirect_bind(mut self, addr: IpAddr) -> Self {
+        self.redirect_bind = addr;
+        self
+    }
+
+    /// Show device-code prompts through `handler` (the TUI renders a card)
+    pub fn on_device_prompt<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&DeviceCodePrompt) + Send + Sync + 'static,
+    {
+        self.device_prompt = Some(Arc::new(handler));
+        self
+    }
+
+    /// Send users to PKCE authorization URLs through `handler` instead of
+    /// opening the browser
+    pub fn on_authorize_url<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.authorize_url = Some(Arc::new(handler));
+        self
+    }
+
+    fn client(config: &ProviderConfig) -> Result<BasicClient> {
+        Ok(BasicClient::new(
+            ClientId::new(config.client_id.clone()),
+            None,
+            AuthUrl::new(config.auth_url.clone())?,
+            Some(TokenUrl::new(config.token_url.clone())?),
+        ))
+    }
+
     /// Register a provider
     pub async fn register_provider(&self, name: String, config: ProviderConfig) {
         let mut providers = self.providers.write().await;
@@ -64,12 +159,7 @@ impl OAuthBroker {
         tracing::info!("Starting device code flow for provider: {}", provider);
 
         // Create OAuth client
-        let client = BasicClient::new(
-            ClientId::new(config.client_id.clone()),
-            None,
-            AuthUrl::new(config.auth_url.clone())?,
-            Some(TokenUrl::new(config.token_url.clone())?),
-        );
+        let client = Self::client(config)?;
 
         let client = if let Some(device_url) = &config.device_auth_url {
             client.set_device_authorization_url(DeviceAuthorizationUrl::new(device_url.clone())?)
@@ -78,7 +168,7 @@ impl OAuthBroker {
         };
 
         // Request device authorization
-        let device_auth = client
+        let device_auth: StandardDeviceAuthorizationResponse = client
             .exchange_device_code()?
             .add_scopes(scopes.iter().map(|s| Scope::new(s.clone())))
             .request_async(async_http_client)
@@ -86,78 +176,257 @@ impl OAuthBroker {
 
         // Display user code and verification URL
         tracing::info!("Device code: {}", device_auth.user_code().secret());
-        tracing::info!("Verification URL: {}", device_auth.verification_uri());
+        tracing::info!("Verification URL: {}", device_auth.verification_uri().as_str());
+        if let Some(show) = &self.device_prompt {
+            show(&DeviceCodePrompt {
+                provider: provider.to_string(),
+                user_code: device_auth.user_code().secret().clone(),
+                verification_uri: device_auth.verification_uri().to_string(),
+                complete_uri: device_auth
+                    .verification_uri_complete()
+                    .map(|uri| uri.secret().clone()),
+            });
+        }
 
-        // In a real implementation:
-        // 1. Display the code to the user in the TUI
-        // 2. Poll for token
-        // 3. Store token in vault
-        // 4. Return handle
+        // Poll at the server's interval, backing off on slow_down, until the
+        // user approves or the device code expires
+        let token = client
+            .exchange_device_access_token(&device_auth)
+            .request_async(async_http_client, tokio::time::sleep, None)
+            .await
+            .map_err(|e| anyhow::anyhow!("Device code authorization for {} failed: {}", provider, e))?;
 
-        let handle_id = uuid::Uuid::new_v4().to_string();
-        let handle = TokenHandle {
-            id: handle_id.clone(),
+        let mut handle = TokenHandle {
+            id: uuid::Uuid::new_v4().to_string(),
             provider: provider.to_string(),
             scopes,
+            expires_at: None,
         };
-
-        // Store placeholder token in vault
-        self.vault.store(&handle_id, "placeholder-token").await?;
+        self.store_token(&mut handle, &token).await?;
 
         Ok(handle)
     }
 
-    /// Request a token via PKCE flow
+    /// Request a token via PKCE flow, catching the redirect on a loopback server
     pub async fn request_token_pkce(
         &self,
         provider: &str,
         scopes: Vec<String>,
     ) -> Result<TokenHandle> {
         tracing::info!("Starting PKCE flow for provider: {}", provider);
-        
-        // Placeholder for PKCE implementation
-        // Real implementation would:
-        // 1. Generate code verifier and challenge
-        // 2. Redirect to authorization URL
-        // 3. Handle callback
-        // 4. Exchange code for token
-        // 5. Store in vault
-        
-        let handle_id = uuid::Uuid::new_v4().to_string();
-        let handle = TokenHandle {
-            id: handle_id.clone(),
-            provider: provider.to_string(),
-            scopes,
+
+        let config = self.providers.read().await.get(provider).cloned()
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider))?;
+        let (server, redirect_uri) = self.bind_redirect(&config).await?;
+        let (auth_url, state) = self.begin_pkce(provider, scopes, &redirect_uri).await?;
+
+        tracing::info!("Open this URL to authorize: {}", auth_url);
+        match &self.authorize_url {
+            Some(handler) => handler(&auth_url),
+            None => {
+                if let Err(e) = open_in_browser(&auth_url) {
+                    tracing::warn!("Could not open a browser ({}); open the URL above manually", e);
+                }
+            }
+        }
+
+        let params = match server.wait_for_redirect(DEFAULT_PENDING_TTL).await {
+            Ok(params) => params,
+            Err(e) => {
+                let _ = self.pending.consume(&state).await;
+                return Err(e);
+            }
         };
+        if params.error.is_some() {
+            let _ = self.pending.consume(&state).await;
+            return Err(denied_error(provider, params).into());
+        }
+        let (code, state) = params.into_code_and_state()?;
+        self.complete_pkce(&state, &code).await
+    }
 
-        self.vault.store(&handle_id, "placeholder-token").await?;
+    /// Listen for the redirect as configured for the provider; returns the
+    /// server and the redirect URI to send
+    async fn bind_redirect(&self, config: &ProviderConfig) -> Result<(LoopbackServer, String)> {
+        let Some(uri) = &config.redirect_uri else {
+            let ports = config.redirect_ports.or(self.redirect_ports);
+            let server = LoopbackServer::bind_in(self.redirect_bind, ports).await?;
+            let redirect_uri = server.redirect_uri();
+            return Ok((server, redirect_uri));
+        };
+
+        let url = Url::parse(uri).map_err(|e| anyhow::anyhow!("Invalid redirect URI '{}': {}", uri, e))?;
+        let host = url.host_str().unwrap_or_default().trim_matches(['[', ']']);
+        let addr = if host == "localhost" {
+            DEFAULT_BIND_ADDR
+        } else {
+            host.parse::<IpAddr>()
+                .ok()
+                .filter(IpAddr::is_loopback)
+                .ok_or_else(|| anyhow::anyhow!("Redirect URI '{}' is not a loopback address", uri))?
+        };
+        if url.scheme() != "http" {
+            anyhow::bail!("Redirect URI '{}' must use http", uri);
+        }
+        let port = url
+            .port()
+            .filter(|port| *port != 0)
+            .ok_or_else(|| anyhow::anyhow!("Redirect URI '{}' has no port", uri))?;
+        let server = LoopbackServer::bind_in(addr, Some(PortRange { start: port, end: port }))
+            .await?
+            .with_path(url.path());
+        Ok((server, uri.clone()))
+    }
+
+    /// Start a PKCE authorization; returns the URL to open and its `state`.
+    ///
+    /// The state and code verifier are kept until `complete_pkce` consumes them.
+    pub async fn begin_pkce(
+        &self,
+        provider: &str,
+        scopes: Vec<String>,
+        redirect_uri: &str,
+    ) -> Result<(String, String)> {
+        let providers = self.providers.read().await;
+        let config = providers.get(provider)
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider))?;
+
+        let client = Self::client(config)?
+            .set_redirect_uri(RedirectUrl::new(redirect_uri.to_string())?);
+        let (challenge, verifier) = PkceCodeChallenge::new_random_sha256();
+        let (auth_url, state) = client
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(scopes.iter().map(|s| Scope::new(s.clone())))
+            .set_pkce_challenge(challenge)
+            .url();
+
+        self.pending
+            .insert(
+                state.secret().clone(),
+                provider,
+                verifier.secret().clone(),
+                redirect_uri,
+                scopes,
+            )
+            .await?;
+
+        Ok((auth_url.to_string(), state.secret().clone()))
+    }
+
+    /// Finish a PKCE authorization from the redirect's `state` and `code`
+    pub async fn complete_pkce(&self, state: &str, code: &str) -> Result<TokenHandle> {
+        let pending = self.pending.consume(state).await?;
+
+        let providers = self.providers.read().await;
+        let config = providers.get(&pending.provider)
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", pending.provider))?;
+
+        let client = Self::client(config)?
+            .set_redirect_uri(RedirectUrl::new(pending.redirect_uri.clone())?);
+        let token = client
+            .exchange_code(AuthorizationCode::new(code.to_string()))
+            .set_pkce_verifier(PkceCodeVerifier::new(pending.pkce_verifier.clone()))
+            .request_async(async_http_client)
+            .await?;
+
+        let mut handle = TokenHandle {
+            id: uuid::Uuid::new_v4().to_string(),
+            provider: pending.provider.clone(),
+            scopes: pending.scopes.clone(),
+            expires_at: None,
+        };
+        self.store_token(&mut handle, &token).await?;
 
         Ok(handle)
     }
 
-    /// Refresh a token
-    pub async fn refresh(&self, handle: &TokenHandle) -> Result<()> {
-        tracing::info!("Refreshing token for handle: {}", handle.id);
-        
-        // Real implementation would:
-        // 1. Retrieve refresh token from vault
-        // 2. Exchange for new access token
-        // 3. Update vault
-        
+    /// Keep the access token under the handle's id and any refresh token
+    /// under `refresh_label(id)`, and note when the access token expires
+    async fn store_token(&self, handle: &mut TokenHandle, token: &BasicTokenResponse) -> Result<()> {
+        self.vault.store(&handle.id, token.access_token().secret()).await?;
+        if let Some(refresh) = token.refresh_token() {
+            self.vault.store(&refresh_label(&handle.id), refresh.secret()).await?;
+        }
+        handle.expires_at = token.expires_in().map(|ttl| SystemTime::now() + ttl);
         Ok(())
     }
 
-    /// Revoke a token
+    /// Exchange the handle's stored refresh token for a new access token,
+    /// keeping the refresh token the provider rotates in, if any
+    pub async fn refresh(&self, handle: &mut TokenHandle) -> Result<()> {
+        tracing::info!("Refreshing token for handle: {}", handle.id);
+
+        let providers = self.providers.read().await;
+        let config = providers
+            .get(&handle.provider)
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", handle.provider))?;
+        let refresh_token = self.vault.fetch(&refresh_label(&handle.id)).await.map_err(|_| {
+            OmniError::oauth(
+                format!("No refresh token stored for {}", handle.provider),
+                Some("The provider did not issue a refresh token".to_string()),
+                RecoveryAction::PromptUser("re-authenticate".to_string()),
+            )
+        })?;
+
+        let token = Self::client(config)?
+            .exchange_refresh_token(&RefreshToken::new(refresh_token))
+            .request_async(async_http_client)
+            .await
+            .map_err(|e| match e {
+                RequestTokenError::ServerResponse(response)
+                    if *response.error() == BasicErrorResponseType::InvalidGrant =>
+                {
+                    let detail = response
+                        .error_description()
+                        .map(|d| format!(": {}", d))
+                        .unwrap_or_default();
+                    anyhow::Error::from(OmniError::oauth(
+                        format!("Refresh token for {} was rejected{}", handle.provider, detail),
+                        Some("The refresh token expired or was revoked".to_string()),
+                        RecoveryAction::PromptUser("re-authenticate".to_string()),
+                    ))
+                }
+                e => anyhow::anyhow!("Token refresh for {} failed: {}", handle.provider, e),
+            })?;
+        drop(providers);
+
+        self.store_token(handle, &token).await
+    }
+
+    /// Revoke a token at the provider's revocation endpoint (RFC 7009),
+    /// then remove it from the vault. If the provider rejects the request
+    /// the token is kept, so the revocation can be retried.
     pub async fn revoke(&self, handle: &TokenHandle) -> Result<()> {
         tracing::info!("Revoking token for handle: {}", handle.id);
-        
-        // Real implementation would:
-        // 1. Call provider's revocation endpoint
-        // 2. Remove from vault
-        // 3. Log in consent ledger
-        
+
+        let endpoint = self.providers.read().await.get(&handle.provider).and_then(|config| {
+            config.revocation_url.clone().map(|url| (url, config.client_id.clone()))
+        });
+        match endpoint {
+            Some((url, client_id)) => {
+                // Revoking the refresh token ends its access tokens at most
+                // providers; the access token is revoked too for the rest
+                if let Ok(refresh) = self.vault.fetch(&refresh_label(&handle.id)).await {
+                    post_revocation(&url, &client_id, &handle.provider, &refresh, "refresh_token").await?;
+                }
+                let access = self.vault.fetch(&handle.id).await?;
+                post_revocation(&url, &client_id, &handle.provider, &access, "access_token").await?;
+            }
+            None => tracing::warn!(
+                "{} has no revocation endpoint; the token is only deleted locally and stays valid until it expires",
+                handle.provider
+            ),
+        }
+
         self.vault.delete(&handle.id).await?;
-        
+        // Not every provider issues a refresh token
+        let _ = self.vault.delete(&refresh_label(&handle.id)).await;
+
+        if let Some(ledger) = &self.ledger {
+            ledger
+                .log_revoke(BROKER_AGENT_ID.to_string(), format!("oauth.{}", handle.provider))
+                .await?;
+        }
         Ok(())
     }
 
@@ -167,9 +436,183 @@ impl OAuthBroker {
     }
 }
 
+/// POST `token` to an RFC 7009 revocation endpoint as a public client
+async fn post_revocation(url: &str, client_id: &str, provider: &str, token: &str, hint: &str) -> Result<()> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .form(&[("token", token), ("token_type_hint", hint), ("client_id", client_id)])
+        .send()
+        .await?;
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let status = response.status();
+    let error = response
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| body.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_else(|| status.to_string());
+    Err(OmniError::oauth(
+        format!("{} refused to revoke the {}: {}", provider, hint.replace('_', " "), error),
+        Some("The token is still stored and valid at the provider".to_string()),
+        RecoveryAction::Retry,
+    )
+    .into())
+}
+
+/// Error for a redirect carrying the provider's `error`, e.g. `access_denied`
+/// when the user declined consent
+fn denied_error(provider: &str, params: RedirectParams) -> OmniError {
+    let error = params.error.unwrap_or_default();
+    let detail = params.error_description.map(|d| format!(": {}", d)).unwrap_or_default();
+    if error == "access_denied" {
+        OmniError::oauth(
+            format!("Authorization for {} was denied{}", provider, detail),
+            Some("Access was declined on the provider's consent page".to_string()),
+            RecoveryAction::PromptUser("Run oauth:connect again and approve access".to_string()),
+        )
+    } else {
+        OmniError::oauth(
+            format!("Authorization for {} failed: {}{}", provider, error, detail),
+            Some("The provider rejected the authorization request".to_string()),
+            RecoveryAction::Retry,
+        )
+    }
+}
+
+/// Open `url` in the default browser
+fn open_in_browser(url: &str) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    command
+        .arg(url)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Vault label of the refresh token that goes with handle `id`
+pub fn refresh_label(id: &str) -> String {
+    format!("{}:refresh", id)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::oauth::consent::ConsentAction;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answer each request on `listener` with the next (status, JSON body)
+    /// from `responses`, recording the request paths and bodies
+    async fn serve(listener: tokio::net::TcpListener, responses: Vec<(&'static str, &'static str)>) -> Vec<String> {
+        let mut requests = Vec::new();
+        for (status, body) in responses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let body_start = loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break end + 4;
+                }
+            };
+            let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+            let length: usize = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .map(|len| len.trim().parse().unwrap())
+                .unwrap_or(0);
+            while request.len() < body_start + length {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let path = headers.split_whitespace().nth(1).unwrap_or_default().to_string();
+            requests.push(format!("{} {}", path, String::from_utf8_lossy(&request[body_start..])));
+
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+        requests
+    }
+
+    #[tokio::test]
+    async fn test_device_code_polls_until_approved() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(
+            listener,
+            vec![
+                (
+                    "200 OK",
+                    r#"{"device_code":"dev-123","user_code":"WDJB-MJHT","verification_uri":"https://example.com/device","expires_in":600,"interval":0}"#,
+                ),
+                ("400 Bad Request", r#"{"error":"authorization_pending"}"#),
+                ("400 Bad Request", r#"{"error":"authorization_pending"}"#),
+                (
+                    "200 OK",
+                    r#"{"access_token":"gho_real","token_type":"bearer","expires_in":28800,"refresh_token":"ghr_refresh"}"#,
+                ),
+            ],
+        ));
+
+        let prompts = Arc::new(Mutex::new(Vec::new()));
+        let seen = prompts.clone();
+        let vault = Arc::new(TokenVault::new_in_memory());
+        let broker = OAuthBroker::new(vault.clone())
+            .on_device_prompt(move |prompt| seen.lock().unwrap().push(prompt.clone()));
+        broker
+            .register_provider(
+                "github".to_string(),
+                ProviderConfig {
+                    client_id: "test-client".to_string(),
+                    auth_url: format!("{}/auth", base),
+                    token_url: format!("{}/token", base),
+                    device_auth_url: Some(format!("{}/device", base)),
+                    scopes: vec!["repo".to_string()],
+                    redirect_uri: None,
+                    redirect_ports: None,
+                    revocation_url: None,
+                },
+            )
+            .await;
+
+        let handle = broker
+            .request_token_device_code("github", vec!["repo".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(broker.get_token(&handle).await.unwrap(), "gho_real");
+        assert_eq!(vault.fetch(&refresh_label(&handle.id)).await.unwrap(), "ghr_refresh");
+        assert!(!handle.needs_refresh(Duration::from_secs(60)));
+        assert!(handle.needs_refresh(Duration::from_secs(8 * 60 * 60)));
+
+        let prompts = prompts.lock().unwrap().clone();
+        assert_eq!(prompts.len(), 1);
+        assert_eq!(prompts[0].user_code, "WDJB-MJHT");
+        assert_eq!(prompts[0].verification_uri, "https://example.com/device");
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("/device "));
+        assert!(requests[1..].iter().all(|r| r.starts_with("/token ") && r.contains("device_code=dev-123")));
+    }
 
     #[tokio::test]
     async fn test_broker_creation() {
@@ -183,8 +626,229 @@ mod tests {
             token_url: "https://example.com/token".to_string(),
             device_auth_url: None,
             scopes: vec!["read".to_string()],
+            redirect_uri: None,
+            redirect_ports: None,
+            revocation_url: None,
         };
         
         broker.register_provider("test".to_string(), config).await;
     }
+
+    #[tokio::test]
+    async fn test_complete_pkce_rejects_unknown_state() {
+        let vault = Arc::new(TokenVault::new_in_memory());
+        let broker = OAuthBroker::new(vault);
+        let config = ProviderConfig {
+            client_id: "test-client".to_string(),
+            auth_url: "https://example.com/auth".to_string(),
+            token_url: "https://example.com/token".to_string(),
+            device_auth_url: None,
+            scopes: vec!["read".to_string()],
+            redirect_uri: None,
+            redirect_ports: None,
+            revocation_url: None,
+        };
+        broker.register_provider("test".to_string(), config).await;
+
+        let (url, state) = broker
+            .begin_pkce("test", vec!["read".to_string()], "http://127.0.0.1:8400/callback")
+            .await
+            .unwrap();
+        assert!(url.contains(&format!("state={}", state)));
+        assert!(url.contains("code_challenge_method=S256"));
+
+        // A forged state never reaches the token endpoint
+        let err = broker.complete_pkce("forged", "code").await.unwrap_err();
+        assert!(err.to_string().contains("OAuth state"));
+    }
+
+    /// Broker whose "browser" follows the authorization URL straight to the
+    /// redirect, with the query the provider would append
+    async fn pkce_broker(token_url: String, redirect_query: fn(&str) -> String) -> (OAuthBroker, Arc<TokenVault>) {
+        // A port that was free a moment ago, for the fixed redirect URI
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let vault = Arc::new(TokenVault::new_in_memory());
+        let broker = OAuthBroker::new(vault.clone()).on_authorize_url(move |auth_url| {
+            let url = Url::parse(auth_url).unwrap();
+            let param = |name: &str| url.query_pairs().find(|(k, _)| k == name).unwrap().1.into_owned();
+            let redirect = Url::parse(&param("redirect_uri")).unwrap();
+            let target = format!("{}?{}", redirect.path(), redirect_query(&param("state")));
+            let addr = format!("{}:{}", redirect.host_str().unwrap(), redirect.port().unwrap());
+            tokio::spawn(async move {
+                let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+                let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target);
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = Vec::new();
+                stream.read_to_end(&mut response).await.unwrap();
+            });
+        });
+        broker
+            .register_provider(
+                "test".to_string(),
+                ProviderConfig {
+                    client_id: "test-client".to_string(),
+                    auth_url: "https://example.com/auth".to_string(),
+                    token_url,
+                    device_auth_url: None,
+                    scopes: vec!["read".to_string()],
+                    redirect_uri: Some(format!("http://127.0.0.1:{}/oauth/done", port)),
+                    redirect_ports: None,
+                    revocation_url: None,
+                },
+            )
+            .await;
+        (broker, vault)
+    }
+
+    #[tokio::test]
+    async fn test_pkce_exchanges_redirect_code() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let token_url = format!("http://{}/token", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(
+            listener,
+            vec![("200 OK", r#"{"access_token":"pkce_access","token_type":"bearer","refresh_token":"pkce_refresh"}"#)],
+        ));
+        let (broker, vault) = pkce_broker(token_url, |state| format!("code=auth-code&state={}", state)).await;
+
+        let handle = broker.request_token_pkce("test", vec!["read".to_string()]).await.unwrap();
+        assert_eq!(broker.get_token(&handle).await.unwrap(), "pkce_access");
+        assert_eq!(vault.fetch(&refresh_label(&handle.id)).await.unwrap(), "pkce_refresh");
+        // No expires_in in the response, so the expiry is unknown
+        assert_eq!(handle.expires_at, None);
+        assert!(!handle.needs_refresh(Duration::from_secs(3600)));
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("code=auth-code"));
+        assert!(requests[0].contains("code_verifier="));
+        assert!(requests[0].contains("%2Foauth%2Fdone"));
+    }
+
+    #[tokio::test]
+    async fn test_pkce_denied_consent_is_oauth_error() {
+        let (broker, _) = pkce_broker("https://example.com/token".to_string(), |state| {
+            format!("error=access_denied&error_description=User%20declined&state={}", state)
+        })
+        .await;
+
+        let err = broker.request_token_pkce("test", vec!["read".to_string()]).await.unwrap_err();
+        let err = err.downcast_ref::<OmniError>().expect("expected an OmniError");
+        assert!(matches!(err, OmniError::OAuth { .. }));
+        assert!(err.to_string().contains("denied: User declined"));
+        assert!(matches!(err.recovery_action(), RecoveryAction::PromptUser(_)));
+    }
+
+    /// Broker with a "test" provider whose token and revocation endpoints
+    /// are under `base`, holding a handle with refresh token `old_refresh`
+    async fn token_broker(base: &str) -> (OAuthBroker, Arc<TokenVault>, TokenHandle) {
+        let vault = Arc::new(TokenVault::new_in_memory());
+        let broker = OAuthBroker::new(vault.clone());
+        let config = ProviderConfig {
+            client_id: "test-client".to_string(),
+            auth_url: format!("{}/auth", base),
+            token_url: format!("{}/token", base),
+            device_auth_url: None,
+            scopes: vec!["read".to_string()],
+            redirect_uri: None,
+            redirect_ports: None,
+            revocation_url: Some(format!("{}/revoke", base)),
+        };
+        broker.register_provider("test".to_string(), config).await;
+        let handle = TokenHandle {
+            id: "handle-1".to_string(),
+            provider: "test".to_string(),
+            scopes: vec!["read".to_string()],
+            expires_at: Some(SystemTime::now()),
+        };
+        vault.store(&handle.id, "old_access").await.unwrap();
+        vault.store(&refresh_label(&handle.id), "old_refresh").await.unwrap();
+        (broker, vault, handle)
+    }
+
+    #[tokio::test]
+    async fn test_refresh_stores_new_and_rotated_tokens() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(
+            listener,
+            vec![(
+                "200 OK",
+                r#"{"access_token":"new_access","token_type":"bearer","expires_in":3600,"refresh_token":"new_refresh"}"#,
+            )],
+        ));
+        let (broker, vault, mut handle) = token_broker(&base).await;
+        assert!(handle.needs_refresh(Duration::ZERO));
+
+        broker.refresh(&mut handle).await.unwrap();
+        assert_eq!(broker.get_token(&handle).await.unwrap(), "new_access");
+        assert_eq!(vault.fetch(&refresh_label(&handle.id)).await.unwrap(), "new_refresh");
+        assert!(!handle.needs_refresh(Duration::from_secs(60)));
+        assert!(handle.needs_refresh(Duration::from_secs(3600)));
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("/token "));
+        assert!(requests[0].contains("grant_type=refresh_token"));
+        assert!(requests[0].contains("refresh_token=old_refresh"));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_invalid_grant_prompts_reauthentication() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, vec![("400 Bad Request", r#"{"error":"invalid_grant"}"#)]));
+        let (broker, vault, mut handle) = token_broker(&base).await;
+
+        let err = broker.refresh(&mut handle).await.unwrap_err();
+        let err = err.downcast_ref::<OmniError>().expect("expected an OmniError");
+        assert!(matches!(err, OmniError::OAuth { .. }));
+        assert!(matches!(err.recovery_action(), RecoveryAction::PromptUser(p) if p == "re-authenticate"));
+        // The old token is left alone
+        assert_eq!(vault.fetch(&handle.id).await.unwrap(), "old_access");
+    }
+
+    #[tokio::test]
+    async fn test_revoke_posts_tokens_to_provider_and_logs_it() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(listener, vec![("200 OK", ""), ("200 OK", "")]));
+        let (broker, vault, handle) = token_broker(&base).await;
+        let ledger = Arc::new(ConsentLedger::new());
+        let broker = broker.with_consent_ledger(ledger.clone());
+
+        broker.revoke(&handle).await.unwrap();
+        assert!(vault.fetch(&handle.id).await.is_err());
+        assert!(vault.fetch(&refresh_label(&handle.id)).await.is_err());
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("/revoke "));
+        assert!(requests[0].contains("token=old_refresh&token_type_hint=refresh_token&client_id=test-client"));
+        assert!(requests[1].contains("token=old_access&token_type_hint=access_token"));
+
+        let entries = ledger.get_for_agent(BROKER_AGENT_ID).await;
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(&entries[0].action, ConsentAction::Revoke { capability } if capability == "oauth.test"));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_keeps_token_when_provider_refuses() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, vec![("503 Service Unavailable", r#"{"error":"temporarily_unavailable"}"#)]));
+        let (broker, vault, handle) = token_broker(&base).await;
+
+        let err = broker.revoke(&handle).await.unwrap_err();
+        let err = err.downcast_ref::<OmniError>().expect("expected an OmniError");
+        assert!(err.to_string().contains("temporarily_unavailable"));
+        assert!(matches!(err.recovery_action(), RecoveryAction::Retry));
+        assert_eq!(vault.fetch(&handle.id).await.unwrap(), "old_access");
+
+        // Without an endpoint the token can only be forgotten locally
+        broker
+            .register_provider(
+                "test".to_string(),
+                ProviderConfig { revocation_url: None, ..crate::oauth::providers::google_provider("id".to_string()) },
+            )
+            .await;
+        broker.revoke(&handle).await.unwrap();
+        assert!(vault.fetch(&handle.id).await.is_err());
+    }
 }
@@ -1,10 +1,14 @@
 //! Consent ledger for audit trail
 
 use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
-use std::time::SystemTime;
-use tokio::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::state::SqliteStore;
 
 /// Consent action types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,8 +25,111 @@ pub enum ConsentAction {
         capability: String,
         reason: String,
     },
+    Disable {
+        reason: String,
+    },
+    /// Capability handed down from a parent agent; `chain` lists the
+    /// delegating agents, root first
+    Delegate {
+        capability: String,
+        duration_s: Option<u64>,
+        chain: Vec<String>,
+    },
+    /// A limited-use grant ran out after `uses` uses
+    Exhausted {
+        capability: String,
+        uses: u32,
+    },
+    /// Agent stopped because the shell exited; `forced` if it had to be killed
+    Shutdown {
+        forced: bool,
+    },
+}
+
+/// Kind of a consent action, without its details
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsentKind {
+    Grant,
+    Revoke,
+    Deny,
+    Disable,
+    Delegate,
+    Exhausted,
+    Shutdown,
+}
+
+impl ConsentKind {
+    /// Tag the action is stored under, e.g. "Grant"
+    fn tag(self) -> &'static str {
+        match self {
+            ConsentKind::Grant => "Grant",
+            ConsentKind::Revoke => "Revoke",
+            ConsentKind::Deny => "Deny",
+            ConsentKind::Disable => "Disable",
+            ConsentKind::Delegate => "Delegate",
+            ConsentKind::Exhausted => "Exhausted",
+            ConsentKind::Shutdown => "Shutdown",
+        }
+    }
 }
 
+impl ConsentAction {
+    pub fn kind(&self) -> ConsentKind {
+        match self {
+            ConsentAction::Grant { .. } => ConsentKind::Grant,
+            ConsentAction::Revoke { .. } => ConsentKind::Revoke,
+            ConsentAction::Deny { .. } => ConsentKind::Deny,
+            ConsentAction::Disable { .. } => ConsentKind::Disable,
+            ConsentAction::Delegate { .. } => ConsentKind::Delegate,
+            ConsentAction::Exhausted { .. } => ConsentKind::Exhausted,
+            ConsentAction::Shutdown { .. } => ConsentKind::Shutdown,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ConsentAction::Grant { .. } => "grant",
+            ConsentAction::Revoke { .. } => "revoke",
+            ConsentAction::Deny { .. } => "deny",
+            ConsentAction::Disable { .. } => "disable",
+            ConsentAction::Exhausted { .. } => "exhausted",
+            ConsentAction::Delegate { .. } => "delegate",
+            ConsentAction::Shutdown { .. } => "shutdown",
+        }
+    }
+
+    fn capability(&self) -> &str {
+        match self {
+            ConsentAction::Grant { capability, .. }
+            | ConsentAction::Revoke { capability }
+            | ConsentAction::Deny { capability, .. }
+            | ConsentAction::Exhausted { capability, .. }
+            | ConsentAction::Delegate { capability, .. } => capability,
+            ConsentAction::Disable { .. } | ConsentAction::Shutdown { .. } => "",
+        }
+    }
+
+    /// Grant duration or the recorded reason
+    fn detail(&self) -> String {
+        match self {
+            ConsentAction::Grant { duration_s: Some(secs), .. } => format!("{}s", secs),
+            ConsentAction::Deny { reason, .. } | ConsentAction::Disable { reason } => {
+                redact_secrets(reason)
+            }
+            ConsentAction::Exhausted { uses, .. } => format!("{} uses", uses),
+            ConsentAction::Delegate { duration_s, chain, .. } => match duration_s {
+                Some(secs) => format!("{}s via {}", secs, chain.join(" > ")),
+                None => format!("via {}", chain.join(" > ")),
+            },
+            ConsentAction::Shutdown { forced: true } => "forced".to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Column header of the CSV export
+pub const CSV_HEADER: &str = "timestamp,agent_id,action,capability,detail,user_id";
+
 /// Consent ledger entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsentEntry {
@@ -30,18 +137,125 @@ pub struct ConsentEntry {
     pub agent_id: String,
     pub action: ConsentAction,
     pub user_id: Option<String>,
+    /// Consent request this entry answers, if it came from one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// `entry_hash` of the entry before this one; `GENESIS_HASH` for the first
+    #[serde(default)]
+    pub prev_hash: String,
+    /// sha256 of `prev_hash` and this entry, set when it is logged
+    #[serde(default)]
+    pub entry_hash: String,
+}
+
+/// Which entries `ConsentLedger::query` returns; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct ConsentQuery {
+    /// Logged at or after this time
+    pub since: Option<SystemTime>,
+    /// Logged before this time
+    pub until: Option<SystemTime>,
+    pub agent_id: Option<String>,
+    pub kind: Option<ConsentKind>,
+}
+
+impl ConsentQuery {
+    fn matches(&self, entry: &ConsentEntry) -> bool {
+        self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+            && self.agent_id.as_ref().is_none_or(|id| *id == entry.agent_id)
+            && self.kind.is_none_or(|kind| kind == entry.action.kind())
+    }
+}
+
+/// `prev_hash` of the first entry in a ledger
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+impl ConsentEntry {
+    /// sha256 over `prev_hash` followed by the entry's fields (timestamp to
+    /// the millisecond, as stored), hex-encoded
+    fn compute_hash(&self) -> Result<String> {
+        let timestamp_ms = self.timestamp.duration_since(UNIX_EPOCH)?.as_millis();
+        let fields = serde_json::to_vec(&(
+            timestamp_ms,
+            &self.agent_id,
+            &self.action,
+            &self.user_id,
+            &self.request_id,
+        ))?;
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(&fields);
+        Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+    }
 }
 
 /// Append-only consent ledger
 pub struct ConsentLedger {
     entries: Arc<RwLock<Vec<ConsentEntry>>>,
+    /// When set, entries go to the `consent_log` table instead of `entries`
+    db: Option<Arc<Mutex<Connection>>>,
 }
 
 impl ConsentLedger {
     pub fn new() -> Self {
         ConsentLedger {
             entries: Arc::new(RwLock::new(Vec::new())),
+            db: None,
+        }
+    }
+
+    /// Ledger that writes every entry to the `consent_log` table of `conn`,
+    /// so the audit trail survives restarts
+    pub fn with_connection(conn: Arc<Mutex<Connection>>) -> Self {
+        ConsentLedger {
+            db: Some(conn),
+            ..Self::new()
+        }
+    }
+
+    /// Ledger that keeps its audit trail in `store`
+    pub fn new_persistent(store: Arc<SqliteStore>) -> Self {
+        ConsentLedger::with_connection(store.shared_connection())
+    }
+
+    /// Record an entry, chained to the one before it; there is deliberately
+    /// no way to change or remove one
+    async fn append(&self, mut entry: ConsentEntry) -> Result<()> {
+        if let Some(db) = &self.db {
+            let conn = db.lock().await;
+            entry.prev_hash = last_hash(&conn)?;
+            entry.entry_hash = entry.compute_hash()?;
+            return insert_entry(&conn, &entry);
+        }
+        let mut entries = self.entries.write().await;
+        entry.prev_hash = match entries.last() {
+            Some(last) => last.entry_hash.clone(),
+            None => GENESIS_HASH.to_string(),
+        };
+        entry.entry_hash = entry.compute_hash()?;
+        entries.push(entry);
+        Ok(())
+    }
+
+    /// Check every entry's hash and link to the previous one, failing at the
+    /// first entry that was altered, removed or inserted after the fact
+    pub async fn verify_chain(&self) -> Result<()> {
+        let entries = match &self.db {
+            Some(db) => load_entries(&*db.lock().await, &ConsentQuery::default())?,
+            None => self.entries.read().await.clone(),
+        };
+        let mut expected_prev = GENESIS_HASH.to_string();
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.prev_hash != expected_prev {
+                anyhow::bail!("Consent ledger chain broken at entry {}: previous entry is missing or altered", index);
+            }
+            if entry.compute_hash()? != entry.entry_hash {
+                anyhow::bail!("Consent ledger entry {} ({}) has been altered", index, entry.agent_id);
+            }
+            expected_prev = entry.entry_hash.clone();
         }
+        Ok(())
     }
 
     /// Log a grant
@@ -59,10 +273,12 @@ impl ConsentLedger {
                 duration_s,
             },
             user_id: None,
+            request_id: None,
+            prev_hash: String::new(),
+            entry_hash: String::new(),
         };
 
-        let mut entries = self.entries.write().await;
-        entries.push(entry);
+        self.append(entry).await?;
 
         tracing::info!("Consent granted: {} -> {}", agent_id, capability);
         Ok(())
@@ -77,10 +293,12 @@ impl ConsentLedger {
                 capability: capability.clone(),
             },
             user_id: None,
+            request_id: None,
+            prev_hash: String::new(),
+            entry_hash: String::new(),
         };
 
-        let mut entries = self.entries.write().await;
-        entries.push(entry);
+        self.append(entry).await?;
 
         tracing::info!("Consent revoked: {} -> {}", agent_id, capability);
         Ok(())
@@ -96,37 +314,335 @@ impl ConsentLedger {
                 reason: reason.clone(),
             },
             user_id: None,
+            request_id: None,
+            prev_hash: String::new(),
+            entry_hash: String::new(),
         };
 
-        let mut entries = self.entries.write().await;
-        entries.push(entry);
+        self.append(entry).await?;
 
         tracing::info!("Consent denied: {} -> {} ({})", agent_id, capability, reason);
         Ok(())
     }
 
+    /// Log an agent being disabled by policy
+    pub async fn log_disable(&self, agent_id: String, reason: String) -> Result<()> {
+        let entry = ConsentEntry {
+            timestamp: SystemTime::now(),
+            agent_id: agent_id.clone(),
+            action: ConsentAction::Disable {
+                reason: reason.clone(),
+            },
+            user_id: None,
+            request_id: None,
+            prev_hash: String::new(),
+            entry_hash: String::new(),
+        };
+
+        self.append(entry).await?;
+
+        tracing::info!("Agent disabled: {} ({})", agent_id, reason);
+        Ok(())
+    }
+
+    /// Log a capability delegated to `agent_id` through `chain`
+    pub async fn log_delegate(
+        &self,
+        agent_id: String,
+        capability: String,
+        duration_s: Option<u64>,
+        chain: Vec<String>,
+    ) -> Result<()> {
+        tracing::info!("Consent delegated: {} -> {} via {}", agent_id, capability, chain.join(" > "));
+        let entry = ConsentEntry {
+            timestamp: SystemTime::now(),
+            agent_id,
+            action: ConsentAction::Delegate {
+                capability,
+                duration_s,
+                chain,
+            },
+            user_id: None,
+            request_id: None,
+            prev_hash: String::new(),
+            entry_hash: String::new(),
+        };
+
+        self.append(entry).await?;
+        Ok(())
+    }
+
+    /// Log a limited-use grant running out
+    pub async fn log_exhausted(&self, agent_id: String, capability: String, uses: u32) -> Result<()> {
+        let entry = ConsentEntry {
+            timestamp: SystemTime::now(),
+            agent_id: agent_id.clone(),
+            action: ConsentAction::Exhausted {
+                capability: capability.clone(),
+                uses,
+            },
+            user_id: None,
+            request_id: None,
+            prev_hash: String::new(),
+            entry_hash: String::new(),
+        };
+
+        self.append(entry).await?;
+
+        tracing::info!("Consent exhausted: {} -> {} after {} use(s)", agent_id, capability, uses);
+        Ok(())
+    }
+
+    /// Log an agent being stopped at shell exit
+    pub async fn log_shutdown(&self, agent_id: String, forced: bool) -> Result<()> {
+        let entry = ConsentEntry {
+            timestamp: SystemTime::now(),
+            agent_id: agent_id.clone(),
+            action: ConsentAction::Shutdown { forced },
+            user_id: None,
+            request_id: None,
+            prev_hash: String::new(),
+            entry_hash: String::new(),
+        };
+
+        self.append(entry).await?;
+
+        tracing::info!("Agent shut down: {}{}", agent_id, if forced { " (killed)" } else { "" });
+        Ok(())
+    }
+
+    /// Log the grant or denial answering consent request `request_id`
+    pub async fn log_response(
+        &self,
+        agent_id: String,
+        request_id: String,
+        action: ConsentAction,
+    ) -> Result<()> {
+        tracing::info!(
+            "Consent request {} answered: {} -> {} ({})",
+            request_id,
+            agent_id,
+            action.capability(),
+            action.name()
+        );
+        let entry = ConsentEntry {
+            timestamp: SystemTime::now(),
+            agent_id,
+            action,
+            user_id: None,
+            request_id: Some(request_id),
+            prev_hash: String::new(),
+            entry_hash: String::new(),
+        };
+
+        self.append(entry).await?;
+        Ok(())
+    }
+
     /// Get all entries
     pub async fn get_all(&self) -> Vec<ConsentEntry> {
-        let entries = self.entries.read().await;
-        entries.clone()
+        self.load(&ConsentQuery::default()).await
     }
 
     /// Get entries for a specific agent
     pub async fn get_for_agent(&self, agent_id: &str) -> Vec<ConsentEntry> {
-        let entries = self.entries.read().await;
+        let filter = ConsentQuery {
+            agent_id: Some(agent_id.to_string()),
+            ..ConsentQuery::default()
+        };
+        self.load(&filter).await
+    }
+
+    /// Entries matching `filter`, oldest first
+    pub async fn query(&self, filter: ConsentQuery) -> Vec<ConsentEntry> {
+        let mut entries = self.load(&filter).await;
+        entries.sort_by_key(|entry| entry.timestamp);
         entries
-            .iter()
-            .filter(|e| e.agent_id == agent_id)
-            .cloned()
-            .collect()
+    }
+
+    /// Entries matching `filter` in the order they were logged
+    async fn load(&self, filter: &ConsentQuery) -> Vec<ConsentEntry> {
+        if let Some(db) = &self.db {
+            return load_entries(&*db.lock().await, filter).unwrap_or_else(|e| {
+                tracing::warn!("Failed to read consent log: {}", e);
+                Vec::new()
+            });
+        }
+        let entries = self.entries.read().await;
+        entries.iter().filter(|e| filter.matches(e)).cloned().collect()
     }
 
     /// Export ledger (with secrets redacted)
     pub async fn export(&self) -> Result<String> {
-        let entries = self.entries.read().await;
-        let json = serde_json::to_string_pretty(&*entries)?;
+        let entries = self.get_all().await;
+        let json = serde_json::to_string_pretty(&entries)?;
         Ok(json)
     }
+
+    /// Export ledger as CSV with ISO-8601 UTC timestamps; secrets in free-text
+    /// reasons are redacted
+    pub async fn export_csv(&self) -> String {
+        let entries = self.get_all().await;
+        let mut csv = format!("{}\n", CSV_HEADER);
+        for entry in entries.iter() {
+            let row = [
+                iso8601(entry.timestamp),
+                csv_field(&entry.agent_id),
+                entry.action.name().to_string(),
+                csv_field(entry.action.capability()),
+                csv_field(&entry.action.detail()),
+                csv_field(entry.user_id.as_deref().unwrap_or_default()),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+fn insert_entry(conn: &Connection, entry: &ConsentEntry) -> Result<()> {
+    let timestamp_ms = entry.timestamp.duration_since(UNIX_EPOCH)?.as_millis() as i64;
+    conn.execute(
+        "INSERT INTO consent_log (timestamp, agent_id, action, user_id, request_id, prev_hash, entry_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            timestamp_ms,
+            entry.agent_id,
+            serde_json::to_string(&entry.action)?,
+            entry.user_id,
+            entry.request_id,
+            entry.prev_hash,
+            entry.entry_hash,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Hash the next entry chains to
+fn last_hash(conn: &Connection) -> Result<String> {
+    let last: Option<String> = conn
+        .query_row("SELECT entry_hash FROM consent_log ORDER BY id DESC LIMIT 1", [], |row| row.get(0))
+        .optional()?;
+    Ok(last.unwrap_or_else(|| GENESIS_HASH.to_string()))
+}
+
+/// Stored timestamp (whole milliseconds) of the first entry at or after `time`
+fn ceil_ms(time: SystemTime) -> Result<i64> {
+    let since_epoch = time.duration_since(UNIX_EPOCH)?;
+    Ok(since_epoch.as_nanos().div_ceil(1_000_000) as i64)
+}
+
+/// Entries matching `filter` in the order they were logged
+fn load_entries(conn: &Connection, filter: &ConsentQuery) -> Result<Vec<ConsentEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT timestamp, agent_id, action, user_id, request_id, prev_hash, entry_hash
+         FROM consent_log
+         WHERE (?1 IS NULL OR agent_id = ?1)
+           AND (?2 IS NULL OR json_extract(action, '$.action') = ?2)
+           AND (?3 IS NULL OR timestamp >= ?3)
+           AND (?4 IS NULL OR timestamp < ?4)
+         ORDER BY id",
+    )?;
+    let since = filter.since.map(ceil_ms).transpose()?;
+    let until = filter.until.map(ceil_ms).transpose()?;
+    let params = params![filter.agent_id, filter.kind.map(ConsentKind::tag), since, until];
+    let rows = stmt.query_map(params, |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(6)?,
+        ))
+    })?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let (timestamp_ms, agent_id, action, user_id, request_id, prev_hash, entry_hash) = row?;
+        entries.push(ConsentEntry {
+            timestamp: UNIX_EPOCH + Duration::from_millis(timestamp_ms as u64),
+            agent_id,
+            action: serde_json::from_str(&action)?,
+            user_id,
+            request_id,
+            prev_hash,
+            entry_hash,
+        });
+    }
+    Ok(entries)
+}
+
+/// Keys whose values are replaced in exported free text
+const SECRET_KEYS: &[&str] = &["token", "secret", "password", "key"];
+
+/// Mask `key=value` secrets, bearer tokens and long opaque strings
+fn redact_secrets(text: &str) -> String {
+    let mut redacted = Vec::new();
+    let mut after_bearer = false;
+    for word in text.split(' ') {
+        let masked = if after_bearer || looks_opaque(word) {
+            "[REDACTED]".to_string()
+        } else if let Some((key, _)) = word.split_once('=').filter(|(key, value)| {
+            !value.is_empty() && SECRET_KEYS.iter().any(|s| key.to_lowercase().ends_with(s))
+        }) {
+            format!("{}=[REDACTED]", key)
+        } else {
+            word.to_string()
+        };
+        after_bearer = word.eq_ignore_ascii_case("bearer");
+        redacted.push(masked);
+    }
+    redacted.join(" ")
+}
+
+/// Long runs of token characters, e.g. an access token pasted into a reason
+fn looks_opaque(word: &str) -> bool {
+    word.len() >= 32
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | '+' | '='))
+        && word.chars().any(|c| c.is_ascii_digit())
+}
+
+/// Quote a CSV field when needed, and defuse values a spreadsheet would run as a formula
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Format as `YYYY-MM-DDTHH:MM:SSZ` (UTC)
+fn iso8601(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
 }
 
 impl Default for ConsentLedger {
@@ -162,6 +678,45 @@ mod tests {
         // Get entries for agent
         let agent_entries = ledger.get_for_agent("agent1").await;
         assert_eq!(agent_entries.len(), 2);
+
+        // Entries are chained
+        assert_eq!(entries[0].prev_hash, GENESIS_HASH);
+        assert_eq!(entries[1].prev_hash, entries[0].entry_hash);
+        ledger.verify_chain().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_by_time_agent_and_kind() {
+        let ledger = ConsentLedger::new();
+        ledger.log_grant("indexer".to_string(), "files.read".to_string(), None).await.unwrap();
+        ledger.log_deny("crawler".to_string(), "network.http".to_string(), "no".to_string()).await.unwrap();
+        let midpoint = SystemTime::now();
+        ledger.log_revoke("indexer".to_string(), "files.read".to_string()).await.unwrap();
+        ledger.log_grant("crawler".to_string(), "network.http".to_string(), None).await.unwrap();
+
+        let recent = ledger
+            .query(ConsentQuery { since: Some(midpoint), ..ConsentQuery::default() })
+            .await;
+        assert_eq!(recent.len(), 2);
+        assert!(recent[0].timestamp <= recent[1].timestamp);
+        let earlier = ledger
+            .query(ConsentQuery { until: Some(midpoint), ..ConsentQuery::default() })
+            .await;
+        assert_eq!(earlier.len(), 2);
+
+        let grants = ledger
+            .query(ConsentQuery { kind: Some(ConsentKind::Grant), ..ConsentQuery::default() })
+            .await;
+        assert_eq!(grants.iter().map(|e| e.agent_id.as_str()).collect::<Vec<_>>(), ["indexer", "crawler"]);
+
+        let filter = ConsentQuery {
+            agent_id: Some("crawler".to_string()),
+            kind: Some(ConsentKind::Deny),
+            ..ConsentQuery::default()
+        };
+        let denied = ledger.query(filter).await;
+        assert_eq!(denied.len(), 1);
+        assert!(matches!(denied[0].action, ConsentAction::Deny { .. }));
     }
 
     #[tokio::test]
@@ -175,4 +730,111 @@ mod tests {
         let export = ledger.export().await.unwrap();
         assert!(export.contains("network"));
     }
+
+    #[tokio::test]
+    async fn test_export_csv_one_row_per_entry() {
+        let ledger = ConsentLedger::new();
+        ledger
+            .log_grant("agent1".to_string(), "files.read".to_string(), Some(3600))
+            .await
+            .unwrap();
+        ledger
+            .log_deny(
+                "agent1".to_string(),
+                "network.http".to_string(),
+                "blocked, token=abc123 leaked".to_string(),
+            )
+            .await
+            .unwrap();
+        ledger.log_shutdown("agent2".to_string(), true).await.unwrap();
+
+        let csv = ledger.export_csv().await;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines.len(), 4);
+
+        let grant: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(grant.len(), 6);
+        assert_eq!(&grant[1..], ["agent1", "grant", "files.read", "3600s", ""]);
+        assert_eq!(grant[0].len(), "2026-01-01T00:00:00Z".len());
+        assert!(grant[0].ends_with('Z'));
+
+        assert_eq!(lines[2], format!(
+            "{},agent1,deny,network.http,\"blocked, token=[REDACTED] leaked\",",
+            &lines[2][..20]
+        ));
+        assert!(lines[3].ends_with(",agent2,shutdown,,forced,"));
+    }
+
+    #[test]
+    fn test_iso8601_timestamps() {
+        assert_eq!(iso8601(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(iso8601(leap_day), "2024-02-29T12:34:56Z");
+    }
+
+    #[tokio::test]
+    async fn test_consent_log_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+
+        let store = Arc::new(SqliteStore::new(&path).unwrap());
+        let ledger = ConsentLedger::new_persistent(store.clone());
+        ledger.log_grant("indexer".to_string(), "files.read".to_string(), Some(60)).await.unwrap();
+        ledger
+            .log_deny("crawler".to_string(), "network.http".to_string(), "user said no".to_string())
+            .await
+            .unwrap();
+        ledger.log_revoke("indexer".to_string(), "files.read".to_string()).await.unwrap();
+        drop(ledger);
+        drop(store);
+
+        let ledger = ConsentLedger::new_persistent(Arc::new(SqliteStore::new(&path).unwrap()));
+        let entries = ledger.get_all().await;
+        assert_eq!(entries.len(), 3);
+        assert!(matches!(&entries[1].action, ConsentAction::Deny { reason, .. } if reason == "user said no"));
+
+        let indexer = ledger.get_for_agent("indexer").await;
+        assert_eq!(indexer.len(), 2);
+        assert!(matches!(&indexer[0].action, ConsentAction::Grant { duration_s: Some(60), .. }));
+        assert!(matches!(indexer[1].action, ConsentAction::Revoke { .. }));
+        assert_eq!(ledger.export_csv().await.lines().count(), 4);
+
+        // Filters run in SQL against the stored rows
+        let filter = ConsentQuery {
+            agent_id: Some("indexer".to_string()),
+            kind: Some(ConsentKind::Revoke),
+            since: Some(entries[0].timestamp),
+            ..ConsentQuery::default()
+        };
+        assert_eq!(ledger.query(filter).await.len(), 1);
+        let future = ConsentQuery {
+            since: Some(SystemTime::now() + Duration::from_secs(60)),
+            ..ConsentQuery::default()
+        };
+        assert!(ledger.query(future).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_edited_consent_row_fails_verification() {
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let ledger = ConsentLedger::new_persistent(store.clone());
+        ledger.log_grant("indexer".to_string(), "files.read".to_string(), Some(60)).await.unwrap();
+        ledger.log_grant("indexer".to_string(), "network.http".to_string(), None).await.unwrap();
+        ledger.log_revoke("indexer".to_string(), "files.read".to_string()).await.unwrap();
+        ledger.verify_chain().await.unwrap();
+
+        // Edit the table directly, as someone covering their tracks would
+        {
+            let conn = store.connection().await;
+            let conn = conn.lock().await;
+            conn.execute_batch(
+                "DROP TRIGGER consent_log_no_update;
+                 UPDATE consent_log SET action = replace(action, 'network.http', 'files.read') WHERE id = 2;",
+            )
+            .unwrap();
+        }
+        let err = ledger.verify_chain().await.unwrap_err();
+        assert!(err.to_string().contains("entry 1"), "{}", err);
+    }
 }
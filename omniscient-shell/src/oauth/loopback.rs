@@ -0,0 +1,312 @@
+//! One-shot localhost HTTP server that catches the OAuth redirect
+
+use anyhow::{Context, Result};
+use oauth2::url::form_urlencoded;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::utils::config::OAuthConfig;
+
+/// Address the loopback server binds to unless configured otherwise
+pub const DEFAULT_BIND_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Path the provider redirects to
+pub const CALLBACK_PATH: &str = "/callback";
+
+/// Largest request head accepted from the browser
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+const SUCCESS_PAGE: &str = "<!DOCTYPE html><html><head><title>Omniscient Shell</title></head>\
+<body><h2>Authorization complete</h2><p>You may close this window and return to the terminal.</p></body></html>";
+
+const ERROR_PAGE: &str = "<!DOCTYPE html><html><head><title>Omniscient Shell</title></head>\
+<body><h2>Authorization failed</h2><p>You may close this window; details are shown in the terminal.</p></body></html>";
+
+/// Inclusive range of ports the redirect listener may bind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    /// Parse `"8400"` or `"8400-8410"`
+    pub fn parse(s: &str) -> Result<Self> {
+        let parse_port = |p: &str| {
+            p.trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|port| *port != 0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid redirect port '{}'", p.trim()))
+        };
+        let range = match s.split_once('-') {
+            Some((start, end)) => PortRange { start: parse_port(start)?, end: parse_port(end)? },
+            None => {
+                let port = parse_port(s)?;
+                PortRange { start: port, end: port }
+            }
+        };
+        if range.start > range.end {
+            anyhow::bail!("Invalid redirect port range '{}': start is after end", s);
+        }
+        Ok(range)
+    }
+
+    /// The configured `oauth.redirect_ports`, if any
+    pub fn from_config(config: &OAuthConfig) -> Result<Option<Self>> {
+        config.redirect_ports.as_deref().map(Self::parse).transpose()
+    }
+}
+
+impl std::fmt::Display for PortRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+/// Parameters the provider sent back on the redirect
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedirectParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+impl RedirectParams {
+    fn from_query(query: &str) -> Self {
+        let mut params = RedirectParams::default();
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            let value = Some(value.into_owned());
+            match key.as_ref() {
+                "code" => params.code = value,
+                "state" => params.state = value,
+                "error" => params.error = value,
+                "error_description" => params.error_description = value,
+                _ => {}
+            }
+        }
+        params
+    }
+
+    /// The code and state, or the provider's error
+    pub fn into_code_and_state(self) -> Result<(String, String)> {
+        if let Some(error) = self.error {
+            match self.error_description {
+                Some(description) => anyhow::bail!("Authorization failed: {} ({})", error, description),
+                None => anyhow::bail!("Authorization failed: {}", error),
+            }
+        }
+        match (self.code, self.state) {
+            (Some(code), Some(state)) => Ok((code, state)),
+            _ => anyhow::bail!("Redirect is missing the code or state"),
+        }
+    }
+}
+
+/// Localhost server that answers a single OAuth redirect and shuts down
+pub struct LoopbackServer {
+    listener: TcpListener,
+    addr: SocketAddr,
+    /// Path the redirect arrives on
+    path: String,
+}
+
+impl LoopbackServer {
+    /// Bind an ephemeral port on `bind_addr`
+    pub async fn bind(bind_addr: IpAddr) -> Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::new(bind_addr, 0))
+            .await
+            .with_context(|| format!("Failed to bind OAuth redirect listener on {}", bind_addr))?;
+        let addr = listener.local_addr()?;
+        Ok(LoopbackServer { listener, addr, path: CALLBACK_PATH.to_string() })
+    }
+
+    /// Bind the first free port in `ports`, or an ephemeral port if None
+    pub async fn bind_in(bind_addr: IpAddr, ports: Option<PortRange>) -> Result<Self> {
+        let Some(range) = ports else {
+            return Self::bind(bind_addr).await;
+        };
+
+        for port in range.start..=range.end {
+            match TcpListener::bind(SocketAddr::new(bind_addr, port)).await {
+                Ok(listener) => {
+                    let addr = listener.local_addr()?;
+                    return Ok(LoopbackServer { listener, addr, path: CALLBACK_PATH.to_string() });
+                }
+                Err(e) => tracing::debug!("Redirect port {} unavailable: {}", port, e),
+            }
+        }
+        anyhow::bail!(
+            "No free port for the OAuth redirect in the configured range {} on {}",
+            range,
+            bind_addr
+        )
+    }
+
+    /// Catch the redirect on `path` instead of `/callback`
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Redirect URI to register with the provider
+    pub fn redirect_uri(&self) -> String {
+        let host = match self.addr.ip() {
+            IpAddr::V6(ip) => format!("[{}]", ip),
+            IpAddr::V4(ip) => ip.to_string(),
+        };
+        format!("http://{}:{}{}", host, self.addr.port(), self.path)
+    }
+
+    /// Wait for the redirect, answer it and shut down.
+    ///
+    /// Requests for other paths (e.g. `/favicon.ico`) get a 404 and are ignored.
+    pub async fn wait_for_redirect(self, timeout: Duration) -> Result<RedirectParams> {
+        tokio::time::timeout(timeout, async {
+            loop {
+                let (stream, _) = self.listener.accept().await?;
+                match handle_connection(stream, &self.path).await {
+                    Ok(Some(params)) => return Ok(params),
+                    Ok(None) => continue,
+                    Err(e) => tracing::debug!("Ignoring bad OAuth redirect request: {}", e),
+                }
+            }
+        })
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out waiting for the OAuth redirect after {:?}", timeout))?
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, callback_path: &str) -> Result<Option<RedirectParams>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if buf.len() > MAX_REQUEST_BYTES {
+            anyhow::bail!("Request too large");
+        }
+    }
+
+    let head = String::from_utf8_lossy(&buf);
+    let target = head
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .ok_or_else(|| anyhow::anyhow!("Malformed request line"))?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    if path != callback_path {
+        respond(&mut stream, "404 Not Found", "Not found").await?;
+        return Ok(None);
+    }
+
+    let params = RedirectParams::from_query(query);
+    let page = if params.error.is_some() { ERROR_PAGE } else { SUCCESS_PAGE };
+    respond(&mut stream, "200 OK", page).await?;
+    Ok(Some(params))
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn send(port: u16, target: &str) -> String {
+        let mut stream = TcpStream::connect((DEFAULT_BIND_ADDR, port)).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_redirect_yields_code() {
+        let server = LoopbackServer::bind(DEFAULT_BIND_ADDR).await.unwrap();
+        let port = server.port();
+        assert_eq!(server.redirect_uri(), format!("http://127.0.0.1:{}/callback", port));
+
+        let wait = tokio::spawn(server.wait_for_redirect(Duration::from_secs(5)));
+
+        let response = send(port, "/favicon.ico").await;
+        assert!(response.starts_with("HTTP/1.1 404"));
+
+        let response = send(port, "/callback?code=abc%20123&state=xyz").await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("You may close this window"));
+
+        let params = wait.await.unwrap().unwrap();
+        assert_eq!(
+            params.into_code_and_state().unwrap(),
+            ("abc 123".to_string(), "xyz".to_string())
+        );
+    }
+
+    #[test]
+    fn test_port_range_parsing() {
+        assert_eq!(PortRange::parse("8400").unwrap(), PortRange { start: 8400, end: 8400 });
+        assert_eq!(PortRange::parse("8400-8410").unwrap(), PortRange { start: 8400, end: 8410 });
+        assert!(PortRange::parse("8410-8400").is_err());
+        assert!(PortRange::parse("0").is_err());
+        assert!(PortRange::parse("http").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bind_respects_port_range() {
+        // Reserve a port so the range is exhausted
+        let taken = TcpListener::bind((DEFAULT_BIND_ADDR, 0)).await.unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let range = PortRange { start: port, end: port };
+
+        let err = LoopbackServer::bind_in(DEFAULT_BIND_ADDR, Some(range)).await.err().unwrap();
+        assert!(err.to_string().contains(&port.to_string()));
+
+        drop(taken);
+        let server = LoopbackServer::bind_in(DEFAULT_BIND_ADDR, Some(range)).await.unwrap();
+        assert_eq!(server.port(), port);
+        assert_eq!(server.redirect_uri(), format!("http://127.0.0.1:{}/callback", port));
+    }
+
+    #[tokio::test]
+    async fn test_provider_error_is_reported() {
+        let params = RedirectParams::from_query("error=access_denied&state=xyz");
+        let err = params.into_code_and_state().unwrap_err();
+        assert!(err.to_string().contains("access_denied"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_times_out_without_redirect() {
+        let server = LoopbackServer::bind(DEFAULT_BIND_ADDR).await.unwrap();
+        let err = server
+            .wait_for_redirect(Duration::from_secs(30))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Timed out"));
+    }
+}
@@ -4,8 +4,12 @@ pub mod broker;
 pub mod providers;
 pub mod vault;
 pub mod consent;
+pub mod pending;
+pub mod loopback;
 
 pub use broker::{OAuthBroker, ProviderConfig, TokenHandle};
 pub use vault::TokenVault;
-pub use consent::ConsentLedger;
-pub use providers::{github_provider, google_provider};
+pub use consent::{ConsentKind, ConsentLedger, ConsentQuery};
+pub use pending::{PendingAuth, PendingAuthStore};
+pub use loopback::{LoopbackServer, PortRange, RedirectParams};
+pub use providers::{builtin, github_provider, google_provider, BUILTIN_PROVIDERS};
@@ -0,0 +1,147 @@
+//! Short-lived store of pending PKCE authorizations, keyed by `state`
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+/// How long a user has to finish an authorization in the browser
+pub const DEFAULT_PENDING_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Authorization started but not yet completed
+#[derive(Debug, Clone)]
+pub struct PendingAuth {
+    pub provider: String,
+    pub pkce_verifier: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    created: Instant,
+}
+
+/// In-memory store of pending authorizations (CSRF and code-injection guard)
+pub struct PendingAuthStore {
+    entries: Arc<RwLock<HashMap<String, PendingAuth>>>,
+    ttl: Duration,
+}
+
+impl PendingAuthStore {
+    pub fn new() -> Self {
+        Self::with_ttl(DEFAULT_PENDING_TTL)
+    }
+
+    pub fn with_ttl(ttl: Duration) -> Self {
+        PendingAuthStore {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// Record an authorization before redirecting the user
+    pub async fn insert(
+        &self,
+        state: impl Into<String>,
+        provider: impl Into<String>,
+        pkce_verifier: impl Into<String>,
+        redirect_uri: impl Into<String>,
+        scopes: Vec<String>,
+    ) -> Result<()> {
+        let state = state.into();
+        if state.is_empty() {
+            anyhow::bail!("OAuth state must not be empty");
+        }
+
+        let mut entries = self.entries.write().await;
+        self.purge_expired(&mut entries);
+        if entries.contains_key(&state) {
+            anyhow::bail!("OAuth state is already pending");
+        }
+
+        entries.insert(
+            state,
+            PendingAuth {
+                provider: provider.into(),
+                pkce_verifier: pkce_verifier.into(),
+                redirect_uri: redirect_uri.into(),
+                scopes,
+                created: Instant::now(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Take the authorization for a callback's `state`.
+    ///
+    /// Each state can be consumed once; unknown, reused and expired states are rejected.
+    pub async fn consume(&self, state: &str) -> Result<PendingAuth> {
+        let mut entries = self.entries.write().await;
+        self.purge_expired(&mut entries);
+
+        entries
+            .remove(state)
+            .ok_or_else(|| anyhow::anyhow!("Unknown or already used OAuth state"))
+    }
+
+    /// Number of authorizations still pending
+    pub async fn len(&self) -> usize {
+        let mut entries = self.entries.write().await;
+        self.purge_expired(&mut entries);
+        entries.len()
+    }
+
+    fn purge_expired(&self, entries: &mut HashMap<String, PendingAuth>) {
+        entries.retain(|_, pending| pending.created.elapsed() <= self.ttl);
+    }
+}
+
+impl Default for PendingAuthStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn store_with(state: &str) -> PendingAuthStore {
+        let store = PendingAuthStore::new();
+        store
+            .insert(state, "github", "verifier", "http://127.0.0.1:8400/callback", vec![])
+            .await
+            .unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn test_valid_state_proceeds_once() {
+        let store = store_with("abc123").await;
+
+        let pending = store.consume("abc123").await.unwrap();
+        assert_eq!(pending.provider, "github");
+        assert_eq!(pending.pkce_verifier, "verifier");
+
+        // Replaying the callback fails
+        assert!(store.consume("abc123").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_state_is_rejected() {
+        let store = store_with("abc123").await;
+
+        assert!(store.consume("abc124").await.is_err());
+        assert!(store.consume("").await.is_err());
+        // The real authorization is still pending
+        assert_eq!(store.len().await, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_expired_state_is_rejected() {
+        let store = store_with("abc123").await;
+
+        tokio::time::advance(DEFAULT_PENDING_TTL + Duration::from_secs(1)).await;
+        assert!(store.consume("abc123").await.is_err());
+        assert_eq!(store.len().await, 0);
+    }
+}
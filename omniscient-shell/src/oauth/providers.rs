@@ -1,8 +1,25 @@
 //! OAuth provider adapters
+//!
+//! Ready-made configs for well-known providers, so only the client id has to
+//! be supplied: `broker.register_provider("github".into(), github_provider(client_id))`.
 
 use crate::oauth::broker::ProviderConfig;
 
-/// GitHub OAuth provider
+/// Names accepted by `builtin`
+pub const BUILTIN_PROVIDERS: &[&str] = &["github", "google"];
+
+/// Config of the built-in provider called `name` (case-insensitive), e.g.
+/// from a provider name in the config file; None for unknown providers
+pub fn builtin(name: &str, client_id: String) -> Option<ProviderConfig> {
+    match name.to_ascii_lowercase().as_str() {
+        "github" => Some(github_provider(client_id)),
+        "google" => Some(google_provider(client_id)),
+        _ => None,
+    }
+}
+
+/// GitHub OAuth app with device and PKCE flows; scopes cover repositories
+/// and the user's profile
 pub fn github_provider(client_id: String) -> ProviderConfig {
     ProviderConfig {
         client_id,
@@ -10,10 +27,16 @@ pub fn github_provider(client_id: String) -> ProviderConfig {
         token_url: "https://github.com/login/oauth/access_token".to_string(),
         device_auth_url: Some("https://github.com/login/device/code".to_string()),
         scopes: vec!["repo".to_string(), "read:user".to_string()],
+        redirect_uri: None,
+        redirect_ports: None,
+        // GitHub has no RFC 7009 endpoint; its token API needs the client
+        // secret, so tokens are revoked from the account's settings page
+        revocation_url: None,
     }
 }
 
-/// Google OAuth provider
+/// Google OAuth client with device and PKCE flows; scopes cover sign-in
+/// and the email address
 pub fn google_provider(client_id: String) -> ProviderConfig {
     ProviderConfig {
         client_id,
@@ -21,6 +44,9 @@ pub fn google_provider(client_id: String) -> ProviderConfig {
         token_url: "https://oauth2.googleapis.com/token".to_string(),
         device_auth_url: Some("https://oauth2.googleapis.com/device/code".to_string()),
         scopes: vec!["openid".to_string(), "email".to_string()],
+        redirect_uri: None,
+        redirect_ports: None,
+        revocation_url: Some("https://oauth2.googleapis.com/revoke".to_string()),
     }
 }
 
@@ -35,10 +61,23 @@ mod tests {
         assert!(provider.device_auth_url.is_some());
     }
 
+    #[test]
+    fn test_builtin_lookup() {
+        for name in BUILTIN_PROVIDERS {
+            let provider = builtin(name, "id".to_string()).unwrap();
+            assert!(provider.token_url.starts_with("https://"));
+            assert!(!provider.scopes.is_empty());
+        }
+        let github = builtin("GitHub", "id".to_string()).unwrap();
+        assert_eq!(github.device_auth_url.as_deref(), Some("https://github.com/login/device/code"));
+        assert!(builtin("gitlab", "id".to_string()).is_none());
+    }
+
     #[test]
     fn test_google_provider() {
         let provider = google_provider("test-client-id".to_string());
         assert_eq!(provider.client_id, "test-client-id");
         assert!(provider.device_auth_url.is_some());
+        assert!(provider.revocation_url.is_some());
     }
 }
@@ -1,11 +1,35 @@
 //! Encrypted token vault with OS keychain integration
 
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
 use anyhow::Result;
-use argon2::{Argon2, PasswordHasher};
-use argon2::password_hash::{SaltString, rand_core::OsRng};
+use argon2::Argon2;
+use rand::RngCore;
+use rusqlite::{params, Connection, OptionalExtension};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::tui::vault_rotate::RotateRequest;
+use crate::utils::config::VaultConfig;
+use crate::utils::idle::IdleMonitor;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Authenticated with the wrapped data key so it can't be passed off as a token
+const DATA_KEY_AAD: &[u8] = b"vault-data-key";
+/// Environment variable holding the encrypted vault's passphrase
+pub const PASSPHRASE_ENV: &str = "OMNI_VAULT_PASSPHRASE";
+/// Passphrase prompts before startup gives up on unlocking
+const UNLOCK_ATTEMPTS: usize = 3;
+
+/// Encrypted SQLite vault database (`~/.omniscient/vault.db`)
+pub fn default_vault_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".omniscient").join("vault.db")
+}
 
 /// Token vault backend
 pub enum VaultBackend {
@@ -19,25 +43,77 @@ pub struct TokenVault {
     backend: VaultBackend,
     in_memory_store: Arc<RwLock<HashMap<String, String>>>,
     locked: Arc<RwLock<bool>>,
+    /// `vault_tokens` and `vault_key` tables of the EncryptedSqlite backend
+    db: Option<Arc<Mutex<Connection>>>,
+    /// Keys unwrapped by `unlock_with_passphrase`; cleared on lock
+    keys: Arc<RwLock<Option<VaultKeys>>>,
+    /// Locks the vault once it goes unused for the auto-lock timeout
+    auto_lock: Option<IdleMonitor>,
 }
 
 impl TokenVault {
+    /// Vault with the configured backend, locking after the configured idle
+    /// minutes. Must be called within a tokio runtime.
+    pub fn from_config(config: &VaultConfig) -> Result<Self> {
+        let vault = match config.backend.as_str() {
+            "os_keychain" => TokenVault::new_os_keychain(),
+            "encrypted_sqlite" => {
+                let path = default_vault_path();
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                TokenVault::new_encrypted_sqlite(path.display().to_string())?
+            }
+            other => anyhow::bail!("Unknown vault backend '{}'", other),
+        };
+        Ok(vault.with_auto_lock(config.auto_lock_minutes))
+    }
+
     /// Create a new vault with OS keychain backend
     pub fn new_os_keychain() -> Self {
         TokenVault {
             backend: VaultBackend::OsKeychain,
             in_memory_store: Arc::new(RwLock::new(HashMap::new())),
             locked: Arc::new(RwLock::new(false)),
+            db: None,
+            keys: Arc::new(RwLock::new(None)),
+            auto_lock: None,
         }
     }
 
-    /// Create a new vault with encrypted SQLite backend
-    pub fn new_encrypted_sqlite(path: String) -> Self {
-        TokenVault {
+    /// Create a new vault with encrypted SQLite backend at `path`; it starts
+    /// locked until `unlock_with_passphrase`.
+    ///
+    /// Tokens are encrypted with a random data key, stored wrapped under a
+    /// key derived from the passphrase.
+    pub fn new_encrypted_sqlite(path: String) -> Result<Self> {
+        let conn = Connection::open(&path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS vault_tokens (
+                label TEXT PRIMARY KEY,
+                ciphertext BLOB NOT NULL,
+                nonce BLOB NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS vault_key (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                wrapped_key BLOB NOT NULL,
+                nonce BLOB NOT NULL,
+                salt BLOB NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(TokenVault {
             backend: VaultBackend::EncryptedSqlite(path),
             in_memory_store: Arc::new(RwLock::new(HashMap::new())),
-            locked: Arc::new(RwLock::new(false)),
-        }
+            locked: Arc::new(RwLock::new(true)),
+            db: Some(Arc::new(Mutex::new(conn))),
+            keys: Arc::new(RwLock::new(None)),
+            auto_lock: None,
+        })
     }
 
     /// Create an in-memory vault (for testing)
@@ -46,6 +122,38 @@ impl TokenVault {
             backend: VaultBackend::InMemory,
             in_memory_store: Arc::new(RwLock::new(HashMap::new())),
             locked: Arc::new(RwLock::new(false)),
+            db: None,
+            keys: Arc::new(RwLock::new(None)),
+            auto_lock: None,
+        }
+    }
+
+    /// Lock the vault after `minutes` without a store or fetch; 0 disables.
+    /// Must be called within a tokio runtime.
+    pub fn with_auto_lock(mut self, minutes: u32) -> Self {
+        if minutes == 0 {
+            self.auto_lock = None;
+            return self;
+        }
+        let (locked, keys) = (self.locked.clone(), self.keys.clone());
+        self.auto_lock = Some(IdleMonitor::spawn(
+            Duration::from_secs(minutes as u64 * 60),
+            move || {
+                let (locked, keys) = (locked.clone(), keys.clone());
+                async move {
+                    *locked.write().await = true;
+                    *keys.write().await = None;
+                    tracing::info!("Vault locked after {} idle minutes", minutes);
+                }
+            },
+        ));
+        self
+    }
+
+    /// Note an access, restarting the auto-lock countdown
+    pub fn touch(&self) {
+        if let Some(monitor) = &self.auto_lock {
+            monitor.record_activity();
         }
     }
 
@@ -55,7 +163,7 @@ impl TokenVault {
             anyhow::bail!("Vault is locked");
         }
 
-        match &self.backend {
+        let result = match &self.backend {
             VaultBackend::OsKeychain => {
                 #[cfg(not(target_os = "windows"))]
                 {
@@ -73,13 +181,12 @@ impl TokenVault {
                 Ok(())
             }
             VaultBackend::EncryptedSqlite(_path) => {
-                // Placeholder for encrypted SQLite storage
-                // Real implementation would:
-                // 1. Derive key from passphrase using argon2id
-                // 2. Encrypt token with AES-256-GCM
-                // 3. Store in SQLite
-                let mut store = self.in_memory_store.write().await;
-                store.insert(label.to_string(), token.to_string());
+                let record = encrypt(&self.data_key().await?, label.as_bytes(), token.as_bytes())?;
+                self.db()?.lock().await.execute(
+                    "INSERT OR REPLACE INTO vault_tokens (label, ciphertext, nonce)
+                     VALUES (?1, ?2, ?3)",
+                    params![label, record.ciphertext, record.nonce],
+                )?;
                 tracing::info!("Stored token in encrypted SQLite: {}", label);
                 Ok(())
             }
@@ -88,7 +195,11 @@ impl TokenVault {
                 store.insert(label.to_string(), token.to_string());
                 Ok(())
             }
+        };
+        if result.is_ok() {
+            self.touch();
         }
+        result
     }
 
     /// Fetch a token
@@ -97,18 +208,19 @@ impl TokenVault {
             anyhow::bail!("Vault is locked");
         }
 
-        match &self.backend {
+        let result = match &self.backend {
             VaultBackend::OsKeychain => {
                 let entry = keyring::Entry::new("omniscient-shell", label)?;
                 let token = entry.get_password()?;
                 Ok(token)
             }
             VaultBackend::EncryptedSqlite(_path) => {
-                // Placeholder for encrypted SQLite retrieval
-                let store = self.in_memory_store.read().await;
-                store.get(label)
-                    .cloned()
-                    .ok_or_else(|| anyhow::anyhow!("Token not found: {}", label))
+                let data_key = self.data_key().await?;
+                let record = self
+                    .load_record(label)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Token not found: {}", label))?;
+                decrypt_token(&data_key, label, &record)
             }
             VaultBackend::InMemory => {
                 let store = self.in_memory_store.read().await;
@@ -116,7 +228,11 @@ impl TokenVault {
                     .cloned()
                     .ok_or_else(|| anyhow::anyhow!("Token not found: {}", label))
             }
+        };
+        if result.is_ok() {
+            self.touch();
         }
+        result
     }
 
     /// Delete a token
@@ -128,13 +244,15 @@ impl TokenVault {
         match &self.backend {
             VaultBackend::OsKeychain => {
                 let entry = keyring::Entry::new("omniscient-shell", label)?;
-                entry.delete_password()?;
+                entry.delete_credential()?;
                 tracing::info!("Deleted token from OS keychain: {}", label);
                 Ok(())
             }
             VaultBackend::EncryptedSqlite(_path) => {
-                let mut store = self.in_memory_store.write().await;
-                store.remove(label);
+                self.db()?
+                    .lock()
+                    .await
+                    .execute("DELETE FROM vault_tokens WHERE label = ?1", params![label])?;
                 tracing::info!("Deleted token from encrypted SQLite: {}", label);
                 Ok(())
             }
@@ -146,17 +264,86 @@ impl TokenVault {
         }
     }
 
-    /// Lock the vault
+    /// Lock the vault, forgetting any unwrapped keys
     pub async fn lock(&self) {
         let mut locked = self.locked.write().await;
         *locked = true;
+        *self.keys.write().await = None;
         tracing::info!("Vault locked");
     }
 
+    /// Unlock an encrypted vault with its passphrase, creating the data key
+    /// on first use. Fails, leaving the vault locked, if the passphrase
+    /// doesn't unwrap the stored data key.
+    pub async fn unlock_with_passphrase(&self, pass: &str) -> Result<()> {
+        let stored = self
+            .db()?
+            .lock()
+            .await
+            .query_row(
+                "SELECT wrapped_key, nonce, salt FROM vault_key WHERE id = 1",
+                [],
+                |row| Ok((record_from_row(row, 0)?, row.get::<_, Vec<u8>>(2)?)),
+            )
+            .optional()?;
+
+        // Derived without holding the database lock
+        let keys = match stored {
+            Some((wrapped, salt)) => {
+                let wrapping_key = derive_key_blocking(pass, &salt).await?;
+                let data_key = decrypt(&wrapping_key, DATA_KEY_AAD, &wrapped)
+                    .map_err(|_| anyhow::anyhow!("Wrong vault passphrase"))?;
+                VaultKeys { wrapping_key, salt, data_key: Key::<Aes256Gcm>::clone_from_slice(&data_key) }
+            }
+            None => {
+                let mut salt = vec![0u8; SALT_LEN];
+                rand::thread_rng().fill_bytes(&mut salt);
+                let keys = VaultKeys {
+                    wrapping_key: derive_key_blocking(pass, &salt).await?,
+                    salt,
+                    data_key: Aes256Gcm::generate_key(rand::thread_rng()),
+                };
+                let wrapped = keys.wrap(&keys.data_key)?;
+                // Fails on the primary key if another unlock created the key first
+                self.db()?.lock().await.execute(
+                    "INSERT INTO vault_key (id, wrapped_key, nonce, salt) VALUES (1, ?1, ?2, ?3)",
+                    params![wrapped.ciphertext, wrapped.nonce, keys.salt],
+                )?;
+                keys
+            }
+        };
+
+        *self.keys.write().await = Some(keys);
+        self.unlock().await;
+        Ok(())
+    }
+
+    /// Unlock the vault for this session: with `OMNI_VAULT_PASSPHRASE` if it
+    /// is set, else with passphrases from `prompt`, asking again while they
+    /// are wrong. Vaults without a passphrase are already unlocked.
+    pub async fn unlock_at_startup(&self, mut prompt: impl FnMut() -> Result<String>) -> Result<()> {
+        if !self.is_locked().await {
+            return Ok(());
+        }
+        if let Ok(pass) = std::env::var(PASSPHRASE_ENV) {
+            return self.unlock_with_passphrase(&pass).await;
+        }
+
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self.unlock_with_passphrase(&prompt()?).await {
+                Err(e) if attempts < UNLOCK_ATTEMPTS => tracing::warn!("{}", e),
+                result => return result,
+            }
+        }
+    }
+
     /// Unlock the vault
     pub async fn unlock(&self) {
         let mut locked = self.locked.write().await;
         *locked = false;
+        self.touch();
         tracing::info!("Vault unlocked");
     }
 
@@ -165,29 +352,263 @@ impl TokenVault {
         *self.locked.read().await
     }
 
-    /// Rotate encryption keys (for EncryptedSqlite backend)
-    pub async fn rotate_keys(&self) -> Result<()> {
+    /// Rotate encryption keys (for EncryptedSqlite backend), returning how
+    /// many tokens were re-encrypted.
+    ///
+    /// Every token is re-encrypted under a fresh data key, and the new
+    /// wrapped key replaces the old one in the same transaction, so a crash
+    /// leaves either the old key and tokens or the new ones.
+    pub async fn rotate_keys(&self) -> Result<usize> {
         match &self.backend {
-            VaultBackend::EncryptedSqlite(_path) => {
-                // Placeholder for key rotation
-                // Real implementation would:
-                // 1. Generate new encryption key
-                // 2. Re-encrypt all tokens
-                // 3. Update key in secure storage
-                tracing::info!("Rotating encryption keys");
-                Ok(())
-            }
+            VaultBackend::EncryptedSqlite(_path) => self.rekey(None, &|_, _| {}).await,
             _ => {
                 tracing::warn!("Key rotation not applicable for this backend");
-                Ok(())
+                Ok(0)
+            }
+        }
+    }
+
+    /// Rotate the keys as `rotate_keys` does and wrap the new data key under
+    /// `new_passphrase`. `progress` gets (tokens done, total) as they are
+    /// re-encrypted. Fails without changing anything if the vault is locked
+    /// or `current_passphrase` is wrong.
+    pub async fn rotate_keys_with_passphrase(
+        &self,
+        current_passphrase: &str,
+        new_passphrase: &str,
+        progress: impl Fn(usize, usize) + Send + Sync,
+    ) -> Result<usize> {
+        match &self.backend {
+            VaultBackend::EncryptedSqlite(_path) => {
+                self.rekey(Some((current_passphrase, new_passphrase)), &progress).await
+            }
+            _ => anyhow::bail!("Only the encrypted SQLite vault has a passphrase"),
+        }
+    }
+
+    /// Re-encrypt every token under a fresh data key, re-wrapping it under
+    /// a new passphrase when `passphrases` (current, new) is given
+    async fn rekey(
+        &self,
+        passphrases: Option<(&str, &str)>,
+        progress: &(dyn Fn(usize, usize) + Send + Sync),
+    ) -> Result<usize> {
+        if *self.locked.read().await {
+            anyhow::bail!("Vault is locked");
+        }
+        let needs_passphrase = || anyhow::anyhow!("Vault needs a passphrase; unlock it with unlock_with_passphrase");
+
+        // Derive both passphrase keys before taking the key and database locks
+        let derived = match passphrases {
+            Some((current_pass, new_pass)) => {
+                let salt = self
+                    .keys
+                    .read()
+                    .await
+                    .as_ref()
+                    .map(|keys| keys.salt.clone())
+                    .ok_or_else(needs_passphrase)?;
+                let mut new_salt = vec![0u8; SALT_LEN];
+                rand::thread_rng().fill_bytes(&mut new_salt);
+                let current_key = derive_key_blocking(current_pass, &salt).await?;
+                Some((current_key, derive_key_blocking(new_pass, &new_salt).await?, new_salt))
+            }
+            None => None,
+        };
+
+        let mut keys = self.keys.write().await;
+        let current = keys.as_mut().ok_or_else(needs_passphrase)?;
+        let mut conn = self.db()?.lock().await;
+        let tx = conn.transaction()?;
+        let (wrapping_key, salt) = match derived {
+            Some((current_key, new_key, new_salt)) => {
+                // The current passphrase is right if it unwraps the stored data key
+                let wrapped = tx.query_row("SELECT wrapped_key, nonce FROM vault_key WHERE id = 1", [], |row| {
+                    record_from_row(row, 0)
+                })?;
+                if decrypt(&current_key, DATA_KEY_AAD, &wrapped).is_err() {
+                    anyhow::bail!("Wrong vault passphrase");
+                }
+                (new_key, new_salt)
             }
+            None => (current.wrapping_key, current.salt.clone()),
+        };
+        let fresh = VaultKeys {
+            wrapping_key,
+            salt,
+            data_key: Aes256Gcm::generate_key(rand::thread_rng()),
+        };
+
+        let records = {
+            let mut stmt = tx.prepare("SELECT label, ciphertext, nonce FROM vault_tokens")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, record_from_row(row, 1)?)))?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        progress(0, records.len());
+        for (done, (label, record)) in records.iter().enumerate() {
+            let token = decrypt(&current.data_key, label.as_bytes(), record)?;
+            let reencrypted = encrypt(&fresh.data_key, label.as_bytes(), &token)?;
+            tx.execute(
+                "UPDATE vault_tokens SET ciphertext = ?2, nonce = ?3 WHERE label = ?1",
+                params![label, reencrypted.ciphertext, reencrypted.nonce],
+            )?;
+            progress(done + 1, records.len());
+        }
+        let wrapped = fresh.wrap(&fresh.data_key)?;
+        tx.execute(
+            "UPDATE vault_key SET wrapped_key = ?1, nonce = ?2, salt = ?3 WHERE id = 1",
+            params![wrapped.ciphertext, wrapped.nonce, fresh.salt],
+        )?;
+        tx.commit()?;
+
+        *current = fresh;
+        tracing::info!("Rotated encryption keys for {} tokens", records.len());
+        Ok(records.len())
+    }
+
+    /// Run a `vault:rotate` request from the dashboard, reporting progress
+    /// and the outcome to its dialog. A vault that locked itself since
+    /// startup is unlocked with the request's current passphrase first.
+    pub async fn rotate_for(&self, request: &RotateRequest) {
+        let result = async {
+            if self.is_locked().await {
+                self.unlock_with_passphrase(request.current_passphrase()).await?;
+            }
+            self.rotate_keys_with_passphrase(request.current_passphrase(), request.new_passphrase(), |done, total| {
+                request.progress.update(done, total)
+            })
+            .await
+        }
+        .await;
+        if let Err(e) = &result {
+            tracing::warn!("Vault rotation failed: {}", e);
+        }
+        request.progress.finish(result);
+    }
+
+    /// Handler for `Dashboard::on_vault_rotate` that runs each request with
+    /// `rotate_for` on a spawned task
+    pub fn rotate_handler(self: Arc<Self>) -> impl Fn(RotateRequest) + Send + Sync + 'static {
+        move |request| {
+            let vault = self.clone();
+            tokio::spawn(async move { vault.rotate_for(&request).await });
         }
     }
 }
 
+impl TokenVault {
+    fn db(&self) -> Result<&Arc<Mutex<Connection>>> {
+        self.db
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault backend has no database"))
+    }
+
+    async fn data_key(&self) -> Result<Key<Aes256Gcm>> {
+        self.keys
+            .read()
+            .await
+            .as_ref()
+            .map(|keys| keys.data_key)
+            .ok_or_else(|| anyhow::anyhow!("Vault needs a passphrase; unlock it with unlock_with_passphrase"))
+    }
+
+    async fn load_record(&self, label: &str) -> Result<Option<EncryptedRecord>> {
+        let conn = self.db()?.lock().await;
+        Ok(conn
+            .query_row(
+                "SELECT ciphertext, nonce FROM vault_tokens WHERE label = ?1",
+                params![label],
+                |row| record_from_row(row, 0),
+            )
+            .optional()?)
+    }
+}
+
+/// Keys of an unlocked EncryptedSqlite vault
+struct VaultKeys {
+    /// Derived from the passphrase and `salt`; only wraps the data key
+    wrapping_key: Key<Aes256Gcm>,
+    salt: Vec<u8>,
+    /// Encrypts the tokens
+    data_key: Key<Aes256Gcm>,
+}
+
+impl VaultKeys {
+    fn wrap(&self, data_key: &Key<Aes256Gcm>) -> Result<EncryptedRecord> {
+        encrypt(&self.wrapping_key, DATA_KEY_AAD, data_key)
+    }
+}
+
+/// A ciphertext and the nonce it was encrypted with
+struct EncryptedRecord {
+    ciphertext: Vec<u8>,
+    nonce: Vec<u8>,
+}
+
+fn record_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<EncryptedRecord> {
+    Ok(EncryptedRecord {
+        ciphertext: row.get(first)?,
+        nonce: row.get(first + 1)?,
+    })
+}
+
+/// Derive a 256-bit key from the passphrase with argon2id
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// `derive_key` on the blocking pool, since argon2 would stall the runtime
+async fn derive_key_blocking(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>> {
+    let (passphrase, salt) = (passphrase.to_string(), salt.to_vec());
+    tokio::task::spawn_blocking(move || derive_key(&passphrase, &salt)).await?
+}
+
+/// Encrypt `plaintext` under a fresh nonce; `aad` (the token's label) is
+/// authenticated so a record can't be swapped onto another label
+fn encrypt(key: &Key<Aes256Gcm>, aad: &[u8], plaintext: &[u8]) -> Result<EncryptedRecord> {
+    let mut nonce = vec![0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = Aes256Gcm::new(key)
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+    Ok(EncryptedRecord { ciphertext, nonce })
+}
+
+fn decrypt(key: &Key<Aes256Gcm>, aad: &[u8], record: &EncryptedRecord) -> Result<Vec<u8>> {
+    if record.nonce.len() != NONCE_LEN {
+        anyhow::bail!("Corrupt vault record");
+    }
+    Aes256Gcm::new(key)
+        .decrypt(Nonce::from_slice(&record.nonce), Payload { msg: &record.ciphertext, aad })
+        .map_err(|_| anyhow::anyhow!("Decryption failed"))
+}
+
+fn decrypt_token(key: &Key<Aes256Gcm>, label: &str, record: &EncryptedRecord) -> Result<String> {
+    let plaintext = decrypt(key, label.as_bytes(), record)
+        .map_err(|e| anyhow::anyhow!("Failed to decrypt token {}: {}", label, e))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tui::vault_rotate::{RotateAction, RotateStatus, VaultRotateDialog};
+    use crossterm::event::{KeyCode, KeyEvent};
+
+    #[tokio::test]
+    async fn test_from_config_picks_backend() {
+        let config = crate::utils::config::Config::default().vault;
+        let vault = TokenVault::from_config(&config).unwrap();
+        assert!(matches!(vault.backend, VaultBackend::OsKeychain));
+        assert!(vault.auto_lock.is_some());
+
+        let floppy = VaultConfig { backend: "floppy".to_string(), ..config };
+        assert!(TokenVault::from_config(&floppy).is_err());
+    }
 
     #[tokio::test]
     async fn test_in_memory_vault() {
@@ -231,4 +652,219 @@ mod tests {
         let token = vault.fetch("test").await.unwrap();
         assert_eq!(token, "value");
     }
+
+    #[tokio::test]
+    async fn test_encrypted_sqlite_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db").display().to_string();
+
+        let vault = TokenVault::new_encrypted_sqlite(path.clone()).unwrap();
+        assert!(vault.is_locked().await);
+        assert!(vault.store("github", "gho_secret").await.is_err());
+
+        vault.unlock_with_passphrase("correct horse").await.unwrap();
+        vault.store("github", "gho_secret").await.unwrap();
+        vault.store("gitlab", "glpat_secret").await.unwrap();
+        assert_eq!(vault.fetch("github").await.unwrap(), "gho_secret");
+
+        // Only ciphertext reaches the disk
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!raw.windows(10).any(|w| w == b"gho_secret"));
+
+        vault.rotate_keys().await.unwrap();
+        vault.delete("gitlab").await.unwrap();
+        assert!(vault.fetch("gitlab").await.is_err());
+        vault.lock().await;
+        drop(vault);
+
+        let reopened = TokenVault::new_encrypted_sqlite(path).unwrap();
+        assert!(reopened.unlock_with_passphrase("wrong").await.is_err());
+        assert!(reopened.is_locked().await);
+        reopened.unlock_with_passphrase("correct horse").await.unwrap();
+        assert_eq!(reopened.fetch("github").await.unwrap(), "gho_secret");
+    }
+
+    #[tokio::test]
+    async fn test_unlock_at_startup_prompts_until_right() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db").display().to_string();
+        let vault = TokenVault::new_encrypted_sqlite(path.clone()).unwrap();
+        vault.unlock_with_passphrase("correct horse").await.unwrap();
+        vault.store("github", "gho_secret").await.unwrap();
+        drop(vault);
+
+        let vault = TokenVault::new_encrypted_sqlite(path.clone()).unwrap();
+        let mut answers = vec!["correct horse", "wrong"];
+        vault.unlock_at_startup(|| Ok(answers.pop().unwrap().to_string())).await.unwrap();
+        assert_eq!(vault.fetch("github").await.unwrap(), "gho_secret");
+
+        // Gives up after three wrong passphrases, leaving the vault locked
+        let vault = TokenVault::new_encrypted_sqlite(path).unwrap();
+        let mut prompts = 0;
+        let result = vault
+            .unlock_at_startup(|| {
+                prompts += 1;
+                Ok("wrong".to_string())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(prompts, 3);
+        assert!(vault.is_locked().await);
+
+        // Nothing to ask for a vault without a passphrase
+        let keychain = TokenVault::new_in_memory();
+        keychain.unlock_at_startup(|| panic!("prompted")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rotate_keys_keeps_every_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db").display().to_string();
+        let vault = TokenVault::new_encrypted_sqlite(path.clone()).unwrap();
+        vault.unlock_with_passphrase("correct horse").await.unwrap();
+        let tokens = [("github", "gho_1"), ("gitlab", "glpat_2"), ("slack", "xoxb_3"), ("jira", "atl_4")];
+        for (label, token) in tokens {
+            vault.store(label, token).await.unwrap();
+        }
+
+        let snapshot = |path: &str| {
+            let conn = Connection::open(path).unwrap();
+            let key: Vec<u8> = conn.query_row("SELECT wrapped_key FROM vault_key", [], |row| row.get(0)).unwrap();
+            let token: Vec<u8> = conn
+                .query_row("SELECT ciphertext FROM vault_tokens WHERE label = 'github'", [], |row| row.get(0))
+                .unwrap();
+            (key, token)
+        };
+        let before = snapshot(&path);
+        vault.rotate_keys().await.unwrap();
+        let after = snapshot(&path);
+        assert_ne!(before.0, after.0);
+        assert_ne!(before.1, after.1);
+
+        for (label, token) in tokens {
+            assert_eq!(vault.fetch(label).await.unwrap(), token);
+        }
+        vault.store("linear", "lin_5").await.unwrap();
+
+        // The rotated key is what's on disk
+        drop(vault);
+        let reopened = TokenVault::new_encrypted_sqlite(path).unwrap();
+        reopened.unlock_with_passphrase("correct horse").await.unwrap();
+        for (label, token) in tokens.into_iter().chain([("linear", "lin_5")]) {
+            assert_eq!(reopened.fetch(label).await.unwrap(), token);
+        }
+
+        reopened.lock().await;
+        assert!(reopened.rotate_keys().await.is_err());
+    }
+
+    /// Enter passphrases in the `vault:rotate` dialog and return its request
+    fn submit_rotate(current: &str, new: &str) -> (VaultRotateDialog, RotateRequest) {
+        let mut dialog = VaultRotateDialog::new();
+        for text in [current, new, new] {
+            for c in text.chars() {
+                dialog.handle_key(KeyEvent::from(KeyCode::Char(c)));
+            }
+            if let RotateAction::Submit(request) = dialog.handle_key(KeyEvent::from(KeyCode::Enter)) {
+                return (dialog, request);
+            }
+        }
+        panic!("dialog did not submit");
+    }
+
+    async fn sqlite_vault_with_tokens(path: &str, count: usize) -> Arc<TokenVault> {
+        let vault = Arc::new(TokenVault::new_encrypted_sqlite(path.to_string()).unwrap());
+        vault.unlock_with_passphrase("correct horse").await.unwrap();
+        for i in 0..count {
+            vault.store(&format!("token-{}", i), &format!("secret-{}", i)).await.unwrap();
+        }
+        vault
+    }
+
+    #[tokio::test]
+    async fn test_rotate_command_changes_passphrase_and_keeps_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db").display().to_string();
+        let vault = sqlite_vault_with_tokens(&path, 25).await;
+
+        let (dialog, request) = submit_rotate("correct horse", "battery staple");
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let result = vault
+            .rotate_keys_with_passphrase(request.current_passphrase(), request.new_passphrase(), move |done, total| {
+                sink.lock().unwrap().push((done, total))
+            })
+            .await;
+        assert_eq!(result.unwrap(), 25);
+        let seen = seen.lock().unwrap().clone();
+        assert_eq!(seen.first(), Some(&(0, 25)));
+        assert_eq!(seen.last(), Some(&(25, 25)));
+
+        // As the dashboard runs it: on a task, reporting to the dialog
+        let (dialog_again, request) = submit_rotate("battery staple", "battery staple");
+        let task_vault = vault.clone();
+        tokio::spawn(async move { task_vault.rotate_for(&request).await }).await.unwrap();
+        assert_eq!(dialog_again.status(), Some(RotateStatus::Done(25)));
+        drop((dialog, vault));
+
+        let reopened = TokenVault::new_encrypted_sqlite(path).unwrap();
+        assert!(reopened.unlock_with_passphrase("correct horse").await.is_err());
+        reopened.unlock_with_passphrase("battery staple").await.unwrap();
+        for i in 0..25 {
+            assert_eq!(reopened.fetch(&format!("token-{}", i)).await.unwrap(), format!("secret-{}", i));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotate_command_refuses_wrong_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db").display().to_string();
+        let vault = sqlite_vault_with_tokens(&path, 3).await;
+        let raw = || std::fs::read(&path).unwrap();
+        let before = raw();
+
+        let (dialog, request) = submit_rotate("wrong", "battery staple");
+        vault.rotate_for(&request).await;
+        assert!(matches!(dialog.status(), Some(RotateStatus::Failed(m)) if m.contains("Wrong vault passphrase")));
+        assert_eq!(raw(), before);
+
+        // A locked vault stays locked when the passphrase is wrong
+        vault.lock().await;
+        let (dialog, request) = submit_rotate("wrong", "battery staple");
+        vault.rotate_for(&request).await;
+        assert!(matches!(dialog.status(), Some(RotateStatus::Failed(m)) if m.contains("Wrong vault passphrase")));
+        assert!(vault.is_locked().await);
+        assert_eq!(raw(), before);
+
+        vault.unlock_with_passphrase("correct horse").await.unwrap();
+        for i in 0..3 {
+            assert_eq!(vault.fetch(&format!("token-{}", i)).await.unwrap(), format!("secret-{}", i));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_auto_lock_after_idle_timeout() {
+        async fn advance(minutes: u64) {
+            tokio::time::advance(Duration::from_secs(minutes * 60)).await;
+            tokio::task::yield_now().await;
+        }
+
+        let vault = TokenVault::new_in_memory().with_auto_lock(10);
+        vault.store("github", "gho_secret").await.unwrap();
+
+        // Each access restarts the countdown
+        advance(8).await;
+        assert_eq!(vault.fetch("github").await.unwrap(), "gho_secret");
+        advance(8).await;
+        assert!(!vault.is_locked().await);
+
+        advance(3).await;
+        assert!(vault.is_locked().await);
+        assert!(vault.fetch("github").await.is_err());
+
+        // Unlocking starts a new countdown
+        vault.unlock().await;
+        advance(11).await;
+        assert!(vault.is_locked().await);
+    }
 }
@@ -0,0 +1,158 @@
+//! cgroup v2 limits for native agents (Linux)
+//!
+//! Each agent gets its own cgroup beside the one this process runs in, e.g.
+//! `app.slice/omniscient-agent-<name>-<pid>-<n>` under a systemd user
+//! session, where `<pid>` is this process. A cgroup is removed once its
+//! agent has exited; ones left behind by a shell that died are removed by
+//! the next shell's first `AgentCgroup::create`.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Once;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Name prefix of agent cgroups, used to find stale ones
+const CGROUP_PREFIX: &str = "omniscient-agent-";
+
+/// `cpu.max` period in microseconds; the quota is a share of it
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// Distinguishes cgroups of agents spawned by this process
+static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Stale cgroups are looked for once per process
+static REMOVE_STALE: Once = Once::new();
+
+/// A cgroup limiting the CPU and memory of the processes added to it
+pub struct AgentCgroup {
+    path: PathBuf,
+}
+
+impl AgentCgroup {
+    /// Create a cgroup for agent `name` limited to `cpu_millicores` and
+    /// `memory_bytes`
+    pub fn create(name: &str, cpu_millicores: u32, memory_bytes: u64) -> Result<Self> {
+        let cgroup = fs::read_to_string("/proc/self/cgroup").context("Failed to read /proc/self/cgroup")?;
+        let own = own_cgroup(&cgroup).ok_or_else(|| anyhow::anyhow!("cgroup v2 is not mounted"))?;
+        let parent = &parent_cgroup(own);
+        // On hybrid hierarchies the unified path can be a plain tmpfs directory
+        if !parent.join("cgroup.controllers").exists() {
+            anyhow::bail!("{} is not a cgroup v2 directory", parent.display());
+        }
+        REMOVE_STALE.call_once(|| remove_stale(parent));
+
+        // Usually enabled already; fails harmlessly when it is
+        let _ = fs::write(parent.join("cgroup.subtree_control"), "+cpu +memory");
+
+        let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = parent.join(format!("{}{}-{}-{}", CGROUP_PREFIX, name, std::process::id(), id));
+        fs::create_dir(&path).with_context(|| format!("Failed to create cgroup {}", path.display()))?;
+
+        let cgroup = AgentCgroup { path };
+        let limits = cgroup
+            .write("memory.max", &memory_bytes.to_string())
+            .and_then(|_| cgroup.write("cpu.max", &cpu_max(cpu_millicores)));
+        if let Err(e) = limits {
+            let _ = fs::remove_dir(&cgroup.path);
+            return Err(e);
+        }
+        Ok(cgroup)
+    }
+
+    /// Open `cgroup.procs` for writing. A process that writes "0" to it
+    /// moves itself (and the processes it starts later) into the cgroup.
+    pub fn procs(&self) -> Result<fs::File> {
+        let path = self.path.join("cgroup.procs");
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))
+    }
+
+    /// Remove the cgroup once its processes have exited; one still in use
+    /// is kept
+    pub fn remove(&self) {
+        if let Err(e) = fs::remove_dir(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::debug!("Failed to remove cgroup {}: {}", self.path.display(), e);
+            }
+        }
+    }
+
+    fn write(&self, file: &str, value: &str) -> Result<()> {
+        let path = self.path.join(file);
+        fs::write(&path, value).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+impl Drop for AgentCgroup {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+/// This process's cgroup path from /proc/self/cgroup, if cgroup v2 is in use
+fn own_cgroup(proc_cgroup: &str) -> Option<&str> {
+    proc_cgroup.lines().find_map(|line| line.strip_prefix("0::"))
+}
+
+/// Directory of the cgroup containing `own`; the root for top-level cgroups
+fn parent_cgroup(own: &str) -> PathBuf {
+    match own.trim_matches('/').rsplit_once('/') {
+        Some((parent, _)) => Path::new(CGROUP_ROOT).join(parent),
+        None => PathBuf::from(CGROUP_ROOT),
+    }
+}
+
+/// `cpu.max` value allowing `millicores` thousandths of a CPU
+fn cpu_max(millicores: u32) -> String {
+    format!("{} {}", millicores as u64 * CPU_PERIOD_US / 1000, CPU_PERIOD_US)
+}
+
+/// Process id of the shell that created the agent cgroup `name`
+fn owner_pid(name: &str) -> Option<u32> {
+    let rest = name.strip_prefix(CGROUP_PREFIX)?;
+    let (rest, _id) = rest.rsplit_once('-')?;
+    let (_agent, pid) = rest.rsplit_once('-')?;
+    pid.parse().ok()
+}
+
+/// Remove agent cgroups whose shell is no longer running. Those of live
+/// shells, including ones an agent is about to join, are left alone.
+fn remove_stale(parent: &Path) {
+    let Ok(entries) = fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = owner_pid(&entry.file_name().to_string_lossy()) else {
+            continue;
+        };
+        if !Path::new("/proc").join(pid.to_string()).exists() {
+            let _ = fs::remove_dir(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cgroup_values() {
+        assert_eq!(own_cgroup("0::/user.slice/app.slice/term.scope\n"), Some("/user.slice/app.slice/term.scope"));
+        assert_eq!(own_cgroup("12:memory:/user.slice\n"), None);
+        assert_eq!(parent_cgroup("/user.slice/app.slice/term.scope"), Path::new("/sys/fs/cgroup/user.slice/app.slice"));
+        assert_eq!(parent_cgroup("/"), Path::new(CGROUP_ROOT));
+
+        assert_eq!(owner_pid("omniscient-agent-lint_v2-4242-7"), Some(4242));
+        assert_eq!(owner_pid("omniscient-agent-lint"), None);
+        assert_eq!(owner_pid("user.slice"), None);
+
+        assert_eq!(cpu_max(500), "50000 100000");
+        assert_eq!(cpu_max(2000), "200000 100000");
+    }
+}
@@ -3,6 +3,8 @@
 pub mod process;
 pub mod filesystem;
 pub mod sandbox;
+#[cfg(target_os = "linux")]
+pub mod cgroup;
 
 #[cfg(windows)]
 pub mod windows {
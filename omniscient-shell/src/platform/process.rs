@@ -2,6 +2,79 @@
 
 use anyhow::Result;
 use std::process::Child;
+use std::time::Duration;
+
+/// Clock ticks per second in /proc CPU times (USER_HZ, fixed at 100 on Linux)
+#[cfg(target_os = "linux")]
+const USER_HZ: u64 = 100;
+
+/// Point-in-time resource usage of a process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessSample {
+    /// Resident memory
+    pub memory_bytes: u64,
+    /// User plus system CPU time so far
+    pub cpu_time: Duration,
+}
+
+/// Sample a process's memory and CPU time
+#[cfg(target_os = "linux")]
+pub fn sample_process(pid: u32) -> Result<ProcessSample> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid))?;
+    let rss_kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    // The command name (field 2) may contain spaces, so count fields after its ')'
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid))?;
+    let after_comm = stat
+        .rsplit_once(')')
+        .map(|(_, rest)| rest)
+        .ok_or_else(|| anyhow::anyhow!("Malformed /proc/{}/stat", pid))?;
+    let fields: Vec<&str> = after_comm.split_whitespace().collect();
+    // utime and stime are fields 14 and 15 of the full line
+    let ticks = |i: usize| fields.get(i).and_then(|f| f.parse::<u64>().ok()).unwrap_or(0);
+    let cpu_ticks = ticks(11) + ticks(12);
+
+    Ok(ProcessSample {
+        memory_bytes: rss_kb * 1024,
+        cpu_time: Duration::from_millis(cpu_ticks * 1000 / USER_HZ),
+    })
+}
+
+/// Sample a process's memory and CPU time
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn sample_process(pid: u32) -> Result<ProcessSample> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=,time=", "-p", &pid.to_string()])
+        .output()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut fields = text.split_whitespace();
+    let (Some(rss_kb), Some(time)) = (fields.next(), fields.next()) else {
+        anyhow::bail!("Process {} not found", pid);
+    };
+
+    // [[dd-]hh:]mm:ss[.ss]
+    let (days, clock) = time.split_once('-').unwrap_or(("0", time));
+    let clock_seconds = clock
+        .split(':')
+        .fold(0.0, |acc, part| acc * 60.0 + part.parse::<f64>().unwrap_or(0.0));
+    let seconds = days.parse::<f64>().unwrap_or(0.0) * 86400.0 + clock_seconds;
+
+    Ok(ProcessSample {
+        memory_bytes: rss_kb.parse::<u64>().unwrap_or(0) * 1024,
+        cpu_time: Duration::from_secs_f64(seconds),
+    })
+}
+
+/// Sample a process's memory and CPU time
+#[cfg(not(unix))]
+pub fn sample_process(_pid: u32) -> Result<ProcessSample> {
+    anyhow::bail!("Process resource sampling is not implemented on this platform")
+}
 
 /// Process management
 pub struct ProcessManager;
@@ -29,3 +102,15 @@ impl Default for ProcessManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sample_own_process() {
+        let sample = sample_process(std::process::id()).unwrap();
+        assert!(sample.memory_bytes > 0);
+    }
+}
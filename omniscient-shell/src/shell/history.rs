@@ -1,41 +1,197 @@
-//! Command history management
+//! Command history, kept across sessions in a history file
+//!
+//! The file holds one JSON string per line so multi-line commands survive
+//! the round trip. New commands are appended; the file is rewritten only
+//! when the oldest commands have to be dropped.
 
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
+/// Commands kept when no size is configured
+pub const DEFAULT_MAX_SIZE: usize = 1000;
+
+/// History file under `~/.omniscient`
+pub fn default_history_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".omniscient").join("history")
+}
+
+#[derive(Debug)]
 pub struct History {
-    commands: Arc<Mutex<Vec<String>>>,
+    commands: VecDeque<String>,
     max_size: usize,
+    /// File the history is saved to; `None` keeps it in memory only
+    path: Option<PathBuf>,
 }
 
 impl History {
+    /// History kept in memory only
     pub fn new(max_size: usize) -> Self {
         History {
-            commands: Arc::new(Mutex::new(Vec::new())),
+            commands: VecDeque::new(),
             max_size,
+            path: None,
+        }
+    }
+
+    /// History kept in memory only, starting with `commands` (oldest first)
+    pub fn from_commands(commands: Vec<String>, max_size: usize) -> Self {
+        let mut history = History::new(max_size);
+        for command in commands {
+            history.push(command);
+        }
+        history
+    }
+
+    /// Load the history saved at `path`, saving new commands there too. A
+    /// missing file starts an empty history; unreadable lines are skipped.
+    pub fn load(path: impl Into<PathBuf>, max_size: usize) -> Result<Self> {
+        let path = path.into();
+        let mut history = History::new(max_size);
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                for command in contents.lines().filter_map(|line| serde_json::from_str::<String>(line).ok()) {
+                    history.push(command);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read history file {}", path.display()))
+            }
+        }
+        history.path = Some(path);
+        Ok(history)
+    }
+
+    /// Record a command; a repeat of the previous command is skipped
+    pub fn add(&mut self, command: &str) -> Result<()> {
+        if command.trim().is_empty() || self.commands.back().map(String::as_str) == Some(command) {
+            return Ok(());
+        }
+        let trimmed = self.push(command.to_string());
+        match &self.path {
+            Some(path) if trimmed => self.rewrite(path),
+            Some(path) => append(path, command),
+            None => Ok(()),
+        }
+    }
+
+    /// Commands oldest first, from earlier sessions and this one
+    pub fn get_all(&self) -> Vec<String> {
+        self.commands.iter().cloned().collect()
+    }
+
+    /// Commands containing `query`, ignoring case as the command palette
+    /// does. Commands starting with it rank first, then those with it at the
+    /// start of a word, then the rest; each group newest first. A command
+    /// run several times is listed once.
+    pub fn search(&self, query: &str) -> Vec<&str> {
+        let query = query.to_lowercase();
+        let mut results: Vec<(usize, &str)> = Vec::new();
+        for command in self.commands.iter().rev() {
+            let Some(quality) = match_quality(&command.to_lowercase(), &query) else {
+                continue;
+            };
+            if !results.iter().any(|(_, seen)| seen == command) {
+                results.push((quality, command));
+            }
         }
+        results.sort_by_key(|(quality, _)| *quality);
+        results.into_iter().map(|(_, command)| command).collect()
     }
 
-    pub async fn add(&self, command: String) {
-        let mut commands = self.commands.lock().await;
-        commands.push(command);
-        
-        // Trim to max size
-        if commands.len() > self.max_size {
-            commands.remove(0);
+    /// Add without saving; returns whether old commands were dropped to
+    /// stay within `max_size`
+    fn push(&mut self, command: String) -> bool {
+        self.commands.push_back(command);
+        let excess = self.commands.len().saturating_sub(self.max_size);
+        self.commands.drain(..excess);
+        excess > 0
+    }
+
+    fn rewrite(&self, path: &Path) -> Result<()> {
+        let mut contents = String::new();
+        for command in &self.commands {
+            contents.push_str(&serde_json::to_string(command)?);
+            contents.push('\n');
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
         }
+        fs::write(path, contents).with_context(|| format!("Failed to write history file {}", path.display()))
+    }
+}
+
+/// How well `command` matches `query`, both lowercase; lower is better,
+/// None if it doesn't contain it
+fn match_quality(command: &str, query: &str) -> Option<usize> {
+    let starts: Vec<usize> = command.match_indices(query).map(|(i, _)| i).collect();
+    match starts.first()? {
+        0 => Some(0),
+        _ if starts.iter().any(|&i| !command[..i].ends_with(char::is_alphanumeric)) => Some(1),
+        _ => Some(2),
+    }
+}
+
+fn append(path: &Path, command: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
     }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open history file {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(command)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_persists_across_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
 
-    pub async fn get_all(&self) -> Vec<String> {
-        let commands = self.commands.lock().await;
-        commands.clone()
+        let mut history = History::load(&path, 10).unwrap();
+        history.add("Get-Date").unwrap();
+        history.add("Get-Date").unwrap();
+        history.add("Get-ChildItem |\n  Sort-Object Length").unwrap();
+        history.add("Get-Date").unwrap();
+
+        let mut next = History::load(&path, 10).unwrap();
+        assert_eq!(next.get_all(), vec!["Get-Date", "Get-ChildItem |\n  Sort-Object Length", "Get-Date"]);
+        next.add("Get-Process").unwrap();
+        assert_eq!(History::load(&path, 10).unwrap().get_all().len(), 4);
     }
 
-    pub async fn search(&self, query: &str) -> Vec<String> {
-        let commands = self.commands.lock().await;
-        commands.iter()
-            .filter(|cmd| cmd.contains(query))
-            .cloned()
-            .collect()
+    #[test]
+    fn test_history_drops_oldest_past_max_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        let mut history = History::load(&path, 2).unwrap();
+        for command in ["a", "b", "c"] {
+            history.add(command).unwrap();
+        }
+        assert_eq!(history.get_all(), vec!["b", "c"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "\"b\"\n\"c\"\n");
+        assert_eq!(History::load(&path, 1).unwrap().get_all(), vec!["c"]);
+    }
+
+    #[test]
+    fn test_search_ranks_prefix_then_word_then_recency() {
+        let commands = ["git status", "Get-Date", "ls | grep get", "budget", "GET-Process", "Get-Date"];
+        let history = History::from_commands(commands.iter().map(|c| c.to_string()).collect(), 10);
+
+        assert_eq!(history.search("get"), vec!["Get-Date", "GET-Process", "ls | grep get", "budget"]);
+        assert_eq!(history.search("DATE"), vec!["Get-Date"]);
+        assert!(history.search("missing").is_empty());
+        assert_eq!(history.search("").len(), 5);
     }
 }
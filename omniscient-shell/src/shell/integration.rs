@@ -1,14 +1,25 @@
 //! PowerShell integration implementation
 
 use anyhow::{Context, Result};
-use std::process::{Command, Stdio};
+use std::process::{Command, ExitStatus, Stdio};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Child;
+use tokio::sync::{mpsc, Mutex};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tokio_util::sync::CancellationToken;
+
+use crate::shell::history::{History, DEFAULT_MAX_SIZE};
+use crate::shell::process_tree::ProcessTree;
+
+/// Output lines buffered ahead of a slow reader of `execute_streaming`
+const STREAM_BUFFER: usize = 64;
 
 /// PowerShell integration layer
 pub struct PowerShellIntegration {
     pwsh_path: String,
-    history: Arc<Mutex<Vec<String>>>,
+    history: Arc<Mutex<History>>,
 }
 
 impl PowerShellIntegration {
@@ -19,10 +30,24 @@ impl PowerShellIntegration {
 
         Ok(PowerShellIntegration {
             pwsh_path,
-            history: Arc::new(Mutex::new(Vec::new())),
+            history: Arc::new(Mutex::new(History::new(DEFAULT_MAX_SIZE))),
         })
     }
 
+    /// Create an integration bound to an explicit PowerShell executable
+    pub fn with_path(pwsh_path: impl Into<String>) -> Self {
+        PowerShellIntegration {
+            pwsh_path: pwsh_path.into(),
+            history: Arc::new(Mutex::new(History::new(DEFAULT_MAX_SIZE))),
+        }
+    }
+
+    /// Record commands in `history`, e.g. one loaded from the history file
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = Arc::new(Mutex::new(history));
+        self
+    }
+
     /// Find PowerShell executable on the system
     fn find_powershell() -> Result<String> {
         // Try pwsh first (PowerShell 7+)
@@ -56,15 +81,15 @@ impl PowerShellIntegration {
 
     /// Execute a PowerShell command
     pub async fn execute(&self, command: &str) -> Result<String> {
-        let mut history = self.history.lock().await;
-        history.push(command.to_string());
+        record(&self.history, command).await;
 
-        let output = Command::new(&self.pwsh_path)
+        let output = tokio::process::Command::new(&self.pwsh_path)
             .arg("-NoProfile")
             .arg("-NonInteractive")
             .arg("-Command")
             .arg(command)
             .output()
+            .await
             .context("Failed to execute PowerShell command")?;
 
         if output.status.success() {
@@ -75,10 +100,43 @@ impl PowerShellIntegration {
         }
     }
 
-    /// Get command history
+    /// Execute a PowerShell command, yielding its stdout line by line as it
+    /// is written. A failed command ends the stream with an error carrying
+    /// stderr. Dropping the stream kills the command. Must be called within
+    /// a tokio runtime.
+    pub fn execute_streaming(&self, command: &str) -> impl Stream<Item = Result<String>> {
+        self.execute_cancellable(command, CancellationToken::new())
+    }
+
+    /// Like `execute_streaming`, but cancelling `cancel` kills the command
+    /// and every process it started, ending the stream with an error
+    pub fn execute_cancellable(&self, command: &str, cancel: CancellationToken) -> impl Stream<Item = Result<String>> {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let history = self.history.clone();
+        let mut process = tokio::process::Command::new(&self.pwsh_path);
+        process
+            .arg("-NoProfile")
+            .arg("-NonInteractive")
+            .arg("-Command")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let command = command.to_string();
+
+        tokio::spawn(async move {
+            record(&history, &command).await;
+            if let Err(e) = stream_output(process, &tx, &cancel).await {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+
+    /// Get command history: commands saved by earlier sessions, then this one's
     pub async fn get_history(&self) -> Vec<String> {
-        let history = self.history.lock().await;
-        history.clone()
+        self.history.lock().await.get_all()
     }
 
     /// Get PowerShell version
@@ -87,6 +145,63 @@ impl PowerShellIntegration {
     }
 }
 
+/// Add `command` to the history; failing to save it isn't worth failing the command
+async fn record(history: &Mutex<History>, command: &str) {
+    if let Err(e) = history.lock().await.add(command) {
+        tracing::warn!("{:#}", e);
+    }
+}
+
+/// Run `process`, sending its stdout lines to `tx` until it exits. If the
+/// receiver is dropped or `cancel` fires first, the command's whole process
+/// tree is killed.
+async fn stream_output(
+    mut process: tokio::process::Command,
+    tx: &mpsc::Sender<Result<String>>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    ProcessTree::prepare(&mut process);
+    let mut child = process.spawn().context("Failed to execute PowerShell command")?;
+    let tree = ProcessTree::attach(&child)?;
+    // Drained alongside stdout so a chatty stderr can't fill its pipe and stall the command
+    let mut stderr = child.stderr.take().context("PowerShell stderr not captured")?;
+    let errors = tokio::spawn(async move {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text).await;
+        text
+    });
+
+    let status = tokio::select! {
+        _ = tx.closed() => Ok(None),
+        _ = cancel.cancelled() => Err(anyhow::anyhow!("PowerShell command cancelled")),
+        status = forward_lines(&mut child, tx) => status,
+    };
+    let status = match status {
+        Ok(Some(status)) => status,
+        stopped => {
+            tree.kill()?;
+            return stopped.map(|_| ());
+        }
+    };
+    if !status.success() {
+        let stderr = errors.await.unwrap_or_default();
+        anyhow::bail!("PowerShell command failed: {}", stderr);
+    }
+    Ok(())
+}
+
+/// Send the child's stdout lines to `tx`, then wait for it to exit; None if
+/// the receiver went away first
+async fn forward_lines(child: &mut Child, tx: &mpsc::Sender<Result<String>>) -> Result<Option<ExitStatus>> {
+    let mut stdout = BufReader::new(child.stdout.take().context("PowerShell stdout not captured")?).lines();
+    while let Some(line) = stdout.next_line().await? {
+        if tx.send(Ok(line)).await.is_err() {
+            return Ok(None);
+        }
+    }
+    Ok(Some(child.wait().await?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,4 +222,92 @@ mod tests {
             assert!(version.is_ok());
         }
     }
+
+    /// Stand-in for pwsh that runs its -Command argument with sh
+    #[cfg(unix)]
+    fn fake_pwsh(dir: &std::path::Path) -> PowerShellIntegration {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join("pwsh");
+        std::fs::write(&path, "#!/bin/sh\neval \"$4\"\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        PowerShellIntegration::with_path(path.to_str().unwrap())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_streaming_yields_lines_then_failure() {
+        use tokio_stream::StreamExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ps = fake_pwsh(dir.path());
+        let items: Vec<Result<String>> = ps
+            .execute_streaming("echo one; echo two; echo bad >&2; exit 3")
+            .collect()
+            .await;
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap(), "one");
+        assert_eq!(items[1].as_ref().unwrap(), "two");
+        assert!(items[2].as_ref().unwrap_err().to_string().contains("bad"));
+        assert_eq!(ps.get_history().await.len(), 1);
+    }
+
+    /// Wait for `pid` to exit, failing the test after a few seconds
+    #[cfg(target_os = "linux")]
+    async fn assert_exits(pid: &str) {
+        let status = std::path::PathBuf::from(format!("/proc/{}/status", pid));
+        for _ in 0..50 {
+            let gone = std::fs::read_to_string(&status).map_or(true, |s| s.contains("zombie"));
+            if gone {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        panic!("process {} is still running", pid);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_dropping_stream_kills_command() {
+        use std::time::Duration;
+        use tokio_stream::StreamExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ps = fake_pwsh(dir.path());
+        let mut stream = Box::pin(ps.execute_streaming("echo $$; sleep 30"));
+
+        // The first line arrives while the command is still running
+        let pid = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("no output before the command finished")
+            .unwrap()
+            .unwrap();
+        drop(stream);
+        assert_exits(&pid).await;
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_cancel_kills_processes_the_command_started() {
+        use std::time::Duration;
+        use tokio_stream::StreamExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ps = fake_pwsh(dir.path());
+        let cancel = CancellationToken::new();
+        let mut stream = Box::pin(ps.execute_cancellable("sleep 30 & echo $!; wait", cancel.clone()));
+
+        let grandchild = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        cancel.cancel();
+
+        let ended = tokio::time::timeout(Duration::from_secs(5), stream.next()).await.unwrap();
+        assert!(ended.unwrap().unwrap_err().to_string().contains("cancelled"));
+        assert!(stream.next().await.is_none());
+        assert_exits(&grandchild).await;
+    }
 }
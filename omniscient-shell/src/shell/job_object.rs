@@ -0,0 +1,103 @@
+//! Windows job objects: kill a process together with everything it started
+//!
+//! A process assigned after it has been spawned can start others before
+//! then that escape the job; spawn it with `CREATE_SUSPENDED` and
+//! `resume_process` it once assigned to close that gap.
+
+use anyhow::Result;
+use std::os::windows::io::RawHandle;
+use windows::Win32::Foundation::{CloseHandle, BOOL, HANDLE};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+};
+use windows::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+    TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_JOB_MEMORY,
+    JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+};
+use windows::Win32::System::Threading::{OpenThread, ResumeThread, THREAD_SUSPEND_RESUME};
+
+/// Job object holding a command's processes; dropping it kills them too
+#[derive(Debug)]
+pub struct Job(HANDLE);
+
+// The handle is only passed to thread-safe Win32 calls
+unsafe impl Send for Job {}
+unsafe impl Sync for Job {}
+
+impl Job {
+    /// Create a job whose processes together may use at most `memory_limit`
+    /// bytes, if given
+    pub fn new(memory_limit: Option<u64>) -> Result<Self> {
+        unsafe {
+            let job = Job(CreateJobObjectW(None, None)?);
+            let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if let Some(bytes) = memory_limit {
+                limits.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                limits.JobMemoryLimit = bytes as usize;
+            }
+            SetInformationJobObject(
+                job.0,
+                JobObjectExtendedLimitInformation,
+                &limits as *const _ as *const std::ffi::c_void,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            )?;
+            Ok(job)
+        }
+    }
+
+    /// Create a job holding only `process`
+    pub fn with_process(process: RawHandle) -> Result<Self> {
+        let job = Job::new(None)?;
+        job.assign(process)?;
+        Ok(job)
+    }
+
+    /// Add `process` (and the processes it starts later) to the job
+    pub fn assign(&self, process: RawHandle) -> Result<()> {
+        unsafe { AssignProcessToJobObject(self.0, HANDLE(process))? };
+        Ok(())
+    }
+
+    /// Forcefully end every process in the job
+    pub fn terminate(&self) -> Result<()> {
+        unsafe { TerminateJobObject(self.0, 1)? };
+        Ok(())
+    }
+}
+
+/// Start the threads of process `pid`, spawned with `CREATE_SUSPENDED`
+pub fn resume_process(pid: u32) -> Result<()> {
+    let mut resumed = false;
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0)?;
+        let mut entry = THREADENTRY32 {
+            dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+            ..Default::default()
+        };
+        let mut found = Thread32First(snapshot, &mut entry).is_ok();
+        while found {
+            if entry.th32OwnerProcessID == pid {
+                if let Ok(thread) = OpenThread(THREAD_SUSPEND_RESUME, BOOL::from(false), entry.th32ThreadID) {
+                    resumed |= ResumeThread(thread) != u32::MAX;
+                    let _ = CloseHandle(thread);
+                }
+            }
+            found = Thread32Next(snapshot, &mut entry).is_ok();
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    if !resumed {
+        anyhow::bail!("Found no thread of process {} to resume", pid);
+    }
+    Ok(())
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
@@ -4,5 +4,8 @@ pub mod integration;
 pub mod command_router;
 pub mod process_supervision;
 pub mod history;
+pub mod process_tree;
+#[cfg(windows)]
+pub mod job_object;
 
 pub use integration::PowerShellIntegration;
@@ -1,30 +1,454 @@
-//! Process supervision for PowerShell instances
+//! Process supervision: restart long-running processes, such as native
+//! agents, when they exit with an error
+//!
+//! Each restart waits twice as long as the one before. A process is given
+//! up on (marked dead) once it has used up its restarts, or earlier if it
+//! keeps crashing right after starting.
 
-use std::process::Child;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::future::Future;
+use std::process::ExitStatus;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 
-pub struct ProcessSupervisor {
-    processes: Arc<Mutex<Vec<Child>>>,
+use crate::utils::config::RestartConfig;
+
+/// Longest wait between restarts, however many there have been
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A running process the supervisor can wait on and stop
+#[async_trait]
+pub trait SupervisedProcess: Send + 'static {
+    /// Wait for the process to exit
+    async fn wait(&mut self) -> Result<ExitStatus>;
+
+    /// Force the process to exit
+    fn kill(&mut self) -> Result<()>;
+
+    /// Ask the process to exit, e.g. with SIGTERM; by default it is killed
+    fn terminate(&mut self) -> Result<()> {
+        self.kill()
+    }
+
+    /// OS process id, while it is running
+    fn id(&self) -> Option<u32>;
+}
+
+#[async_trait]
+impl SupervisedProcess for tokio::process::Child {
+    async fn wait(&mut self) -> Result<ExitStatus> {
+        Ok(tokio::process::Child::wait(self).await?)
+    }
+
+    fn id(&self) -> Option<u32> {
+        tokio::process::Child::id(self)
+    }
+
+    fn kill(&mut self) -> Result<()> {
+        Ok(self.start_kill()?)
+    }
+
+    #[cfg(unix)]
+    fn terminate(&mut self) -> Result<()> {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        match tokio::process::Child::id(self) {
+            Some(pid) => Ok(kill(Pid::from_raw(pid as i32), Signal::SIGTERM)?),
+            None => Ok(()),
+        }
+    }
 }
 
-impl ProcessSupervisor {
-    pub fn new() -> Self {
-        ProcessSupervisor {
-            processes: Arc::new(Mutex::new(Vec::new())),
+/// When and how often a crashed process is restarted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    pub max_retries: u32,
+    /// Wait before the first restart; doubled for each one after
+    pub backoff: Duration,
+    /// Exiting sooner than this after starting counts as a fast crash
+    pub min_uptime: Duration,
+    /// Fast crashes in a row before the process is marked dead
+    pub max_fast_crashes: u32,
+}
+
+impl RestartPolicy {
+    pub fn from_config(config: &RestartConfig) -> Self {
+        RestartPolicy {
+            max_retries: config.max_retries,
+            backoff: Duration::from_millis(config.backoff_ms),
+            min_uptime: Duration::from_secs(config.min_uptime_secs),
+            max_fast_crashes: config.max_fast_crashes,
         }
     }
 
-    pub async fn add_process(&self, child: Child) {
-        let mut processes = self.processes.lock().await;
-        processes.push(child);
+    /// Wait before restart number `restart` (counting from 0)
+    fn backoff_for(&self, restart: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(restart)).min(MAX_BACKOFF)
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self::from_config(&RestartConfig::default())
+    }
+}
+
+/// Health of a supervised process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    /// Exited with an error; waiting to be restarted
+    Crashed,
+    /// Being started again after a crash
+    Restarting,
+    /// Crashed too often; no longer restarted
+    Dead,
+    /// Exited successfully or was stopped
+    Stopped,
+}
+
+/// One entry of `Supervisor::status`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessStatus {
+    pub name: String,
+    pub state: ProcessState,
+    pub restarts: u32,
+    /// Process id of the latest start
+    pub pid: Option<u32>,
+}
+
+struct Supervised {
+    state: ProcessState,
+    restarts: u32,
+    pid: Option<u32>,
+    stop: CancellationToken,
+    /// How long a stopped process gets to exit after being asked to
+    grace: Duration,
+    /// Set when the process had to be killed after its grace period
+    killed: bool,
+    /// Cancelled once the process is no longer supervised
+    done: CancellationToken,
+    /// Taken by `stop` to wait for the process to be killed
+    task: Option<JoinHandle<()>>,
+}
+
+type Processes = Arc<Mutex<HashMap<String, Supervised>>>;
+
+/// Keeps processes running, restarting them when they crash
+pub struct Supervisor {
+    policy: RestartPolicy,
+    processes: Processes,
+}
+
+impl Supervisor {
+    pub fn new(policy: RestartPolicy) -> Self {
+        Supervisor {
+            policy,
+            processes: Arc::new(Mutex::new(HashMap::new())),
+        }
     }
 
-    pub async fn cleanup(&self) {
+    /// Start a process with `spawn` and keep it running under `name`,
+    /// calling `spawn` again after each crash. A process already supervised
+    /// under `name` is stopped first.
+    pub async fn supervise<P, F, Fut>(&self, name: impl Into<String>, spawn: F)
+    where
+        P: SupervisedProcess,
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<P>> + Send + 'static,
+    {
+        let name = name.into();
+        self.stop(&name).await;
+
+        let stop = CancellationToken::new();
+        let done = CancellationToken::new();
         let mut processes = self.processes.lock().await;
-        for child in processes.iter_mut() {
-            let _ = child.kill();
+        let task = tokio::spawn(run(
+            name.clone(),
+            spawn,
+            self.policy,
+            (stop.clone(), done.clone()),
+            self.processes.clone(),
+        ));
+        processes.insert(
+            name,
+            Supervised {
+                state: ProcessState::Restarting,
+                restarts: 0,
+                pid: None,
+                stop,
+                grace: Duration::ZERO,
+                killed: false,
+                done,
+                task: Some(task),
+            },
+        );
+    }
+
+    /// Wait until the process supervised under `name` exits successfully,
+    /// is stopped or dies; returns its final state
+    pub async fn wait(&self, name: &str) -> Option<ProcessState> {
+        let done = self.processes.lock().await.get(name)?.done.clone();
+        done.cancelled().await;
+        self.processes.lock().await.get(name).map(|process| process.state)
+    }
+
+    /// State of every supervised process, by name
+    pub async fn status(&self) -> Vec<ProcessStatus> {
+        let processes = self.processes.lock().await;
+        let mut status: Vec<ProcessStatus> = processes
+            .iter()
+            .map(|(name, process)| ProcessStatus {
+                name: name.clone(),
+                state: process.state,
+                restarts: process.restarts,
+                pid: process.pid,
+            })
+            .collect();
+        status.sort_by(|a, b| a.name.cmp(&b.name));
+        status
+    }
+
+    /// Kill the process supervised under `name` and stop restarting it
+    pub async fn stop(&self, name: &str) {
+        self.stop_within(&[name.to_string()], Duration::ZERO).await;
+    }
+
+    /// Ask each process in `names` to exit and stop restarting it; those
+    /// still running after `grace` are killed. All of them are stopped at
+    /// once. Returns the names of the ones that had to be killed.
+    pub async fn stop_within(&self, names: &[String], grace: Duration) -> Vec<String> {
+        let mut tasks = Vec::new();
+        {
+            let mut processes = self.processes.lock().await;
+            for name in names {
+                let Some(process) = processes.get_mut(name) else {
+                    continue;
+                };
+                process.grace = grace;
+                process.stop.cancel();
+                if let Some(task) = process.task.take() {
+                    tasks.push((name, task));
+                }
+            }
+        }
+
+        let mut killed = Vec::new();
+        for (name, task) in tasks {
+            let _ = task.await;
+            if self.processes.lock().await.get(name).is_some_and(|process| process.killed) {
+                killed.push(name.clone());
+            }
+        }
+        killed
+    }
+
+    /// Stop every supervised process
+    pub async fn shutdown(&self) {
+        let names: Vec<String> = self.processes.lock().await.keys().cloned().collect();
+        self.stop_within(&names, Duration::ZERO).await;
+    }
+}
+
+/// Supervise one process until it exits successfully, is stopped or dies
+async fn run<P, F, Fut>(
+    name: String,
+    spawn: F,
+    policy: RestartPolicy,
+    (stop, done): (CancellationToken, CancellationToken),
+    processes: Processes,
+) where
+    P: SupervisedProcess,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<P>>,
+{
+    let _done = done.drop_guard();
+    let set_state = |state: ProcessState, restarts: u32| {
+        let processes = processes.clone();
+        let name = name.clone();
+        async move {
+            if let Some(process) = processes.lock().await.get_mut(&name) {
+                process.state = state;
+                process.restarts = restarts;
+            }
+        }
+    };
+
+    let mut restarts = 0;
+    let mut fast_crashes = 0;
+    loop {
+        let started = Instant::now();
+        let exit = match spawn().await {
+            Ok(mut process) => {
+                if let Some(supervised) = processes.lock().await.get_mut(&name) {
+                    supervised.pid = process.id();
+                }
+                set_state(ProcessState::Running, restarts).await;
+                tokio::select! {
+                    status = process.wait() => status,
+                    _ = stop.cancelled() => {
+                        let grace = processes.lock().await.get(&name).map_or(Duration::ZERO, |p| p.grace);
+                        let killed = stop_process(&mut process, grace).await;
+                        if let Some(supervised) = processes.lock().await.get_mut(&name) {
+                            supervised.killed = killed;
+                        }
+                        break;
+                    }
+                }
+            }
+            Err(e) => Err(e),
+        };
+        match exit {
+            Ok(status) if status.success() => break,
+            Ok(status) => tracing::warn!("Supervised process '{}' exited with {}", name, status),
+            Err(e) => tracing::warn!("Supervised process '{}' failed: {:#}", name, e),
         }
-        processes.clear();
+
+        if started.elapsed() < policy.min_uptime {
+            fast_crashes += 1;
+        } else {
+            fast_crashes = 0;
+        }
+        if restarts >= policy.max_retries || fast_crashes >= policy.max_fast_crashes {
+            tracing::error!("Supervised process '{}' keeps crashing; giving up after {} restarts", name, restarts);
+            set_state(ProcessState::Dead, restarts).await;
+            return;
+        }
+
+        set_state(ProcessState::Crashed, restarts).await;
+        tokio::select! {
+            _ = tokio::time::sleep(policy.backoff_for(restarts)) => {}
+            _ = stop.cancelled() => break,
+        }
+        restarts += 1;
+        set_state(ProcessState::Restarting, restarts).await;
+    }
+    set_state(ProcessState::Stopped, restarts).await;
+}
+
+/// Ask `process` to exit, killing it if it is still running after `grace`;
+/// returns whether it had to be killed
+async fn stop_process<P: SupervisedProcess>(process: &mut P, grace: Duration) -> bool {
+    if !grace.is_zero()
+        && process.terminate().is_ok()
+        && tokio::time::timeout(grace, process.wait()).await.is_ok()
+    {
+        return false;
+    }
+    let _ = process.kill();
+    let _ = process.wait().await;
+    true
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn sh(script: &'static str) -> impl Fn() -> std::future::Ready<Result<tokio::process::Child>> {
+        move || {
+            let child = tokio::process::Command::new("sh").arg("-c").arg(script).spawn();
+            std::future::ready(child.map_err(Into::into))
+        }
+    }
+
+    async fn wait_for(supervisor: &Supervisor, name: &str, state: ProcessState) -> ProcessStatus {
+        for _ in 0..100 {
+            let status = supervisor.status().await.into_iter().find(|s| s.name == name).unwrap();
+            if status.state == state {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("process never became {:?}", state);
+    }
+
+    fn policy(max_retries: u32, min_uptime: Duration) -> RestartPolicy {
+        RestartPolicy { max_retries, backoff: Duration::from_millis(5), min_uptime, max_fast_crashes: 3 }
+    }
+
+    #[tokio::test]
+    async fn test_crashing_process_is_restarted_until_dead() {
+        let supervisor = Supervisor::new(policy(2, Duration::ZERO));
+        let spawns = Arc::new(AtomicU32::new(0));
+        let counter = spawns.clone();
+        let spawn = sh("exit 1");
+        supervisor
+            .supervise("agent", move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                spawn()
+            })
+            .await;
+
+        assert_eq!(supervisor.wait("agent").await, Some(ProcessState::Dead));
+        let status = wait_for(&supervisor, "agent", ProcessState::Dead).await;
+        assert_eq!(status.restarts, 2);
+        assert!(status.pid.is_some());
+        assert_eq!(spawns.load(Ordering::SeqCst), 3);
+        assert_eq!(supervisor.wait("missing").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_fast_crashes_give_up_before_max_retries() {
+        let supervisor = Supervisor::new(policy(10, Duration::from_secs(60)));
+        supervisor.supervise("agent", sh("exit 1")).await;
+        assert_eq!(wait_for(&supervisor, "agent", ProcessState::Dead).await.restarts, 2);
+
+        assert_eq!(RestartPolicy::default().backoff_for(0), Duration::from_millis(500));
+        assert_eq!(RestartPolicy::default().backoff_for(2), Duration::from_secs(2));
+        assert_eq!(RestartPolicy::default().backoff_for(20), MAX_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn test_stop_kills_running_process() {
+        let supervisor = Supervisor::new(RestartPolicy::default());
+        supervisor.supervise("agent", sh("sleep 30")).await;
+        wait_for(&supervisor, "agent", ProcessState::Running).await;
+
+        supervisor.shutdown().await;
+        let status = supervisor.status().await.remove(0);
+        assert_eq!(status.state, ProcessState::Stopped);
+        assert_eq!(status.restarts, 0);
+
+        // Exiting successfully isn't a crash
+        supervisor.supervise("clean", sh("exit 0")).await;
+        assert_eq!(wait_for(&supervisor, "clean", ProcessState::Stopped).await.restarts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stop_within_gives_grace_before_killing() {
+        let dir = tempfile::tempdir().unwrap();
+        let ready = dir.path().join("ready");
+        let stubborn = format!("trap '' TERM; touch {}; exec sleep 30", ready.display());
+        let supervisor = Supervisor::new(RestartPolicy::default());
+        supervisor.supervise("polite", sh("exec sleep 30")).await;
+        supervisor
+            .supervise("stubborn", move || {
+                let child = tokio::process::Command::new("sh").arg("-c").arg(&stubborn).spawn();
+                std::future::ready(child.map_err(anyhow::Error::from))
+            })
+            .await;
+        wait_for(&supervisor, "polite", ProcessState::Running).await;
+        for _ in 0..100 {
+            if ready.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let names = vec!["polite".to_string(), "stubborn".to_string(), "missing".to_string()];
+        let started = Instant::now();
+        let killed = supervisor.stop_within(&names, Duration::from_millis(300)).await;
+        assert_eq!(killed, vec!["stubborn".to_string()]);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(supervisor.status().await.iter().all(|s| s.state == ProcessState::Stopped));
     }
 }
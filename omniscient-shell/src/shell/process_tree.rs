@@ -0,0 +1,62 @@
+//! Killing a command together with every process it started
+//!
+//! Killing only the direct child would leave anything it spawned running.
+//! On Unix the command leads its own process group and the whole group is
+//! signalled; on Windows it is placed in a job object that is terminated.
+
+use anyhow::Result;
+use tokio::process::{Child, Command};
+
+#[cfg(windows)]
+use crate::shell::job_object::Job;
+
+/// The processes of one spawned command
+pub struct ProcessTree {
+    #[cfg(unix)]
+    pgid: nix::unistd::Pid,
+    #[cfg(windows)]
+    job: Job,
+}
+
+impl ProcessTree {
+    /// Set up `command` so its processes can be killed together; call before spawning
+    pub fn prepare(command: &mut Command) {
+        #[cfg(unix)]
+        command.process_group(0);
+        #[cfg(not(unix))]
+        let _ = command;
+    }
+
+    /// Track the processes of `child`, spawned from a prepared command
+    #[cfg(unix)]
+    pub fn attach(child: &Child) -> Result<Self> {
+        let pid = child.id().ok_or_else(|| anyhow::anyhow!("Process has already exited"))?;
+        Ok(ProcessTree { pgid: nix::unistd::Pid::from_raw(pid as i32) })
+    }
+
+    /// Track the processes of `child`, spawned from a prepared command
+    #[cfg(windows)]
+    pub fn attach(child: &Child) -> Result<Self> {
+        let handle = child.raw_handle().ok_or_else(|| anyhow::anyhow!("Process has already exited"))?;
+        Ok(ProcessTree { job: Job::with_process(handle)? })
+    }
+
+    /// Forcefully end every process of the command
+    #[cfg(unix)]
+    pub fn kill(&self) -> Result<()> {
+        use nix::errno::Errno;
+        use nix::sys::signal::{killpg, Signal};
+
+        match killpg(self.pgid, Signal::SIGKILL) {
+            // Everything has already exited
+            Ok(()) | Err(Errno::ESRCH) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Forcefully end every process of the command
+    #[cfg(windows)]
+    pub fn kill(&self) -> Result<()> {
+        self.job.terminate()
+    }
+}
@@ -0,0 +1,109 @@
+//! Arguments previously entered for parameterized commands
+//!
+//! History is kept per command and parameter, so `workspace:select <path>`
+//! recalls paths and never another command's values. Each list is stored in
+//! the KV store as JSON, oldest first.
+
+use anyhow::{Context, Result};
+use std::sync::Arc;
+
+use crate::state::backend::StateBackend;
+use crate::state::kv_store::KVStore;
+use crate::tui::command_palette::HistoryCursor;
+
+/// Entries kept per command parameter; the oldest are dropped first
+pub const MAX_ENTRIES: usize = 50;
+
+/// Per-command argument history persisted in the KV store
+pub struct ArgumentHistory {
+    kv: KVStore,
+}
+
+fn key(command: &str, param: &str) -> String {
+    format!("arg_history:{}:{}", command, param)
+}
+
+impl ArgumentHistory {
+    pub fn new(backend: Arc<dyn StateBackend>) -> Self {
+        ArgumentHistory { kv: KVStore::new(backend) }
+    }
+
+    /// Record `value` as the latest argument for `param` of `command` (its
+    /// canonical name, not an alias). Repeating a value moves it to the end.
+    pub async fn record(&self, command: &str, param: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(());
+        }
+        let mut entries = self.entries(command, param).await?;
+        entries.retain(|entry| entry != value);
+        entries.push(value.to_string());
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+
+        self.kv.set(&key(command, param), &serde_json::to_string(&entries)?).await
+    }
+
+    /// Arguments entered for `param` of `command`, oldest first
+    pub async fn entries(&self, command: &str, param: &str) -> Result<Vec<String>> {
+        let key = key(command, param);
+        match self.kv.get(&key).await? {
+            Some(json) => serde_json::from_str(&json)
+                .with_context(|| format!("Corrupt argument history in {}", key)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Cursor for browsing the history with Up/Down in the parameter prompt
+    pub async fn cursor(&self, command: &str, param: &str) -> Result<HistoryCursor> {
+        Ok(HistoryCursor::new(self.entries(command, param).await?))
+    }
+
+    /// Forget the arguments entered for `param` of `command`
+    pub async fn clear(&self, command: &str, param: &str) -> Result<()> {
+        self.kv.delete(&key(command, param)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::backend::MemoryBackend;
+    use crate::state::sqlite::SqliteStore;
+
+    #[tokio::test]
+    async fn test_entered_argument_is_recalled_for_its_command() {
+        let history = ArgumentHistory::new(Arc::new(SqliteStore::in_memory().unwrap()));
+        history.record("workspace:select", "path", "~/projects/a").await.unwrap();
+        history.record("workspace:select", "path", " ~/projects/b ").await.unwrap();
+        history.record("workspace:select", "path", "~/projects/a").await.unwrap();
+        history.record("workspace:select", "path", "").await.unwrap();
+        history.record("agent:enable", "name", "indexer").await.unwrap();
+
+        assert_eq!(
+            history.entries("workspace:select", "path").await.unwrap(),
+            vec!["~/projects/b", "~/projects/a"]
+        );
+        assert_eq!(history.entries("agent:enable", "name").await.unwrap(), vec!["indexer"]);
+        assert!(history.entries("agent:disable", "name").await.unwrap().is_empty());
+
+        let mut cursor = history.cursor("workspace:select", "path").await.unwrap();
+        assert_eq!(cursor.up(), Some("~/projects/a"));
+        assert_eq!(cursor.up(), Some("~/projects/b"));
+
+        history.clear("agent:enable", "name").await.unwrap();
+        assert!(history.entries("agent:enable", "name").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_history_is_capped() {
+        let history = ArgumentHistory::new(Arc::new(MemoryBackend::new()));
+        for i in 0..MAX_ENTRIES + 5 {
+            history.record("oauth:connect", "provider", &format!("p{}", i)).await.unwrap();
+        }
+
+        let entries = history.entries("oauth:connect", "provider").await.unwrap();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0], "p5");
+    }
+}
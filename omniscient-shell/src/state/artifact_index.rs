@@ -0,0 +1,103 @@
+//! Artifact index keyed by workspace
+
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::state::backend::StateBackend;
+use crate::workspace::artifacts::{normalize_tag, Artifact};
+
+/// Artifact index over a state backend
+///
+/// Every row carries the id of the workspace that produced it, so artifacts
+/// stored in a shared global root can still be listed per workspace.
+pub struct ArtifactIndex {
+    backend: Arc<dyn StateBackend>,
+}
+
+impl ArtifactIndex {
+    pub fn new(backend: Arc<dyn StateBackend>) -> Self {
+        ArtifactIndex { backend }
+    }
+
+    /// Insert or update an artifact for a workspace
+    pub async fn insert(&self, workspace: &str, artifact: &Artifact) -> Result<()> {
+        self.backend.artifact_put(workspace, artifact).await
+    }
+
+    /// List all artifacts belonging to a workspace
+    pub async fn list(&self, workspace: &str) -> Result<Vec<Artifact>> {
+        self.backend.artifacts_in(workspace).await
+    }
+
+    /// List artifacts of any kind or workspace carrying `tag`
+    pub async fn list_by_tag(&self, tag: &str) -> Result<Vec<Artifact>> {
+        self.backend.artifacts_tagged(&normalize_tag(tag)?).await
+    }
+
+    /// Remove an artifact from the index
+    pub async fn remove(&self, id: &str) -> Result<()> {
+        self.backend.artifact_remove(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::sqlite::SqliteStore;
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn test_artifacts_keyed_by_workspace() {
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let index = ArtifactIndex::new(store);
+
+        let a = Artifact::new("a".to_string(), "diff".to_string(), PathBuf::from("/tmp/a.diff"));
+        let b = Artifact::new("b".to_string(), "log".to_string(), PathBuf::from("/tmp/b.log"));
+        index.insert("ws-one", &a).await.unwrap();
+        index.insert("ws-two", &b).await.unwrap();
+
+        let listed = index.list("ws-one").await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, "a");
+        assert_eq!(listed[0].kind, "diff");
+
+        index.remove("a").await.unwrap();
+        assert!(index.list("ws-one").await.unwrap().is_empty());
+        assert_eq!(index.list("ws-two").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_by_tag() {
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let index = ArtifactIndex::new(store);
+
+        let mut diff = Artifact::new("d".to_string(), "diff".to_string(), PathBuf::from("/tmp/d.diff"));
+        let mut log = Artifact::new("l".to_string(), "log".to_string(), PathBuf::from("/tmp/l.log"));
+        let mut other = Artifact::new("o".to_string(), "diff".to_string(), PathBuf::from("/tmp/o.diff"));
+        diff.add_tag("Release-1.2").unwrap();
+        log.add_tag("release-1.2 ").unwrap();
+        log.add_tag("ci").unwrap();
+        other.add_tag("release-1.20").unwrap();
+        index.insert("ws-one", &diff).await.unwrap();
+        index.insert("ws-two", &log).await.unwrap();
+        index.insert("ws-one", &other).await.unwrap();
+
+        let mut tagged: Vec<_> = index
+            .list_by_tag("RELEASE-1.2")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        tagged.sort();
+        assert_eq!(tagged, ["d", "l"]);
+        assert_eq!(index.list("ws-two").await.unwrap()[0].tags, ["release-1.2", "ci"]);
+
+        log.remove_tag("release-1.2");
+        index.insert("ws-two", &log).await.unwrap();
+        let tagged = index.list_by_tag("release-1.2").await.unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].id, "d");
+        assert_eq!(index.list_by_tag("ci").await.unwrap().len(), 1);
+    }
+}
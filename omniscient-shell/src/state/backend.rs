@@ -0,0 +1,134 @@
+//! Storage backends behind the KV store, event ledger and artifact index
+//!
+//! `SqliteStore` is the default backend. `MemoryBackend` keeps everything in
+//! memory, for tests and for deployments that can't write a database file.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::RwLock;
+
+use crate::workspace::artifacts::Artifact;
+
+/// One row of the event log; `data` is the serialized event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventRecord {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub event_type: String,
+    pub agent_id: String,
+    pub data: String,
+}
+
+/// Operations the state stores need from a storage backend
+#[async_trait]
+pub trait StateBackend: Send + Sync {
+    async fn kv_set(&self, key: &str, value: &str) -> Result<()>;
+    async fn kv_get(&self, key: &str) -> Result<Option<String>>;
+    async fn kv_delete(&self, key: &str) -> Result<()>;
+    async fn kv_keys(&self) -> Result<Vec<String>>;
+
+    async fn event_append(&self, record: EventRecord) -> Result<()>;
+    /// Event data for `agent_id`, oldest first
+    async fn events_for_agent(&self, agent_id: &str) -> Result<Vec<String>>;
+    /// Data of the last `limit` events, newest first
+    async fn events_recent(&self, limit: usize) -> Result<Vec<String>>;
+
+    /// Insert or replace an artifact, recording the workspace it belongs to
+    async fn artifact_put(&self, workspace: &str, artifact: &Artifact) -> Result<()>;
+    /// Artifacts of `workspace`, oldest first
+    async fn artifacts_in(&self, workspace: &str) -> Result<Vec<Artifact>>;
+    /// Artifacts of any workspace carrying the normalized `tag`, oldest first
+    async fn artifacts_tagged(&self, tag: &str) -> Result<Vec<Artifact>>;
+    async fn artifact_remove(&self, id: &str) -> Result<()>;
+}
+
+/// Backend that keeps all state in memory and loses it on exit
+#[derive(Default)]
+pub struct MemoryBackend {
+    kv: RwLock<BTreeMap<String, String>>,
+    events: RwLock<Vec<EventRecord>>,
+    /// Artifacts by id, with their workspace
+    artifacts: RwLock<HashMap<String, (String, Artifact)>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn artifacts_where(&self, keep: impl Fn(&str, &Artifact) -> bool) -> Vec<Artifact> {
+        let mut artifacts: Vec<Artifact> = self
+            .artifacts
+            .read()
+            .await
+            .values()
+            .filter(|(workspace, artifact)| keep(workspace, artifact))
+            .map(|(_, artifact)| artifact.clone())
+            .collect();
+        artifacts.sort_by_key(|artifact| artifact.created_at);
+        artifacts
+    }
+}
+
+#[async_trait]
+impl StateBackend for MemoryBackend {
+    async fn kv_set(&self, key: &str, value: &str) -> Result<()> {
+        self.kv.write().await.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    async fn kv_get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.kv.read().await.get(key).cloned())
+    }
+
+    async fn kv_delete(&self, key: &str) -> Result<()> {
+        self.kv.write().await.remove(key);
+        Ok(())
+    }
+
+    async fn kv_keys(&self) -> Result<Vec<String>> {
+        Ok(self.kv.read().await.keys().cloned().collect())
+    }
+
+    async fn event_append(&self, record: EventRecord) -> Result<()> {
+        self.events.write().await.push(record);
+        Ok(())
+    }
+
+    async fn events_for_agent(&self, agent_id: &str) -> Result<Vec<String>> {
+        let log = self.events.read().await;
+        let mut events: Vec<&EventRecord> = log.iter().filter(|record| record.agent_id == agent_id).collect();
+        // Stable, so events with the same timestamp stay in append order
+        events.sort_by_key(|record| record.timestamp);
+        Ok(events.into_iter().map(|record| record.data.clone()).collect())
+    }
+
+    async fn events_recent(&self, limit: usize) -> Result<Vec<String>> {
+        let log = self.events.read().await;
+        let mut events: Vec<&EventRecord> = log.iter().collect();
+        events.sort_by_key(|record| record.timestamp);
+        Ok(events.into_iter().rev().take(limit).map(|record| record.data.clone()).collect())
+    }
+
+    async fn artifact_put(&self, workspace: &str, artifact: &Artifact) -> Result<()> {
+        self.artifacts
+            .write()
+            .await
+            .insert(artifact.id.clone(), (workspace.to_string(), artifact.clone()));
+        Ok(())
+    }
+
+    async fn artifacts_in(&self, workspace: &str) -> Result<Vec<Artifact>> {
+        Ok(self.artifacts_where(|ws, _| ws == workspace).await)
+    }
+
+    async fn artifacts_tagged(&self, tag: &str) -> Result<Vec<Artifact>> {
+        Ok(self.artifacts_where(|_, artifact| artifact.tags.iter().any(|t| t == tag)).await)
+    }
+
+    async fn artifact_remove(&self, id: &str) -> Result<()> {
+        self.artifacts.write().await.remove(id);
+        Ok(())
+    }
+}
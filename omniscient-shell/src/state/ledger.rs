@@ -1,28 +1,23 @@
 //! Event-sourced ledger
 
 use anyhow::Result;
-use rusqlite::params;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
-use crate::state::sqlite::SqliteStore;
+use crate::state::backend::{EventRecord, StateBackend};
 use crate::agents::event_protocol::Event;
 
 /// Event ledger
 pub struct EventLedger {
-    store: Arc<SqliteStore>,
+    backend: Arc<dyn StateBackend>,
 }
 
 impl EventLedger {
-    pub fn new(store: Arc<SqliteStore>) -> Self {
-        EventLedger { store }
+    pub fn new(backend: Arc<dyn StateBackend>) -> Self {
+        EventLedger { backend }
     }
 
     /// Append an event to the ledger
     pub async fn append(&self, event: &Event) -> Result<()> {
-        let conn = self.store.connection().await;
-        let conn = conn.lock().await;
-
         let timestamp = event.timestamp
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs();
@@ -30,10 +25,14 @@ impl EventLedger {
         let event_type = format!("{:?}", event.event_type);
         let data = serde_json::to_string(&event)?;
 
-        conn.execute(
-            "INSERT INTO event_log (timestamp, event_type, agent_id, data) VALUES (?1, ?2, ?3, ?4)",
-            params![timestamp as i64, event_type, event.agent_id, data],
-        )?;
+        self.backend
+            .event_append(EventRecord {
+                timestamp,
+                event_type: event_type.clone(),
+                agent_id: event.agent_id.clone(),
+                data,
+            })
+            .await?;
 
         tracing::debug!("Event appended to ledger: {} from {}", event_type, event.agent_id);
         Ok(())
@@ -41,66 +40,49 @@ impl EventLedger {
 
     /// Get all events for an agent
     pub async fn get_for_agent(&self, agent_id: &str) -> Result<Vec<Event>> {
-        let conn = self.store.connection().await;
-        let conn = conn.lock().await;
-
-        let mut stmt = conn.prepare(
-            "SELECT data FROM event_log WHERE agent_id = ?1 ORDER BY timestamp ASC"
-        )?;
-
-        let events: Result<Vec<Event>> = stmt
-            .query_map([agent_id], |row| {
-                let data: String = row.get(0)?;
-                Ok(data)
-            })?
-            .map(|result| {
-                let data = result?;
-                let event: Event = serde_json::from_str(&data)?;
-                Ok(event)
-            })
-            .collect();
-
-        events
+        decode(self.backend.events_for_agent(agent_id).await?)
     }
 
     /// Get recent events (last n)
     pub async fn get_recent(&self, limit: usize) -> Result<Vec<Event>> {
-        let conn = self.store.connection().await;
-        let conn = conn.lock().await;
-
-        let mut stmt = conn.prepare(
-            "SELECT data FROM event_log ORDER BY timestamp DESC LIMIT ?1"
-        )?;
-
-        let events: Result<Vec<Event>> = stmt
-            .query_map([limit], |row| {
-                let data: String = row.get(0)?;
-                Ok(data)
-            })?
-            .map(|result| {
-                let data = result?;
-                let event: Event = serde_json::from_str(&data)?;
-                Ok(event)
-            })
-            .collect();
-
-        events
+        decode(self.backend.events_recent(limit).await?)
     }
 }
 
+fn decode(rows: Vec<String>) -> Result<Vec<Event>> {
+    rows.iter()
+        .map(|data| Ok(serde_json::from_str(data)?))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::backend::MemoryBackend;
+    use crate::state::sqlite::SqliteStore;
 
-    #[tokio::test]
-    async fn test_event_ledger() {
-        let store = Arc::new(SqliteStore::in_memory().unwrap());
-        let ledger = EventLedger::new(store);
+    async fn check_event_ledger(backend: Arc<dyn StateBackend>) {
+        let ledger = EventLedger::new(backend);
 
         let event = Event::input("test-agent", "test input".to_string(), 1);
         ledger.append(&event).await.unwrap();
+        ledger.append(&Event::input("other-agent", "other".to_string(), 2)).await.unwrap();
 
         let events = ledger.get_for_agent("test-agent").await.unwrap();
         assert_eq!(events.len(), 1);
+
+        let recent = ledger.get_recent(1).await.unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].agent_id, "other-agent");
+    }
+
+    #[tokio::test]
+    async fn test_event_ledger() {
+        check_event_ledger(Arc::new(SqliteStore::in_memory().unwrap())).await;
+    }
+
+    #[tokio::test]
+    async fn test_event_ledger_in_memory_backend() {
+        check_event_ledger(Arc::new(MemoryBackend::new())).await;
     }
 }
@@ -4,7 +4,77 @@ use anyhow::Result;
 use rusqlite::Connection;
 
 /// Migration version
-const CURRENT_VERSION: i32 = 1;
+const CURRENT_VERSION: i32 = 6;
+
+/// Key-value, event log and artifact index tables (v1, artifact columns
+/// added in v2 and v4)
+const BASE_TABLES: &str = "CREATE TABLE IF NOT EXISTS kv_store (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS event_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    event_type TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS artifact_index (
+    id TEXT PRIMARY KEY,
+    workspace TEXT NOT NULL DEFAULT '',
+    kind TEXT NOT NULL,
+    path TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    bookmarked INTEGER NOT NULL DEFAULT 0,
+    tags TEXT NOT NULL DEFAULT ''
+);";
+
+/// Schema of the persistent telemetry table (v3)
+const TELEMETRY_EVENTS_TABLE: &str = "CREATE TABLE IF NOT EXISTS telemetry_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    event_type TEXT NOT NULL,
+    duration_ms INTEGER,
+    metadata TEXT NOT NULL,
+    success INTEGER NOT NULL
+)";
+
+/// Schema of the persistent consent ledger (v5), hash chain included. It is
+/// an audit log, so triggers refuse to change or delete rows once written.
+const CONSENT_LOG_TABLE: &str = "CREATE TABLE IF NOT EXISTS consent_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    agent_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    user_id TEXT,
+    request_id TEXT,
+    prev_hash TEXT NOT NULL DEFAULT '',
+    entry_hash TEXT NOT NULL DEFAULT ''
+);
+CREATE INDEX IF NOT EXISTS consent_log_agent ON consent_log (agent_id);
+CREATE TRIGGER IF NOT EXISTS consent_log_no_update BEFORE UPDATE ON consent_log
+BEGIN SELECT RAISE(ABORT, 'consent_log is append-only'); END;
+CREATE TRIGGER IF NOT EXISTS consent_log_no_delete BEFORE DELETE ON consent_log
+BEGIN SELECT RAISE(ABORT, 'consent_log is append-only'); END;";
+
+/// Schema of saved capability grants (v6). Times are milliseconds since the
+/// epoch; revoked and used-up grants keep their row so their history isn't
+/// lost. `delegated_by` is a JSON array of the delegating agents.
+const CAPABILITY_GRANTS_TABLE: &str = "CREATE TABLE IF NOT EXISTS capability_grants (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL DEFAULT '',
+    capability TEXT NOT NULL,
+    path_constraint TEXT,
+    granted_at INTEGER NOT NULL,
+    expires_at INTEGER,
+    revoked INTEGER NOT NULL DEFAULT 0,
+    max_uses INTEGER,
+    uses INTEGER NOT NULL DEFAULT 0,
+    delegated_by TEXT NOT NULL DEFAULT '[]'
+)";
 
 /// Run migrations
 pub fn migrate(conn: &mut Connection) -> Result<()> {
@@ -33,9 +103,24 @@ pub fn migrate(conn: &mut Connection) -> Result<()> {
         if version < 1 {
             migrate_to_v1(conn)?;
         }
+        if version < 2 {
+            migrate_to_v2(conn)?;
+        }
+        if version < 3 {
+            migrate_to_v3(conn)?;
+        }
+        if version < 4 {
+            migrate_to_v4(conn)?;
+        }
+        if version < 5 {
+            migrate_to_v5(conn)?;
+        }
+        if version < 6 {
+            migrate_to_v6(conn)?;
+        }
         // Add future migrations here:
-        // if version < 2 {
-        //     migrate_to_v2(conn)?;
+        // if version < 7 {
+        //     migrate_to_v7(conn)?;
         // }
     }
 
@@ -44,7 +129,9 @@ pub fn migrate(conn: &mut Connection) -> Result<()> {
 
 fn migrate_to_v1(conn: &mut Connection) -> Result<()> {
     tracing::info!("Migrating to schema version 1");
-    
+
+    conn.execute_batch(BASE_TABLES)?;
+
     // Record migration
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)?
@@ -58,6 +145,130 @@ fn migrate_to_v1(conn: &mut Connection) -> Result<()> {
     Ok(())
 }
 
+fn migrate_to_v2(conn: &mut Connection) -> Result<()> {
+    tracing::info!("Migrating to schema version 2");
+
+    // Key artifacts to their workspace (only needed for pre-existing indexes)
+    let has_index: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'artifact_index'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )? > 0;
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('artifact_index') WHERE name = 'workspace'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )? > 0;
+
+    if has_index && !has_column {
+        conn.execute(
+            "ALTER TABLE artifact_index ADD COLUMN workspace TEXT NOT NULL DEFAULT ''",
+            [],
+        )?;
+    }
+
+    // Record migration
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+
+    conn.execute(
+        "INSERT INTO schema_version (version, applied_at) VALUES (?1, ?2)",
+        [2, now as i32],
+    )?;
+
+    Ok(())
+}
+
+fn migrate_to_v3(conn: &mut Connection) -> Result<()> {
+    tracing::info!("Migrating to schema version 3");
+
+    conn.execute(TELEMETRY_EVENTS_TABLE, [])?;
+
+    // Record migration
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+
+    conn.execute(
+        "INSERT INTO schema_version (version, applied_at) VALUES (?1, ?2)",
+        [3, now as i32],
+    )?;
+
+    Ok(())
+}
+
+fn migrate_to_v4(conn: &mut Connection) -> Result<()> {
+    tracing::info!("Migrating to schema version 4");
+
+    // Artifact tags, stored as ",tag-a,tag-b," (only needed for pre-existing indexes)
+    let has_index: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'artifact_index'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )? > 0;
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('artifact_index') WHERE name = 'tags'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )? > 0;
+
+    if has_index && !has_column {
+        conn.execute(
+            "ALTER TABLE artifact_index ADD COLUMN tags TEXT NOT NULL DEFAULT ''",
+            [],
+        )?;
+    }
+
+    // Record migration
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+
+    conn.execute(
+        "INSERT INTO schema_version (version, applied_at) VALUES (?1, ?2)",
+        [4, now as i32],
+    )?;
+
+    Ok(())
+}
+
+fn migrate_to_v5(conn: &mut Connection) -> Result<()> {
+    tracing::info!("Migrating to schema version 5");
+
+    conn.execute_batch(CONSENT_LOG_TABLE)?;
+
+    // Record migration
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+
+    conn.execute(
+        "INSERT INTO schema_version (version, applied_at) VALUES (?1, ?2)",
+        [5, now as i32],
+    )?;
+
+    Ok(())
+}
+
+fn migrate_to_v6(conn: &mut Connection) -> Result<()> {
+    tracing::info!("Migrating to schema version 6");
+
+    conn.execute(CAPABILITY_GRANTS_TABLE, [])?;
+
+    // Record migration
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+
+    conn.execute(
+        "INSERT INTO schema_version (version, applied_at) VALUES (?1, ?2)",
+        [6, now as i32],
+    )?;
+
+    Ok(())
+}
+
 /// Check if database needs migration
 pub fn needs_migration(conn: &Connection) -> Result<bool> {
     let version: i32 = conn
@@ -122,6 +333,97 @@ mod tests {
         assert_eq!(current_version(&conn).unwrap(), CURRENT_VERSION);
     }
 
+    #[test]
+    fn test_v2_adds_artifact_workspace_column() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE artifact_index (id TEXT PRIMARY KEY, kind TEXT NOT NULL)",
+            [],
+        ).unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let columns: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('artifact_index') WHERE name = 'workspace'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(columns, 1);
+    }
+
+    #[test]
+    fn test_v3_creates_telemetry_table() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        let tables: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'telemetry_events'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(tables, 1);
+    }
+
+    #[test]
+    fn test_v4_adds_artifact_tags_column() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE artifact_index (id TEXT PRIMARY KEY, kind TEXT NOT NULL)",
+            [],
+        ).unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let columns: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('artifact_index') WHERE name = 'tags'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(columns, 1);
+    }
+
+    #[test]
+    fn test_v5_creates_append_only_consent_log() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO consent_log (timestamp, agent_id, action) VALUES (1, 'indexer', '{}')",
+            [],
+        ).unwrap();
+        assert!(conn.execute("UPDATE consent_log SET agent_id = 'other'", []).is_err());
+        assert!(conn.execute("DELETE FROM consent_log", []).is_err());
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM consent_log", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, 1);
+
+        let columns: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('consent_log') WHERE name IN ('prev_hash', 'entry_hash')",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(columns, 2);
+    }
+
+    #[test]
+    fn test_v6_creates_capability_grants_table() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        let tables: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'capability_grants'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(tables, 1);
+
+        let columns: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('capability_grants') WHERE name = 'agent_id'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(columns, 1);
+    }
+
     #[test]
     fn test_version_check() {
         let mut conn = Connection::open_in_memory().unwrap();
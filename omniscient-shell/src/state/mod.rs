@@ -1,10 +1,16 @@
 //! State management and persistence
 
+pub mod backend;
 pub mod sqlite;
 pub mod ledger;
 pub mod kv_store;
 pub mod migrations;
+pub mod artifact_index;
+pub mod arg_history;
 
+pub use backend::{MemoryBackend, StateBackend};
 pub use sqlite::SqliteStore;
 pub use ledger::EventLedger;
 pub use kv_store::KVStore;
+pub use artifact_index::ArtifactIndex;
+pub use arg_history::ArgumentHistory;
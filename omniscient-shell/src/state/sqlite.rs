@@ -1,99 +1,78 @@
 //! SQLite-backed state storage
 
 use anyhow::Result;
-use rusqlite::{Connection, params};
-use std::path::Path;
+use async_trait::async_trait;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
 use tokio::sync::Mutex;
 
+use crate::state::backend::{EventRecord, StateBackend};
+use crate::state::migrations;
+use crate::utils::config::StateConfig;
+use crate::utils::errors::{OmniError, RecoveryAction};
+use crate::workspace::artifacts::{Artifact, ArtifactKind};
+
+/// How long to wait for another instance to release the database
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default state database (`~/.omniscient/state.db`)
+pub fn default_state_db_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".omniscient").join("state.db")
+}
+
+/// The configured state database, or the default
+pub fn state_db_path(config: &StateConfig) -> PathBuf {
+    config
+        .db_path
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(default_state_db_path)
+}
+
 /// SQLite state store
 pub struct SqliteStore {
     conn: Arc<Mutex<Connection>>,
+    /// Database file, named when another instance holds it
+    path: PathBuf,
 }
 
 impl SqliteStore {
-    /// Create a new store at the given path
+    /// Create a new store at the given path, migrating its schema to the
+    /// current version
     pub fn new(path: &Path) -> Result<Self> {
-        let conn = Connection::open(path)?;
-        
-        // Create tables
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS kv_store (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS event_log (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                timestamp INTEGER NOT NULL,
-                event_type TEXT NOT NULL,
-                agent_id TEXT NOT NULL,
-                data TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS artifact_index (
-                id TEXT PRIMARY KEY,
-                kind TEXT NOT NULL,
-                path TEXT NOT NULL,
-                created_at INTEGER NOT NULL,
-                size_bytes INTEGER NOT NULL,
-                bookmarked INTEGER NOT NULL DEFAULT 0
-            )",
-            [],
-        )?;
+        Self::with_busy_timeout(path, BUSY_TIMEOUT)
+    }
+
+    /// Create a store at `path`, which other instances may open too. A
+    /// statement waits up to `timeout` for another instance's write to
+    /// finish, then fails with an `OmniError::Storage` saying the database
+    /// is locked.
+    pub fn with_busy_timeout(path: &Path, timeout: Duration) -> Result<Self> {
+        let mut conn = Connection::open(path)?;
+        conn.busy_timeout(timeout)?;
+
+        migrations::migrate(&mut conn).map_err(|e| match e.downcast::<rusqlite::Error>() {
+            Ok(e) => locked_error(path, e),
+            Err(e) => e,
+        })?;
 
         Ok(SqliteStore {
             conn: Arc::new(Mutex::new(conn)),
+            path: path.to_path_buf(),
         })
     }
 
     /// Create an in-memory store
     pub fn in_memory() -> Result<Self> {
-        let conn = Connection::open_in_memory()?;
-        
-        conn.execute(
-            "CREATE TABLE kv_store (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE event_log (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                timestamp INTEGER NOT NULL,
-                event_type TEXT NOT NULL,
-                agent_id TEXT NOT NULL,
-                data TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE artifact_index (
-                id TEXT PRIMARY KEY,
-                kind TEXT NOT NULL,
-                path TEXT NOT NULL,
-                created_at INTEGER NOT NULL,
-                size_bytes INTEGER NOT NULL,
-                bookmarked INTEGER NOT NULL DEFAULT 0
-            )",
-            [],
-        )?;
+        let mut conn = Connection::open_in_memory()?;
+        migrations::migrate(&mut conn)?;
 
         Ok(SqliteStore {
             conn: Arc::new(Mutex::new(conn)),
+            path: PathBuf::from(":memory:"),
         })
     }
 
@@ -101,6 +80,194 @@ impl SqliteStore {
     pub async fn connection(&self) -> Arc<Mutex<Connection>> {
         self.conn.clone()
     }
+
+    /// Shared connection, for stores built outside async code that keep
+    /// their own tables in this database
+    pub fn shared_connection(&self) -> Arc<Mutex<Connection>> {
+        self.conn.clone()
+    }
+
+    /// Run `f` on the connection; a database another instance kept locked
+    /// past the busy timeout is reported as such
+    async fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T> {
+        let conn = self.conn.lock().await;
+        f(&conn).map_err(|e| locked_error(&self.path, e))
+    }
+}
+
+#[async_trait]
+impl StateBackend for SqliteStore {
+    async fn kv_set(&self, key: &str, value: &str) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .as_secs();
+
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO kv_store (key, value, created_at, updated_at) 
+                 VALUES (?1, ?2, COALESCE((SELECT created_at FROM kv_store WHERE key = ?1), ?3), ?3)",
+                params![key, value, now as i64],
+            )
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    async fn kv_get(&self, key: &str) -> Result<Option<String>> {
+        self.with_conn(|conn| {
+            conn.query_row("SELECT value FROM kv_store WHERE key = ?1", [key], |row| row.get(0))
+                .optional()
+        })
+        .await
+    }
+
+    async fn kv_delete(&self, key: &str) -> Result<()> {
+        self.with_conn(|conn| conn.execute("DELETE FROM kv_store WHERE key = ?1", params![key]))
+            .await?;
+        Ok(())
+    }
+
+    async fn kv_keys(&self) -> Result<Vec<String>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare("SELECT key FROM kv_store ORDER BY key")?;
+            let keys = stmt.query_map([], |row| row.get(0))?.collect();
+            keys
+        })
+        .await
+    }
+
+    async fn event_append(&self, record: EventRecord) -> Result<()> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO event_log (timestamp, event_type, agent_id, data) VALUES (?1, ?2, ?3, ?4)",
+                params![record.timestamp as i64, record.event_type, record.agent_id, record.data],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn events_for_agent(&self, agent_id: &str) -> Result<Vec<String>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT data FROM event_log WHERE agent_id = ?1 ORDER BY timestamp ASC, id ASC"
+            )?;
+            let events = stmt.query_map([agent_id], |row| row.get(0))?.collect();
+            events
+        })
+        .await
+    }
+
+    async fn events_recent(&self, limit: usize) -> Result<Vec<String>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT data FROM event_log ORDER BY timestamp DESC, id DESC LIMIT ?1"
+            )?;
+            let events = stmt.query_map([limit], |row| row.get(0))?.collect();
+            events
+        })
+        .await
+    }
+
+    async fn artifact_put(&self, workspace: &str, artifact: &Artifact) -> Result<()> {
+        let created_at = artifact.created_at
+            .duration_since(UNIX_EPOCH)?
+            .as_secs();
+
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO artifact_index (id, workspace, kind, path, created_at, size_bytes, bookmarked, tags)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    artifact.id,
+                    workspace,
+                    artifact.kind.as_str(),
+                    artifact.path.to_string_lossy(),
+                    created_at as i64,
+                    artifact.size_bytes as i64,
+                    artifact.bookmarked,
+                    encode_tags(&artifact.tags),
+                ],
+            )
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    async fn artifacts_in(&self, workspace: &str) -> Result<Vec<Artifact>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, kind, path, created_at, size_bytes, bookmarked, tags
+                 FROM artifact_index WHERE workspace = ?1 ORDER BY created_at ASC"
+            )?;
+            let artifacts = stmt.query_map([workspace], artifact_from_row)?.collect();
+            artifacts
+        })
+        .await
+    }
+
+    async fn artifacts_tagged(&self, tag: &str) -> Result<Vec<Artifact>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, kind, path, created_at, size_bytes, bookmarked, tags
+                 FROM artifact_index WHERE instr(tags, ',' || ?1 || ',') > 0 ORDER BY created_at ASC"
+            )?;
+            let artifacts = stmt.query_map([tag], artifact_from_row)?.collect();
+            artifacts
+        })
+        .await
+    }
+
+    async fn artifact_remove(&self, id: &str) -> Result<()> {
+        self.with_conn(|conn| conn.execute("DELETE FROM artifact_index WHERE id = ?1", params![id]))
+            .await?;
+        Ok(())
+    }
+}
+
+fn artifact_from_row(row: &rusqlite::Row) -> rusqlite::Result<Artifact> {
+    let kind: String = row.get(1)?;
+    let path: String = row.get(2)?;
+    let created_at: i64 = row.get(3)?;
+    let size_bytes: i64 = row.get(4)?;
+    let tags: String = row.get(6)?;
+    Ok(Artifact {
+        id: row.get(0)?,
+        kind: ArtifactKind::from(kind),
+        path: PathBuf::from(path),
+        created_at: UNIX_EPOCH + Duration::from_secs(created_at as u64),
+        size_bytes: size_bytes as u64,
+        bookmarked: row.get(5)?,
+        tags: tags.split(',').filter(|t| !t.is_empty()).map(String::from).collect(),
+    })
+}
+
+/// Tags column format: ",tag-a,tag-b," so a tag can be matched with its delimiters
+fn encode_tags(tags: &[String]) -> String {
+    if tags.is_empty() {
+        String::new()
+    } else {
+        format!(",{},", tags.join(","))
+    }
+}
+
+/// Explain a database held by another instance; other errors pass through
+fn locked_error(path: &Path, err: rusqlite::Error) -> anyhow::Error {
+    let busy = matches!(
+        err.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    );
+    if !busy {
+        return err.into();
+    }
+    OmniError::storage(
+        format!("State database {} is locked by another instance", path.display()),
+        Some("Another omniscient-shell may be running against the same database".to_string()),
+        RecoveryAction::PromptUser("Close the other instance and start this one again".to_string()),
+    )
+    .into()
 }
 
 #[cfg(test)]
@@ -111,6 +278,65 @@ mod tests {
     async fn test_in_memory_store() {
         let store = SqliteStore::in_memory().unwrap();
         // Basic creation test
-        assert!(store.conn.lock().await.is_ok());
+        assert!(store.conn.try_lock().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_is_migrated() {
+        let store = SqliteStore::in_memory().unwrap();
+        assert!(!migrations::needs_migration(&*store.conn.lock().await).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_opening_migrates_existing_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+        {
+            // Created before the store ran migrations, and before artifacts had tags
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE artifact_index (id TEXT PRIMARY KEY, workspace TEXT NOT NULL DEFAULT '',
+                     kind TEXT NOT NULL, path TEXT NOT NULL, created_at INTEGER NOT NULL,
+                     size_bytes INTEGER NOT NULL, bookmarked INTEGER NOT NULL DEFAULT 0);",
+            )
+            .unwrap();
+        }
+
+        let store = SqliteStore::new(&path).unwrap();
+        assert!(!migrations::needs_migration(&*store.conn.lock().await).unwrap());
+        let artifact = Artifact {
+            id: "a1".to_string(),
+            kind: ArtifactKind::Diff,
+            path: PathBuf::from("/tmp/a1.diff"),
+            created_at: UNIX_EPOCH,
+            size_bytes: 1,
+            bookmarked: false,
+            tags: vec!["review".to_string()],
+        };
+        store.artifact_put("ws", &artifact).await.unwrap();
+        assert_eq!(store.artifacts_tagged("review").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_held_by_another_instance_gets_locked_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+        let first = SqliteStore::new(&path).unwrap();
+
+        // Opening doesn't hold the database: a second instance can use it too
+        let second = SqliteStore::with_busy_timeout(&path, Duration::from_millis(50)).unwrap();
+        first.kv_set("theme", "dark").await.unwrap();
+        assert_eq!(second.kv_get("theme").await.unwrap().as_deref(), Some("dark"));
+
+        // A write waits out the other instance's transaction, then says why it failed
+        first.conn.lock().await.execute_batch("BEGIN IMMEDIATE").unwrap();
+        let err = second.kv_set("theme", "light").await.expect_err("database should be locked by the first store");
+        let err = err.downcast_ref::<OmniError>().expect("expected an OmniError");
+        assert!(err.to_string().contains("locked by another instance"));
+        assert!(err.hint().is_some());
+        assert!(matches!(err.recovery_action(), RecoveryAction::PromptUser(_)));
+
+        first.conn.lock().await.execute_batch("COMMIT").unwrap();
+        second.kv_set("theme", "light").await.unwrap();
     }
 }
@@ -0,0 +1,241 @@
+//! Capability review screen: lists active grants and lets the user revoke them
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+use std::time::SystemTime;
+
+use crate::agents::capabilities::GrantSummary;
+use crate::tui::theme::Theme;
+
+/// One active grant as shown on the review screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrantRow {
+    pub agent_id: String,
+    /// Capability string, e.g. `files.read`
+    pub capability: String,
+    pub expires_at: Option<SystemTime>,
+    /// Human-readable constraint, e.g. `under /project`
+    pub constraint: Option<String>,
+}
+
+impl From<GrantSummary> for GrantRow {
+    fn from(grant: GrantSummary) -> Self {
+        GrantRow {
+            agent_id: grant.agent_id,
+            capability: grant.capability,
+            expires_at: grant.expires_at,
+            constraint: grant.constraint,
+        }
+    }
+}
+
+impl GrantRow {
+    fn label(&self, now: SystemTime) -> String {
+        let expiry = match self.expires_at {
+            None => "no expiry".to_string(),
+            Some(at) => match at.duration_since(now) {
+                Ok(left) => format!("expires in {}m", left.as_secs().div_ceil(60)),
+                Err(_) => "expired".to_string(),
+            },
+        };
+        match &self.constraint {
+            Some(constraint) => {
+                format!("{}  {} {}  ({})", self.agent_id, self.capability, constraint, expiry)
+            }
+            None => format!("{}  {}  ({})", self.agent_id, self.capability, expiry),
+        }
+    }
+}
+
+/// Grant the user chose to revoke
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevokeRequest {
+    pub agent_id: String,
+    pub capability: String,
+    pub constraint: Option<String>,
+}
+
+/// What the dashboard should do after a key on the review screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReviewAction {
+    None,
+    Close,
+    Revoke(RevokeRequest),
+}
+
+/// State of the review screen
+pub struct CapabilityReview {
+    rows: Vec<GrantRow>,
+    selected: usize,
+    confirming: bool,
+}
+
+impl CapabilityReview {
+    pub fn new(rows: Vec<GrantRow>) -> Self {
+        CapabilityReview {
+            rows,
+            selected: 0,
+            confirming: false,
+        }
+    }
+
+    pub fn rows(&self) -> &[GrantRow] {
+        &self.rows
+    }
+
+    pub fn selected(&self) -> Option<&GrantRow> {
+        self.rows.get(self.selected)
+    }
+
+    /// Whether the screen is waiting for the user to confirm a revoke
+    pub fn is_confirming(&self) -> bool {
+        self.confirming
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.rows.is_empty() {
+            self.selected = (self.selected + 1) % self.rows.len();
+        }
+        self.confirming = false;
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.rows.is_empty() {
+            self.selected = (self.selected + self.rows.len() - 1) % self.rows.len();
+        }
+        self.confirming = false;
+    }
+
+    /// Ask for confirmation before revoking the selected grant
+    pub fn request_revoke(&mut self) {
+        self.confirming = self.selected().is_some();
+    }
+
+    /// Confirm the pending revoke, removing the row from the list
+    pub fn confirm(&mut self) -> Option<RevokeRequest> {
+        if !self.confirming {
+            return None;
+        }
+        self.confirming = false;
+        let row = self.rows.remove(self.selected);
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+        Some(RevokeRequest {
+            agent_id: row.agent_id,
+            capability: row.capability,
+            constraint: row.constraint,
+        })
+    }
+
+    pub fn cancel(&mut self) {
+        self.confirming = false;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> ReviewAction {
+        if self.confirming {
+            return match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => {
+                    self.confirm().map_or(ReviewAction::None, ReviewAction::Revoke)
+                }
+                _ => {
+                    self.cancel();
+                    ReviewAction::None
+                }
+            };
+        }
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => self.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.select_previous(),
+            KeyCode::Enter | KeyCode::Char('r') | KeyCode::Delete => self.request_revoke(),
+            KeyCode::Esc | KeyCode::Char('q') => return ReviewAction::Close,
+            _ => {}
+        }
+        ReviewAction::None
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let now = SystemTime::now();
+        let items: Vec<ListItem> = if self.rows.is_empty() {
+            vec![ListItem::new("No active grants")]
+        } else {
+            self.rows.iter().map(|row| ListItem::new(row.label(now))).collect()
+        };
+
+        let title = if self.confirming {
+            "Capabilities - revoke selected grant? [y] Yes  [n] No"
+        } else {
+            "Capabilities - [Enter] Revoke  [Esc] Close"
+        };
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .style(Style::default().fg(theme.foreground))
+            .highlight_style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        let mut state = ListState::default();
+        if !self.rows.is_empty() {
+            state.select(Some(self.selected));
+        }
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn row(agent_id: &str, capability: &str) -> GrantRow {
+        GrantRow {
+            agent_id: agent_id.to_string(),
+            capability: capability.to_string(),
+            expires_at: None,
+            constraint: None,
+        }
+    }
+
+    fn press(review: &mut CapabilityReview, code: KeyCode) -> ReviewAction {
+        review.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn test_confirmed_selection_revokes_that_grant() {
+        let mut review = CapabilityReview::new(vec![
+            row("indexer", "files.read"),
+            row("indexer", "network.http"),
+            row("mailer", "oauth.gmail"),
+        ]);
+
+        assert_eq!(press(&mut review, KeyCode::Down), ReviewAction::None);
+        assert_eq!(press(&mut review, KeyCode::Enter), ReviewAction::None);
+        assert!(review.is_confirming());
+
+        let action = press(&mut review, KeyCode::Char('y'));
+        assert_eq!(
+            action,
+            ReviewAction::Revoke(RevokeRequest {
+                agent_id: "indexer".to_string(),
+                capability: "network.http".to_string(),
+                constraint: None,
+            })
+        );
+        assert_eq!(review.rows().len(), 2);
+        assert_eq!(review.selected().unwrap().capability, "oauth.gmail");
+    }
+
+    #[test]
+    fn test_declined_confirmation_keeps_grant() {
+        let mut review = CapabilityReview::new(vec![row("indexer", "files.read")]);
+
+        press(&mut review, KeyCode::Enter);
+        assert_eq!(press(&mut review, KeyCode::Char('n')), ReviewAction::None);
+        assert!(!review.is_confirming());
+        assert_eq!(review.rows().len(), 1);
+        assert_eq!(press(&mut review, KeyCode::Esc), ReviewAction::Close);
+    }
+}
@@ -1,5 +1,7 @@
 //! Card widgets for displaying content
 
+use crate::tui::device_code::DeviceCodePrompt;
+
 pub struct Card {
     pub title: String,
     pub content: String,
@@ -12,4 +14,32 @@ impl Card {
             content: content.into(),
         }
     }
+
+    /// Resource usage card for a running agent
+    pub fn resource_usage(agent_id: &str, cpu_percent: f32, memory_bytes: u64) -> Self {
+        Card {
+            title: format!("{} resources", agent_id),
+            content: format!(
+                "CPU: {:.1}%\nMemory: {:.1} MiB",
+                cpu_percent,
+                memory_bytes as f64 / (1024.0 * 1024.0)
+            ),
+        }
+    }
+
+    /// Device-code sign-in card, with a QR code if it fits in `cols` x `rows`
+    pub fn device_code(prompt: &DeviceCodePrompt, cols: u16, rows: u16) -> Self {
+        Card {
+            title: format!("Sign in to {}", prompt.provider),
+            content: prompt.text(cols, rows),
+        }
+    }
+
+    /// Consent prompt card asking the user to grant a capability
+    pub fn consent_request(agent_id: &str, capability: &str, summary: &str, risk: &str) -> Self {
+        Card {
+            title: format!("{} requests {}", agent_id, capability),
+            content: format!("{}\nRisk: {}\n[y] Allow  [n] Deny", summary, risk),
+        }
+    }
 }
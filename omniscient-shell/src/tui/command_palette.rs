@@ -2,6 +2,11 @@
 
 use anyhow::Result;
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Closure behind a `CommandHandler::Custom` command; receives the words typed
+/// after the command name
+pub type CustomHandler = Arc<dyn Fn(&[&str]) -> Result<()> + Send + Sync>;
 
 /// Command definition
 #[derive(Debug, Clone)]
@@ -13,34 +18,47 @@ pub struct Command {
 }
 
 /// Command handler type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CommandHandler {
     WorkspaceSelect,
     WorkspaceClear,
     AgentList,
     AgentEnable,
     AgentDisable,
+    AgentLint,
+    CapabilityReview,
     ConfigReload,
     ConfigEdit,
+    ProfileSwitch,
     OAuthConnect,
     OAuthRevoke,
     VaultLock,
     VaultUnlock,
+    VaultRotate,
     ThemeSwitch,
     LayoutSwitch,
+    PreviewOpen,
+    LogOpen,
+    LogTail,
+    LogLevel,
     Help,
+    Doctor,
     Quit,
+    /// Command added at runtime by an agent or plugin, routed by id
+    Custom(String),
 }
 
 /// Command palette for quick actions
 pub struct CommandPalette {
     commands: HashMap<String, Command>,
+    custom_handlers: HashMap<String, CustomHandler>,
 }
 
 impl CommandPalette {
     pub fn new() -> Self {
         let mut palette = CommandPalette {
             commands: HashMap::new(),
+            custom_handlers: HashMap::new(),
         };
 
         palette.register_default_commands();
@@ -52,7 +70,7 @@ impl CommandPalette {
         self.register(Command {
             name: "workspace:select".to_string(),
             description: "Select a workspace directory".to_string(),
-            aliases: vec!["ws:select".to_string(), "ws".to_string()],
+            aliases: vec!["ws:select".to_string(), "ws".to_string(), "omni:workspace".to_string()],
             handler: CommandHandler::WorkspaceSelect,
         });
 
@@ -85,6 +103,20 @@ impl CommandPalette {
             handler: CommandHandler::AgentDisable,
         });
 
+        self.register(Command {
+            name: "agent:lint".to_string(),
+            description: "Check an agent manifest for likely mistakes".to_string(),
+            aliases: vec!["lint".to_string()],
+            handler: CommandHandler::AgentLint,
+        });
+
+        self.register(Command {
+            name: "capability:review".to_string(),
+            description: "Review and revoke active capability grants".to_string(),
+            aliases: vec!["grants".to_string(), "consent".to_string()],
+            handler: CommandHandler::CapabilityReview,
+        });
+
         // Config commands
         self.register(Command {
             name: "config:reload".to_string(),
@@ -100,6 +132,13 @@ impl CommandPalette {
             handler: CommandHandler::ConfigEdit,
         });
 
+        self.register(Command {
+            name: "profile:switch".to_string(),
+            description: "Switch to the next config profile".to_string(),
+            aliases: vec!["profile".to_string()],
+            handler: CommandHandler::ProfileSwitch,
+        });
+
         // OAuth commands
         self.register(Command {
             name: "oauth:connect".to_string(),
@@ -130,6 +169,13 @@ impl CommandPalette {
             handler: CommandHandler::VaultUnlock,
         });
 
+        self.register(Command {
+            name: "vault:rotate".to_string(),
+            description: "Change the vault passphrase and re-encrypt all tokens".to_string(),
+            aliases: vec![],
+            handler: CommandHandler::VaultRotate,
+        });
+
         // UI commands
         self.register(Command {
             name: "theme:switch".to_string(),
@@ -145,6 +191,35 @@ impl CommandPalette {
             handler: CommandHandler::LayoutSwitch,
         });
 
+        self.register(Command {
+            name: "preview:open".to_string(),
+            description: "Show a file in the preview pane, refreshed when it changes".to_string(),
+            aliases: vec!["preview".to_string(), "omni:preview".to_string()],
+            handler: CommandHandler::PreviewOpen,
+        });
+
+        // Log commands
+        self.register(Command {
+            name: "log:open".to_string(),
+            description: "Show the log file in the log pane and follow it".to_string(),
+            aliases: vec!["log".to_string()],
+            handler: CommandHandler::LogOpen,
+        });
+
+        self.register(Command {
+            name: "log:tail".to_string(),
+            description: "Follow new log lines in the log pane".to_string(),
+            aliases: vec![],
+            handler: CommandHandler::LogTail,
+        });
+
+        self.register(Command {
+            name: "log:level".to_string(),
+            description: "Cycle the minimum level shown in the log pane".to_string(),
+            aliases: vec![],
+            handler: CommandHandler::LogLevel,
+        });
+
         // System commands
         self.register(Command {
             name: "help".to_string(),
@@ -153,6 +228,13 @@ impl CommandPalette {
             handler: CommandHandler::Help,
         });
 
+        self.register(Command {
+            name: "omni:doctor".to_string(),
+            description: "Check PowerShell, graphics, vault, config and database".to_string(),
+            aliases: vec!["doctor".to_string()],
+            handler: CommandHandler::Doctor,
+        });
+
         self.register(Command {
             name: "quit".to_string(),
             description: "Quit the application".to_string(),
@@ -170,6 +252,39 @@ impl CommandPalette {
         }
     }
 
+    /// Register the closure run for `CommandHandler::Custom(id)`
+    pub fn register_handler<F>(&mut self, id: impl Into<String>, handler: F)
+    where
+        F: Fn(&[&str]) -> Result<()> + Send + Sync + 'static,
+    {
+        self.custom_handlers.insert(id.into(), Arc::new(handler));
+    }
+
+    /// Run a typed command line such as `deploy staging --dry-run`.
+    ///
+    /// Custom commands are run here and yield `None`; built-in handlers are
+    /// returned for the dashboard to act on.
+    pub fn dispatch(&self, input: &str) -> Result<Option<CommandHandler>> {
+        let mut words = input.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let command = self
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown command: {}", name))?;
+
+        match &command.handler {
+            CommandHandler::Custom(id) => {
+                let handler = self
+                    .custom_handlers
+                    .get(id)
+                    .ok_or_else(|| anyhow::anyhow!("No handler registered for '{}'", id))?;
+                let args: Vec<&str> = words.collect();
+                handler(&args)?;
+                Ok(None)
+            }
+            builtin => Ok(Some(builtin.clone())),
+        }
+    }
+
     /// Search for commands matching a query
     pub fn search(&self, query: &str) -> Vec<&Command> {
         let query_lower = query.to_lowercase();
@@ -204,6 +319,44 @@ impl CommandPalette {
     }
 }
 
+/// Up/Down browsing of a command parameter's previous arguments, newest first
+#[derive(Debug, Clone, Default)]
+pub struct HistoryCursor {
+    /// Oldest first
+    entries: Vec<String>,
+    /// Entry shown in the prompt; None while editing a fresh argument
+    position: Option<usize>,
+}
+
+impl HistoryCursor {
+    pub fn new(entries: Vec<String>) -> Self {
+        HistoryCursor { entries, position: None }
+    }
+
+    /// Step to an older argument (Up), staying on the oldest
+    pub fn up(&mut self) -> Option<&str> {
+        let position = match self.position {
+            Some(i) => i.saturating_sub(1),
+            None => self.entries.len().checked_sub(1)?,
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Step to a newer argument (Down); None once past the newest, when the
+    /// prompt goes back to what was being typed
+    pub fn down(&mut self) -> Option<&str> {
+        let next = self.position? + 1;
+        if next < self.entries.len() {
+            self.position = Some(next);
+            Some(&self.entries[next])
+        } else {
+            self.position = None;
+            None
+        }
+    }
+}
+
 impl Default for CommandPalette {
     fn default() -> Self {
         Self::new()
@@ -243,4 +396,56 @@ mod tests {
         let results = palette.search("vault");
         assert!(!results.is_empty());
     }
+
+    #[test]
+    fn test_custom_handler_receives_arguments() {
+        use std::sync::Mutex;
+
+        let mut palette = CommandPalette::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        palette.register(Command {
+            name: "deploy:run".to_string(),
+            description: "Deploy the current workspace".to_string(),
+            aliases: vec!["deploy".to_string()],
+            handler: CommandHandler::Custom("deployer.run".to_string()),
+        });
+        palette.register_handler("deployer.run", move |args| {
+            sink.lock().unwrap().extend(args.iter().map(|a| a.to_string()));
+            Ok(())
+        });
+
+        assert_eq!(palette.dispatch("deploy staging --dry-run").unwrap(), None);
+        assert_eq!(*seen.lock().unwrap(), vec!["staging", "--dry-run"]);
+
+        assert_eq!(palette.dispatch("quit").unwrap(), Some(CommandHandler::Quit));
+        assert!(palette.dispatch("nope").is_err());
+    }
+
+    #[test]
+    fn test_custom_command_without_handler_fails() {
+        let mut palette = CommandPalette::new();
+        palette.register(Command {
+            name: "orphan".to_string(),
+            description: "No handler behind this".to_string(),
+            aliases: vec![],
+            handler: CommandHandler::Custom("missing".to_string()),
+        });
+
+        let err = palette.dispatch("orphan").unwrap_err();
+        assert!(err.to_string().contains("missing"));
+    }
+
+    #[test]
+    fn test_history_cursor_walks_newest_first() {
+        let mut cursor = HistoryCursor::new(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(cursor.down(), None);
+        assert_eq!(cursor.up(), Some("b"));
+        assert_eq!(cursor.up(), Some("a"));
+        assert_eq!(cursor.up(), Some("a"));
+        assert_eq!(cursor.down(), Some("b"));
+        assert_eq!(cursor.down(), None);
+
+        assert_eq!(HistoryCursor::default().up(), None);
+    }
 }
@@ -2,29 +2,165 @@
 
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent},
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
+    terminal::{self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
+    layout::{Margin, Rect},
     style::{Color, Style},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph},
     Terminal,
 };
+use std::collections::VecDeque;
+use std::future::Future;
 use std::io::stdout;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
 
-use crate::utils::config::Config;
+use crate::utils::config::{self, default_config_path, load_config_from, Config, ConfigWatcher, ThemeConfig};
 use crate::graphics::GraphicsBackend;
+use crate::shell::command_router::{CommandRouter, RouteTarget};
 use crate::shell::PowerShellIntegration;
+use crate::tui::capability_review::{CapabilityReview, GrantRow, ReviewAction, RevokeRequest};
+use crate::tui::command_palette::{CommandHandler, CommandPalette};
+use crate::tui::layout::{LayoutManager, SplitId};
+use crate::tui::log_tail::{self, LogLevel, LogTailer};
+use crate::tui::resize::ResizeWatcher;
+use crate::tui::shell_pane::{ShellAction, ShellPane};
 use crate::tui::theme::Theme;
+use crate::tui::vault_rotate::{RotateAction, RotateRequest, VaultRotateDialog};
+use crate::diagnostics::{self, CheckResult};
+use crate::media::{LivePreview, MediaCache, PreviewAdapter};
+use crate::utils::errors::{OmniError, RecoveryAction};
+use crate::utils::idle::{IdleAction, IdleMonitor};
+use crate::utils::profiles::Profiles;
+use crate::workspace::{ArtifactStorage, Workspace, WorkspaceWatcher};
+
+/// Called with the configured actions when the session goes idle
+pub type IdleHandler = Arc<dyn Fn(&[IdleAction]) + Send + Sync>;
+
+/// Lists the active grants shown on the capability review screen
+pub type GrantSource = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Vec<GrantRow>> + Send>> + Send + Sync>;
+
+/// Revokes a grant the user confirmed on the capability review screen
+pub type RevokeHandler = Arc<dyn Fn(&RevokeRequest) + Send + Sync>;
+
+/// Starts a vault rotation confirmed in the `vault:rotate` dialog; reports
+/// progress through `RotateRequest::progress`
+pub type VaultRotateHandler = Arc<dyn Fn(RotateRequest) + Send + Sync>;
+
+/// How often the preview task checks the previewed file for changes
+const PREVIEW_POLL: Duration = Duration::from_millis(250);
+
+/// Runs once when the dashboard exits, e.g. to stop running agents
+pub type ShutdownHook = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+/// Index, name and rect of each pane to draw: all of them, or only the
+/// focused one filling `area` while zoomed
+fn visible_panes(layout: &LayoutManager, area: Rect, focused: usize, zoomed: bool) -> Vec<(usize, &str, Rect)> {
+    let panes = layout.panes(area);
+    if zoomed {
+        if let Some((name, _)) = panes.get(focused) {
+            return vec![(focused, *name, area)];
+        }
+    }
+    panes.into_iter().enumerate().map(|(index, (name, rect))| (index, name, rect)).collect()
+}
+
+/// Title and placeholder text for a pane
+fn pane_text(name: &str) -> (&'static str, &'static str) {
+    match name {
+        "shell" => ("Shell", "Type a PowerShell command and press Enter"),
+        "agent" => ("Agent Console", "AI agent outputs will stream here..."),
+        "preview" => ("Preview", "Media and file previews..."),
+        _ => ("Log", "System logs and errors..."),
+    }
+}
 
 pub struct Dashboard {
     config: Config,
+    /// File the layout is saved to when auto_save is on
+    config_path: PathBuf,
+    /// Reloads the config when its file changes on disk
+    config_watcher: Option<ConfigWatcher>,
+    /// Set by the config watcher, handled on the UI loop
+    config_changed: Arc<AtomicBool>,
+    /// Config file contents as last saved by the dashboard, so its own
+    /// layout saves don't trigger a reload
+    saved_config: Option<String>,
+    /// Profiles cycled through by `profile:switch`
+    profiles: Profiles,
+    /// Workspace chosen with `omni:workspace select <path>`
+    workspace: Arc<Workspace>,
+    /// Effective config after a workspace change, applied on the UI loop
+    workspace_config: Arc<std::sync::Mutex<Option<Config>>>,
     theme: Theme,
     graphics: Box<dyn GraphicsBackend>,
-    shell: PowerShellIntegration,
+    shell: Arc<PowerShellIntegration>,
+    /// Sends where commands typed in the shell pane go
+    router: CommandRouter,
+    /// Runs `omni:` commands typed in the shell pane
+    palette: CommandPalette,
+    shell_pane: ShellPane,
+    /// Output lines of PowerShell commands, sent as the commands write them
+    shell_output: mpsc::UnboundedSender<String>,
+    shell_output_rx: mpsc::UnboundedReceiver<String>,
+    /// PowerShell commands started from the shell pane, cancelled by Ctrl+C
+    running: Vec<(CancellationToken, JoinHandle<()>)>,
+    layout: LayoutManager,
+    /// Area of the last drawn frame, used to map mouse positions to splits
+    area: Rect,
+    /// Terminal size (cols, rows) last passed to the graphics backend
+    term_size: (u16, u16),
+    resize_watcher: Option<ResizeWatcher>,
+    /// Index of the focused pane
+    focused: usize,
+    /// Show only the focused pane, full screen
+    zoomed: bool,
+    /// Split whose border is being dragged with the mouse
+    dragging: Option<SplitId>,
+    idle: Option<IdleMonitor>,
+    idle_actions: Vec<IdleAction>,
+    idle_handler: Option<IdleHandler>,
+    /// Set by the idle monitor, handled on the UI loop
+    idle_fired: Arc<AtomicBool>,
+    dimmed: bool,
+    /// Open capability review screen, drawn over the panes
+    review: Option<CapabilityReview>,
+    grant_source: Option<GrantSource>,
+    /// Grants still being listed for the review screen
+    review_pending: Option<oneshot::Receiver<Vec<GrantRow>>>,
+    revoke_handler: Option<RevokeHandler>,
+    /// Open `vault:rotate` dialog, drawn over the panes
+    vault_rotate: Option<VaultRotateDialog>,
+    vault_rotate_handler: Option<VaultRotateHandler>,
+    /// Files to show in the preview pane, sent to the task running the `LivePreview`
+    preview_paths: Option<mpsc::UnboundedSender<PathBuf>>,
+    /// Preview pane text, set by the preview task
+    preview_text: Arc<std::sync::Mutex<Option<String>>>,
+    /// `omni:doctor` results, drawn over the panes until dismissed
+    doctor: Option<Vec<CheckResult>>,
+    /// Results of checks still running on a blocking thread
+    doctor_pending: Option<oneshot::Receiver<Vec<CheckResult>>>,
+    shutdown_hook: Option<ShutdownHook>,
+    /// Log file shown by `log:open` and `log:tail`
+    log_path: PathBuf,
+    log_level: LogLevel,
+    /// Follows the log file once a log command has run
+    log_tail: Option<LogTailer>,
+    log_lines: VecDeque<String>,
     should_quit: bool,
 }
 
@@ -35,20 +171,156 @@ impl Dashboard {
         shell: PowerShellIntegration,
     ) -> Result<Self> {
         let theme = Theme::from_config(&config.theme);
-        
+        theme.validate_accessibility(config.theme.strict_contrast)?;
+        let layout = LayoutManager::from_config(&config.layout);
+        let idle_actions = IdleAction::parse_all(&config.session.idle_actions);
+        let (shell_output, shell_output_rx) = mpsc::unbounded_channel();
+
+        let storage = ArtifactStorage::from_config(&config.workspace)?;
+        let mut workspace = Workspace::with_storage(storage).with_config(config.clone());
+        let workspace_config = Arc::new(std::sync::Mutex::new(None));
+        let changed = workspace_config.clone();
+        workspace.on_config_change(move |config| *changed.lock().unwrap() = Some(config.clone()));
+
         Ok(Dashboard {
             config,
+            config_path: default_config_path(),
+            config_watcher: None,
+            config_changed: Arc::new(AtomicBool::new(false)),
+            saved_config: None,
+            profiles: Profiles::default(),
+            workspace: Arc::new(workspace),
+            workspace_config,
             theme,
             graphics,
-            shell,
+            shell: Arc::new(shell),
+            router: CommandRouter::new(),
+            palette: CommandPalette::new(),
+            shell_pane: ShellPane::new(),
+            shell_output,
+            shell_output_rx,
+            running: Vec::new(),
+            layout,
+            area: Rect::default(),
+            term_size: (0, 0),
+            resize_watcher: None,
+            focused: 0,
+            zoomed: false,
+            dragging: None,
+            idle: None,
+            idle_actions,
+            idle_handler: None,
+            idle_fired: Arc::new(AtomicBool::new(false)),
+            dimmed: false,
+            review: None,
+            grant_source: None,
+            review_pending: None,
+            revoke_handler: None,
+            vault_rotate: None,
+            vault_rotate_handler: None,
+            preview_paths: None,
+            preview_text: Arc::new(std::sync::Mutex::new(None)),
+            doctor: None,
+            doctor_pending: None,
+            shutdown_hook: None,
+            log_path: log_tail::default_log_path(),
+            log_level: LogLevel::Info,
+            log_tail: None,
+            log_lines: VecDeque::new(),
             should_quit: false,
         })
     }
 
+    /// Switch between the profiles in `profiles` instead of `~/.omniscient/profiles`
+    pub fn set_profiles(&mut self, profiles: Profiles) {
+        self.profiles = profiles;
+    }
+
+    /// The session's workspace; selecting one layers its config over the user's
+    pub fn workspace(&self) -> Arc<Workspace> {
+        self.workspace.clone()
+    }
+
+    /// Read the log pane from `path` instead of `~/.omniscient/logs/omniscient.log`
+    pub fn set_log_path(&mut self, path: impl Into<PathBuf>) {
+        self.log_path = path.into();
+    }
+
+    /// Save layout changes to `path` instead of `~/.omniscient/config.toml`
+    pub fn set_config_path(&mut self, path: impl Into<PathBuf>) {
+        self.config_path = path.into();
+    }
+
+    /// Re-theme for the session, e.g. after a workspace config changes it.
+    /// The saved user config is left alone.
+    pub fn apply_theme(&mut self, config: &ThemeConfig) -> Result<()> {
+        let theme = Theme::from_config(config);
+        theme.validate_accessibility(config.strict_contrast)?;
+        tracing::info!("Switched to theme '{}'", theme.name);
+        self.theme = theme;
+        Ok(())
+    }
+
+    /// Handle idle actions the dashboard can't apply itself (vault lock, grant revocation)
+    pub fn on_idle(&mut self, handler: impl Fn(&[IdleAction]) + Send + Sync + 'static) {
+        self.idle_handler = Some(Arc::new(handler));
+    }
+
+    /// Supply the grants for the capability review screen and apply revokes from it
+    pub fn on_capability_review<F, Fut>(
+        &mut self,
+        grants: F,
+        revoke: impl Fn(&RevokeRequest) + Send + Sync + 'static,
+    ) where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Vec<GrantRow>> + Send + 'static,
+    {
+        self.grant_source = Some(Arc::new(move || Box::pin(grants())));
+        self.revoke_handler = Some(Arc::new(revoke));
+    }
+
+    /// Run rotations confirmed in the `vault:rotate` dialog, e.g. with
+    /// `TokenVault::rotate_for` on a spawned task
+    pub fn on_vault_rotate(&mut self, handler: impl Fn(RotateRequest) + Send + Sync + 'static) {
+        self.vault_rotate_handler = Some(Arc::new(handler));
+    }
+
+    /// Run `hook` after the terminal is restored on exit
+    pub fn on_shutdown<F, Fut>(&mut self, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.shutdown_hook = Some(Box::new(move || Box::pin(hook())));
+    }
+
     pub async fn run(&mut self) -> Result<()> {
+        let idle_minutes = self.config.session.idle_minutes;
+        if idle_minutes > 0 {
+            let fired = self.idle_fired.clone();
+            self.idle = Some(IdleMonitor::spawn(
+                Duration::from_secs(idle_minutes as u64 * 60),
+                move || {
+                    fired.store(true, Ordering::SeqCst);
+                    async {}
+                },
+            ));
+        }
+
+        // Backstop for resize events that multiplexers fail to deliver
+        match ResizeWatcher::spawn() {
+            Ok(watcher) => self.resize_watcher = Some(watcher),
+            Err(e) => tracing::warn!("Resize detection unavailable: {}", e),
+        }
+
+        self.watch_config();
+        let history = self.shell.get_history().await;
+        self.shell_pane.set_history(history, self.config.shell.history_size);
+
         // Setup terminal
         enable_raw_mode()?;
         stdout().execute(EnterAlternateScreen)?;
+        stdout().execute(EnableMouseCapture)?;
         let backend = CrosstermBackend::new(stdout());
         let mut terminal = Terminal::new(backend)?;
 
@@ -57,96 +329,1141 @@ impl Dashboard {
         // Main event loop
         while !self.should_quit {
             // Draw UI
-            terminal.draw(|frame| {
+            self.poll_log();
+            self.poll_shell();
+            self.poll_doctor();
+            self.poll_review();
+            self.poll_workspace();
+            let shell_pane = &self.shell_pane;
+            let checking = self.doctor_pending.is_some();
+            let (layout, theme, focused, zoomed, dimmed, review, vault_rotate, doctor) = (
+                &self.layout,
+                &self.theme,
+                self.focused,
+                self.zoomed,
+                self.dimmed,
+                &self.review,
+                &self.vault_rotate,
+                &self.doctor,
+            );
+            let log_lines = self.log_tail.as_ref().map(|_| &self.log_lines);
+            let preview_text = self.preview_text.lock().unwrap().clone();
+            let completed = terminal.draw(|frame| {
                 let size = frame.area();
                 
-                // Create layout based on config
-                let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Percentage(60),
-                        Constraint::Percentage(40),
-                    ])
-                    .split(size);
-
-                let top_chunks = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([
-                        Constraint::Percentage(60),
-                        Constraint::Percentage(40),
-                    ])
-                    .split(chunks[0]);
-
-                let bottom_chunks = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([
-                        Constraint::Percentage(50),
-                        Constraint::Percentage(50),
-                    ])
-                    .split(chunks[1]);
-
-                // Shell pane
-                let shell_block = Block::default()
-                    .title("Shell")
-                    .borders(Borders::ALL)
-                    .style(Style::default().fg(self.theme.foreground));
-                let shell_content = Paragraph::new("PowerShell console will appear here...")
-                    .block(shell_block);
-                frame.render_widget(shell_content, top_chunks[0]);
-
-                // Agent pane
-                let agent_block = Block::default()
-                    .title("Agent Console")
-                    .borders(Borders::ALL)
-                    .style(Style::default().fg(self.theme.foreground));
-                let agent_content = Paragraph::new("AI agent outputs will stream here...")
-                    .block(agent_block);
-                frame.render_widget(agent_content, top_chunks[1]);
-
-                // Preview pane
-                let preview_block = Block::default()
-                    .title("Preview")
-                    .borders(Borders::ALL)
-                    .style(Style::default().fg(self.theme.foreground));
-                let preview_content = Paragraph::new("Media and file previews...")
-                    .block(preview_block);
-                frame.render_widget(preview_content, bottom_chunks[0]);
-
-                // Log pane
-                let log_block = Block::default()
-                    .title("Log")
-                    .borders(Borders::ALL)
-                    .style(Style::default().fg(self.theme.foreground));
-                let log_content = Paragraph::new("System logs and errors...")
-                    .block(log_block);
-                frame.render_widget(log_content, bottom_chunks[1]);
+                let panes = visible_panes(layout, size, focused, zoomed);
+                let pane_style = |index: usize| {
+                    let color = if dimmed {
+                        Color::DarkGray
+                    } else if index == focused {
+                        theme.accent
+                    } else {
+                        theme.foreground
+                    };
+                    Style::default().fg(color)
+                };
+
+                for (index, name, rect) in panes {
+                    let (title, placeholder) = pane_text(name);
+                    let block = Block::default()
+                        .title(title)
+                        .borders(Borders::ALL)
+                        .style(pane_style(index));
+                    let text = match log_lines {
+                        _ if name == "shell" => shell_pane.render_text(rect.height.saturating_sub(2) as usize),
+                        _ if name == "preview" => preview_text.clone().unwrap_or_else(|| placeholder.to_string()),
+                        // Newest lines at the bottom, as many as fit inside the border
+                        Some(lines) if name == "log" => {
+                            let visible = rect.height.saturating_sub(2) as usize;
+                            let skip = lines.len().saturating_sub(visible);
+                            lines.iter().skip(skip).cloned().collect::<Vec<_>>().join("\n")
+                        }
+                        _ => placeholder.to_string(),
+                    };
+                    frame.render_widget(Paragraph::new(text).block(block), rect);
+                }
+
+                if let Some(review) = review {
+                    let margin = Margin::new(size.width / 8, size.height / 6);
+                    review.render(frame, size.inner(margin), theme);
+                }
+                if let Some(dialog) = vault_rotate {
+                    let margin = Margin::new(size.width / 6, size.height / 3);
+                    dialog.render(frame, size.inner(margin), theme);
+                }
+                let doctor_text = match doctor {
+                    Some(results) => Some(results.iter().map(|r| r.to_string()).collect::<Vec<_>>().join("\n")),
+                    None if checking => Some("Running checks...".to_string()),
+                    None => None,
+                };
+                if let Some(text) = doctor_text {
+                    let block = Block::default().title("omni:doctor - [Esc] Close").borders(Borders::ALL);
+                    let area = size.inner(Margin::new(size.width / 8, size.height / 4));
+                    frame.render_widget(Clear, area);
+                    frame.render_widget(
+                        Paragraph::new(text).block(block).style(Style::default().fg(theme.foreground)),
+                        area,
+                    );
+                }
             })?;
+            self.area = completed.area;
 
             // Handle input
             if event::poll(std::time::Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    self.handle_key(key).await?;
-                }
+                let event = event::read()?;
+                self.handle_event(event).await?;
+            }
+            if self.resize_watcher.as_ref().is_some_and(|w| w.take_pending()) {
+                let (cols, rows) = terminal::size()?;
+                self.handle_resize(cols, rows)?;
+            }
+            if self.idle_fired.swap(false, Ordering::SeqCst) {
+                self.go_idle();
+            }
+            if self.config_changed.swap(false, Ordering::SeqCst) {
+                self.config_file_changed();
             }
         }
 
         // Cleanup
         disable_raw_mode()?;
+        stdout().execute(DisableMouseCapture)?;
         stdout().execute(LeaveAlternateScreen)?;
 
+        self.shutdown().await;
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) {
+        if let Some(hook) = self.shutdown_hook.take() {
+            hook().await;
+        }
+    }
+
+    async fn handle_event(&mut self, event: Event) -> Result<()> {
+        if matches!(event, Event::Key(_) | Event::Mouse(_)) {
+            self.record_activity();
+        }
+        match event {
+            Event::Key(key) => self.handle_key(key).await,
+            Event::Mouse(mouse) => self.handle_mouse(mouse),
+            Event::Resize(cols, rows) => self.handle_resize(cols, rows),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_activity(&mut self) {
+        self.dimmed = false;
+        if let Some(idle) = &self.idle {
+            idle.record_activity();
+        }
+    }
+
+    fn go_idle(&mut self) {
+        if self.idle_actions.contains(&IdleAction::DimUi) {
+            self.dimmed = true;
+        }
+        if let Some(handler) = &self.idle_handler {
+            handler(&self.idle_actions);
+        }
+    }
+
+    /// Pass a new terminal size to the graphics backend; the next draw reflows the panes.
+    ///
+    /// Crossterm events and SIGWINCH can both report the same resize, so repeats are ignored.
+    fn handle_resize(&mut self, cols: u16, rows: u16) -> Result<()> {
+        if self.term_size == (cols, rows) {
+            return Ok(());
+        }
+        self.term_size = (cols, rows);
+
+        // Pixel size is only reported by some terminals; 0 means unknown
+        let (pixel_w, pixel_h) = terminal::window_size()
+            .map(|size| (size.width, size.height))
+            .unwrap_or((0, 0));
+        tracing::debug!("Terminal resized to {}x{} cells", cols, rows);
+        self.graphics.resize(cols, rows, pixel_w, pixel_h)
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        match mouse.kind {
+            // Splits are hidden while a pane is zoomed
+            MouseEventKind::Down(MouseButton::Left) if !self.zoomed => {
+                self.dragging = self.layout.split_at(self.area, mouse.column, mouse.row);
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some(id) = self.dragging {
+                    self.layout.drag_to(id, self.area, mouse.column, mouse.row);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) if self.dragging.is_some() => {
+                self.dragging = None;
+                self.persist_layout();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Resize the split next to the focused pane by one cell
+    fn resize_focused(&mut self, code: KeyCode) {
+        if self.zoomed {
+            return;
+        }
+        // Only the built-in dashboard is resizable, so panes 0-1 are the top row
+        let row_split = if self.focused < 2 { SplitId::Top } else { SplitId::Bottom };
+        let (id, delta) = match code {
+            KeyCode::Left => (row_split, -1),
+            KeyCode::Right => (row_split, 1),
+            KeyCode::Up => (SplitId::Rows, -1),
+            KeyCode::Down => (SplitId::Rows, 1),
+            _ => return,
+        };
+        if self.layout.nudge(id, self.area, delta) {
+            self.persist_layout();
+        }
+    }
+
+    /// Load the profile after the active one and make it active
+    fn switch_profile(&mut self) -> Result<()> {
+        let name = self.profiles.next_after(&self.profiles.active());
+        let path = self.profiles.path(&name)?;
+        let config = load_config_from(&path)?;
+        let theme = Theme::from_config(&config.theme);
+        theme.validate_accessibility(config.theme.strict_contrast)?;
+        self.profiles.set_active(&name)?;
+
+        self.apply_config(config, theme);
+        self.config_path = path;
+        self.saved_config = None;
+        if self.config_watcher.is_some() {
+            self.watch_config();
+        }
+        tracing::info!("Switched to profile '{}'", name);
         Ok(())
     }
 
+    /// Re-read the config file and apply its theme, layout and session
+    /// settings; settings that need a restart are reported and left as they are
+    fn reload_config(&mut self) -> Result<()> {
+        let reloaded = self.config.reload_from(&self.config_path)?;
+        let theme = Theme::from_config(&reloaded.config.theme);
+        theme.validate_accessibility(reloaded.config.theme.strict_contrast)?;
+
+        self.apply_config(reloaded.config, theme);
+        for setting in &reloaded.restart_required {
+            tracing::warn!("{} changed; restart the shell to apply it", setting);
+        }
+        tracing::info!("Reloaded config from {}", self.config_path.display());
+        Ok(())
+    }
+
+    /// Watch the current config file, replacing any previous watcher
+    fn watch_config(&mut self) {
+        let changed = self.config_changed.clone();
+        match config::watch(&self.config_path, move || changed.store(true, Ordering::SeqCst)) {
+            Ok(watcher) => self.config_watcher = Some(watcher),
+            Err(e) => {
+                self.config_watcher = None;
+                tracing::warn!("Config changes won't be picked up automatically: {:#}", e);
+            }
+        }
+    }
+
+    /// Reload after the config file changed on disk. An invalid file is
+    /// reported and the running config kept.
+    fn config_file_changed(&mut self) {
+        let contents = std::fs::read_to_string(&self.config_path).ok();
+        if contents.is_some() && contents == self.saved_config {
+            return;
+        }
+        if let Err(e) = self.reload_config() {
+            let err = OmniError::config(
+                format!("{} changed but was not applied: {}", self.config_path.display(), e),
+                Some("The current config stays in effect until the file is valid".to_string()),
+                RecoveryAction::PromptUser("Fix the config file and save it again".to_string()),
+            );
+            tracing::warn!("{}", err.display_with_recovery());
+        }
+    }
+
+    /// Switch to `config`, whose `theme` has already been validated
+    fn apply_config(&mut self, config: Config, theme: Theme) {
+        self.theme = theme;
+        self.layout = LayoutManager::from_config(&config.layout);
+        self.idle_actions = IdleAction::parse_all(&config.session.idle_actions);
+        self.config = config;
+        self.focused = 0;
+    }
+
+    /// Start following the log file, from the start of the file or only new lines
+    fn open_log(&mut self, from_start: bool) {
+        self.log_lines.clear();
+        self.log_tail = Some(if from_start {
+            LogTailer::from_start(&self.log_path, self.log_level)
+        } else {
+            LogTailer::new(&self.log_path, self.log_level)
+        });
+        self.poll_log();
+    }
+
+    /// Move new log lines into the log pane
+    fn poll_log(&mut self) {
+        let Some(tail) = &mut self.log_tail else {
+            return;
+        };
+        self.log_lines.extend(tail.poll());
+        let excess = self.log_lines.len().saturating_sub(log_tail::MAX_LINES);
+        self.log_lines.drain(..excess);
+    }
+
+    /// Move output of running PowerShell commands into the shell pane
+    fn poll_shell(&mut self) {
+        while let Ok(output) = self.shell_output_rx.try_recv() {
+            self.shell_pane.push_output(&output);
+        }
+    }
+
+    /// Show `omni:doctor` results once its checks finish
+    fn poll_doctor(&mut self) {
+        let Some(pending) = &mut self.doctor_pending else {
+            return;
+        };
+        match pending.try_recv() {
+            Ok(results) => {
+                for result in &results {
+                    tracing::info!("{}", result);
+                }
+                self.doctor = Some(results);
+                self.doctor_pending = None;
+            }
+            Err(oneshot::error::TryRecvError::Empty) => {}
+            Err(oneshot::error::TryRecvError::Closed) => {
+                tracing::warn!("omni:doctor checks did not finish");
+                self.doctor_pending = None;
+            }
+        }
+    }
+
+    /// Open the capability review screen once its grants are listed
+    fn poll_review(&mut self) {
+        let Some(pending) = &mut self.review_pending else {
+            return;
+        };
+        match pending.try_recv() {
+            Ok(rows) => {
+                self.review = Some(CapabilityReview::new(rows));
+                self.review_pending = None;
+            }
+            Err(oneshot::error::TryRecvError::Empty) => {}
+            Err(oneshot::error::TryRecvError::Closed) => {
+                tracing::warn!("Listing capability grants did not finish");
+                self.review_pending = None;
+            }
+        }
+    }
+
+    /// Re-theme once a workspace selection has changed the effective config
+    fn poll_workspace(&mut self) {
+        let Some(config) = self.workspace_config.lock().unwrap().take() else {
+            return;
+        };
+        if let Err(e) = self.apply_theme(&config.theme) {
+            tracing::warn!("Keeping the current theme: {}", e);
+        }
+    }
+
+    /// Run `omni:workspace select <path>` or `omni:workspace clear`; the
+    /// selection happens off the UI loop and errors go to the shell pane
+    fn workspace_command(&mut self, args: &[&str]) {
+        let path = match args {
+            ["clear"] => return self.run_command(CommandHandler::WorkspaceClear),
+            ["select", path @ ..] => path.join(" "),
+            path => path.join(" "),
+        };
+        if path.is_empty() {
+            self.shell_pane.push_output("Usage: omni:workspace select <path>");
+            return;
+        }
+        let (workspace, output) = (self.workspace.clone(), self.shell_output.clone());
+        tokio::spawn(async move {
+            if let Err(e) = workspace.select(&path).await {
+                let _ = output.send(e.to_string());
+            }
+        });
+    }
+
+    /// Show `path` in the preview pane. The first call starts the task
+    /// holding the `LivePreview`, which regenerates the preview whenever the
+    /// file is modified.
+    fn show_preview(&mut self, path: PathBuf) {
+        if self.preview_paths.is_none() {
+            let cache = match MediaCache::from_config(&self.config.media) {
+                Ok(cache) => Arc::new(cache),
+                Err(e) => {
+                    self.shell_pane.push_output(&format!("Preview unavailable: {:#}", e));
+                    return;
+                }
+            };
+            let adapter = Arc::new(PreviewAdapter::from_config(&self.config.media));
+            let preview = LivePreview::new(cache, adapter, WorkspaceWatcher::default());
+            let (sender, receiver) = mpsc::unbounded_channel();
+            tokio::spawn(run_preview(preview, receiver, self.preview_text.clone()));
+            self.preview_paths = Some(sender);
+        }
+        if let Some(paths) = &self.preview_paths {
+            let _ = paths.send(path);
+        }
+    }
+
+    /// Name of the focused pane
+    fn focused_pane(&self) -> Option<&str> {
+        self.layout.panes(self.area).get(self.focused).map(|(name, _)| *name)
+    }
+
+    /// Run a line entered in the shell pane: `omni:` commands through the
+    /// command palette, anything else in PowerShell, streaming its output
+    fn submit_shell(&mut self, line: String) {
+        match self.router.route(&line) {
+            Ok(RouteTarget::PowerShell) => {
+                let cancel = CancellationToken::new();
+                let mut lines = Box::pin(self.shell.execute_cancellable(&line, cancel.clone()));
+                let output = self.shell_output.clone();
+                let task = tokio::spawn(async move {
+                    while let Some(line) = lines.next().await {
+                        let text = line.unwrap_or_else(|e| format!("{:#}", e));
+                        if output.send(text).is_err() {
+                            break;
+                        }
+                    }
+                });
+                self.running.retain(|(_, task)| !task.is_finished());
+                self.running.push((cancel, task));
+            }
+            Ok(RouteTarget::OmniscientShell) => match self.palette.dispatch(&line) {
+                // Built-in handlers don't get arguments, but selection needs the path
+                Ok(Some(CommandHandler::WorkspaceSelect)) => {
+                    let args: Vec<&str> = line.split_whitespace().skip(1).collect();
+                    self.workspace_command(&args);
+                }
+                Ok(Some(CommandHandler::PreviewOpen)) => {
+                    let path = line.split_whitespace().skip(1).collect::<Vec<_>>().join(" ");
+                    if path.is_empty() {
+                        self.shell_pane.push_output("Usage: omni:preview <path>");
+                    } else {
+                        self.show_preview(PathBuf::from(path));
+                    }
+                }
+                Ok(Some(handler)) => self.run_command(handler),
+                Ok(None) => {}
+                Err(e) => self.shell_pane.push_output(&e.to_string()),
+            },
+            Ok(RouteTarget::Agent(name)) => {
+                self.shell_pane.push_output(&format!("Agent '{}' can't be run from the shell pane", name));
+            }
+            Err(e) => self.shell_pane.push_output(&e.to_string()),
+        }
+    }
+
+    /// Kill the PowerShell commands still running from the shell pane;
+    /// returns false if there were none
+    fn cancel_running(&mut self) -> bool {
+        self.running.retain(|(_, task)| !task.is_finished());
+        if self.running.is_empty() {
+            return false;
+        }
+        for (cancel, _) in self.running.drain(..) {
+            cancel.cancel();
+        }
+        self.shell_pane.push_output("^C");
+        true
+    }
+
+    /// Run a command palette action
+    pub fn run_command(&mut self, handler: CommandHandler) {
+        match handler {
+            CommandHandler::LayoutSwitch => {
+                let name = self.layout.cycle_preset().to_string();
+                tracing::info!("Switched to layout preset '{}'", name);
+                self.focused = 0;
+                self.persist_layout();
+            }
+            CommandHandler::CapabilityReview => {
+                // Listing waits on the grant store, so it mustn't hold up drawing
+                let Some(list) = self.grant_source.clone() else {
+                    self.review = Some(CapabilityReview::new(Vec::new()));
+                    return;
+                };
+                let (sender, receiver) = oneshot::channel();
+                tokio::spawn(async move {
+                    let _ = sender.send(list().await);
+                });
+                self.review_pending = Some(receiver);
+            }
+            CommandHandler::ProfileSwitch => {
+                if let Err(e) = self.switch_profile() {
+                    tracing::warn!("Failed to switch profile: {}", e);
+                }
+            }
+            CommandHandler::ConfigReload => {
+                if let Err(e) = self.reload_config() {
+                    tracing::warn!("Failed to reload config, keeping the current one: {}", e);
+                }
+            }
+            CommandHandler::LogOpen => self.open_log(true),
+            CommandHandler::LogTail => self.open_log(false),
+            CommandHandler::LogLevel => {
+                self.log_level = self.log_level.next();
+                tracing::info!("Log pane shows {:?} and above", self.log_level);
+                if let Some(tail) = &mut self.log_tail {
+                    tail.set_min_level(self.log_level);
+                }
+            }
+            CommandHandler::VaultRotate => {
+                if self.vault_rotate_handler.is_some() {
+                    self.vault_rotate = Some(VaultRotateDialog::new());
+                } else {
+                    tracing::warn!("No encrypted vault to rotate");
+                }
+            }
+            CommandHandler::Doctor => {
+                // Checks run pwsh and open files, so they mustn't hold up drawing
+                let (sender, receiver) = oneshot::channel();
+                let (config_path, backend) = (self.config_path.clone(), self.graphics.backend_type());
+                tokio::task::spawn_blocking(move || {
+                    let _ = sender.send(diagnostics::run_with_backend(&config_path, backend));
+                });
+                self.doctor_pending = Some(receiver);
+            }
+            CommandHandler::WorkspaceSelect => self.workspace_command(&[]),
+            CommandHandler::WorkspaceClear => {
+                let workspace = self.workspace.clone();
+                tokio::spawn(async move { workspace.clear().await });
+            }
+            CommandHandler::Quit => self.should_quit = true,
+            other => tracing::debug!("Command {:?} is not handled by the dashboard", other),
+        }
+    }
+
+    /// Record the current preset and split ratios in config, saving it when auto_save is on
+    fn persist_layout(&mut self) {
+        self.config.layout.default.preset = self.layout.active_preset().to_string();
+        self.config.layout.default.split_ratios = self.layout.ratios();
+        if !self.config.workspace.auto_save {
+            return;
+        }
+        // Write back only the layout so session overrides (--theme, workspace
+        // configs) never end up in the user's file. If the file can't be read,
+        // saving would replace whatever the user has there, so skip it
+        let mut saved = match load_config_from(&self.config_path) {
+            Ok(saved) => saved,
+            Err(e) => {
+                tracing::warn!(
+                    "Not saving layout: {} could not be loaded: {:#}",
+                    self.config_path.display(),
+                    e
+                );
+                return;
+            }
+        };
+        saved.layout.default = self.config.layout.default.clone();
+        match saved.save(&self.config_path) {
+            Ok(()) => self.saved_config = std::fs::read_to_string(&self.config_path).ok(),
+            Err(e) => tracing::warn!("Failed to save layout: {}", e),
+        }
+    }
+
     async fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        if let Some(review) = &mut self.review {
+            match review.handle_key(key) {
+                ReviewAction::Close => self.review = None,
+                ReviewAction::Revoke(request) => {
+                    if let Some(revoke) = &self.revoke_handler {
+                        revoke(&request);
+                    }
+                }
+                ReviewAction::None => {}
+            }
+            return Ok(());
+        }
+        if let Some(dialog) = &mut self.vault_rotate {
+            match dialog.handle_key(key) {
+                RotateAction::Close => self.vault_rotate = None,
+                RotateAction::Submit(request) => {
+                    if let Some(rotate) = &self.vault_rotate_handler {
+                        rotate(request);
+                    }
+                }
+                RotateAction::None => {}
+            }
+            return Ok(());
+        }
+        if self.doctor.is_some() || self.doctor_pending.is_some() {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Enter) {
+                self.doctor = None;
+                self.doctor_pending = None;
+            }
+            return Ok(());
+        }
+        // Typing goes to the shell pane's input line while it has focus
+        if self.focused_pane() == Some("shell") {
+            match self.shell_pane.handle_key(key) {
+                ShellAction::Ignored => {}
+                ShellAction::None => return Ok(()),
+                ShellAction::Submit(line) => {
+                    self.submit_shell(line);
+                    return Ok(());
+                }
+            }
+        }
         match key.code {
             KeyCode::Char('q') | KeyCode::Esc => {
                 self.should_quit = true;
             }
-            KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-                self.should_quit = true;
+            // Stops running commands first, quits once nothing is running
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = !self.cancel_running();
+            }
+            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.run_command(CommandHandler::LayoutSwitch);
+            }
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.run_command(CommandHandler::CapabilityReview);
+            }
+            KeyCode::Tab => {
+                let count = self.layout.panes(self.area).len().max(1);
+                self.focused = (self.focused + 1) % count;
+            }
+            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.zoomed = !self.zoomed;
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.resize_focused(key.code);
             }
             _ => {}
         }
         Ok(())
     }
 }
+
+/// Keep the preview pane on the latest file sent to `paths`, regenerating
+/// its preview when the file changes, until the dashboard goes away
+async fn run_preview(
+    mut preview: LivePreview,
+    mut paths: mpsc::UnboundedReceiver<PathBuf>,
+    text: Arc<std::sync::Mutex<Option<String>>>,
+) {
+    let mut poll = tokio::time::interval(PREVIEW_POLL);
+    loop {
+        let updated = tokio::select! {
+            path = paths.recv() => match path {
+                Some(path) => preview.show(&path).await.map(|()| true),
+                None => break,
+            },
+            _ = poll.tick() => preview.refresh().await,
+        };
+        let shown = match updated {
+            Ok(false) => continue,
+            Ok(true) => preview_text(&preview),
+            Err(e) => format!("Preview failed: {:#}", e),
+        };
+        *text.lock().unwrap() = Some(shown);
+    }
+}
+
+/// Pane text for a preview: placeholders are text, image data is summarized
+fn preview_text(preview: &LivePreview) -> String {
+    let name = preview.shown().map(|path| path.display().to_string()).unwrap_or_default();
+    match preview.data() {
+        Some(data) => match std::str::from_utf8(data) {
+            Ok(text) => text.to_string(),
+            Err(_) => format!("{} ({} byte preview)", name, data.len()),
+        },
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::backend::{BackendType, Capabilities, Region};
+    use crate::graphics::image::RgbaImage;
+    use crate::oauth::vault::TokenVault;
+    use crate::tui::vault_rotate::RotateStatus;
+    use std::sync::{Arc, Mutex};
+
+    /// Sizes a `MockBackend` was resized to
+    type Resizes = Arc<Mutex<Vec<(u16, u16)>>>;
+
+    struct MockBackend {
+        resizes: Resizes,
+    }
+
+    impl GraphicsBackend for MockBackend {
+        fn backend_type(&self) -> BackendType {
+            BackendType::Overlay
+        }
+
+        fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        fn render_image(&mut self, _region: &Region, _image: &RgbaImage) -> Result<()> {
+            Ok(())
+        }
+
+        fn render_video_frame(&mut self, _region: &Region, _frame: &RgbaImage) -> Result<()> {
+            Ok(())
+        }
+
+        fn clear_region(&mut self, _region: &Region) -> Result<()> {
+            Ok(())
+        }
+
+        fn supports_resolution(&self, _width: u32, _height: u32) -> bool {
+            true
+        }
+
+        fn benchmark(&mut self) -> Result<f32> {
+            Ok(1.0)
+        }
+
+        fn resize(&mut self, cols: u16, rows: u16, _pixel_w: u16, _pixel_h: u16) -> Result<()> {
+            self.resizes.lock().unwrap().push((cols, rows));
+            Ok(())
+        }
+    }
+
+    /// Dashboard over a `MockBackend`, with layout auto-save off so tests
+    /// never write the user's config; also returns the backend's resizes
+    fn test_dashboard() -> (Dashboard, Resizes) {
+        let resizes = Arc::new(Mutex::new(Vec::new()));
+        let backend = MockBackend { resizes: resizes.clone() };
+        let mut config = Config::default();
+        config.workspace.auto_save = false;
+        let dashboard =
+            Dashboard::new(config, Box::new(backend), PowerShellIntegration::with_path("pwsh")).unwrap();
+        (dashboard, resizes)
+    }
+
+    #[tokio::test]
+    async fn test_resize_event_reaches_backend() {
+        let (mut dashboard, resizes) = test_dashboard();
+
+        dashboard.handle_event(Event::Resize(120, 40)).await.unwrap();
+
+        assert_eq!(*resizes.lock().unwrap(), vec![(120, 40)]);
+        assert!(!dashboard.should_quit);
+    }
+
+    #[tokio::test]
+    async fn test_keyboard_resizes_focused_split() {
+        let (mut dashboard, _) = test_dashboard();
+        dashboard.area = Rect::new(0, 0, 100, 50);
+
+        let ctrl = |code| Event::Key(KeyEvent::new(code, KeyModifiers::CONTROL));
+        dashboard.handle_event(ctrl(KeyCode::Right)).await.unwrap();
+        dashboard.handle_event(Event::Key(KeyEvent::from(KeyCode::Tab))).await.unwrap();
+        dashboard.handle_event(Event::Key(KeyEvent::from(KeyCode::Tab))).await.unwrap();
+        dashboard.handle_event(ctrl(KeyCode::Left)).await.unwrap();
+
+        assert_eq!(dashboard.config.layout.default.split_ratios, vec![60, 61, 49]);
+    }
+
+    #[tokio::test]
+    async fn test_zoom_shows_focused_pane_full_screen() {
+        let (mut dashboard, _) = test_dashboard();
+        let area = Rect::new(0, 0, 100, 50);
+        dashboard.area = area;
+        let key = |code| Event::Key(KeyEvent::from(code));
+        let zoom = || Event::Key(KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL));
+        let visible = |d: &Dashboard| {
+            visible_panes(&d.layout, area, d.focused, d.zoomed)
+                .into_iter()
+                .map(|(index, name, rect)| (index, name.to_string(), rect))
+                .collect::<Vec<_>>()
+        };
+
+        dashboard.handle_event(zoom()).await.unwrap();
+        assert_eq!(visible(&dashboard), vec![(0, "shell".to_string(), area)]);
+
+        // Tab picks which pane fills the screen
+        dashboard.handle_event(key(KeyCode::Tab)).await.unwrap();
+        assert_eq!(visible(&dashboard), vec![(1, "agent".to_string(), area)]);
+        dashboard.handle_event(Event::Key(KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL))).await.unwrap();
+        assert_eq!(dashboard.layout.ratios(), vec![60, 60, 50]);
+
+        dashboard.handle_event(zoom()).await.unwrap();
+        assert_eq!(visible(&dashboard).len(), 4);
+        assert!(!dashboard.should_quit);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shell_pane_runs_typed_commands() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Stands in for pwsh, echoing the -Command argument
+        let dir = tempfile::tempdir().unwrap();
+        let pwsh = dir.path().join("pwsh");
+        std::fs::write(&pwsh, "#!/bin/sh\necho \"ran: $4\"\n").unwrap();
+        std::fs::set_permissions(&pwsh, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let (mut dashboard, _) = test_dashboard();
+        dashboard.shell = Arc::new(PowerShellIntegration::with_path(pwsh.to_str().unwrap()));
+        let pinged = Arc::new(Mutex::new(false));
+        let sink = pinged.clone();
+        dashboard.palette.register(crate::tui::command_palette::Command {
+            name: "omni:ping".to_string(),
+            description: "Test command".to_string(),
+            aliases: vec![],
+            handler: CommandHandler::Custom("ping".to_string()),
+        });
+        dashboard.palette.register_handler("ping", move |_| {
+            *sink.lock().unwrap() = true;
+            Ok(())
+        });
+
+        let mut type_line = async |line: &str| {
+            for c in line.chars() {
+                dashboard.handle_event(Event::Key(KeyEvent::from(KeyCode::Char(c)))).await.unwrap();
+            }
+            dashboard.handle_event(Event::Key(KeyEvent::from(KeyCode::Enter))).await.unwrap();
+        };
+        type_line("omni:ping").await;
+        type_line("Get-Date").await;
+        assert!(*pinged.lock().unwrap());
+
+        assert!(!dashboard.should_quit);
+        let output = tokio::time::timeout(Duration::from_secs(5), dashboard.shell_output_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        dashboard.shell_pane.push_output(&output);
+        assert_eq!(
+            dashboard.shell_pane.lines().collect::<Vec<_>>(),
+            vec!["PS> omni:ping", "PS> Get-Date", "ran: Get-Date"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_ctrl_c_cancels_running_command_before_quitting() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let pwsh = dir.path().join("pwsh");
+        std::fs::write(&pwsh, "#!/bin/sh\neval \"$4\"\n").unwrap();
+        std::fs::set_permissions(&pwsh, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let (mut dashboard, _) = test_dashboard();
+        dashboard.shell = Arc::new(PowerShellIntegration::with_path(pwsh.to_str().unwrap()));
+        for c in "sleep 30".chars() {
+            dashboard.handle_event(Event::Key(KeyEvent::from(KeyCode::Char(c)))).await.unwrap();
+        }
+        dashboard.handle_event(Event::Key(KeyEvent::from(KeyCode::Enter))).await.unwrap();
+
+        let ctrl_c = || Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        dashboard.handle_event(ctrl_c()).await.unwrap();
+        assert!(!dashboard.should_quit);
+        assert_eq!(dashboard.shell_pane.lines().last(), Some("^C"));
+        let output = tokio::time::timeout(Duration::from_secs(5), dashboard.shell_output_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(output.contains("cancelled"));
+
+        dashboard.handle_event(ctrl_c()).await.unwrap();
+        assert!(dashboard.should_quit);
+    }
+
+    #[tokio::test]
+    async fn test_idle_dims_until_input() {
+        let (mut dashboard, _) = test_dashboard();
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let seen = handled.clone();
+        dashboard.on_idle(move |actions| seen.lock().unwrap().extend_from_slice(actions));
+
+        dashboard.go_idle();
+        assert!(dashboard.dimmed);
+        assert_eq!(*handled.lock().unwrap(), vec![IdleAction::LockVault, IdleAction::DimUi]);
+
+        dashboard.handle_event(Event::Key(KeyEvent::from(KeyCode::Tab))).await.unwrap();
+        assert!(!dashboard.dimmed);
+    }
+
+    #[tokio::test]
+    async fn test_capability_review_revokes_selected_grant() {
+        let (mut dashboard, _) = test_dashboard();
+        let revoked = Arc::new(Mutex::new(Vec::new()));
+        let seen = revoked.clone();
+        dashboard.on_capability_review(
+            || async {
+                ["files.read", "network.http"]
+                    .iter()
+                    .map(|capability| GrantRow {
+                        agent_id: "indexer".to_string(),
+                        capability: capability.to_string(),
+                        expires_at: None,
+                        constraint: None,
+                    })
+                    .collect()
+            },
+            move |request| seen.lock().unwrap().push(request.capability.clone()),
+        );
+
+        dashboard.run_command(CommandHandler::CapabilityReview);
+        for _ in 0..200 {
+            dashboard.poll_review();
+            if dashboard.review.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(dashboard.review.is_some());
+        for code in [KeyCode::Down, KeyCode::Enter, KeyCode::Char('y'), KeyCode::Esc] {
+            dashboard.handle_event(Event::Key(KeyEvent::from(code))).await.unwrap();
+        }
+
+        assert_eq!(*revoked.lock().unwrap(), vec!["network.http".to_string()]);
+        assert!(dashboard.review.is_none());
+        assert!(!dashboard.should_quit);
+    }
+
+    #[tokio::test]
+    async fn test_vault_rotate_hands_request_to_handler() {
+        let (mut dashboard, _) = test_dashboard();
+        // Nothing to rotate until a vault is wired in
+        dashboard.run_command(CommandHandler::VaultRotate);
+        assert!(dashboard.vault_rotate.is_none());
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let sink = requests.clone();
+        dashboard.on_vault_rotate(move |request| {
+            request.progress.finish(Ok(2));
+            sink.lock().unwrap().push(request);
+        });
+        dashboard.run_command(CommandHandler::VaultRotate);
+        let keys = "old\nnew\nnew\n\n".chars().map(|c| match c {
+            '\n' => KeyCode::Enter,
+            c => KeyCode::Char(c),
+        });
+        for code in keys {
+            dashboard.handle_event(Event::Key(KeyEvent::from(code))).await.unwrap();
+        }
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].new_passphrase(), "new");
+        // The last Enter closed the finished dialog without quitting
+        assert!(dashboard.vault_rotate.is_none());
+        assert!(!dashboard.should_quit);
+    }
+
+    #[tokio::test]
+    async fn test_vault_rotate_unlocks_and_rotates_a_locked_vault() {
+        let (mut dashboard, _) = test_dashboard();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db").display().to_string();
+        let vault = Arc::new(TokenVault::new_encrypted_sqlite(path.clone()).unwrap());
+        vault.unlock_with_passphrase("old").await.unwrap();
+        vault.store("github", "gho_secret").await.unwrap();
+        // Auto-locked since startup
+        vault.lock().await;
+
+        dashboard.on_vault_rotate(vault.clone().rotate_handler());
+        dashboard.run_command(CommandHandler::VaultRotate);
+        let keys = "old\nnew\nnew\n".chars().map(|c| match c {
+            '\n' => KeyCode::Enter,
+            c => KeyCode::Char(c),
+        });
+        for code in keys {
+            dashboard.handle_event(Event::Key(KeyEvent::from(code))).await.unwrap();
+        }
+        let dialog = dashboard.vault_rotate.as_ref().unwrap();
+        for _ in 0..200 {
+            if matches!(dialog.status(), Some(RotateStatus::Done(_) | RotateStatus::Failed(_))) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(dialog.status(), Some(RotateStatus::Done(1)));
+        assert_eq!(vault.fetch("github").await.unwrap(), "gho_secret");
+
+        let reopened = TokenVault::new_encrypted_sqlite(path).unwrap();
+        reopened.unlock_with_passphrase("new").await.unwrap();
+        assert_eq!(reopened.fetch("github").await.unwrap(), "gho_secret");
+    }
+
+    #[tokio::test]
+    async fn test_workspace_select_applies_its_theme() {
+        let (mut dashboard, _) = test_dashboard();
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join(crate::workspace::selection::WORKSPACE_CONFIG_PATH);
+        std::fs::create_dir_all(local.parent().unwrap()).unwrap();
+        std::fs::write(
+            &local,
+            "[theme]\nname = \"Paper\"\nbackground = \"#ffffff\"\nforeground = \"#1f2328\"\naccent = \"#0969da\"\n",
+        )
+        .unwrap();
+
+        async fn wait_for_theme(dashboard: &mut Dashboard, name: &str) {
+            for _ in 0..200 {
+                dashboard.poll_workspace();
+                if dashboard.theme.name == name {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            panic!("theme never became {}", name);
+        }
+
+        dashboard.submit_shell(format!("omni:workspace select {}", dir.path().display()));
+        wait_for_theme(&mut dashboard, "Paper").await;
+        assert_eq!(dashboard.workspace().root().await.unwrap(), dir.path());
+
+        dashboard.submit_shell("omni:workspace clear".to_string());
+        wait_for_theme(&mut dashboard, "NeoCyan").await;
+        assert!(!dashboard.workspace().is_selected().await);
+    }
+
+    #[tokio::test]
+    async fn test_preview_command_fills_the_preview_pane() {
+        let (mut dashboard, _) = test_dashboard();
+        let dir = tempfile::tempdir().unwrap();
+        dashboard.config.media.cache_dir = Some(dir.path().join("cache").to_string_lossy().into_owned());
+        let source = dir.path().join("render.png");
+        std::fs::write(&source, b"first render").unwrap();
+
+        dashboard.submit_shell(format!("omni:preview {}", source.display()));
+        for _ in 0..200 {
+            if dashboard.preview_text.lock().unwrap().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(dashboard.preview_text.lock().unwrap().as_deref(), Some("[image] render.png"));
+    }
+
+    #[tokio::test]
+    async fn test_doctor_runs_off_the_ui_loop() {
+        let (mut dashboard, _) = test_dashboard();
+        let dir = tempfile::tempdir().unwrap();
+        dashboard.set_config_path(dir.path().join("config.toml"));
+
+        dashboard.run_command(CommandHandler::Doctor);
+        assert!(dashboard.doctor.is_none());
+        for _ in 0..200 {
+            dashboard.poll_doctor();
+            if dashboard.doctor.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        let results = dashboard.doctor.as_ref().expect("checks should finish");
+        assert!(results.iter().any(|r| r.name == "Database"));
+
+        dashboard.handle_key(KeyEvent::from(KeyCode::Esc)).await.unwrap();
+        assert!(dashboard.doctor.is_none());
+        assert!(!dashboard.should_quit);
+    }
+
+    #[tokio::test]
+    async fn test_resize_updates_cached_size_once() {
+        let (mut dashboard, resizes) = test_dashboard();
+
+        dashboard.handle_resize(100, 30).unwrap();
+        assert_eq!(dashboard.term_size, (100, 30));
+
+        // The same resize reported again by SIGWINCH is not passed on twice
+        dashboard.handle_resize(100, 30).unwrap();
+        dashboard.handle_resize(90, 30).unwrap();
+        assert_eq!(dashboard.term_size, (90, 30));
+        assert_eq!(*resizes.lock().unwrap(), vec![(100, 30), (90, 30)]);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_hook_runs_once() {
+        use std::sync::atomic::AtomicUsize;
+
+        let (mut dashboard, _) = test_dashboard();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        dashboard.on_shutdown(move || async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        dashboard.shutdown().await;
+        dashboard.shutdown().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_config_reload_applies_theme_and_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let (mut dashboard, _) = test_dashboard();
+        dashboard.set_config_path(&path);
+
+        let mut edited = Config::default();
+        edited.theme.accent = "#ff8800".to_string();
+        edited.layout.default.split_ratios = vec![70, 50, 50];
+        edited.graphics.preferred = "kitty".to_string();
+        edited.save(&path).unwrap();
+        dashboard.run_command(CommandHandler::ConfigReload);
+
+        assert_eq!(dashboard.theme.accent, Theme::from_config(&edited.theme).accent);
+        assert_eq!(dashboard.layout.ratios(), vec![70, 50, 50]);
+        // Needs a restart, so the running value stays
+        assert_eq!(dashboard.config.graphics.preferred, Config::default().graphics.preferred);
+
+        // A broken file leaves the current config in place
+        std::fs::write(&path, "version = ").unwrap();
+        dashboard.run_command(CommandHandler::ConfigReload);
+        assert_eq!(dashboard.config.theme.accent, "#ff8800");
+        assert!(!dashboard.should_quit);
+    }
+
+    #[test]
+    fn test_config_file_change_ignores_own_layout_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let (mut dashboard, _) = test_dashboard();
+        dashboard.config.workspace.auto_save = true;
+        dashboard.set_config_path(&path);
+
+        dashboard.persist_layout();
+        dashboard.focused = 1;
+        dashboard.config_file_changed();
+        assert_eq!(dashboard.focused, 1, "reloaded after its own save");
+
+        let mut edited = Config::default();
+        edited.theme.accent = "#ff8800".to_string();
+        edited.save(&path).unwrap();
+        dashboard.config_file_changed();
+        assert_eq!(dashboard.config.theme.accent, "#ff8800");
+
+        std::fs::write(&path, "version = ").unwrap();
+        dashboard.config_file_changed();
+        assert_eq!(dashboard.config.theme.accent, "#ff8800");
+    }
+
+    #[test]
+    fn test_layout_save_skipped_when_config_unreadable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let (mut dashboard, _) = test_dashboard();
+        dashboard.config.workspace.auto_save = true;
+        dashboard.set_config_path(&path);
+
+        std::fs::write(&path, "version = ").unwrap();
+        dashboard.persist_layout();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "version = ");
+    }
+}
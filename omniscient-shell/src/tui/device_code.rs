@@ -0,0 +1,130 @@
+//! Device-code sign-in prompt, with a scannable QR code when it fits
+
+use anyhow::Result;
+
+/// Blank modules around the code; scanners need some quiet zone
+const QUIET_ZONE: usize = 2;
+
+/// What the user needs to finish a device-code sign-in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceCodePrompt {
+    pub provider: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Verification URI with the user code filled in, if the provider offers one
+    pub complete_uri: Option<String>,
+}
+
+impl DeviceCodePrompt {
+    /// URL encoded in the QR code: the complete URI when available
+    pub fn link(&self) -> &str {
+        self.complete_uri.as_deref().unwrap_or(&self.verification_uri)
+    }
+
+    /// Prompt text: the QR code when it fits in `cols` x `rows` cells, then
+    /// the code and URL, which are always shown
+    pub fn text(&self, cols: u16, rows: u16) -> String {
+        let footer = format!("Visit {} and enter code {}", self.verification_uri, self.user_code);
+
+        let qr = qr_matrix(self.link()).ok().map(|matrix| render_half_blocks(&matrix));
+        match qr {
+            // Leave a row for the footer
+            Some(lines)
+                if lines.len() < rows as usize
+                    && lines.first().map_or(0, |l| l.chars().count()) <= cols as usize =>
+            {
+                format!("{}\n{}", lines.join("\n"), footer)
+            }
+            _ => footer,
+        }
+    }
+}
+
+/// QR modules for `data`, row by row; `true` is a dark module
+#[cfg(feature = "qr")]
+pub fn qr_matrix(data: &str) -> Result<Vec<Vec<bool>>> {
+    let code = qrcode::QrCode::new(data.as_bytes())?;
+    let width = code.width();
+    let colors = code.to_colors();
+    Ok(colors
+        .chunks(width)
+        .map(|row| row.iter().map(|c| *c == qrcode::Color::Dark).collect())
+        .collect())
+}
+
+#[cfg(not(feature = "qr"))]
+pub fn qr_matrix(_data: &str) -> Result<Vec<Vec<bool>>> {
+    anyhow::bail!("QR code support not compiled in. Enable the 'qr' feature.")
+}
+
+/// Render modules as half-block text, two rows per line.
+///
+/// Light modules are drawn and dark ones left blank, so the code reads
+/// correctly on the usual light-on-dark terminal.
+pub fn render_half_blocks(matrix: &[Vec<bool>]) -> Vec<String> {
+    let size = matrix.len() + 2 * QUIET_ZONE;
+    let light = |row: usize, col: usize| {
+        let (Some(r), Some(c)) = (row.checked_sub(QUIET_ZONE), col.checked_sub(QUIET_ZONE)) else {
+            return true;
+        };
+        !matrix.get(r).and_then(|cells| cells.get(c)).copied().unwrap_or(false)
+    };
+
+    (0..size)
+        .step_by(2)
+        .map(|row| {
+            (0..size)
+                .map(|col| match (light(row, col), row + 1 < size && light(row + 1, col)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt() -> DeviceCodePrompt {
+        DeviceCodePrompt {
+            provider: "github".to_string(),
+            user_code: "ABCD-EFGH".to_string(),
+            verification_uri: "https://github.com/login/device".to_string(),
+            complete_uri: None,
+        }
+    }
+
+    #[test]
+    fn test_small_terminal_falls_back_to_url() {
+        assert_eq!(
+            prompt().text(20, 5),
+            "Visit https://github.com/login/device and enter code ABCD-EFGH"
+        );
+    }
+
+    #[test]
+    fn test_half_blocks_pair_rows() {
+        let lines = render_half_blocks(&[vec![true, false], vec![false, true]]);
+        // 2 modules + quiet zone on each side, two rows per line
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "██▄▀██");
+        assert!(lines.iter().all(|l| l.chars().count() == 6));
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn test_qr_matrix_for_known_url() {
+        let matrix = qr_matrix("https://github.com/login/device?user_code=ABCD-EFGH").unwrap();
+        assert!(!matrix.is_empty());
+        assert!(matrix.iter().all(|row| row.len() == matrix.len()));
+        assert!(matrix.iter().flatten().any(|dark| *dark));
+
+        let text = prompt().text(80, 40);
+        assert!(text.lines().count() > 1);
+        assert!(text.ends_with("enter code ABCD-EFGH"));
+    }
+}
@@ -1,11 +1,521 @@
 //! Layout management
 
+use anyhow::Result;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+use crate::utils::config::{LayoutConfig, LayoutPresetConfig};
+
+/// Smallest share of a split any pane can be resized down to (percent)
+pub const MIN_PANE_PERCENT: u16 = 10;
+
+/// Name of the built-in two-by-two preset
+pub const DASHBOARD_PRESET: &str = "dashboard";
+
+/// Every known pane, in the built-in dashboard's default drawing order
+pub const DASHBOARD_PANES: [&str; 4] = ["shell", "agent", "preview", "log"];
+
+/// Panes per row of the built-in dashboard
+const PANES_PER_ROW: usize = 2;
+
+/// Panes laid out along one direction, sized by percentage
+#[derive(Debug, Clone, PartialEq)]
+pub struct Split {
+    pub direction: Direction,
+    pub ratios: Vec<u16>,
+}
+
+impl Split {
+    pub fn new(direction: Direction, ratios: Vec<u16>) -> Self {
+        Split { direction, ratios }
+    }
+
+    /// Divide `area` into one rect per pane
+    pub fn areas(&self, area: Rect) -> Vec<Rect> {
+        Layout::default()
+            .direction(self.direction)
+            .constraints(self.ratios.iter().map(|r| Constraint::Percentage(*r)))
+            .split(area)
+            .to_vec()
+    }
+
+    /// Move the boundary after pane `boundary` by `delta` cells out of `total` cells.
+    /// Returns false if nothing changed.
+    pub fn move_boundary(&mut self, boundary: usize, delta: i32, total: u16) -> bool {
+        if total == 0 || boundary + 1 >= self.ratios.len() {
+            return false;
+        }
+
+        let before = self.ratios[boundary] as i32;
+        let after = self.ratios[boundary + 1] as i32;
+        let min = MIN_PANE_PERCENT as i32;
+
+        // Convert cells to percent, rounding to the nearest whole percent
+        let percent = (delta * 200 + total as i32 * delta.signum()) / (total as i32 * 2);
+        let percent = percent.clamp(min - before, after - min);
+        if percent == 0 {
+            return false;
+        }
+
+        self.ratios[boundary] = (before + percent) as u16;
+        self.ratios[boundary + 1] = (after - percent) as u16;
+        true
+    }
+
+    /// Index of the boundary under `(col, row)` in `area`, if any
+    fn boundary_at(&self, area: Rect, col: u16, row: u16) -> Option<usize> {
+        let rects = self.areas(area);
+        rects.windows(2).position(|pair| {
+            let (first, second) = (pair[0], pair[1]);
+            match self.direction {
+                Direction::Horizontal => {
+                    (col + 1 == first.right() || col == second.x)
+                        && row >= area.y
+                        && row < area.bottom()
+                }
+                Direction::Vertical => {
+                    (row + 1 == first.bottom() || row == second.y)
+                        && col >= area.x
+                        && col < area.right()
+                }
+            }
+        })
+    }
+
+    /// Move `boundary` so it sits at the given cell along the split direction
+    fn drag_boundary(&mut self, area: Rect, boundary: usize, col: u16, row: u16) -> bool {
+        let rects = self.areas(area);
+        let Some(next) = rects.get(boundary + 1) else {
+            return false;
+        };
+        let (delta, total) = match self.direction {
+            Direction::Horizontal => (col as i32 - next.x as i32, area.width),
+            Direction::Vertical => (row as i32 - next.y as i32, area.height),
+        };
+        self.move_boundary(boundary, delta, total)
+    }
+}
+
+/// A named single-direction layout loaded from config
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutPreset {
+    pub name: String,
+    pub panes: Vec<String>,
+    pub split: Split,
+}
+
+impl LayoutPreset {
+    /// Validate a preset from config
+    pub fn from_config(config: &LayoutPresetConfig) -> Result<Self> {
+        if config.name == DASHBOARD_PRESET {
+            anyhow::bail!("'{}' is a built-in preset name", DASHBOARD_PRESET);
+        }
+        if config.panes.is_empty() {
+            anyhow::bail!("no panes listed");
+        }
+        if let Some(pane) = config.panes.iter().find(|p| !DASHBOARD_PANES.contains(&p.as_str())) {
+            anyhow::bail!("unknown pane '{}'", pane);
+        }
+
+        let direction = match config.direction.as_str() {
+            "horizontal" => Direction::Horizontal,
+            "vertical" => Direction::Vertical,
+            other => anyhow::bail!("unknown direction '{}'", other),
+        };
+
+        let ratios = if config.ratios.is_empty() {
+            // Split evenly, giving any remainder to the last pane
+            let count = config.panes.len() as u16;
+            let mut ratios = vec![100 / count; count as usize];
+            ratios[count as usize - 1] += 100 % count;
+            ratios
+        } else {
+            if config.ratios.len() != config.panes.len() {
+                anyhow::bail!(
+                    "{} ratios given for {} panes",
+                    config.ratios.len(),
+                    config.panes.len()
+                );
+            }
+            let total: u32 = config.ratios.iter().map(|r| *r as u32).sum();
+            if total != 100 {
+                anyhow::bail!("ratios add up to {} instead of 100", total);
+            }
+            config.ratios.clone()
+        };
+
+        Ok(LayoutPreset {
+            name: config.name.clone(),
+            panes: config.panes.clone(),
+            split: Split::new(direction, ratios),
+        })
+    }
+}
+
+/// The dashboard splits that can be resized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitId {
+    /// Between the top and bottom rows
+    Rows,
+    /// Between the two panes of the top row
+    Top,
+    /// Between the two panes of the bottom row
+    Bottom,
+}
+
+/// Known panes among `names`, in order; unknown and repeated names are
+/// skipped with a warning
+fn known_panes(names: &[String]) -> Vec<&'static str> {
+    let mut panes: Vec<&'static str> = Vec::new();
+    for name in names {
+        match DASHBOARD_PANES.iter().find(|pane| **pane == name.as_str()) {
+            Some(pane) if panes.contains(pane) => tracing::warn!("Skipping repeated pane '{}'", name),
+            Some(pane) => panes.push(pane),
+            None => tracing::warn!(
+                "Skipping unknown pane '{}'; expected one of {}",
+                name,
+                DASHBOARD_PANES.join(", ")
+            ),
+        }
+    }
+    panes
+}
+
+/// Dashboard layout: the built-in rows of up to two panes each, or a named preset
 pub struct LayoutManager {
-    // Layout logic
+    /// Panes of the built-in dashboard, filling the top row first
+    dashboard_panes: Vec<&'static str>,
+    rows: Split,
+    top: Split,
+    bottom: Split,
+    presets: Vec<LayoutPreset>,
+    /// Index into `presets`; None while the built-in dashboard is shown
+    active: Option<usize>,
 }
 
 impl LayoutManager {
     pub fn new() -> Self {
-        LayoutManager {}
+        Self::from_ratios(&[60, 60, 50])
+    }
+
+    /// Build from the first-pane percentages of the rows, top and bottom splits
+    pub fn from_ratios(ratios: &[u16]) -> Self {
+        let two_way = |index: usize, default: u16| {
+            let first = ratios
+                .get(index)
+                .copied()
+                .unwrap_or(default)
+                .clamp(MIN_PANE_PERCENT, 100 - MIN_PANE_PERCENT);
+            vec![first, 100 - first]
+        };
+
+        LayoutManager {
+            dashboard_panes: DASHBOARD_PANES.to_vec(),
+            rows: Split::new(Direction::Vertical, two_way(0, 60)),
+            top: Split::new(Direction::Horizontal, two_way(1, 60)),
+            bottom: Split::new(Direction::Horizontal, two_way(2, 50)),
+            presets: Vec::new(),
+            active: None,
+        }
+    }
+
+    /// Build from config, skipping unknown panes and invalid presets with a warning
+    pub fn from_config(config: &LayoutConfig) -> Self {
+        let mut manager = Self::from_ratios(&config.default.split_ratios);
+        let panes = known_panes(&config.default.panes);
+        if panes.is_empty() {
+            tracing::warn!("No known panes in layout.default.panes, showing all of them");
+        } else {
+            manager.dashboard_panes = panes;
+        }
+
+        for preset in &config.presets {
+            if manager.presets.iter().any(|p| p.name == preset.name) {
+                tracing::warn!("Skipping duplicate layout preset '{}'", preset.name);
+                continue;
+            }
+            match LayoutPreset::from_config(preset) {
+                Ok(preset) => manager.presets.push(preset),
+                Err(e) => tracing::warn!("Skipping invalid layout preset '{}': {}", preset.name, e),
+            }
+        }
+
+        if let Err(e) = manager.apply_preset(&config.default.preset) {
+            tracing::warn!("{}, using '{}'", e, DASHBOARD_PRESET);
+        }
+        manager
+    }
+
+    /// Switch to the named preset
+    pub fn apply_preset(&mut self, name: &str) -> Result<()> {
+        if name == DASHBOARD_PRESET {
+            self.active = None;
+            return Ok(());
+        }
+        match self.presets.iter().position(|p| p.name == name) {
+            Some(index) => {
+                self.active = Some(index);
+                Ok(())
+            }
+            None => anyhow::bail!("Unknown layout preset '{}'", name),
+        }
+    }
+
+    /// Name of the preset currently shown
+    pub fn active_preset(&self) -> &str {
+        match self.active {
+            Some(index) => &self.presets[index].name,
+            None => DASHBOARD_PRESET,
+        }
+    }
+
+    /// Names of all available presets, built-in first
+    pub fn preset_names(&self) -> Vec<&str> {
+        std::iter::once(DASHBOARD_PRESET)
+            .chain(self.presets.iter().map(|p| p.name.as_str()))
+            .collect()
+    }
+
+    /// Switch to the next preset, wrapping back to the dashboard; returns its name
+    pub fn cycle_preset(&mut self) -> &str {
+        self.active = match self.active {
+            None if !self.presets.is_empty() => Some(0),
+            Some(index) if index + 1 < self.presets.len() => Some(index + 1),
+            _ => None,
+        };
+        self.active_preset()
+    }
+
+    /// First-pane percentages of the rows, top and bottom splits (for persisting)
+    pub fn ratios(&self) -> Vec<u16> {
+        vec![self.rows.ratios[0], self.top.ratios[0], self.bottom.ratios[0]]
+    }
+
+    /// Name and rect of each visible pane
+    pub fn panes(&self, area: Rect) -> Vec<(&str, Rect)> {
+        if let Some(index) = self.active {
+            let preset = &self.presets[index];
+            return preset
+                .panes
+                .iter()
+                .map(String::as_str)
+                .zip(preset.split.areas(area))
+                .collect();
+        }
+
+        let (top, bottom) = self.row_areas(area);
+        let mut rects = Self::row_rects(&self.top, top, self.top_panes().len());
+        if let Some(bottom) = bottom {
+            rects.extend(Self::row_rects(&self.bottom, bottom, self.bottom_panes().len()));
+        }
+        self.dashboard_panes.iter().copied().zip(rects).collect()
+    }
+
+    fn top_panes(&self) -> &[&'static str] {
+        &self.dashboard_panes[..self.dashboard_panes.len().min(PANES_PER_ROW)]
+    }
+
+    fn bottom_panes(&self) -> &[&'static str] {
+        &self.dashboard_panes[self.top_panes().len()..]
+    }
+
+    /// Areas of the top and bottom rows; the top row takes everything when
+    /// the bottom one is empty
+    fn row_areas(&self, area: Rect) -> (Rect, Option<Rect>) {
+        if self.bottom_panes().is_empty() {
+            return (area, None);
+        }
+        let rows = self.rows.areas(area);
+        (rows[0], Some(rows[1]))
+    }
+
+    /// Rects of a row holding `count` panes; a lone pane fills the row
+    fn row_rects(split: &Split, area: Rect, count: usize) -> Vec<Rect> {
+        if count == 1 {
+            vec![area]
+        } else {
+            split.areas(area)
+        }
+    }
+
+    /// Whether the split separates two panes of the built-in dashboard
+    fn has_split(&self, id: SplitId) -> bool {
+        if self.active.is_some() {
+            return false;
+        }
+        match id {
+            SplitId::Rows => !self.bottom_panes().is_empty(),
+            SplitId::Top => self.top_panes().len() == PANES_PER_ROW,
+            SplitId::Bottom => self.bottom_panes().len() == PANES_PER_ROW,
+        }
+    }
+
+    /// Split whose boundary lies under `(col, row)`; presets are not resizable
+    pub fn split_at(&self, area: Rect, col: u16, row: u16) -> Option<SplitId> {
+        let (top, bottom) = self.row_areas(area);
+        [
+            (SplitId::Rows, &self.rows, Some(area)),
+            (SplitId::Top, &self.top, Some(top)),
+            (SplitId::Bottom, &self.bottom, bottom),
+        ]
+        .into_iter()
+        .find(|(id, split, split_area)| {
+            self.has_split(*id) && split_area.is_some_and(|a| split.boundary_at(a, col, row).is_some())
+        })
+        .map(|(id, _, _)| id)
+    }
+
+    /// Move a split's boundary to follow the mouse at `(col, row)`
+    pub fn drag_to(&mut self, id: SplitId, area: Rect, col: u16, row: u16) -> bool {
+        if !self.has_split(id) {
+            return false;
+        }
+        let (split, split_area) = self.split_mut(id, area);
+        split.drag_boundary(split_area, 0, col, row)
+    }
+
+    /// Move a split's boundary by `delta` steps (keyboard resize); a step is
+    /// one cell, or one percent on terminals wider than 100 cells
+    pub fn nudge(&mut self, id: SplitId, area: Rect, delta: i32) -> bool {
+        if !self.has_split(id) {
+            return false;
+        }
+        let (split, split_area) = self.split_mut(id, area);
+        let total = match split.direction {
+            Direction::Horizontal => split_area.width,
+            Direction::Vertical => split_area.height,
+        };
+        let step = (total as i32 + 99) / 100;
+        split.move_boundary(0, delta * step.max(1), total)
+    }
+
+    /// Only called for splits that exist, so the bottom row is present for `Bottom`
+    fn split_mut(&mut self, id: SplitId, area: Rect) -> (&mut Split, Rect) {
+        let (top, bottom) = self.row_areas(area);
+        match id {
+            SplitId::Rows => (&mut self.rows, area),
+            SplitId::Top => (&mut self.top, top),
+            SplitId::Bottom => (&mut self.bottom, bottom.unwrap_or(area)),
+        }
+    }
+}
+
+impl Default for LayoutManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_boundary_by_cells() {
+        let mut split = Split::new(Direction::Horizontal, vec![60, 40]);
+        assert!(split.move_boundary(0, 5, 100));
+        assert_eq!(split.ratios, vec![65, 35]);
+
+        // 5 cells out of 50 is 10 percent
+        let mut split = Split::new(Direction::Horizontal, vec![50, 50]);
+        assert!(split.move_boundary(0, -5, 50));
+        assert_eq!(split.ratios, vec![40, 60]);
+
+        // Less than half a percent is not a move
+        assert!(!split.move_boundary(0, 1, 1000));
+        assert_eq!(split.ratios, vec![40, 60]);
+    }
+
+    #[test]
+    fn test_move_boundary_respects_minimum() {
+        let mut split = Split::new(Direction::Vertical, vec![60, 40]);
+        assert!(split.move_boundary(0, 80, 100));
+        assert_eq!(split.ratios, vec![90, 10]);
+
+        assert!(!split.move_boundary(0, 10, 100));
+        assert!(!split.move_boundary(1, 10, 100));
+    }
+
+    #[test]
+    fn test_drag_moves_boundary_to_mouse() {
+        let area = Rect::new(0, 0, 100, 50);
+        let mut layout = LayoutManager::new();
+
+        // Top split boundary sits at column 60
+        assert_eq!(layout.split_at(area, 60, 5), Some(SplitId::Top));
+        assert_eq!(layout.split_at(area, 30, 5), None);
+
+        assert!(layout.drag_to(SplitId::Top, area, 70, 5));
+        assert_eq!(layout.ratios(), vec![60, 70, 50]);
+        assert_eq!(layout.panes(area)[1].1.x, 70);
+
+        assert!(layout.nudge(SplitId::Rows, area, -5));
+        assert_eq!(layout.ratios(), vec![50, 70, 50]);
+    }
+
+    #[test]
+    fn test_dashboard_uses_configured_panes() {
+        let area = Rect::new(0, 0, 100, 50);
+        let mut config = crate::utils::config::Config::default().layout;
+        config.default.panes = vec!["shell".to_string(), "terminal".to_string(), "log".to_string()];
+        let mut layout = LayoutManager::from_config(&config);
+
+        // Unknown "terminal" is skipped: one row of two panes, full height
+        let panes = layout.panes(area);
+        assert_eq!(panes, vec![("shell", Rect::new(0, 0, 60, 50)), ("log", Rect::new(60, 0, 40, 50))]);
+        assert_eq!(layout.split_at(area, 60, 30), Some(SplitId::Top));
+        assert!(!layout.nudge(SplitId::Rows, area, 1));
+        assert!(!layout.nudge(SplitId::Bottom, area, 1));
+
+        config.default.panes = vec!["log".to_string(), "agent".to_string(), "shell".to_string()];
+        layout = LayoutManager::from_config(&config);
+        let panes = layout.panes(area);
+        assert_eq!(panes.len(), 3);
+        assert_eq!(panes[2], ("shell", Rect::new(0, 30, 100, 20)));
+        assert!(layout.nudge(SplitId::Rows, area, 1));
+        assert_eq!(layout.split_at(area, 50, 40), None);
+
+        config.default.panes = vec!["nothing".to_string()];
+        assert_eq!(LayoutManager::from_config(&config).panes(area).len(), 4);
+    }
+
+    fn preset(name: &str, direction: &str, panes: &[&str], ratios: &[u16]) -> LayoutPresetConfig {
+        LayoutPresetConfig {
+            name: name.to_string(),
+            direction: direction.to_string(),
+            panes: panes.iter().map(|p| p.to_string()).collect(),
+            ratios: ratios.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_apply_presets() {
+        let mut config = crate::utils::config::Config::default().layout;
+        config.presets = vec![
+            preset("focus", "horizontal", &["shell"], &[]),
+            preset("full", "vertical", &["shell", "agent", "log"], &[60, 25, 15]),
+            preset("broken", "diagonal", &["shell"], &[]),
+            preset("typo", "vertical", &["shel"], &[]),
+        ];
+        let area = Rect::new(0, 0, 100, 50);
+        let mut layout = LayoutManager::from_config(&config);
+
+        assert_eq!(layout.preset_names(), vec!["dashboard", "focus", "full"]);
+        assert_eq!(layout.panes(area).len(), 4);
+
+        layout.apply_preset("focus").unwrap();
+        let panes = layout.panes(area);
+        assert_eq!(panes, vec![("shell", area)]);
+
+        layout.apply_preset("full").unwrap();
+        let panes = layout.panes(area);
+        assert_eq!(panes.len(), 3);
+        assert_eq!(panes[0].1.height, 30);
+        assert!(layout.split_at(area, 60, 5).is_none());
+
+        assert!(layout.apply_preset("broken").is_err());
+        assert_eq!(layout.active_preset(), "full");
+
+        assert_eq!(layout.cycle_preset(), "dashboard");
+        assert_eq!(layout.cycle_preset(), "focus");
     }
 }
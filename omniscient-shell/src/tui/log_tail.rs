@@ -0,0 +1,256 @@
+//! Follows the log file for the log pane, like `tail -F`
+//!
+//! The log is rotated by renaming it aside and starting a new file at the same
+//! path, or by truncating it in place. Both are detected on the next poll: the
+//! rest of the old file is read first, then the new one from the start.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::PathBuf;
+
+/// Lines kept in the log pane; older lines are dropped
+pub const MAX_LINES: usize = 1000;
+
+/// Log file written under `~/.omniscient/logs`
+pub fn default_log_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".omniscient").join("logs").join("omniscient.log")
+}
+
+/// Severity of a log line, least severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn parse(word: &str) -> Option<Self> {
+        match word.to_ascii_uppercase().as_str() {
+            "TRACE" => Some(LogLevel::Trace),
+            "DEBUG" => Some(LogLevel::Debug),
+            "INFO" => Some(LogLevel::Info),
+            "WARN" | "WARNING" => Some(LogLevel::Warn),
+            "ERROR" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+
+    /// Level of a formatted line such as `2024-05-01T10:00:00Z  WARN shell: ...`;
+    /// the level is one of the first few words
+    pub fn of_line(line: &str) -> Option<Self> {
+        line.split_whitespace().take(3).find_map(Self::parse)
+    }
+
+    /// The next level up, wrapping from Error back to Trace
+    pub fn next(self) -> Self {
+        match self {
+            LogLevel::Trace => LogLevel::Debug,
+            LogLevel::Debug => LogLevel::Info,
+            LogLevel::Info => LogLevel::Warn,
+            LogLevel::Warn => LogLevel::Error,
+            LogLevel::Error => LogLevel::Trace,
+        }
+    }
+}
+
+/// Identity of the file at a path, used to notice it was replaced
+#[cfg(unix)]
+fn file_id(meta: &std::fs::Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (meta.dev(), meta.ino())
+}
+
+// Without inodes, only truncation is detected
+#[cfg(not(unix))]
+fn file_id(_meta: &std::fs::Metadata) -> (u64, u64) {
+    (0, 0)
+}
+
+/// Reads lines appended to a log file at or above a minimum level
+pub struct LogTailer {
+    path: PathBuf,
+    reader: Option<BufReader<File>>,
+    file_id: (u64, u64),
+    offset: u64,
+    min_level: LogLevel,
+    /// Level of the last line seen, applied to continuation lines without one
+    last_level: Option<LogLevel>,
+    /// Text of a line still being written (no trailing newline yet)
+    partial: String,
+}
+
+impl LogTailer {
+    /// Follow `path` from its current end; lines already in the file are skipped
+    pub fn new(path: impl Into<PathBuf>, min_level: LogLevel) -> Self {
+        let mut tailer = LogTailer {
+            path: path.into(),
+            reader: None,
+            file_id: (0, 0),
+            offset: 0,
+            min_level,
+            last_level: None,
+            partial: String::new(),
+        };
+        if tailer.open() {
+            if let Some(reader) = &mut tailer.reader {
+                tailer.offset = reader.seek(SeekFrom::End(0)).unwrap_or(0);
+            }
+        }
+        tailer
+    }
+
+    /// Follow `path` from the start, including the lines already written
+    pub fn from_start(path: impl Into<PathBuf>, min_level: LogLevel) -> Self {
+        let mut tailer = LogTailer::new(path, min_level);
+        tailer.offset = 0;
+        if let Some(reader) = &mut tailer.reader {
+            let _ = reader.seek(SeekFrom::Start(0));
+        }
+        tailer
+    }
+
+    /// Only return lines at or above `level` from now on
+    pub fn set_min_level(&mut self, level: LogLevel) {
+        self.min_level = level;
+    }
+
+    /// Lines appended since the last poll, following the file across rotation
+    pub fn poll(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.reader.is_none() && !self.open() {
+            return lines;
+        }
+        self.read_available(&mut lines);
+
+        if self.rotated() {
+            tracing::debug!("Log file {} was rotated, reopening", self.path.display());
+            self.partial.clear();
+            if self.open() {
+                self.read_available(&mut lines);
+            }
+        }
+        lines
+    }
+
+    /// Open the file at `path` from the start; false if it doesn't exist yet
+    fn open(&mut self) -> bool {
+        self.reader = None;
+        self.offset = 0;
+        let Ok(file) = File::open(&self.path) else {
+            return false;
+        };
+        self.file_id = file.metadata().map(|meta| file_id(&meta)).unwrap_or_default();
+        self.reader = Some(BufReader::new(file));
+        true
+    }
+
+    /// Whether `path` now names a different file, or ours was truncated
+    fn rotated(&self) -> bool {
+        match std::fs::metadata(&self.path) {
+            Ok(meta) => file_id(&meta) != self.file_id || meta.len() < self.offset,
+            // Renamed aside and not recreated yet; keep reading the old file
+            Err(_) => false,
+        }
+    }
+
+    fn read_available(&mut self, lines: &mut Vec<String>) {
+        let Some(reader) = &mut self.reader else {
+            return;
+        };
+        let mut buf = String::new();
+        loop {
+            buf.clear();
+            match reader.read_line(&mut buf) {
+                Ok(0) => break,
+                Ok(read) => {
+                    self.offset += read as u64;
+                    if !buf.ends_with('\n') {
+                        self.partial.push_str(&buf);
+                        break;
+                    }
+                    let mut line = std::mem::take(&mut self.partial);
+                    line.push_str(buf.trim_end_matches(['\r', '\n']));
+                    let level = LogLevel::of_line(&line).or(self.last_level);
+                    self.last_level = level;
+                    if level.is_none_or(|level| level >= self.min_level) {
+                        lines.push(line);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to read {}: {}", self.path.display(), e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn append(path: &Path, text: &str) {
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_picks_up_appended_lines_and_filters_levels() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("omniscient.log");
+        append(&path, "2024-05-01T10:00:00Z  INFO shell: old line\n");
+
+        let mut tailer = LogTailer::new(&path, LogLevel::Info);
+        assert!(tailer.poll().is_empty());
+
+        append(&path, "2024-05-01T10:00:01Z  INFO shell: started\n");
+        append(&path, "2024-05-01T10:00:02Z DEBUG shell: noisy\n");
+        append(&path, "2024-05-01T10:00:03Z  WARN shell: slow render\n  at pane 2\n");
+        append(&path, "2024-05-01T10:00:04Z ERROR shell: half");
+        assert_eq!(
+            tailer.poll(),
+            vec![
+                "2024-05-01T10:00:01Z  INFO shell: started",
+                "2024-05-01T10:00:03Z  WARN shell: slow render",
+                "  at pane 2",
+            ]
+        );
+
+        // The unfinished line is returned once it is complete
+        append(&path, " written\n");
+        assert_eq!(tailer.poll(), vec!["2024-05-01T10:00:04Z ERROR shell: half written"]);
+
+        tailer.set_min_level(LogLevel::Error);
+        append(&path, "2024-05-01T10:00:05Z  WARN shell: hidden\n");
+        assert!(tailer.poll().is_empty());
+    }
+
+    #[test]
+    fn test_follows_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("omniscient.log");
+        let mut tailer = LogTailer::new(&path, LogLevel::Trace);
+        // Missing files are picked up once created
+        assert!(tailer.poll().is_empty());
+        append(&path, "INFO first\n");
+        assert_eq!(tailer.poll(), vec!["INFO first"]);
+
+        // Rename aside: the tail of the old file comes before the new file
+        append(&path, "INFO last in old\n");
+        std::fs::rename(&path, dir.path().join("omniscient.log.1")).unwrap();
+        append(&path, "INFO first in new\n");
+        assert_eq!(tailer.poll(), vec!["INFO last in old", "INFO first in new"]);
+
+        // Truncation in place
+        std::fs::write(&path, "").unwrap();
+        assert!(tailer.poll().is_empty());
+        append(&path, "WARN after truncate\n");
+        assert_eq!(tailer.poll(), vec!["WARN after truncate"]);
+    }
+}
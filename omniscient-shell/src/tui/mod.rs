@@ -6,6 +6,12 @@ pub mod cards;
 pub mod theme;
 pub mod layout;
 pub mod command_palette;
+pub mod capability_review;
+pub mod device_code;
+pub mod resize;
+pub mod log_tail;
+pub mod vault_rotate;
+pub mod shell_pane;
 
 pub use dashboard::Dashboard;
-pub use command_palette::{CommandPalette, Command, CommandHandler};
+pub use command_palette::{CommandPalette, Command, CommandHandler, HistoryCursor};
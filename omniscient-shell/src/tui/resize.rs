@@ -0,0 +1,93 @@
+//! Terminal resize detection that doesn't depend on crossterm's resize events
+//!
+//! Some multiplexers (tmux in particular) don't always deliver a resize event,
+//! so panes stay at the old size. On Unix we listen for SIGWINCH directly; on
+//! Windows we poll the console size.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// How often the console size is polled where there is no resize signal
+#[cfg(not(unix))]
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Flags that the terminal may have been resized; the UI loop re-queries the size
+pub struct ResizeWatcher {
+    pending: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl ResizeWatcher {
+    /// Start watching; must be called within a tokio runtime
+    pub fn spawn() -> std::io::Result<Self> {
+        let pending = Arc::new(AtomicBool::new(false));
+        let handle = Self::watch(pending.clone())?;
+        Ok(ResizeWatcher { pending, handle })
+    }
+
+    #[cfg(unix)]
+    fn watch(pending: Arc<AtomicBool>) -> std::io::Result<JoinHandle<()>> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut winch = signal(SignalKind::window_change())?;
+        Ok(tokio::spawn(async move {
+            while winch.recv().await.is_some() {
+                pending.store(true, Ordering::SeqCst);
+            }
+        }))
+    }
+
+    #[cfg(not(unix))]
+    fn watch(pending: Arc<AtomicBool>) -> std::io::Result<JoinHandle<()>> {
+        let mut last = crossterm::terminal::size()?;
+        Ok(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if let Ok(size) = crossterm::terminal::size() {
+                    if size != last {
+                        last = size;
+                        pending.store(true, Ordering::SeqCst);
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Whether a resize was seen since the last call
+    pub fn take_pending(&self) -> bool {
+        self.pending.swap(false, Ordering::SeqCst)
+    }
+}
+
+impl Drop for ResizeWatcher {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use nix::sys::signal::{raise, Signal};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_sigwinch_marks_resize_pending() {
+        let watcher = ResizeWatcher::spawn().unwrap();
+        assert!(!watcher.take_pending());
+
+        raise(Signal::SIGWINCH).unwrap();
+        let mut seen = false;
+        for _ in 0..100 {
+            if watcher.take_pending() {
+                seen = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(seen);
+        assert!(!watcher.take_pending());
+    }
+}
@@ -0,0 +1,308 @@
+//! Shell pane: an input line for PowerShell commands above their output
+//!
+//! Output is kept in a bounded scrollback so a chatty command can't grow
+//! memory without limit; the oldest lines are dropped first. Up/Down recall
+//! earlier commands and Ctrl+R searches them.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::VecDeque;
+
+use crate::shell::history::{History, DEFAULT_MAX_SIZE};
+use crate::tui::command_palette::HistoryCursor;
+
+/// Lines of output kept for scrolling back
+pub const MAX_LINES: usize = 1000;
+
+/// Prompt shown before the input line and echoed commands
+pub const PROMPT: &str = "PS> ";
+
+/// What the dashboard should do after a key in the shell pane
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellAction {
+    /// The key wasn't for the shell pane
+    Ignored,
+    None,
+    /// Run the entered command line
+    Submit(String),
+}
+
+/// Input line and scrollback of the shell pane
+#[derive(Debug)]
+pub struct ShellPane {
+    input: String,
+    lines: VecDeque<String>,
+    /// Lines scrolled up from the newest output
+    scroll: usize,
+    history: History,
+    /// Up/Down position in the history; None while typing a fresh line
+    recall: Option<HistoryCursor>,
+    /// Line being typed when Up started recalling, restored by Down
+    draft: String,
+    /// Open Ctrl+R search
+    search: Option<HistorySearch>,
+}
+
+/// Incremental history search: the query and which match is shown
+#[derive(Debug, Default)]
+struct HistorySearch {
+    query: String,
+    selected: usize,
+}
+
+impl ShellPane {
+    pub fn new() -> Self {
+        ShellPane {
+            input: String::new(),
+            lines: VecDeque::new(),
+            scroll: 0,
+            history: History::new(DEFAULT_MAX_SIZE),
+            recall: None,
+            draft: String::new(),
+            search: None,
+        }
+    }
+
+    /// Recall and search `commands` (oldest first), e.g. those of earlier sessions
+    pub fn set_history(&mut self, commands: Vec<String>, max_size: usize) {
+        self.history = History::from_commands(commands, max_size);
+        self.recall = None;
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Output lines, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    /// Edit the input line; typed characters, Backspace, Enter, Up/Down,
+    /// PageUp/PageDown and Ctrl+R belong to the pane, anything else
+    /// (including other Ctrl chords) is ignored
+    pub fn handle_key(&mut self, key: KeyEvent) -> ShellAction {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if self.search.is_some() {
+            if let Some(action) = self.handle_search_key(key) {
+                return action;
+            }
+        }
+        if ctrl && key.code == KeyCode::Char('r') {
+            self.search = Some(HistorySearch::default());
+            return ShellAction::None;
+        }
+        if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return ShellAction::Ignored;
+        }
+        match key.code {
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.recall = None;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.recall = None;
+            }
+            KeyCode::Enter => {
+                let command = std::mem::take(&mut self.input);
+                return self.submit(command);
+            }
+            KeyCode::Up => {
+                let cursor = self.recall.get_or_insert_with(|| {
+                    self.draft = self.input.clone();
+                    HistoryCursor::new(self.history.get_all())
+                });
+                if let Some(command) = cursor.up() {
+                    self.input = command.to_string();
+                }
+            }
+            KeyCode::Down => {
+                if let Some(cursor) = &mut self.recall {
+                    match cursor.down().map(str::to_string) {
+                        Some(command) => self.input = command,
+                        None => {
+                            self.recall = None;
+                            self.input = std::mem::take(&mut self.draft);
+                        }
+                    }
+                }
+            }
+            KeyCode::PageUp => self.scroll = (self.scroll + 10).min(self.lines.len().saturating_sub(1)),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(10),
+            _ => return ShellAction::Ignored,
+        }
+        ShellAction::None
+    }
+
+    /// Keys while searching: typing refines the query, Ctrl+R steps to the
+    /// next older match, Enter runs the match and Esc goes back to the input
+    /// line. Any other key takes the match into the input line and is then
+    /// handled as usual (returns None).
+    fn handle_search_key(&mut self, key: KeyEvent) -> Option<ShellAction> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let search = self.search.as_mut()?;
+        match key.code {
+            KeyCode::Char('r') if ctrl => {
+                let matches = self.history.search(&search.query).len();
+                search.selected = (search.selected + 1).min(matches.saturating_sub(1));
+            }
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                search.query.push(c);
+                search.selected = 0;
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.selected = 0;
+            }
+            KeyCode::Esc => self.search = None,
+            KeyCode::Enter => {
+                let command = self.search_match().unwrap_or_default().to_string();
+                self.search = None;
+                return Some(self.submit(command));
+            }
+            _ => {
+                if let Some(command) = self.search_match() {
+                    self.input = command.to_string();
+                }
+                self.search = None;
+                self.recall = None;
+                return None;
+            }
+        }
+        Some(ShellAction::None)
+    }
+
+    /// History entry shown by the open search
+    fn search_match(&self) -> Option<&str> {
+        let search = self.search.as_ref()?;
+        self.history.search(&search.query).get(search.selected).copied()
+    }
+
+    /// Echo `command` and hand it to the dashboard to run
+    fn submit(&mut self, command: String) -> ShellAction {
+        self.recall = None;
+        if command.trim().is_empty() {
+            return ShellAction::None;
+        }
+        self.push_output(&format!("{}{}", PROMPT, command));
+        let _ = self.history.add(command.trim());
+        ShellAction::Submit(command.trim().to_string())
+    }
+
+    /// Append output, dropping the oldest lines past `MAX_LINES`; jumps back
+    /// to the newest output
+    pub fn push_output(&mut self, text: &str) {
+        self.lines.extend(text.trim_end().lines().map(str::to_string));
+        let excess = self.lines.len().saturating_sub(MAX_LINES);
+        self.lines.drain(..excess);
+        self.scroll = 0;
+    }
+
+    /// Text for a pane `height` rows tall inside its border: as much output
+    /// as fits above the input line
+    pub fn render_text(&self, height: usize) -> String {
+        let visible = height.saturating_sub(1);
+        let end = self.lines.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(visible);
+        let mut text: Vec<&str> = self.lines.range(start..end).map(String::as_str).collect();
+        let input = match &self.search {
+            Some(search) => {
+                format!("(history search) '{}': {}_", search.query, self.search_match().unwrap_or_default())
+            }
+            None => format!("{}{}_", PROMPT, self.input),
+        };
+        text.push(&input);
+        text.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_line(pane: &mut ShellPane, line: &str) -> ShellAction {
+        for c in line.chars() {
+            pane.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        pane.handle_key(KeyEvent::from(KeyCode::Enter))
+    }
+
+    #[test]
+    fn test_enter_submits_typed_line() {
+        let mut pane = ShellPane::new();
+        pane.handle_key(KeyEvent::from(KeyCode::Char('x')));
+        pane.handle_key(KeyEvent::from(KeyCode::Backspace));
+        assert_eq!(type_line(&mut pane, "Get-Date"), ShellAction::Submit("Get-Date".to_string()));
+        assert_eq!(pane.input(), "");
+        assert_eq!(pane.lines().collect::<Vec<_>>(), vec!["PS> Get-Date"]);
+
+        assert_eq!(type_line(&mut pane, "  "), ShellAction::None);
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(pane.handle_key(ctrl_c), ShellAction::Ignored);
+        assert_eq!(pane.handle_key(KeyEvent::from(KeyCode::Tab)), ShellAction::Ignored);
+    }
+
+    #[test]
+    fn test_scrollback_is_bounded() {
+        let mut pane = ShellPane::new();
+        let output: Vec<String> = (0..MAX_LINES + 5).map(|i| i.to_string()).collect();
+        pane.push_output(&output.join("\n"));
+        assert_eq!(pane.lines().count(), MAX_LINES);
+        assert_eq!(pane.lines().next(), Some("5"));
+
+        let newest = (MAX_LINES + 4).to_string();
+        assert_eq!(pane.render_text(3), format!("{}\n{}\nPS> _", MAX_LINES + 3, newest));
+        pane.handle_key(KeyEvent::from(KeyCode::PageUp));
+        assert!(!pane.render_text(3).contains(&newest));
+        pane.handle_key(KeyEvent::from(KeyCode::PageDown));
+        assert!(pane.render_text(3).contains(&newest));
+    }
+
+    #[test]
+    fn test_up_down_recall_previous_commands() {
+        let mut pane = ShellPane::new();
+        pane.set_history(vec!["Get-Date".to_string()], 10);
+        type_line(&mut pane, "Get-Process");
+        for c in "dra".chars() {
+            pane.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+
+        pane.handle_key(KeyEvent::from(KeyCode::Up));
+        assert_eq!(pane.input(), "Get-Process");
+        pane.handle_key(KeyEvent::from(KeyCode::Up));
+        pane.handle_key(KeyEvent::from(KeyCode::Up));
+        assert_eq!(pane.input(), "Get-Date");
+        pane.handle_key(KeyEvent::from(KeyCode::Down));
+        assert_eq!(pane.input(), "Get-Process");
+        pane.handle_key(KeyEvent::from(KeyCode::Down));
+        assert_eq!(pane.input(), "dra");
+    }
+
+    #[test]
+    fn test_ctrl_r_searches_history() {
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        let mut pane = ShellPane::new();
+        let commands = ["Get-ChildItem", "git log", "Get-Date"];
+        pane.set_history(commands.iter().map(|c| c.to_string()).collect(), 10);
+
+        pane.handle_key(ctrl_r);
+        for c in "GET".chars() {
+            pane.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        assert_eq!(pane.render_text(1), "(history search) 'GET': Get-Date_");
+        pane.handle_key(ctrl_r);
+        pane.handle_key(ctrl_r);
+        assert!(pane.render_text(1).ends_with(": Get-ChildItem_"));
+        assert_eq!(pane.handle_key(KeyEvent::from(KeyCode::Enter)), ShellAction::Submit("Get-ChildItem".to_string()));
+
+        pane.handle_key(ctrl_r);
+        pane.handle_key(KeyEvent::from(KeyCode::Char('l')));
+        assert_eq!(pane.handle_key(KeyEvent::from(KeyCode::Esc)), ShellAction::None);
+        assert_eq!(pane.input(), "");
+        pane.handle_key(ctrl_r);
+        pane.handle_key(KeyEvent::from(KeyCode::Char('l')));
+        pane.handle_key(KeyEvent::from(KeyCode::Right));
+        assert_eq!(pane.input(), "git log");
+        assert_eq!(pane.render_text(1), "PS> git log_");
+    }
+}
@@ -1,7 +1,12 @@
 //! Theme system for consistent styling
 
+use anyhow::{Context, Result};
 use ratatui::style::Color;
-use crate::utils::config::ThemeConfig;
+use std::path::Path;
+use crate::utils::config::{combine_errors, parse_hex_color, ThemeConfig};
+
+/// Minimum WCAG AA contrast ratio for normal-size text
+pub const MIN_CONTRAST_RATIO: f32 = 4.5;
 
 pub struct Theme {
     pub name: String,
@@ -28,21 +33,139 @@ impl Theme {
             accent: Color::Rgb(0, 209, 255),
         }
     }
-}
 
-fn parse_color(hex: &str) -> Color {
-    // Simple hex color parser
-    let hex = hex.trim_start_matches('#');
-    if hex.len() == 6 {
-        if let (Ok(r), Ok(g), Ok(b)) = (
-            u8::from_str_radix(&hex[0..2], 16),
-            u8::from_str_radix(&hex[2..4], 16),
-            u8::from_str_radix(&hex[4..6], 16),
-        ) {
-            return Color::Rgb(r, g, b);
+    /// WCAG contrast ratio between two colors, from 1.0 (none) to 21.0.
+    ///
+    /// `Color::Reset` leaves the choice to the terminal, so it can't be judged
+    /// and is treated as full contrast.
+    pub fn contrast_ratio(fg: Color, bg: Color) -> f32 {
+        match (relative_luminance(fg), relative_luminance(bg)) {
+            (Some(a), Some(b)) => (a.max(b) + 0.05) / (a.min(b) + 0.05),
+            _ => 21.0,
+        }
+    }
+
+    /// Check text colors against the background.
+    ///
+    /// Pairs below `MIN_CONTRAST_RATIO` are logged as warnings, or rejected
+    /// when `strict` is set.
+    pub fn validate_accessibility(&self, strict: bool) -> Result<()> {
+        let problems: Vec<String> = [("foreground", self.foreground), ("accent", self.accent)]
+            .into_iter()
+            .filter_map(|(role, color)| {
+                let ratio = Self::contrast_ratio(color, self.background);
+                (ratio < MIN_CONTRAST_RATIO).then(|| {
+                    format!("{} contrast {:.2}:1 is below {}:1", role, ratio, MIN_CONTRAST_RATIO)
+                })
+            })
+            .collect();
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        if strict {
+            anyhow::bail!("Theme '{}' is not accessible: {}", self.name, problems.join("; "));
         }
+        for problem in &problems {
+            tracing::warn!("Theme '{}': {}", self.name, problem);
+        }
+        Ok(())
     }
-    Color::White
+}
+
+/// sRGB value of a color, using xterm's defaults for the palette colors
+fn to_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let rgb = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black => (0, 0, 0),
+        Color::Red => (205, 0, 0),
+        Color::Green => (0, 205, 0),
+        Color::Yellow => (205, 205, 0),
+        Color::Blue => (0, 0, 238),
+        Color::Magenta => (205, 0, 205),
+        Color::Cyan => (0, 205, 205),
+        Color::Gray => (229, 229, 229),
+        Color::DarkGray => (127, 127, 127),
+        Color::LightRed => (255, 0, 0),
+        Color::LightGreen => (0, 255, 0),
+        Color::LightYellow => (255, 255, 0),
+        Color::LightBlue => (92, 92, 255),
+        Color::LightMagenta => (255, 0, 255),
+        Color::LightCyan => (0, 255, 255),
+        Color::White => (255, 255, 255),
+        Color::Indexed(i @ 0..=15) => {
+            const BASE: [Color; 16] = [
+                Color::Black, Color::Red, Color::Green, Color::Yellow,
+                Color::Blue, Color::Magenta, Color::Cyan, Color::Gray,
+                Color::DarkGray, Color::LightRed, Color::LightGreen, Color::LightYellow,
+                Color::LightBlue, Color::LightMagenta, Color::LightCyan, Color::White,
+            ];
+            return to_rgb(BASE[i as usize]);
+        }
+        Color::Indexed(i @ 16..=231) => {
+            let i = (i - 16) as usize;
+            (CUBE[i / 36], CUBE[(i / 6) % 6], CUBE[i % 6])
+        }
+        Color::Indexed(i) => {
+            let level = 8 + (i - 232) * 10;
+            (level, level, level)
+        }
+    };
+    Some(rgb)
+}
+
+/// WCAG relative luminance, 0.0 for black to 1.0 for white
+fn relative_luminance(color: Color) -> Option<f32> {
+    let (r, g, b) = to_rgb(color)?;
+    let channel = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    Some(0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b))
+}
+
+/// Names accepted by `--theme`
+pub const BUILTIN_THEMES: &[&str] = &["NeoCyan"];
+
+/// Resolve a `--theme` argument: a built-in theme name, or the path of a TOML
+/// file with `name`, `background`, `foreground` and `accent` keys
+pub fn resolve_theme_config(spec: &str) -> Result<ThemeConfig> {
+    let path = Path::new(spec);
+    let config = if path.is_file() {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read theme file: {}", path.display()))?;
+        toml::from_str::<ThemeConfig>(&contents)
+            .with_context(|| format!("Failed to parse theme file: {}", path.display()))?
+    } else {
+        match spec.to_lowercase().as_str() {
+            "neocyan" | "neo-cyan" => ThemeConfig {
+                name: "NeoCyan".to_string(),
+                background: "#0b0e10".to_string(),
+                foreground: "#c9d1d9".to_string(),
+                accent: "#00d1ff".to_string(),
+                strict_contrast: false,
+            },
+            _ => anyhow::bail!(
+                "Unknown theme '{}': expected one of {} or a theme file path",
+                spec,
+                BUILTIN_THEMES.join(", ")
+            ),
+        }
+    };
+
+    config.validate().map_err(combine_errors)?;
+    Ok(config)
+}
+
+/// Colors are validated when the config is loaded; anything else falls back to white
+fn parse_color(hex: &str) -> Color {
+    parse_hex_color(hex).map_or(Color::White, |(r, g, b)| Color::Rgb(r, g, b))
 }
 
 impl Default for Theme {
@@ -50,3 +173,51 @@ impl Default for Theme {
         Theme::neo_cyan()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_black_on_white_is_maximum_contrast() {
+        let ratio = Theme::contrast_ratio(Color::Black, Color::White);
+        assert!((ratio - 21.0).abs() < 0.01, "ratio was {}", ratio);
+        assert_eq!(ratio, Theme::contrast_ratio(Color::White, Color::Black));
+    }
+
+    #[test]
+    fn test_low_contrast_theme_is_flagged() {
+        assert!(Theme::neo_cyan().validate_accessibility(true).is_ok());
+
+        let theme = Theme {
+            name: "Murky".to_string(),
+            background: Color::Rgb(40, 40, 40),
+            foreground: Color::Rgb(90, 90, 90),
+            accent: Color::Rgb(0, 209, 255),
+        };
+        assert!(Theme::contrast_ratio(theme.foreground, theme.background) < MIN_CONTRAST_RATIO);
+        let err = theme.validate_accessibility(true).unwrap_err().to_string();
+        assert!(err.contains("foreground"));
+        assert!(!err.contains("accent"));
+        assert!(theme.validate_accessibility(false).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_theme_by_name_or_file() {
+        assert_eq!(resolve_theme_config("neocyan").unwrap().name, "NeoCyan");
+        assert!(resolve_theme_config("no-such-theme").is_err());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("paper.toml");
+        std::fs::write(
+            &path,
+            "name = \"Paper\"\nbackground = \"#ffffff\"\nforeground = \"#1f2328\"\naccent = \"#0969da\"\n",
+        )
+        .unwrap();
+        assert_eq!(resolve_theme_config(path.to_str().unwrap()).unwrap().name, "Paper");
+
+        std::fs::write(&path, "name = \"Bad\"\nbackground = \"white\"\nforeground = \"#000000\"\naccent = \"#000000\"\n").unwrap();
+        let err = resolve_theme_config(path.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("background"));
+    }
+}
@@ -0,0 +1,281 @@
+//! `vault:rotate` dialog: asks for the current and a new passphrase, then
+//! shows progress while the vault re-encrypts its tokens
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::tui::theme::Theme;
+
+/// Where a rotation started from the dialog has got to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RotateStatus {
+    /// Submitted, waiting for the vault
+    Pending,
+    Running { done: usize, total: usize },
+    /// Finished; the number of tokens re-encrypted
+    Done(usize),
+    Failed(String),
+}
+
+/// Shared between the dialog and the task running the rotation
+#[derive(Debug, Clone)]
+pub struct RotateProgress(Arc<Mutex<RotateStatus>>);
+
+impl Default for RotateProgress {
+    fn default() -> Self {
+        RotateProgress(Arc::new(Mutex::new(RotateStatus::Pending)))
+    }
+}
+
+impl RotateProgress {
+    pub fn update(&self, done: usize, total: usize) {
+        *self.0.lock().unwrap() = RotateStatus::Running { done, total };
+    }
+
+    pub fn finish(&self, result: anyhow::Result<usize>) {
+        *self.0.lock().unwrap() = match result {
+            Ok(count) => RotateStatus::Done(count),
+            Err(e) => RotateStatus::Failed(e.to_string()),
+        };
+    }
+
+    pub fn status(&self) -> RotateStatus {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Passphrases the user entered, handed to whoever owns the vault
+#[derive(Clone)]
+pub struct RotateRequest {
+    current: String,
+    new: String,
+    pub progress: RotateProgress,
+}
+
+impl RotateRequest {
+    pub fn current_passphrase(&self) -> &str {
+        &self.current
+    }
+
+    pub fn new_passphrase(&self) -> &str {
+        &self.new
+    }
+}
+
+// Keep the passphrases out of logs
+impl fmt::Debug for RotateRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RotateRequest").field("progress", &self.progress).finish_non_exhaustive()
+    }
+}
+
+/// What the dashboard should do after a key in the dialog
+#[derive(Debug, Clone)]
+pub enum RotateAction {
+    None,
+    Close,
+    Submit(RotateRequest),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Current,
+    New,
+    Confirm,
+}
+
+/// State of the rotate dialog
+pub struct VaultRotateDialog {
+    field: Field,
+    current: String,
+    new: String,
+    confirm: String,
+    /// Problem with the last entry, e.g. passphrases that don't match
+    error: Option<String>,
+    /// Set once the request is submitted
+    progress: Option<RotateProgress>,
+}
+
+impl Default for VaultRotateDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VaultRotateDialog {
+    pub fn new() -> Self {
+        VaultRotateDialog {
+            field: Field::Current,
+            current: String::new(),
+            new: String::new(),
+            confirm: String::new(),
+            error: None,
+            progress: None,
+        }
+    }
+
+    /// Status of the submitted rotation, if any
+    pub fn status(&self) -> Option<RotateStatus> {
+        self.progress.as_ref().map(RotateProgress::status)
+    }
+
+    fn input(&mut self) -> &mut String {
+        match self.field {
+            Field::Current => &mut self.current,
+            Field::New => &mut self.new,
+            Field::Confirm => &mut self.confirm,
+        }
+    }
+
+    /// Move to the next field, submitting after the confirmation
+    fn advance(&mut self) -> RotateAction {
+        self.error = None;
+        match self.field {
+            Field::Current if self.current.is_empty() => {
+                self.error = Some("Enter the current passphrase".to_string());
+            }
+            Field::Current => self.field = Field::New,
+            Field::New if self.new.is_empty() => {
+                self.error = Some("The new passphrase can't be empty".to_string());
+            }
+            Field::New => self.field = Field::Confirm,
+            Field::Confirm if self.confirm != self.new => {
+                self.error = Some("Passphrases don't match".to_string());
+                self.new.clear();
+                self.confirm.clear();
+                self.field = Field::New;
+            }
+            Field::Confirm => {
+                let progress = RotateProgress::default();
+                self.progress = Some(progress.clone());
+                self.confirm.clear();
+                return RotateAction::Submit(RotateRequest {
+                    current: std::mem::take(&mut self.current),
+                    new: std::mem::take(&mut self.new),
+                    progress,
+                });
+            }
+        }
+        RotateAction::None
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> RotateAction {
+        if let Some(status) = self.status() {
+            // The rotation can't be interrupted; close once it's over
+            return match (status, key.code) {
+                (RotateStatus::Done(_) | RotateStatus::Failed(_), KeyCode::Enter | KeyCode::Esc) => {
+                    RotateAction::Close
+                }
+                _ => RotateAction::None,
+            };
+        }
+        match key.code {
+            KeyCode::Esc => return RotateAction::Close,
+            KeyCode::Enter => return self.advance(),
+            KeyCode::Backspace => {
+                self.input().pop();
+            }
+            KeyCode::Char(c) => self.input().push(c),
+            _ => {}
+        }
+        RotateAction::None
+    }
+
+    fn text(&self) -> String {
+        if let Some(status) = self.status() {
+            return match status {
+                RotateStatus::Pending => "Checking passphrase...".to_string(),
+                RotateStatus::Running { done, total } => {
+                    let percent = (done * 100).checked_div(total).unwrap_or(100);
+                    format!("Re-encrypting tokens: {} of {} ({}%)", done, total, percent)
+                }
+                RotateStatus::Done(count) => {
+                    format!("Re-encrypted {} tokens under the new passphrase.\n\n[Enter] Close", count)
+                }
+                RotateStatus::Failed(message) => {
+                    format!("Rotation failed, nothing was changed: {}\n\n[Enter] Close", message)
+                }
+            };
+        }
+        let row = |field: Field, label: &str, value: &str| {
+            let marker = if field == self.field { ">" } else { " " };
+            format!("{} {:<24}{}", marker, label, "*".repeat(value.chars().count()))
+        };
+        let mut lines = vec![
+            row(Field::Current, "Current passphrase:", &self.current),
+            row(Field::New, "New passphrase:", &self.new),
+            row(Field::Confirm, "Confirm new passphrase:", &self.confirm),
+        ];
+        if let Some(error) = &self.error {
+            lines.push(String::new());
+            lines.push(error.clone());
+        }
+        lines.join("\n")
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let paragraph = Paragraph::new(self.text())
+            .block(
+                Block::default()
+                    .title("Rotate vault keys - [Enter] Next  [Esc] Cancel")
+                    .borders(Borders::ALL),
+            )
+            .style(Style::default().fg(theme.foreground));
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn press(dialog: &mut VaultRotateDialog, code: KeyCode) -> RotateAction {
+        dialog.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_text(dialog: &mut VaultRotateDialog, text: &str) {
+        for c in text.chars() {
+            press(dialog, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn test_mismatched_confirmation_asks_again() {
+        let mut dialog = VaultRotateDialog::new();
+        type_text(&mut dialog, "old");
+        press(&mut dialog, KeyCode::Enter);
+        type_text(&mut dialog, "new one");
+        press(&mut dialog, KeyCode::Enter);
+        type_text(&mut dialog, "new on");
+        assert!(matches!(press(&mut dialog, KeyCode::Enter), RotateAction::None));
+        assert!(dialog.text().contains("Passphrases don't match"));
+        // Passphrases are never shown
+        assert!(!dialog.text().contains("old"));
+
+        type_text(&mut dialog, "new one");
+        press(&mut dialog, KeyCode::Enter);
+        type_text(&mut dialog, "new one");
+        let RotateAction::Submit(request) = press(&mut dialog, KeyCode::Enter) else {
+            panic!("expected the rotation to be submitted");
+        };
+        assert_eq!(request.current_passphrase(), "old");
+        assert_eq!(request.new_passphrase(), "new one");
+        assert!(!format!("{:?}", request).contains("new one"));
+
+        // Keys are ignored until the rotation finishes
+        request.progress.update(3, 4);
+        assert!(matches!(press(&mut dialog, KeyCode::Esc), RotateAction::None));
+        assert!(dialog.text().contains("3 of 4 (75%)"));
+        request.progress.finish(Ok(4));
+        assert!(matches!(press(&mut dialog, KeyCode::Enter), RotateAction::Close));
+    }
+}
@@ -0,0 +1,180 @@
+//! Artifact kinds shared by workspaces, retention config and agent events
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Artifact kind
+///
+/// Single source of truth for the kinds referenced by artifact events and
+/// retention config. Custom kinds round-trip through `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ArtifactKind {
+    Diff,
+    Log,
+    Preview,
+    Code,
+    Scratch,
+    Other(String),
+}
+
+impl ArtifactKind {
+    /// All built-in kinds
+    pub const KNOWN: [ArtifactKind; 5] = [
+        ArtifactKind::Diff,
+        ArtifactKind::Log,
+        ArtifactKind::Preview,
+        ArtifactKind::Code,
+        ArtifactKind::Scratch,
+    ];
+
+    /// Parse a kind; unknown names become `Other`
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "diff" => ArtifactKind::Diff,
+            "log" => ArtifactKind::Log,
+            "preview" => ArtifactKind::Preview,
+            "code" => ArtifactKind::Code,
+            "scratch" => ArtifactKind::Scratch,
+            other => ArtifactKind::Other(other.to_string()),
+        }
+    }
+
+    /// Parse a kind from config. Custom kinds must be made of letters,
+    /// digits, '-', '_' and '.', and may not spell a built-in kind differently;
+    /// names close to a built-in kind are rejected as likely typos unless
+    /// listed in `allowed`.
+    pub fn parse_validated(s: &str, allowed: &[String]) -> Result<Self> {
+        let kind = Self::parse(s);
+        let ArtifactKind::Other(name) = &kind else {
+            return Ok(kind);
+        };
+        if name.is_empty() {
+            anyhow::bail!("Artifact kind cannot be empty");
+        }
+        if let Some(c) = name.chars().find(|c| !(c.is_ascii_alphanumeric() || "-_.".contains(*c))) {
+            anyhow::bail!("Artifact kind '{}' contains {:?}; use letters, digits, '-', '_' or '.'", name, c);
+        }
+        if let Some(known) = Self::KNOWN.iter().find(|k| k.as_str().eq_ignore_ascii_case(name)) {
+            anyhow::bail!("Artifact kind '{}' is reserved; write it as '{}'", name, known);
+        }
+
+        // Allow more slack for longer names so short custom kinds aren't flagged
+        let is_typo = |known: &ArtifactKind| {
+            let known = known.as_str();
+            edit_distance(name, known) <= (known.len() / 3).max(1)
+        };
+        if let Some(known) = Self::KNOWN.iter().find(|k| is_typo(k)) {
+            if !allowed.iter().any(|a| a == name) {
+                anyhow::bail!(
+                    "Artifact kind '{}' looks like a typo of '{}'; add it to custom_kinds if it is intended",
+                    name,
+                    known
+                );
+            }
+        }
+        Ok(kind)
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ArtifactKind::Diff => "diff",
+            ArtifactKind::Log => "log",
+            ArtifactKind::Preview => "preview",
+            ArtifactKind::Code => "code",
+            ArtifactKind::Scratch => "scratch",
+            ArtifactKind::Other(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<String> for ArtifactKind {
+    fn from(s: String) -> Self {
+        ArtifactKind::parse(&s)
+    }
+}
+
+impl From<&str> for ArtifactKind {
+    fn from(s: &str) -> Self {
+        ArtifactKind::parse(s)
+    }
+}
+
+impl From<ArtifactKind> for String {
+    fn from(kind: ArtifactKind) -> Self {
+        kind.as_str().to_string()
+    }
+}
+
+impl PartialEq<&str> for ArtifactKind {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// Levenshtein distance, used to spot typos in kind names
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_kind_parsing() {
+        for kind in ArtifactKind::KNOWN {
+            assert_eq!(ArtifactKind::parse(kind.as_str()), kind);
+            assert_eq!(ArtifactKind::parse_validated(&kind.to_string(), &[]).unwrap(), kind);
+        }
+
+        let custom = ArtifactKind::parse("notebook");
+        assert_eq!(custom, ArtifactKind::Other("notebook".to_string()));
+        assert_eq!(custom.to_string(), "notebook");
+        assert!(ArtifactKind::parse_validated("notebook", &[]).is_ok());
+
+        let json = serde_json::to_string(&custom).unwrap();
+        assert_eq!(json, "\"notebook\"");
+        assert_eq!(serde_json::from_str::<ArtifactKind>(&json).unwrap(), custom);
+    }
+
+    #[test]
+    fn test_near_misses_need_allow_listing() {
+        // Close to a built-in kind, so only accepted once allow-listed
+        let allowed: Vec<String> = ["blog", "logs", "node", "diffs", "preveiw"].map(String::from).to_vec();
+        for name in &allowed {
+            let err = ArtifactKind::parse_validated(name, &[]).unwrap_err();
+            assert!(err.to_string().contains("typo"), "{}", err);
+            assert_eq!(
+                ArtifactKind::parse_validated(name, &allowed).unwrap(),
+                ArtifactKind::Other(name.to_string())
+            );
+        }
+        assert!(ArtifactKind::parse_validated("coverage.v2", &[]).is_ok());
+
+        // Reserved and ill-formed kinds can't be allow-listed
+        let allowed: Vec<String> = ["", "my kind", "a,b", "Preview", "DIFF"].map(String::from).to_vec();
+        for name in &allowed {
+            assert!(ArtifactKind::parse_validated(name, &allowed).is_err(), "{:?}", name);
+        }
+        let err = ArtifactKind::parse_validated("Preview", &[]).unwrap_err();
+        assert!(err.to_string().contains("'preview'"));
+    }
+}
@@ -0,0 +1,86 @@
+//! Build metadata for bug reports (`--build-info`)
+
+use serde::Serialize;
+use std::fmt;
+
+/// Compile-time build information
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_sha: &'static str,
+    pub build_date: &'static str,
+    pub target: &'static str,
+    pub profile: &'static str,
+    pub features: Vec<&'static str>,
+}
+
+/// Get build information for this binary
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: env!("OMNI_GIT_SHA"),
+        build_date: env!("OMNI_BUILD_DATE"),
+        target: env!("OMNI_TARGET"),
+        profile: if cfg!(debug_assertions) { "debug" } else { "release" },
+        features: enabled_features(),
+    }
+}
+
+/// Cargo features compiled into this binary
+fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "notcurses") {
+        features.push("notcurses");
+    }
+    if cfg!(feature = "kitty") {
+        features.push("kitty");
+    }
+    if cfg!(feature = "overlay") {
+        features.push("overlay");
+    }
+    if cfg!(feature = "wasm") {
+        features.push("wasm");
+    }
+    if cfg!(feature = "native") {
+        features.push("native");
+    }
+    if cfg!(feature = "media") {
+        features.push("media");
+    }
+    if cfg!(feature = "qr") {
+        features.push("qr");
+    }
+    features
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let features = if self.features.is_empty() {
+            "(none)".to_string()
+        } else {
+            self.features.join(", ")
+        };
+
+        writeln!(f, "omniscient-shell {}", self.version)?;
+        writeln!(f, "commit:   {}", self.git_sha)?;
+        writeln!(f, "built:    {}", self.build_date)?;
+        writeln!(f, "target:   {}", self.target)?;
+        writeln!(f, "profile:  {}", self.profile)?;
+        write!(f, "features: {}", features)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let info = build_info();
+        assert!(!info.version.is_empty());
+        assert!(!info.target.is_empty());
+        assert_eq!(info.features.contains(&"wasm"), cfg!(feature = "wasm"));
+        assert_eq!(info.features.contains(&"media"), cfg!(feature = "media"));
+        assert_eq!(info.features.contains(&"notcurses"), cfg!(feature = "notcurses"));
+    }
+}
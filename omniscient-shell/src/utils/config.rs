@@ -4,6 +4,14 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::time::Duration;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, DebouncedEventKind, Debouncer};
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+use crate::utils::artifact_kind::ArtifactKind;
+use crate::utils::errors::{OmniError, RecoveryAction};
+use crate::utils::telemetry::TelemetryConfig;
 
 /// Main configuration structure (schema v0.1)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +27,16 @@ pub struct Config {
     pub oauth: OAuthConfig,
     pub vault: VaultConfig,
     pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub media: MediaConfig,
+    #[serde(default)]
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub shell: ShellConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub state: StateConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +46,10 @@ pub struct WorkspaceConfig {
     pub root: Option<String>,
     #[serde(default = "default_true")]
     pub auto_save: bool,
+    #[serde(default = "default_artifact_storage")]
+    pub artifact_storage: String, // "workspace" or "global"
+    #[serde(default)]
+    pub artifact_root: Option<String>, // global root, defaults to ~/.omniscient/artifacts
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,17 +60,33 @@ pub struct GraphicsConfig {
     pub auto_benchmark: bool,
     #[serde(default)]
     pub legacy_support: Vec<String>,
+    #[serde(default)]
+    pub pin: Option<String>, // use only this backend: no negotiation or fallback
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LayoutConfig {
     pub default: DefaultLayoutConfig,
+    #[serde(default)]
+    pub presets: Vec<LayoutPresetConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefaultLayoutConfig {
     pub preset: String, // "dashboard"
     pub panes: Vec<String>, // ["shell", "agent", "preview", "log"]
+    #[serde(default = "default_split_ratios")]
+    pub split_ratios: Vec<u16>, // first-pane percent of the rows, top and bottom splits
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutPresetConfig {
+    pub name: String,
+    #[serde(default = "default_preset_direction")]
+    pub direction: String, // "horizontal" or "vertical"
+    pub panes: Vec<String>, // subset of ["shell", "agent", "preview", "log"]
+    #[serde(default)]
+    pub ratios: Vec<u16>, // percent per pane; empty splits evenly
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +95,89 @@ pub struct ThemeConfig {
     pub background: String, // "#0b0e10"
     pub foreground: String, // "#c9d1d9"
     pub accent: String, // "#00d1ff"
+    #[serde(default)]
+    pub strict_contrast: bool, // refuse colors below 4.5:1 contrast instead of warning
+}
+
+impl ThemeConfig {
+    /// Check that every color is a `#rrggbb` or `#rgb` hex value
+    pub fn validate(&self) -> Result<(), Vec<OmniError>> {
+        let errors: Vec<OmniError> = [
+            ("background", &self.background),
+            ("foreground", &self.foreground),
+            ("accent", &self.accent),
+        ]
+        .into_iter()
+        .filter(|(_, value)| parse_hex_color(value).is_none())
+        .map(|(field, value)| {
+            OmniError::config(
+                format!("Theme '{}' has an invalid {} color: \"{}\"", self.name, field, value),
+                Some(format!("Set theme.{} to a hex color such as \"#00d1ff\" or \"#0df\"", field)),
+                RecoveryAction::PromptUser("Fix the theme colors in the config file".to_string()),
+            )
+        })
+        .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Backends `graphics.preferred`, `graphics.fallback` and `graphics.pin` may name
+pub const GRAPHICS_BACKENDS: &[&str] = &["notcurses", "kitty", "overlay"];
+const SANDBOXES: &[&str] = &["wasm", "native"];
+const VAULT_BACKENDS: &[&str] = &["os_keychain", "encrypted_sqlite"];
+const NOTIFICATION_CHANNELS: &[&str] = &["tui", "system"];
+
+/// Record an error unless `value` is one of `known`
+fn check_known(errors: &mut Vec<OmniError>, field: &str, value: &str, known: &[&str]) {
+    if !known.contains(&value) {
+        errors.push(OmniError::config(
+            format!("{} has unknown value \"{}\"", field, value),
+            Some(format!("Set {} to one of: {}", field, known.join(", "))),
+            RecoveryAction::PromptUser("Fix the config file".to_string()),
+        ));
+    }
+}
+
+/// One error reporting every problem `validate` found
+pub fn combine_errors(mut errors: Vec<OmniError>) -> OmniError {
+    if errors.len() == 1 {
+        return errors.remove(0);
+    }
+    let messages: Vec<String> = errors
+        .iter()
+        .map(|e| match e {
+            OmniError::Config { message, .. } => message.clone(),
+            other => other.to_string(),
+        })
+        .collect();
+    let hints: Vec<&str> = errors.iter().filter_map(|e| e.hint()).collect();
+    OmniError::config(
+        format!("{} problems: {}", errors.len(), messages.join("; ")),
+        Some(hints.join("; ")),
+        RecoveryAction::PromptUser("Fix the config file".to_string()),
+    )
+}
+
+/// Parse a `#rrggbb` or `#rgb` color into its red, green and blue components
+pub fn parse_hex_color(value: &str) -> Option<(u8, u8, u8)> {
+    let hex = value.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        6 => Some((channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?)),
+        // #rgb is shorthand for #rrggbb
+        3 => {
+            let short = |i: usize| channel(&hex[i..i + 1]).map(|v| v * 17);
+            Some((short(0)?, short(1)?, short(2)?))
+        }
+        _ => None,
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,7 +186,45 @@ pub struct AgentsConfig {
     pub sandbox_default: String, // "wasm" or "native"
     #[serde(default)]
     pub native_allowed: Vec<String>,
+    #[serde(default)]
+    pub read_only: Vec<String>, // agents that may not modify the workspace, whatever they're granted
+    #[serde(default)]
+    pub trusted_sources: Vec<String>, // agent directories or install URLs
+    #[serde(default)]
+    pub strict_sources: bool, // discover agents only from trusted_sources
+    #[serde(default)]
+    pub require_signed_agents: bool, // register only agents whose manifest.toml.sig verifies
+    #[serde(default)]
+    pub trusted_keys: Vec<String>, // base64 ed25519 public keys agent manifests may be signed with
     pub policy: String, // "user-choice"
+    #[serde(default)]
+    pub max_output_bytes: u64, // output one agent run may produce before it is stopped; 0 is unlimited
+    #[serde(default)]
+    pub auto_disable: AutoDisableConfig,
+    #[serde(default)]
+    pub restart: RestartConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoDisableConfig {
+    #[serde(default = "default_max_denied")]
+    pub max_denied: u32, // denied-access attempts within the window; 0 disables
+    #[serde(default = "default_max_crashes")]
+    pub max_crashes: u32, // crashes within the window; 0 disables
+    #[serde(default = "default_violation_window")]
+    pub window_minutes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestartConfig {
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32, // restarts of a crashed native agent before giving up
+    #[serde(default = "default_restart_backoff")]
+    pub backoff_ms: u64, // delay before the first restart, doubled for each one after
+    #[serde(default = "default_min_uptime")]
+    pub min_uptime_secs: u64, // crashing sooner than this after starting counts as a fast crash
+    #[serde(default = "default_max_fast_crashes")]
+    pub max_fast_crashes: u32, // fast crashes in a row before giving up
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,12 +233,42 @@ pub struct RetentionConfig {
     pub ephemeral: Vec<String>,
     pub days: u32,
     pub max_mb: u32,
+    #[serde(default = "default_sweep_interval")]
+    pub sweep_interval_minutes: u32, // 0 disables background sweeps
+    #[serde(default)]
+    pub custom_kinds: Vec<String>, // custom kinds allowed despite looking like a built-in one, e.g. "logs"
+}
+
+impl RetentionConfig {
+    /// Check every kind in `always_persist` and `ephemeral`, returning one
+    /// error per invalid kind
+    pub fn validate(&self) -> Result<(), Vec<OmniError>> {
+        let errors: Vec<OmniError> = [("always_persist", &self.always_persist), ("ephemeral", &self.ephemeral)]
+            .into_iter()
+            .flat_map(|(field, kinds)| kinds.iter().map(move |kind| (field, kind)))
+            .filter_map(|(field, kind)| {
+                let e = ArtifactKind::parse_validated(kind, &self.custom_kinds).err()?;
+                Some(OmniError::config(
+                    format!("Invalid kind in retention.{}: {}", field, e),
+                    Some("Use a built-in kind (diff, log, preview, code, scratch) or a lowercase custom name".to_string()),
+                    RecoveryAction::PromptUser("Fix the retention kinds in the config file".to_string()),
+                ))
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OAuthConfig {
     #[serde(default)]
     pub providers: std::collections::HashMap<String, ProviderConfig>,
+    #[serde(default)]
+    pub redirect_ports: Option<String>, // "8400" or "8400-8410"; ephemeral if unset
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -103,6 +292,82 @@ pub struct NotificationsConfig {
     pub channels: Vec<String>, // ["tui", "system"]
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaConfig {
+    #[serde(default)]
+    pub preview_concurrency: Option<usize>, // defaults to the number of CPUs
+    #[serde(default)]
+    pub cache_dir: Option<String>, // defaults to ~/.omniscient/media-cache
+    #[serde(default = "default_media_cache_mb")]
+    pub max_size_mb: u64,
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        MediaConfig {
+            preview_concurrency: None,
+            cache_dir: None,
+            max_size_mb: default_media_cache_mb(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionConfig {
+    #[serde(default = "default_idle_minutes")]
+    pub idle_minutes: u32, // no key/mouse input for this long counts as idle; 0 disables
+    #[serde(default = "default_idle_actions")]
+    pub idle_actions: Vec<String>, // "lock_vault", "revoke_short_lived", "dim_ui"
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            idle_minutes: default_idle_minutes(),
+            idle_actions: default_idle_actions(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShellConfig {
+    #[serde(default)]
+    pub history_file: Option<String>, // defaults to ~/.omniscient/history
+    #[serde(default = "default_history_size")]
+    pub history_size: usize, // commands kept; the oldest are dropped first
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        ShellConfig {
+            history_file: None,
+            history_size: default_history_size(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateConfig {
+    #[serde(default)]
+    pub db_path: Option<String>, // defaults to ~/.omniscient/state.db
+}
+
+fn default_history_size() -> usize {
+    1000
+}
+
+fn default_idle_minutes() -> u32 {
+    15
+}
+
+fn default_idle_actions() -> Vec<String> {
+    vec!["lock_vault".to_string(), "dim_ui".to_string()]
+}
+
+fn default_media_cache_mb() -> u64 {
+    512
+}
+
 fn default_true() -> bool {
     true
 }
@@ -111,20 +376,88 @@ fn default_auto_lock() -> u32 {
     10
 }
 
+fn default_sweep_interval() -> u32 {
+    60
+}
+
+fn default_preset_direction() -> String {
+    "horizontal".to_string()
+}
+
+fn default_max_denied() -> u32 {
+    5
+}
+
+fn default_max_crashes() -> u32 {
+    3
+}
+
+fn default_violation_window() -> u32 {
+    10
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+fn default_restart_backoff() -> u64 {
+    500
+}
+
+fn default_min_uptime() -> u64 {
+    10
+}
+
+fn default_max_fast_crashes() -> u32 {
+    3
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        RestartConfig {
+            max_retries: default_max_retries(),
+            backoff_ms: default_restart_backoff(),
+            min_uptime_secs: default_min_uptime(),
+            max_fast_crashes: default_max_fast_crashes(),
+        }
+    }
+}
+
+impl Default for AutoDisableConfig {
+    fn default() -> Self {
+        AutoDisableConfig {
+            max_denied: default_max_denied(),
+            max_crashes: default_max_crashes(),
+            window_minutes: default_violation_window(),
+        }
+    }
+}
+
+fn default_split_ratios() -> Vec<u16> {
+    vec![60, 60, 50]
+}
+
+fn default_artifact_storage() -> String {
+    "workspace".to_string()
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
-            version: "0.1".to_string(),
+            version: CONFIG_VERSION.to_string(),
             workspace: WorkspaceConfig {
                 detection: "explicit".to_string(),
                 root: None,
                 auto_save: true,
+                artifact_storage: default_artifact_storage(),
+                artifact_root: None,
             },
             graphics: GraphicsConfig {
                 preferred: "notcurses".to_string(),
                 fallback: vec!["kitty".to_string(), "overlay".to_string()],
                 auto_benchmark: true,
                 legacy_support: vec![],
+                pin: None,
             },
             layout: LayoutConfig {
                 default: DefaultLayoutConfig {
@@ -135,25 +468,38 @@ impl Default for Config {
                         "preview".to_string(),
                         "log".to_string(),
                     ],
+                    split_ratios: default_split_ratios(),
                 },
+                presets: vec![],
             },
             theme: ThemeConfig {
                 name: "NeoCyan".to_string(),
                 background: "#0b0e10".to_string(),
                 foreground: "#c9d1d9".to_string(),
                 accent: "#00d1ff".to_string(),
+                strict_contrast: false,
             },
             agents: AgentsConfig {
                 enabled: vec![],
                 sandbox_default: "wasm".to_string(),
                 native_allowed: vec![],
+                read_only: vec![],
+                trusted_sources: vec![],
+                strict_sources: false,
+                require_signed_agents: false,
+                trusted_keys: vec![],
                 policy: "user-choice".to_string(),
+                max_output_bytes: 0,
+                auto_disable: AutoDisableConfig::default(),
+                restart: RestartConfig::default(),
             },
             retention: RetentionConfig {
                 always_persist: vec!["diff".to_string(), "log".to_string()],
                 ephemeral: vec!["preview".to_string(), "scratch".to_string()],
                 days: 30,
                 max_mb: 1024,
+                sweep_interval_minutes: default_sweep_interval(),
+                custom_kinds: vec![],
             },
             oauth: OAuthConfig::default(),
             vault: VaultConfig {
@@ -165,6 +511,11 @@ impl Default for Config {
                 profile: "minimal".to_string(),
                 channels: vec!["tui".to_string()],
             },
+            media: MediaConfig::default(),
+            session: SessionConfig::default(),
+            shell: ShellConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            state: StateConfig::default(),
         }
     }
 }
@@ -181,8 +532,14 @@ pub fn load_config() -> Result<Config> {
     load_config_from(&path)
 }
 
-/// Load configuration from a specific path
+/// Load configuration from a specific path. A file written for an older
+/// schema is upgraded and rewritten, keeping the original next to it as
+/// `.bak`, or `.<unix time>.bak` if an earlier upgrade's backup is there.
 pub fn load_config_from(path: &Path) -> Result<Config> {
+    load_with(path, MIGRATIONS)
+}
+
+fn load_with(path: &Path, migrations: &[Migration]) -> Result<Config> {
     if !path.exists() {
         // Create default config
         let config = Config::default();
@@ -193,36 +550,373 @@ pub fn load_config_from(path: &Path) -> Result<Config> {
     let contents = fs::read_to_string(path)
         .with_context(|| format!("Failed to read config file: {}", path.display()))?;
 
-    let config: Config = toml::from_str(&contents)
+    let (config, version) = parse_versioned(&contents, migrations)
         .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
 
-    // Validate version
-    if config.version != "0.1" {
-        anyhow::bail!(
-            "Unsupported config version: {}. Expected 0.1. Please update your config file at: {}",
+    if version != config.version {
+        let backup = backup_path(path);
+        fs::copy(path, &backup)
+            .with_context(|| format!("Failed to back up config file to {}", backup.display()))?;
+        // `save` keeps keys it doesn't know, so first drop those the
+        // migrations renamed or removed
+        let upgraded = upgrade_table(toml::from_str(&contents)?, &version, migrations)?;
+        if let Ok(mut document) = contents.parse::<DocumentMut>() {
+            retain_upgraded(document.as_table_mut(), &upgraded);
+            fs::write(path, document.to_string())
+                .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+        }
+        config.save(path)?;
+        tracing::info!(
+            "Upgraded {} from config version {} to {}; the original is at {}",
+            path.display(),
+            version,
             config.version,
-            path.display()
+            backup.display()
         );
     }
 
     Ok(config)
 }
 
+/// Where to keep the original of a config about to be upgraded, without
+/// replacing the backup of an earlier upgrade
+fn backup_path(path: &Path) -> PathBuf {
+    let with_suffix = |suffix: &str| {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(suffix);
+        PathBuf::from(backup)
+    };
+    let backup = with_suffix(".bak");
+    if !backup.exists() {
+        return backup;
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut backup = with_suffix(&format!(".{}.bak", now));
+    let mut n = 1;
+    while backup.exists() {
+        backup = with_suffix(&format!(".{}-{}.bak", now, n));
+        n += 1;
+    }
+    backup
+}
+
+/// Config schema version this build reads and writes
+pub const CONFIG_VERSION: &str = "0.1";
+
+/// One step of the schema history: `upgrade` turns a document of the
+/// previous version into `version`
+struct Migration {
+    version: &'static str,
+    upgrade: fn(toml::Table) -> Result<toml::Table>,
+}
+
+/// Every schema version, oldest first, ending with `CONFIG_VERSION`
+const MIGRATIONS: &[Migration] = &[
+    // The first schema; there is nothing older to upgrade
+    Migration { version: "0.1", upgrade: Ok },
+];
+
+/// Upgrade a config document of version `from` to `CONFIG_VERSION`, one
+/// schema version at a time
+pub fn migrate(value: toml::Value, from: &str) -> Result<Config> {
+    migrate_with(value, from, MIGRATIONS)
+}
+
+fn migrate_with(value: toml::Value, from: &str, migrations: &[Migration]) -> Result<Config> {
+    let toml::Value::Table(document) = value else {
+        anyhow::bail!("Config must be a TOML table");
+    };
+    toml::Value::Table(upgrade_table(document, from, migrations)?)
+        .try_into()
+        .with_context(|| format!("Config upgraded from version {} is invalid", from))
+}
+
+/// Run the migrations after `from` over a raw config document
+fn upgrade_table(mut document: toml::Table, from: &str, migrations: &[Migration]) -> Result<toml::Table> {
+    let latest = migrations.last().map_or(CONFIG_VERSION, |m| m.version);
+    let start = migrations
+        .iter()
+        .position(|m| m.version == from)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported config version: {}. This build reads versions up to {}", from, latest))?;
+
+    for step in &migrations[start + 1..] {
+        document = (step.upgrade)(document)
+            .with_context(|| format!("Failed to upgrade config to version {}", step.version))?;
+        document.insert("version".to_string(), toml::Value::String(step.version.to_string()));
+    }
+    Ok(document)
+}
+
+/// Parse the contents of a config file, upgrading an older schema in memory
+pub fn parse_config(contents: &str) -> Result<Config> {
+    Ok(parse_versioned(contents, MIGRATIONS)?.0)
+}
+
+/// The parsed config and the version the file was written with
+fn parse_versioned(contents: &str, migrations: &[Migration]) -> Result<(Config, String)> {
+    let document: toml::Table = toml::from_str(contents)?;
+    let version = match document.get("version") {
+        Some(toml::Value::String(version)) => version.clone(),
+        _ => anyhow::bail!("Config has no version; add version = \"{}\"", CONFIG_VERSION),
+    };
+    let latest = migrations.last().map_or(CONFIG_VERSION, |m| m.version);
+    let config = if version == latest {
+        // Straight from the text, so errors keep their line numbers
+        toml::from_str(contents)?
+    } else {
+        migrate_with(toml::Value::Table(document), &version, migrations)?
+    };
+    config.validate().map_err(combine_errors)?;
+    Ok((config, version))
+}
+
+/// Config re-read by a running session
+#[derive(Debug, Clone)]
+pub struct ReloadedConfig {
+    pub config: Config,
+    /// Changed settings that only take effect after a restart, e.g.
+    /// "vault.backend"; `config` keeps their running values
+    pub restart_required: Vec<&'static str>,
+}
+
 /// Save configuration to a specific path
 pub fn save_config(config: &Config, path: &Path) -> Result<()> {
-    // Create parent directory if it doesn't exist
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create config directory: {}", parent.display()))?;
+    config.save(path)
+}
+
+impl Config {
+    /// Check constraints the schema alone can't express, such as theme
+    /// colors and backend names, collecting every violation
+    pub fn validate(&self) -> Result<(), Vec<OmniError>> {
+        let mut errors = self.theme.validate().err().unwrap_or_default();
+
+        let graphics = std::iter::once(("graphics.preferred", &self.graphics.preferred))
+            .chain(self.graphics.fallback.iter().map(|name| ("graphics.fallback", name)))
+            .chain(self.graphics.pin.iter().map(|name| ("graphics.pin", name)));
+        for (field, name) in graphics {
+            check_known(&mut errors, field, name, GRAPHICS_BACKENDS);
+        }
+        check_known(&mut errors, "agents.sandbox_default", &self.agents.sandbox_default, SANDBOXES);
+        check_known(&mut errors, "vault.backend", &self.vault.backend, VAULT_BACKENDS);
+        for channel in &self.notifications.channels {
+            check_known(&mut errors, "notifications.channels", channel, NOTIFICATION_CHANNELS);
+        }
+        errors.extend(self.retention.validate().err().unwrap_or_default());
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
-    let contents = toml::to_string_pretty(config)
-        .context("Failed to serialize config")?;
+    /// Re-read the config at `path` for a session running with `self`.
+    /// Unlike `load_config_from`, a missing file is an error.
+    pub fn reload_from(&self, path: &Path) -> Result<ReloadedConfig> {
+        if !path.exists() {
+            anyhow::bail!("Config file {} no longer exists", path.display());
+        }
+        let mut config = load_config_from(path)?;
+        let restart_required = config.keep_restart_only(self);
+        Ok(ReloadedConfig { config, restart_required })
+    }
+
+    /// Put back settings that are only read at startup, returning the names
+    /// of those that differed
+    fn keep_restart_only(&mut self, running: &Config) -> Vec<&'static str> {
+        fn keep<T: PartialEq + Clone>(name: &'static str, new: &mut T, old: &T, changed: &mut Vec<&'static str>) {
+            if new != old {
+                *new = old.clone();
+                changed.push(name);
+            }
+        }
+
+        let mut changed = Vec::new();
+        keep("graphics.preferred", &mut self.graphics.preferred, &running.graphics.preferred, &mut changed);
+        keep("graphics.fallback", &mut self.graphics.fallback, &running.graphics.fallback, &mut changed);
+        keep("graphics.pin", &mut self.graphics.pin, &running.graphics.pin, &mut changed);
+        keep("vault.backend", &mut self.vault.backend, &running.vault.backend, &mut changed);
+        keep("vault.key_derivation", &mut self.vault.key_derivation, &running.vault.key_derivation, &mut changed);
+        keep("media.cache_dir", &mut self.media.cache_dir, &running.media.cache_dir, &mut changed);
+        keep("shell.history_file", &mut self.shell.history_file, &running.shell.history_file, &mut changed);
+        keep("shell.history_size", &mut self.shell.history_size, &running.shell.history_size, &mut changed);
+        keep("telemetry.enabled", &mut self.telemetry.enabled, &running.telemetry.enabled, &mut changed);
+        keep("telemetry.endpoint", &mut self.telemetry.endpoint, &running.telemetry.endpoint, &mut changed);
+        changed
+    }
+
+    /// Layer a partial TOML document over this config. Tables merge key by
+    /// key; any other value in the overlay replaces the base value.
+    pub fn with_overlay(&self, overlay: &str) -> Result<Config> {
+        let overlay: toml::Table = toml::from_str(overlay).context("Failed to parse config overlay")?;
+        let mut merged = toml::Table::try_from(self).context("Failed to serialize config")?;
+        overlay_table(&mut merged, overlay);
+
+        let config: Config = toml::Value::Table(merged)
+            .try_into()
+            .context("Config overlay produced an invalid config")?;
+        if config.version != CONFIG_VERSION {
+            anyhow::bail!("Unsupported config version in overlay: {}. Expected {}", config.version, CONFIG_VERSION);
+        }
+        config.validate().map_err(combine_errors)?;
+        Ok(config)
+    }
+
+    /// Save to `path`, updating an existing file in place so that comments,
+    /// ordering and formatting of unchanged entries survive
+    pub fn save(&self, path: &Path) -> Result<()> {
+        // Create parent directory if it doesn't exist
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create config directory: {}", parent.display()))?;
+        }
 
-    fs::write(path, contents)
-        .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+        let rendered = toml::to_string_pretty(self)
+            .context("Failed to serialize config")?;
 
-    Ok(())
+        let contents = match fs::read_to_string(path) {
+            Ok(existing) => match existing.parse::<DocumentMut>() {
+                Ok(mut document) => {
+                    let updated = rendered
+                        .parse::<DocumentMut>()
+                        .context("Failed to parse serialized config")?;
+                    merge_table(document.as_table_mut(), updated.as_table());
+                    document.to_string()
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Existing config at {} is not valid TOML, overwriting: {}",
+                        path.display(),
+                        e
+                    );
+                    rendered
+                }
+            },
+            Err(_) => rendered,
+        };
+
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Quiet period after the last write before `watch` reports a change, so an
+/// editor saving in several steps triggers one reload
+pub const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches a config file until dropped
+pub struct ConfigWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+/// Call `on_change` whenever the file at `path` is modified, created or
+/// removed, once it has been quiet for `WATCH_DEBOUNCE`. The parent directory
+/// is watched, so editors that save by replacing the file are still seen.
+pub fn watch(path: impl Into<PathBuf>, on_change: impl Fn() + Send + 'static) -> Result<ConfigWatcher> {
+    let watched = path.into();
+    let dir = match watched.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = watched.file_name().map(|name| name.to_os_string());
+
+    let mut debouncer = new_debouncer(WATCH_DEBOUNCE, move |events: DebounceEventResult| match events {
+        Ok(events) => {
+            // Continuous events fire while writes keep coming; wait for the quiet one
+            let changed = events.iter().any(|event| {
+                event.kind == DebouncedEventKind::Any && event.path.file_name() == name.as_deref()
+            });
+            if changed {
+                on_change();
+            }
+        }
+        Err(e) => tracing::warn!("Config watcher error: {}", e),
+    })
+    .context("Failed to start config watcher")?;
+    debouncer
+        .watcher()
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {}", dir.display()))?;
+
+    Ok(ConfigWatcher { _debouncer: debouncer })
+}
+
+fn overlay_table(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(current)), toml::Value::Table(top)) => overlay_table(current, top),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Bring `existing` in line with `updated`, touching only entries that differ.
+/// Keys found only in `existing` are the user's and are left alone.
+fn merge_table(existing: &mut Table, updated: &Table) {
+    for (key, item) in updated.iter() {
+        match existing.get_mut(key) {
+            Some(current) => merge_item(current, item),
+            None => {
+                existing.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+/// Remove entries of `existing` that an upgrade left out of `upgraded`
+fn retain_upgraded(existing: &mut Table, upgraded: &toml::Table) {
+    existing.retain(|key, _| upgraded.contains_key(key));
+    for (key, item) in existing.iter_mut() {
+        if let (Item::Table(current), Some(toml::Value::Table(new))) = (item, upgraded.get(key.get())) {
+            retain_upgraded(current, new);
+        }
+    }
+}
+
+fn merge_item(existing: &mut Item, updated: &Item) {
+    match (existing, updated) {
+        (Item::Table(current), Item::Table(new)) => merge_table(current, new),
+        (Item::ArrayOfTables(current), Item::ArrayOfTables(new)) if current.len() == new.len() => {
+            for (current, new) in current.iter_mut().zip(new.iter()) {
+                merge_table(current, new);
+            }
+        }
+        (Item::Value(current), Item::Value(new)) => {
+            if !same_value(current, new) {
+                // Keep the comments and spacing around the old value
+                let decor = current.decor().clone();
+                *current = new.clone();
+                *current.decor_mut() = decor;
+            }
+        }
+        (current, new) => *current = new.clone(),
+    }
+}
+
+/// Compare values ignoring formatting
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_value(a, b))
+        }
+        (Value::InlineTable(a), Value::InlineTable(b)) => {
+            a.len() == b.len()
+                && a.iter().all(|(key, value)| b.get(key).is_some_and(|other| same_value(value, other)))
+        }
+        _ => false,
+    }
 }
 
 #[cfg(test)]
@@ -244,4 +938,251 @@ mod tests {
         let parsed: Config = toml::from_str(&toml_str).unwrap();
         assert_eq!(config.version, parsed.version);
     }
+
+    #[test]
+    fn test_save_preserves_comments() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut original = toml::to_string_pretty(&Config::default()).unwrap();
+        original = original.replace(
+            "[theme]\n",
+            "# My favourite colours\n[theme]\n",
+        );
+        original = original.replace(
+            "auto_save = true",
+            "auto_save = true # keep this on",
+        );
+        fs::write(&path, &original).unwrap();
+
+        let mut config = load_config_from(&path).unwrap();
+        config.theme.name = "Solarized".to_string();
+        config.workspace.auto_save = false;
+        config.save(&path).unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("# My favourite colours\n[theme]"));
+        assert!(saved.contains("name = \"Solarized\""));
+        assert!(saved.contains("auto_save = false # keep this on"));
+
+        let reloaded = load_config_from(&path).unwrap();
+        assert_eq!(reloaded.theme.name, "Solarized");
+        assert!(!reloaded.workspace.auto_save);
+    }
+
+    #[test]
+    fn test_save_keeps_keys_only_in_the_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        let original = toml::to_string_pretty(&Config::default())
+            .unwrap()
+            .replace("[theme]\n", "[theme]\nmy_note = \"keep me\"\n");
+        fs::write(&path, format!("{original}\n[plugins]\nenabled = [\"x\"]\n")).unwrap();
+
+        let mut config = load_config_from(&path).unwrap();
+        config.theme.name = "Solarized".to_string();
+        config.save(&path).unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("my_note = \"keep me\""));
+        assert!(saved.contains("[plugins]\nenabled = [\"x\"]"));
+
+        let reloaded = load_config_from(&path).unwrap();
+        assert_eq!(reloaded.theme.name, "Solarized");
+        reloaded.save(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), saved);
+    }
+
+    #[test]
+    fn test_reload_reports_restart_only_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let running = Config::default();
+        assert!(running.reload_from(&path).is_err());
+
+        let mut edited = Config::default();
+        edited.theme.accent = "#ff8800".to_string();
+        edited.vault.backend = "encrypted_sqlite".to_string();
+        edited.save(&path).unwrap();
+
+        let reloaded = running.reload_from(&path).unwrap();
+        assert_eq!(reloaded.config.theme.accent, "#ff8800");
+        assert_eq!(reloaded.restart_required, vec!["vault.backend"]);
+        assert_eq!(reloaded.config.vault.backend, running.vault.backend);
+    }
+
+    #[test]
+    fn test_watch_debounces_a_burst_of_writes() {
+        use std::sync::mpsc;
+        use std::thread::sleep;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let _watcher = watch(&path, move || tx.send(()).unwrap()).unwrap();
+
+        // An editor saving in several steps, next to a file that isn't watched
+        for contents in ["a", "ab", "abc"] {
+            fs::write(&path, contents).unwrap();
+            fs::write(dir.path().join("other.toml"), contents).unwrap();
+            sleep(Duration::from_millis(100));
+        }
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(rx.recv_timeout(Duration::from_secs(1)).is_err(), "burst reported more than once");
+
+        fs::remove_file(&path).unwrap();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_theme_color_is_rejected_on_load() {
+        assert_eq!(parse_hex_color("#0b0e10"), Some((0x0b, 0x0e, 0x10)));
+        assert_eq!(parse_hex_color("#0df"), Some((0x00, 0xdd, 0xff)));
+        for bad in ["0b0e10", "#0b0e1", "#gg0000", "#+1+2+3", "", "#"] {
+            assert_eq!(parse_hex_color(bad), None, "{}", bad);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut config = Config::default();
+        config.theme.accent = "#00d1fg".to_string();
+        config.save(&path).unwrap();
+
+        let err = load_config_from(&path).unwrap_err();
+        let err = err.downcast_ref::<OmniError>().expect("an OmniError");
+        assert!(matches!(err, OmniError::Config { .. }));
+        assert!(err.to_string().contains("accent"));
+        assert!(err.hint().unwrap().contains("theme.accent"));
+    }
+
+    #[test]
+    fn test_validate_reports_every_violation() {
+        assert!(Config::default().validate().is_ok());
+
+        let mut config = Config::default();
+        config.graphics.fallback = vec!["kitty".to_string(), "sixel".to_string()];
+        config.agents.sandbox_default = "docker".to_string();
+        config.vault.backend = "plaintext".to_string();
+        config.notifications.channels = vec!["tui".to_string(), "email".to_string()];
+        config.theme.foreground = "white".to_string();
+        // "logs" is a near miss of "log", so it only passes once allow-listed
+        config.retention.ephemeral = vec!["logs".to_string(), "Scratch".to_string()];
+        config.retention.custom_kinds = vec!["logs".to_string()];
+
+        let errors = config.validate().unwrap_err();
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors.len(), 6, "{:?}", messages);
+        for field in [
+            "foreground",
+            "graphics.fallback",
+            "agents.sandbox_default",
+            "vault.backend",
+            "notifications.channels",
+            "retention.ephemeral",
+        ] {
+            assert!(messages.iter().any(|m| m.contains(field)), "nothing about {}", field);
+        }
+        assert!(errors.iter().all(|e| matches!(e, OmniError::Config { .. })));
+        assert_eq!(errors[1].hint(), Some("Set graphics.fallback to one of: notcurses, kitty, overlay"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        config.save(&path).unwrap();
+        let err = load_config_from(&path).unwrap_err();
+        assert!(format!("{:#}", err).contains("6 problems"));
+    }
+
+    /// Pretend history where 0.1 renamed `theme.highlight` to `theme.accent`
+    fn rename_highlight(mut document: toml::Table) -> Result<toml::Table> {
+        if let Some(toml::Value::Table(theme)) = document.get_mut("theme") {
+            if let Some(highlight) = theme.remove("highlight") {
+                theme.insert("accent".to_string(), highlight);
+            }
+        }
+        Ok(document)
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration { version: "0.0", upgrade: Ok },
+        Migration { version: "0.1", upgrade: rename_highlight },
+    ];
+
+    #[test]
+    fn test_migrate_current_version_is_identity() {
+        let value = toml::Value::try_from(Config::default()).unwrap();
+        let config = migrate(value.clone(), CONFIG_VERSION).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.theme.name, Config::default().theme.name);
+
+        let err = migrate(value, "9.0").unwrap_err();
+        assert!(err.to_string().contains("Unsupported config version: 9.0"));
+        assert!(parse_config("[theme]\nname = \"x\"\n").is_err());
+    }
+
+    #[test]
+    fn test_older_config_is_upgraded_and_backed_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let old = toml::to_string(&Config::default())
+            .unwrap()
+            .replace("version = \"0.1\"", "version = \"0.0\"")
+            .replace("accent =", "highlight =");
+        fs::write(&path, &old).unwrap();
+
+        let config = load_with(&path, TEST_MIGRATIONS).unwrap();
+        assert_eq!(config.version, "0.1");
+        assert_eq!(config.theme.accent, Config::default().theme.accent);
+
+        assert_eq!(fs::read_to_string(dir.path().join("config.toml.bak")).unwrap(), old);
+        let rewritten = fs::read_to_string(&path).unwrap();
+        assert!(rewritten.contains("version = \"0.1\""));
+        assert!(!rewritten.contains("highlight"));
+
+        // Already current: loaded as is
+        load_with(&path, TEST_MIGRATIONS).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), rewritten);
+    }
+
+    #[test]
+    fn test_second_upgrade_keeps_the_first_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let old = |name: &str| {
+            toml::to_string(&Config::default())
+                .unwrap()
+                .replace("version = \"0.1\"", "version = \"0.0\"")
+                .replace("NeoCyan", name)
+        };
+
+        fs::write(&path, old("First")).unwrap();
+        load_with(&path, TEST_MIGRATIONS).unwrap();
+        // e.g. the user restored an older copy of their config
+        fs::write(&path, old("Second")).unwrap();
+        load_with(&path, TEST_MIGRATIONS).unwrap();
+
+        assert_eq!(fs::read_to_string(dir.path().join("config.toml.bak")).unwrap(), old("First"));
+        let backups: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("config.toml.") && name.ends_with(".bak") && name != "config.toml.bak")
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(dir.path().join(&backups[0])).unwrap(), old("Second"));
+    }
+
+    #[test]
+    fn test_overlay_merges_tables() {
+        let base = Config::default();
+        let layered = base
+            .with_overlay("[theme]\nname = \"Paper\"\n\n[agents]\nenabled = [\"indexer\"]\n")
+            .unwrap();
+
+        assert_eq!(layered.theme.name, "Paper");
+        assert_eq!(layered.theme.background, base.theme.background);
+        assert_eq!(layered.agents.enabled, vec!["indexer".to_string()]);
+        assert_eq!(layered.retention.days, base.retention.days);
+
+        assert!(base.with_overlay("[retention]\ndays = \"forever\"\n").is_err());
+    }
 }
@@ -2,6 +2,9 @@
 
 use thiserror::Error;
 
+/// Result carrying a structured `OmniError` with hints and recovery actions
+pub type OmniResult<T> = Result<T, OmniError>;
+
 #[derive(Error, Debug)]
 pub enum OmniError {
     #[error("Configuration error: {message}")]
@@ -46,6 +49,13 @@ pub enum OmniError {
         recovery: RecoveryAction,
     },
 
+    #[error("Storage error: {message}")]
+    Storage {
+        message: String,
+        hint: Option<String>,
+        recovery: RecoveryAction,
+    },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -117,6 +127,14 @@ impl OmniError {
         }
     }
 
+    pub fn storage(message: impl Into<String>, hint: impl Into<Option<String>>, recovery: RecoveryAction) -> Self {
+        OmniError::Storage {
+            message: message.into(),
+            hint: hint.into(),
+            recovery,
+        }
+    }
+
     pub fn hint(&self) -> Option<&str> {
         match self {
             OmniError::Config { hint, .. } => hint.as_deref(),
@@ -125,6 +143,7 @@ impl OmniError {
             OmniError::Agent { hint, .. } => hint.as_deref(),
             OmniError::OAuth { hint, .. } => hint.as_deref(),
             OmniError::Workspace { hint, .. } => hint.as_deref(),
+            OmniError::Storage { hint, .. } => hint.as_deref(),
             _ => None,
         }
     }
@@ -137,6 +156,7 @@ impl OmniError {
             OmniError::Agent { recovery, .. } => recovery.clone(),
             OmniError::OAuth { recovery, .. } => recovery.clone(),
             OmniError::Workspace { recovery, .. } => recovery.clone(),
+            OmniError::Storage { recovery, .. } => recovery.clone(),
             _ => RecoveryAction::None,
         }
     }
@@ -169,6 +189,75 @@ impl OmniError {
     }
 }
 
+impl From<toml::de::Error> for OmniError {
+    fn from(err: toml::de::Error) -> Self {
+        OmniError::config(
+            format!("Invalid TOML: {}", err.message()),
+            Some("Check the config file syntax near the reported line".to_string()),
+            RecoveryAction::PromptUser("Fix the config file or run config:edit".to_string()),
+        )
+    }
+}
+
+impl From<toml::ser::Error> for OmniError {
+    fn from(err: toml::ser::Error) -> Self {
+        OmniError::config(format!("Failed to serialize config: {}", err), None, RecoveryAction::None)
+    }
+}
+
+impl From<rusqlite::Error> for OmniError {
+    fn from(err: rusqlite::Error) -> Self {
+        let busy = matches!(
+            err.sqlite_error_code(),
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+        );
+        if busy {
+            OmniError::storage(
+                format!("State database is busy: {}", err),
+                Some("Another omniscient-shell instance may be using the database".to_string()),
+                RecoveryAction::Retry,
+            )
+        } else {
+            OmniError::storage(
+                format!("State database error: {}", err),
+                Some("The state database under ~/.omniscient may be damaged".to_string()),
+                RecoveryAction::None,
+            )
+        }
+    }
+}
+
+impl From<keyring::Error> for OmniError {
+    fn from(err: keyring::Error) -> Self {
+        match err {
+            keyring::Error::NoEntry => OmniError::oauth(
+                "No token stored in the OS keychain",
+                Some("The provider may not be connected yet".to_string()),
+                RecoveryAction::PromptUser("Connect the provider with oauth:connect".to_string()),
+            ),
+            other => OmniError::oauth(
+                format!("OS keychain error: {}", other),
+                Some("Set vault.backend = \"encrypted_sqlite\" if no keychain is available".to_string()),
+                RecoveryAction::Fallback("encrypted SQLite vault".to_string()),
+            ),
+        }
+    }
+}
+
+impl From<oauth2::reqwest::AsyncHttpClientError> for OmniError {
+    fn from(err: oauth2::reqwest::AsyncHttpClientError) -> Self {
+        let detail = match &err {
+            oauth2::reqwest::Error::Reqwest(inner) => inner.to_string(),
+            other => other.to_string(),
+        };
+        OmniError::oauth(
+            format!("HTTP request to the OAuth provider failed: {}", detail),
+            Some("Check your network connection".to_string()),
+            RecoveryAction::Retry,
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,4 +289,24 @@ mod tests {
             RecoveryAction::Fallback("Kitty protocol".to_string())
         );
     }
+
+    #[test]
+    fn test_from_rusqlite_error() {
+        let err: OmniError = rusqlite::Error::QueryReturnedNoRows.into();
+        assert!(matches!(err, OmniError::Storage { .. }));
+        assert!(!err.to_string().is_empty());
+        assert!(err.to_string().contains("Query returned no rows"));
+        assert!(err.hint().is_some());
+    }
+
+    #[test]
+    fn test_from_toml_and_keyring_errors() {
+        let err: OmniError = toml::from_str::<toml::Value>("version = ").unwrap_err().into();
+        assert!(matches!(err, OmniError::Config { .. }));
+        assert!(matches!(err.recovery_action(), RecoveryAction::PromptUser(_)));
+
+        let err: OmniError = keyring::Error::NoEntry.into();
+        assert!(matches!(err, OmniError::OAuth { .. }));
+        assert!(matches!(err.recovery_action(), RecoveryAction::PromptUser(_)));
+    }
 }
@@ -0,0 +1,159 @@
+//! Session idle detection: fires configured actions after a period without input
+
+use anyhow::Result;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// What to do when the session goes idle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleAction {
+    /// Lock the token vault
+    LockVault,
+    /// Revoke capability grants that have an expiry
+    RevokeShortLived,
+    /// Dim the UI until the next input
+    DimUi,
+}
+
+impl IdleAction {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "lock_vault" => Ok(IdleAction::LockVault),
+            "revoke_short_lived" => Ok(IdleAction::RevokeShortLived),
+            "dim_ui" => Ok(IdleAction::DimUi),
+            _ => anyhow::bail!(
+                "Unknown idle action '{}'. Expected lock_vault, revoke_short_lived or dim_ui",
+                s
+            ),
+        }
+    }
+
+    /// Parse configured actions, skipping unknown ones with a warning
+    pub fn parse_all(actions: &[String]) -> Vec<Self> {
+        actions
+            .iter()
+            .filter_map(|action| match Self::parse(action) {
+                Ok(action) => Some(action),
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Background countdown that fires once per idle period; input restarts it
+pub struct IdleMonitor {
+    activity: watch::Sender<Instant>,
+    handle: JoinHandle<()>,
+}
+
+impl IdleMonitor {
+    /// Start counting down from now; `on_idle` runs after `timeout` without activity
+    pub fn spawn<F, Fut>(timeout: Duration, mut on_idle: F) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let (activity, mut last_activity) = watch::channel(Instant::now());
+
+        let handle = tokio::spawn(async move {
+            loop {
+                let deadline = *last_activity.borrow_and_update() + timeout;
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => {
+                        tracing::info!("Session idle for {:?}", timeout);
+                        on_idle().await;
+                        // Stay quiet until the user is back
+                        if last_activity.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                    changed = last_activity.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        IdleMonitor { activity, handle }
+    }
+
+    /// Note user input, restarting the countdown
+    pub fn record_activity(&self) {
+        self.activity.send_replace(Instant::now());
+    }
+}
+
+impl Drop for IdleMonitor {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn counting_monitor(timeout: Duration) -> (IdleMonitor, Arc<AtomicUsize>) {
+        let fired = Arc::new(AtomicUsize::new(0));
+        let counter = fired.clone();
+        let monitor = IdleMonitor::spawn(timeout, move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        (monitor, fired)
+    }
+
+    async fn advance(secs: u64) {
+        tokio::time::advance(Duration::from_secs(secs)).await;
+        // Let the monitor task observe the new time
+        tokio::task::yield_now().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_activity_resets_countdown() {
+        let (monitor, fired) = counting_monitor(Duration::from_secs(60));
+
+        advance(45).await;
+        monitor.record_activity();
+        advance(45).await;
+        assert_eq!(fired.load(Ordering::SeqCst), 0);
+
+        advance(20).await;
+        assert_eq!(fired.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fires_once_per_idle_period() {
+        let (monitor, fired) = counting_monitor(Duration::from_secs(60));
+
+        advance(61).await;
+        advance(600).await;
+        assert_eq!(fired.load(Ordering::SeqCst), 1);
+
+        monitor.record_activity();
+        advance(61).await;
+        assert_eq!(fired.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_parse_actions() {
+        let actions = IdleAction::parse_all(&[
+            "lock_vault".to_string(),
+            "dim".to_string(),
+            "dim_ui".to_string(),
+        ]);
+        assert_eq!(actions, vec![IdleAction::LockVault, IdleAction::DimUi]);
+    }
+}
@@ -1,6 +1,10 @@
 //! Utility modules for configuration, logging, and error handling
 
 pub mod config;
+pub mod profiles;
 pub mod errors;
 pub mod logging;
 pub mod telemetry;
+pub mod build_info;
+pub mod idle;
+pub mod artifact_kind;
@@ -0,0 +1,145 @@
+//! Named config profiles (e.g. work, personal) stored as whole config files
+//!
+//! Each profile is `~/.omniscient/profiles/<name>.toml`. The profile last
+//! chosen is remembered in `~/.omniscient/active_profile`; without one the
+//! regular `~/.omniscient/config.toml` is used.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+/// Profile name that selects the regular config file
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Profiles under one `.omniscient` directory
+#[derive(Debug, Clone)]
+pub struct Profiles {
+    root: PathBuf,
+}
+
+impl Profiles {
+    /// Profiles under `root`, normally `~/.omniscient`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Profiles { root: root.into() }
+    }
+
+    /// Directory holding the profile configs
+    pub fn dir(&self) -> PathBuf {
+        self.root.join("profiles")
+    }
+
+    /// Config file for `name`; `default` is the regular config file
+    pub fn path(&self, name: &str) -> Result<PathBuf> {
+        if name == DEFAULT_PROFILE {
+            return Ok(self.root.join("config.toml"));
+        }
+        if name.is_empty()
+            || name.starts_with('.')
+            || name.contains(|c: char| c == '/' || c == '\\' || c.is_whitespace())
+        {
+            anyhow::bail!("Invalid profile name: '{}'", name);
+        }
+        Ok(self.dir().join(format!("{}.toml", name)))
+    }
+
+    /// Names of the profiles on disk, sorted, after `default`
+    pub fn list(&self) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(self.dir())
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| {
+                        let path = entry.path();
+                        if path.extension()? != "toml" {
+                            return None;
+                        }
+                        let name = path.file_stem()?.to_string_lossy().into_owned();
+                        (name != DEFAULT_PROFILE).then_some(name)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names.insert(0, DEFAULT_PROFILE.to_string());
+        names
+    }
+
+    fn active_file(&self) -> PathBuf {
+        self.root.join("active_profile")
+    }
+
+    /// Profile chosen last time, or `default`
+    pub fn active(&self) -> String {
+        fs::read_to_string(self.active_file())
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+    }
+
+    /// Remember `name` as the profile for the next launch
+    pub fn set_active(&self, name: &str) -> Result<()> {
+        self.path(name)?;
+        fs::create_dir_all(&self.root)
+            .with_context(|| format!("Failed to create {}", self.root.display()))?;
+        fs::write(self.active_file(), format!("{}\n", name))
+            .with_context(|| format!("Failed to save active profile '{}'", name))
+    }
+
+    /// Config file of the active profile, falling back to `default` if the
+    /// remembered profile's file was removed
+    pub fn active_path(&self) -> PathBuf {
+        let name = self.active();
+        match self.path(&name) {
+            Ok(path) if name == DEFAULT_PROFILE || path.exists() => path,
+            _ => {
+                tracing::warn!("Profile '{}' not found, using the default config", name);
+                self.root.join("config.toml")
+            }
+        }
+    }
+
+    /// The profile after `current` in `list()`, wrapping around
+    pub fn next_after(&self, current: &str) -> String {
+        let names = self.list();
+        let index = names.iter().position(|name| name == current).map_or(0, |i| i + 1);
+        names[index % names.len()].clone()
+    }
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+        Profiles::new(home.join(".omniscient"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_paths_and_active() {
+        let dir = tempfile::tempdir().unwrap();
+        let profiles = Profiles::new(dir.path());
+        assert_eq!(profiles.active(), "default");
+        assert_eq!(profiles.active_path(), dir.path().join("config.toml"));
+        assert!(profiles.path("../escape").is_err());
+        assert!(profiles.set_active("").is_err());
+
+        fs::create_dir_all(profiles.dir()).unwrap();
+        fs::write(profiles.dir().join("work.toml"), "").unwrap();
+        fs::write(profiles.dir().join("personal.toml"), "").unwrap();
+        fs::write(profiles.dir().join("notes.txt"), "").unwrap();
+        assert_eq!(profiles.list(), vec!["default", "personal", "work"]);
+        assert_eq!(profiles.next_after("work"), "default");
+
+        profiles.set_active("work").unwrap();
+        assert_eq!(Profiles::new(dir.path()).active(), "work");
+        assert_eq!(profiles.active_path(), profiles.dir().join("work.toml"));
+
+        // A removed profile falls back to the default config
+        fs::remove_file(profiles.dir().join("work.toml")).unwrap();
+        assert_eq!(profiles.active_path(), dir.path().join("config.toml"));
+    }
+}
@@ -1,18 +1,26 @@
 //! Telemetry system (opt-in only, performance metrics, no secrets)
 
 use anyhow::Result;
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
 use std::sync::Arc;
-use std::time::{Duration, Instant, SystemTime};
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, Notify, RwLock};
+
+use crate::state::SqliteStore;
 
 /// Telemetry configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TelemetryConfig {
     pub enabled: bool,
     pub endpoint: Option<String>,
     pub sample_rate: f32, // 0.0 to 1.0
+    pub buffer_size: usize, // events held until flushed
+    pub overflow: OverflowPolicy,
+    pub flush_interval_secs: u64, // background flush to `endpoint`; 0 disables
 }
 
 impl Default for TelemetryConfig {
@@ -21,12 +29,29 @@ impl Default for TelemetryConfig {
             enabled: false, // Opt-in only
             endpoint: None,
             sample_rate: 1.0,
+            buffer_size: 1000,
+            overflow: OverflowPolicy::DropOldest,
+            flush_interval_secs: 60,
         }
     }
 }
 
+/// What to do with a new event when the buffer is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Evict the oldest event
+    #[default]
+    DropOldest,
+    /// Discard the new event, unless it records a failure
+    DropNewest,
+    /// Wait until `flush` or `drain` makes room, falling back to
+    /// `DropOldest` if nothing does within `BLOCK_TIMEOUT`
+    Block,
+}
+
 /// Telemetry event
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TelemetryEvent {
     pub timestamp: SystemTime,
     pub event_type: String,
@@ -62,10 +87,44 @@ impl PerformanceMetric {
     }
 }
 
+/// How long a flush waits for the endpoint
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a `Block` writer waits for room before evicting the oldest event
+const BLOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Metadata keys containing any of these are dropped before an event is kept
+pub const SENSITIVE_KEYS: &[&str] = &["token", "password", "secret", "key", "auth", "credential"];
+
+/// Wait for a flush or drain to make room. Nothing may ever do so (no
+/// endpoint, or one that keeps failing), so give up after `BLOCK_TIMEOUT`
+/// and return the policy to retry with.
+async fn wait_for_space(space: tokio::sync::futures::Notified<'_>) -> OverflowPolicy {
+    match tokio::time::timeout(BLOCK_TIMEOUT, space).await {
+        Ok(()) => OverflowPolicy::Block,
+        Err(_) => {
+            tracing::warn!(
+                "Telemetry buffer still full after {:?}; dropping the oldest event",
+                BLOCK_TIMEOUT
+            );
+            OverflowPolicy::DropOldest
+        }
+    }
+}
+
+fn is_sensitive(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SENSITIVE_KEYS.iter().any(|sensitive| key.contains(sensitive))
+}
+
 /// Telemetry collector
 pub struct TelemetryCollector {
     config: Arc<RwLock<TelemetryConfig>>,
     events: Arc<RwLock<Vec<TelemetryEvent>>>,
+    /// Wakes `Block` writers when the buffer is emptied
+    space: Arc<Notify>,
+    /// When set, events are buffered in the `telemetry_events` table instead of `events`
+    db: Option<Arc<Mutex<Connection>>>,
 }
 
 impl TelemetryCollector {
@@ -73,9 +132,26 @@ impl TelemetryCollector {
         TelemetryCollector {
             config: Arc::new(RwLock::new(config)),
             events: Arc::new(RwLock::new(Vec::new())),
+            space: Arc::new(Notify::new()),
+            db: None,
+        }
+    }
+
+    /// Collector that buffers events in the `telemetry_events` table of
+    /// `conn` rather than in memory, so unsent events survive restarts. The
+    /// table is bounded by `buffer_size` and `overflow` like the memory buffer.
+    pub fn with_connection(conn: Arc<Mutex<Connection>>, config: TelemetryConfig) -> Self {
+        TelemetryCollector {
+            db: Some(conn),
+            ..Self::new(config)
         }
     }
 
+    /// Collector that buffers events in `store` instead of memory
+    pub fn new_persistent(store: Arc<SqliteStore>, config: TelemetryConfig) -> Self {
+        TelemetryCollector::with_connection(store.shared_connection(), config)
+    }
+
     /// Check if telemetry is enabled
     pub async fn is_enabled(&self) -> bool {
         let config = self.config.read().await;
@@ -95,8 +171,11 @@ impl TelemetryCollector {
         }
 
         // Sample based on rate
-        let config = self.config.read().await;
-        if rand::random::<f32>() > config.sample_rate {
+        let (sample_rate, capacity, mut overflow) = {
+            let config = self.config.read().await;
+            (config.sample_rate, config.buffer_size.max(1), config.overflow)
+        };
+        if rand::random::<f32>() > sample_rate {
             return Ok(());
         }
 
@@ -108,15 +187,182 @@ impl TelemetryCollector {
             success,
         };
 
+        if let Some(db) = &self.db {
+            return self.record_persisted(db, event, capacity, overflow).await;
+        }
+
+        loop {
+            let mut events = self.events.write().await;
+            if events.len() < capacity {
+                events.push(event);
+                return Ok(());
+            }
+
+            match overflow {
+                OverflowPolicy::DropOldest => {
+                    events.remove(0);
+                    events.push(event);
+                    return Ok(());
+                }
+                OverflowPolicy::DropNewest => {
+                    if !event.success {
+                        // Failures are the events worth keeping: evict the oldest success instead
+                        let evict = events.iter().position(|e| e.success).unwrap_or(0);
+                        events.remove(evict);
+                        events.push(event);
+                    }
+                    return Ok(());
+                }
+                OverflowPolicy::Block => {
+                    // Register before unlocking so a flush in between isn't missed
+                    let space = self.space.notified();
+                    drop(events);
+                    overflow = wait_for_space(space).await;
+                    if !self.is_enabled().await {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Save an event to the table, applying the overflow policy once it
+    /// holds `capacity` events
+    async fn record_persisted(
+        &self,
+        db: &Mutex<Connection>,
+        event: TelemetryEvent,
+        capacity: usize,
+        mut overflow: OverflowPolicy,
+    ) -> Result<()> {
+        loop {
+            let conn = db.lock().await;
+            let count = count_events(&conn)?;
+            if count < capacity {
+                return insert_event(&conn, &event);
+            }
+
+            match overflow {
+                OverflowPolicy::DropOldest => {
+                    evict_oldest(&conn, count + 1 - capacity)?;
+                    return insert_event(&conn, &event);
+                }
+                OverflowPolicy::DropNewest => {
+                    if !event.success {
+                        evict_for_failure(&conn)?;
+                        insert_event(&conn, &event)?;
+                    }
+                    return Ok(());
+                }
+                OverflowPolicy::Block => {
+                    let space = self.space.notified();
+                    drop(conn);
+                    overflow = wait_for_space(space).await;
+                    if !self.is_enabled().await {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Take all buffered events, making room for blocked writers
+    pub async fn drain(&self) -> Vec<TelemetryEvent> {
+        if let Some(db) = &self.db {
+            let conn = db.lock().await;
+            let events = match load_events(&conn) {
+                Ok(rows) => rows,
+                Err(e) => {
+                    tracing::warn!("Failed to read telemetry events: {}", e);
+                    return Vec::new();
+                }
+            };
+            if let Some((last_row, _)) = events.last() {
+                if let Err(e) = delete_through(&conn, *last_row) {
+                    tracing::warn!("Failed to clear telemetry events: {}", e);
+                }
+            }
+            self.space.notify_waiters();
+            return events.into_iter().map(|(_, event)| event).collect();
+        }
+
+        let events = std::mem::take(&mut *self.events.write().await);
+        self.space.notify_waiters();
+        events
+    }
+
+    /// POST buffered events as a JSON array to the configured endpoint and
+    /// drop them once accepted; returns how many were sent. Without an
+    /// endpoint this does nothing. On failure the events stay buffered for
+    /// the next flush.
+    pub async fn flush(&self) -> Result<usize> {
+        let Some(endpoint) = self.config.read().await.endpoint.clone() else {
+            return Ok(0);
+        };
+        if let Some(db) = &self.db {
+            return self.flush_persisted(db, &endpoint).await;
+        }
+        let batch = self.events.read().await.clone();
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        send(&endpoint, &batch).await?;
+
+        // Remove what was sent but keep events recorded during the upload.
+        // Overflow may have evicted the start of the batch meanwhile, so
+        // line the buffer up with wherever the batch now begins.
         let mut events = self.events.write().await;
-        events.push(event);
+        let offset = events.first().and_then(|first| batch.iter().position(|e| e == first));
+        let sent = offset.map_or(0, |offset| {
+            events.iter().zip(&batch[offset..]).take_while(|(kept, sent)| kept == sent).count()
+        });
+        events.drain(..sent);
+        self.space.notify_waiters();
+        tracing::debug!("Sent {} telemetry events to {}", batch.len(), endpoint);
+        Ok(batch.len())
+    }
 
-        // Limit buffer size
-        if events.len() > 1000 {
-            events.drain(0..500); // Keep most recent 500
+    /// Send the events in the table and delete their rows once accepted.
+    /// Rows added during the upload have later ids and are kept.
+    async fn flush_persisted(&self, db: &Mutex<Connection>, endpoint: &str) -> Result<usize> {
+        let rows = load_events(&*db.lock().await)?;
+        let Some(&(last_row, _)) = rows.last() else {
+            return Ok(0);
+        };
+        let batch: Vec<TelemetryEvent> = rows.into_iter().map(|(_, event)| event).collect();
+        send(endpoint, &batch).await?;
+
+        delete_through(&*db.lock().await, last_row)?;
+        self.space.notify_waiters();
+        tracing::debug!("Sent {} telemetry events to {}", batch.len(), endpoint);
+        Ok(batch.len())
+    }
+
+    /// Flush every `flush_interval_secs` in the background; None when the
+    /// interval is 0
+    pub async fn spawn_flusher(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let secs = self.config.read().await.flush_interval_secs;
+        if secs == 0 {
+            return None;
         }
 
-        Ok(())
+        let collector = Arc::downgrade(self);
+        Some(tokio::spawn(async move {
+            let period = Duration::from_secs(secs);
+            let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                ticks.tick().await;
+                let Some(collector) = collector.upgrade() else {
+                    return;
+                };
+                if !collector.is_enabled().await {
+                    continue;
+                }
+                if let Err(e) = collector.flush().await {
+                    tracing::warn!("{}; will retry", e);
+                }
+            }
+        }))
     }
 
     /// Record a performance metric
@@ -130,11 +376,7 @@ impl TelemetryCollector {
     /// Sanitize metadata to remove sensitive information
     fn sanitize_metadata(&self, mut metadata: HashMap<String, String>) -> HashMap<String, String> {
         // Remove any keys that might contain secrets
-        let sensitive_keys = ["token", "password", "secret", "key", "auth", "credential"];
-        
-        metadata.retain(|k, _| {
-            !sensitive_keys.iter().any(|&sensitive| k.to_lowercase().contains(sensitive))
-        });
+        metadata.retain(|k, _| !is_sensitive(k));
 
         // Truncate long values
         for value in metadata.values_mut() {
@@ -149,6 +391,13 @@ impl TelemetryCollector {
 
     /// Get summary statistics
     pub async fn get_summary(&self) -> TelemetrySummary {
+        if let Some(db) = &self.db {
+            return summarize(&*db.lock().await).unwrap_or_else(|e| {
+                tracing::warn!("Failed to summarize telemetry: {}", e);
+                TelemetrySummary::default()
+            });
+        }
+
         let events = self.events.read().await;
         
         let total_events = events.len();
@@ -172,10 +421,42 @@ impl TelemetryCollector {
         }
     }
 
+    /// What the recorded events would send: shown before telemetry is
+    /// enabled or flushed
+    pub async fn privacy_report(&self) -> PrivacyReport {
+        PrivacyReport::from_events(&self.events().await)
+    }
+
+    /// Recorded events, oldest first
+    pub async fn events(&self) -> Vec<TelemetryEvent> {
+        if let Some(db) = &self.db {
+            return match load_events(&*db.lock().await) {
+                Ok(rows) => rows.into_iter().map(|(_, event)| event).collect(),
+                Err(e) => {
+                    tracing::warn!("Failed to read telemetry events: {}", e);
+                    Vec::new()
+                }
+            };
+        }
+        self.events.read().await.clone()
+    }
+
+    /// Delete persisted events, if any
+    async fn clear_persisted(&self) {
+        if let Some(db) = &self.db {
+            if let Err(e) = db.lock().await.execute("DELETE FROM telemetry_events", []) {
+                tracing::warn!("Failed to clear telemetry events: {}", e);
+            }
+            self.space.notify_waiters();
+        }
+    }
+
     /// Clear all events
     pub async fn clear(&self) {
+        self.clear_persisted().await;
         let mut events = self.events.write().await;
         events.clear();
+        self.space.notify_waiters();
     }
 
     /// Enable telemetry
@@ -191,8 +472,10 @@ impl TelemetryCollector {
         config.enabled = false;
         
         // Clear existing data
+        self.clear_persisted().await;
         let mut events = self.events.write().await;
         events.clear();
+        self.space.notify_waiters();
         
         tracing::info!("Telemetry disabled and data cleared");
     }
@@ -204,8 +487,116 @@ impl Default for TelemetryCollector {
     }
 }
 
+/// POST `batch` as a JSON array to `endpoint`
+async fn send(endpoint: &str, batch: &[TelemetryEvent]) -> Result<()> {
+    tracing::debug!("{}", PrivacyReport::from_events(batch));
+    reqwest::Client::new()
+        .post(endpoint)
+        .timeout(FLUSH_TIMEOUT)
+        .json(batch)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| anyhow::anyhow!("Failed to send telemetry to {}: {}", endpoint, e))?;
+    Ok(())
+}
+
+fn count_events(conn: &Connection) -> Result<usize> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM telemetry_events", [], |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+/// Delete the `n` oldest events
+fn evict_oldest(conn: &Connection, n: usize) -> Result<()> {
+    conn.execute(
+        "DELETE FROM telemetry_events WHERE id IN (SELECT id FROM telemetry_events ORDER BY id LIMIT ?1)",
+        params![n as i64],
+    )?;
+    Ok(())
+}
+
+/// Make room for a failure: delete the oldest success, or the oldest event
+/// if all of them are failures
+fn evict_for_failure(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "DELETE FROM telemetry_events
+         WHERE id = (SELECT id FROM telemetry_events ORDER BY success DESC, id LIMIT 1)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Delete events up to and including row `last_row`
+fn delete_through(conn: &Connection, last_row: i64) -> Result<()> {
+    conn.execute("DELETE FROM telemetry_events WHERE id <= ?1", params![last_row])?;
+    Ok(())
+}
+
+fn insert_event(conn: &Connection, event: &TelemetryEvent) -> Result<()> {
+    let timestamp_ms = event.timestamp.duration_since(UNIX_EPOCH)?.as_millis() as i64;
+    conn.execute(
+        "INSERT INTO telemetry_events (timestamp, event_type, duration_ms, metadata, success)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            timestamp_ms,
+            event.event_type,
+            event.duration_ms.map(|ms| ms as i64),
+            serde_json::to_string(&event.metadata)?,
+            event.success,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Buffered events with their row ids, oldest first
+fn load_events(conn: &Connection) -> Result<Vec<(i64, TelemetryEvent)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, timestamp, event_type, duration_ms, metadata, success
+         FROM telemetry_events ORDER BY id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<i64>>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, bool>(5)?,
+        ))
+    })?;
+
+    let mut events = Vec::new();
+    for row in rows {
+        let (row_id, timestamp_ms, event_type, duration_ms, metadata, success) = row?;
+        events.push((row_id, TelemetryEvent {
+            timestamp: UNIX_EPOCH + Duration::from_millis(timestamp_ms as u64),
+            event_type,
+            duration_ms: duration_ms.map(|ms| ms as u64),
+            metadata: serde_json::from_str(&metadata)?,
+            success,
+        }));
+    }
+    Ok(events)
+}
+
+/// Same figures as the in-memory summary, computed in SQL
+fn summarize(conn: &Connection) -> Result<TelemetrySummary> {
+    let (total, successful, duration_sum): (i64, i64, i64) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(success), 0), COALESCE(SUM(duration_ms), 0)
+         FROM telemetry_events",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    Ok(TelemetrySummary {
+        total_events: total as usize,
+        successful_events: successful as usize,
+        failed_events: (total - successful) as usize,
+        avg_duration_ms: (total > 0).then(|| (duration_sum / total) as u64),
+    })
+}
+
 /// Telemetry summary statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TelemetrySummary {
     pub total_events: usize,
     pub successful_events: usize,
@@ -213,6 +604,47 @@ pub struct TelemetrySummary {
     pub avg_duration_ms: Option<u64>,
 }
 
+/// Summary of exactly what telemetry would send
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyReport {
+    pub event_count: usize,
+    /// Distinct event types, sorted
+    pub event_types: Vec<String>,
+    /// Distinct metadata keys after sanitization, sorted
+    pub metadata_keys: Vec<String>,
+    /// True when none of `metadata_keys` matches `SENSITIVE_KEYS`
+    pub no_sensitive_keys: bool,
+}
+
+impl PrivacyReport {
+    fn from_events(events: &[TelemetryEvent]) -> Self {
+        let event_types: BTreeSet<&str> = events.iter().map(|e| e.event_type.as_str()).collect();
+        let metadata_keys: BTreeSet<&str> =
+            events.iter().flat_map(|e| e.metadata.keys().map(String::as_str)).collect();
+        PrivacyReport {
+            event_count: events.len(),
+            no_sensitive_keys: !metadata_keys.iter().any(|key| is_sensitive(key)),
+            event_types: event_types.into_iter().map(str::to_string).collect(),
+            metadata_keys: metadata_keys.into_iter().map(str::to_string).collect(),
+        }
+    }
+}
+
+impl fmt::Display for PrivacyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |items: &[String]| if items.is_empty() { "none".to_string() } else { items.join(", ") };
+        writeln!(f, "Telemetry would send {} events", self.event_count)?;
+        writeln!(f, "  Event types: {}", list(&self.event_types))?;
+        writeln!(f, "  Metadata keys: {}", list(&self.metadata_keys))?;
+        write!(
+            f,
+            "  Keys containing {} are removed: {}",
+            SENSITIVE_KEYS.join("/"),
+            if self.no_sensitive_keys { "confirmed" } else { "NOT CONFIRMED" }
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -256,6 +688,28 @@ mod tests {
         assert!(events[0].metadata.contains_key("operation"));
     }
 
+    #[tokio::test]
+    async fn test_privacy_report_lists_sanitized_keys() {
+        let collector = bounded(10, OverflowPolicy::DropOldest);
+        let metadata = HashMap::from([
+            ("operation".to_string(), "render".to_string()),
+            ("token".to_string(), "gho_secret".to_string()),
+        ]);
+        collector.record_event("render", Some(12), metadata, true).await.unwrap();
+        let metadata = HashMap::from([("backend".to_string(), "kitty".to_string())]);
+        collector.record_event("graphics.negotiate", None, metadata, true).await.unwrap();
+
+        let report = collector.privacy_report().await;
+        assert_eq!(report.event_count, 2);
+        assert_eq!(report.event_types, ["graphics.negotiate", "render"]);
+        assert_eq!(report.metadata_keys, ["backend", "operation"]);
+        assert!(report.no_sensitive_keys);
+
+        let text = report.to_string();
+        assert!(text.contains("Metadata keys: backend, operation"));
+        assert!(!text.contains("gho_secret"));
+    }
+
     #[tokio::test]
     async fn test_performance_metric() {
         let mut config = TelemetryConfig::default();
@@ -273,4 +727,219 @@ mod tests {
         assert_eq!(summary.total_events, 1);
         assert!(summary.avg_duration_ms.unwrap() >= 10);
     }
+
+    fn bounded(capacity: usize, overflow: OverflowPolicy) -> TelemetryCollector {
+        TelemetryCollector::new(TelemetryConfig {
+            enabled: true,
+            buffer_size: capacity,
+            overflow,
+            ..TelemetryConfig::default()
+        })
+    }
+
+    async fn record(collector: &TelemetryCollector, name: &str, success: bool) {
+        collector.record_event(name, None, HashMap::new(), success).await.unwrap();
+    }
+
+    async fn names(collector: &TelemetryCollector) -> Vec<String> {
+        collector.events().await.into_iter().map(|e| e.event_type).collect()
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_overflow() {
+        let collector = bounded(2, OverflowPolicy::DropOldest);
+        for name in ["a", "b", "c"] {
+            record(&collector, name, true).await;
+        }
+        assert_eq!(names(&collector).await, ["b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest_overflow_keeps_failures() {
+        let collector = bounded(2, OverflowPolicy::DropNewest);
+        record(&collector, "a", true).await;
+        record(&collector, "b", true).await;
+        record(&collector, "c", true).await;
+        assert_eq!(names(&collector).await, ["a", "b"]);
+
+        record(&collector, "error", false).await;
+        assert_eq!(names(&collector).await, ["b", "error"]);
+    }
+
+    #[tokio::test]
+    async fn test_block_overflow_waits_for_drain() {
+        let collector = Arc::new(bounded(1, OverflowPolicy::Block));
+        record(&collector, "a", true).await;
+
+        let writer = {
+            let collector = collector.clone();
+            tokio::spawn(async move { record(&collector, "b", true).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!writer.is_finished());
+
+        let drained = collector.drain().await;
+        assert_eq!(drained.len(), 1);
+        tokio::time::timeout(Duration::from_secs(1), writer).await.unwrap().unwrap();
+        assert_eq!(names(&collector).await, ["b"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_block_overflow_without_endpoint_drops_oldest() {
+        let collector = bounded(1, OverflowPolicy::Block);
+        assert!(collector.config.read().await.endpoint.is_none());
+        record(&collector, "a", true).await;
+
+        // Nothing will ever flush, so the writer gives up instead of hanging
+        let started = tokio::time::Instant::now();
+        record(&collector, "b", true).await;
+        assert!(started.elapsed() >= BLOCK_TIMEOUT);
+        assert_eq!(names(&collector).await, ["b"]);
+    }
+
+    /// Accept one HTTP request, answer with `status` and return its body
+    async fn serve_once(listener: tokio::net::TcpListener, status: &str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        let body_start = loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+        let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+        let length: usize = headers
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .map(|len| len.trim().parse().unwrap())
+            .unwrap_or(0);
+        while request.len() < body_start + length {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+
+        let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+        socket.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8(request[body_start..].to_vec()).unwrap()
+    }
+
+    fn with_endpoint(endpoint: String) -> TelemetryCollector {
+        TelemetryCollector::new(TelemetryConfig {
+            enabled: true,
+            endpoint: Some(endpoint),
+            ..TelemetryConfig::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_flush_posts_and_clears_buffer() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let collector = with_endpoint(format!("http://{}/events", listener.local_addr().unwrap()));
+        record(&collector, "a", true).await;
+        record(&collector, "b", false).await;
+
+        let server = tokio::spawn(serve_once(listener, "200 OK"));
+        assert_eq!(collector.flush().await.unwrap(), 2);
+
+        let sent: Vec<TelemetryEvent> = serde_json::from_str(&server.await.unwrap()).unwrap();
+        assert_eq!(sent.iter().map(|e| e.event_type.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert!(collector.events().await.is_empty());
+        assert_eq!(collector.flush().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_keeps_events() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let collector = with_endpoint(format!("http://{}/events", listener.local_addr().unwrap()));
+        record(&collector, "a", true).await;
+
+        let server = tokio::spawn(serve_once(listener, "503 Service Unavailable"));
+        assert!(collector.flush().await.is_err());
+        server.await.unwrap();
+        assert_eq!(names(&collector).await, ["a"]);
+
+        // Nothing listening: a connection error also keeps the events
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let collector = with_endpoint(format!("http://{}/events", closed.local_addr().unwrap()));
+        drop(closed);
+        record(&collector, "a", true).await;
+        assert!(collector.flush().await.is_err());
+        assert_eq!(names(&collector).await, ["a"]);
+
+        // No endpoint configured: nothing to do
+        let local = bounded(10, OverflowPolicy::DropOldest);
+        record(&local, "a", true).await;
+        assert_eq!(local.flush().await.unwrap(), 0);
+        assert_eq!(names(&local).await, ["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_persistent_telemetry() {
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let config = TelemetryConfig {
+            enabled: true,
+            buffer_size: 3,
+            ..TelemetryConfig::default()
+        };
+        let collector = TelemetryCollector::new_persistent(store.clone(), config.clone());
+
+        let metadata = std::collections::HashMap::from([
+            ("operation".to_string(), "render".to_string()),
+            ("token".to_string(), "secret123".to_string()),
+        ]);
+        collector.record_event("render", Some(30), metadata, true).await.unwrap();
+        collector.record_event("render", Some(10), Default::default(), false).await.unwrap();
+        collector.record_event("load", None, Default::default(), true).await.unwrap();
+
+        let events = collector.events().await;
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].metadata.get("operation").map(String::as_str), Some("render"));
+        assert!(!events[0].metadata.contains_key("token"));
+        assert_eq!(events[2].duration_ms, None);
+
+        let summary = collector.get_summary().await;
+        assert_eq!(summary.total_events, 3);
+        assert_eq!(summary.successful_events, 2);
+        assert_eq!(summary.failed_events, 1);
+        assert_eq!(summary.avg_duration_ms, Some(13));
+
+        // Events outlive the collector
+        drop(collector);
+        let reopened = TelemetryCollector::new_persistent(store, config);
+        assert_eq!(reopened.get_summary().await.total_events, 3);
+        reopened.disable().await;
+        assert!(reopened.events().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_persistent_buffer_is_bounded_and_flushed() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let config = TelemetryConfig {
+            enabled: true,
+            endpoint: Some(format!("http://{}/events", listener.local_addr().unwrap())),
+            buffer_size: 2,
+            ..TelemetryConfig::default()
+        };
+        let collector = TelemetryCollector::new_persistent(store.clone(), config.clone());
+        for name in ["a", "b", "c"] {
+            record(&collector, name, true).await;
+        }
+        assert_eq!(names(&collector).await, ["b", "c"]);
+
+        // Events recorded before a restart are sent by the next collector
+        drop(collector);
+        let collector = TelemetryCollector::new_persistent(store, config);
+        let server = tokio::spawn(serve_once(listener, "200 OK"));
+        assert_eq!(collector.flush().await.unwrap(), 2);
+
+        let sent: Vec<TelemetryEvent> = serde_json::from_str(&server.await.unwrap()).unwrap();
+        assert_eq!(sent.iter().map(|e| e.event_type.as_str()).collect::<Vec<_>>(), ["b", "c"]);
+        assert!(collector.events().await.is_empty());
+        assert_eq!(collector.flush().await.unwrap(), 0);
+    }
 }
@@ -5,58 +5,107 @@ use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use serde::{Deserialize, Serialize};
 
+pub use crate::utils::artifact_kind::ArtifactKind;
+
+/// Trim and lowercase a tag; tags may not be empty or contain commas
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        anyhow::bail!("Tag cannot be empty");
+    }
+    if tag.contains(',') {
+        anyhow::bail!("Tag cannot contain ',': {}", tag);
+    }
+    Ok(tag)
+}
+
 /// Artifact metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Artifact {
     pub id: String,
-    pub kind: String,
+    pub kind: ArtifactKind,
     pub path: PathBuf,
     pub created_at: SystemTime,
     pub size_bytes: u64,
     pub bookmarked: bool,
+    /// Normalized (trimmed, lowercase) user tags, e.g. "release-1.2"
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl Artifact {
-    pub fn new(id: String, kind: String, path: PathBuf) -> Self {
+    pub fn new(id: String, kind: impl Into<ArtifactKind>, path: PathBuf) -> Self {
         let size_bytes = std::fs::metadata(&path)
             .map(|m| m.len())
             .unwrap_or(0);
 
         Artifact {
             id,
-            kind,
+            kind: kind.into(),
             path,
             created_at: SystemTime::now(),
             size_bytes,
             bookmarked: false,
+            tags: Vec::new(),
         }
     }
 
+    /// Tag the artifact; returns false if it already had the tag
+    pub fn add_tag(&mut self, tag: &str) -> Result<bool> {
+        let tag = normalize_tag(tag)?;
+        if self.tags.contains(&tag) {
+            return Ok(false);
+        }
+        self.tags.push(tag);
+        Ok(true)
+    }
+
+    /// Remove a tag; returns false if the artifact didn't have it
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let Ok(tag) = normalize_tag(tag) else {
+            return false;
+        };
+        let before = self.tags.len();
+        self.tags.retain(|t| *t != tag);
+        self.tags.len() != before
+    }
+
+    /// Whether the artifact carries `tag` (compared normalized)
+    pub fn has_tag(&self, tag: &str) -> bool {
+        normalize_tag(tag).is_ok_and(|tag| self.tags.contains(&tag))
+    }
+
     /// Check if artifact should persist based on retention policy
     pub fn should_persist(&self, policy: &crate::workspace::retention::RetentionPolicy) -> bool {
         if self.bookmarked {
             return true; // Bookmarks always persist
         }
 
-        policy.should_persist(&self.kind)
+        policy.should_persist(self.kind.as_str())
     }
 
     /// Check if artifact is expired based on retention policy
     pub fn is_expired(&self, policy: &crate::workspace::retention::RetentionPolicy) -> bool {
+        self.is_expired_at(policy, SystemTime::now())
+    }
+
+    /// Check expiry relative to the given time
+    pub fn is_expired_at(&self, policy: &crate::workspace::retention::RetentionPolicy, now: SystemTime) -> bool {
         if self.bookmarked {
             return false; // Bookmarks never expire
         }
 
-        if policy.should_persist(&self.kind) {
+        if policy.should_persist(self.kind.as_str()) {
             return false; // Always-persist types never expire
         }
 
         // Check age
-        let age = SystemTime::now()
-            .duration_since(self.created_at)
-            .unwrap_or_default();
+        self.age_at(now).as_secs() > policy.days as u64 * 24 * 3600
+    }
 
-        age.as_secs() > (policy.days * 24 * 3600) as u64
+    /// Age of the artifact relative to the given time
+    pub fn age_at(&self, now: SystemTime) -> std::time::Duration {
+        now.duration_since(self.created_at).unwrap_or_default()
     }
 }
 
@@ -76,4 +125,19 @@ mod tests {
         assert_eq!(artifact.kind, "diff");
         assert!(!artifact.bookmarked);
     }
+
+    #[test]
+    fn test_tags_are_normalized() {
+        let mut artifact = Artifact::new("a".to_string(), "diff".to_string(), PathBuf::from("/tmp/a.diff"));
+        assert!(artifact.add_tag("  Release-1.2 ").unwrap());
+        assert!(!artifact.add_tag("release-1.2").unwrap());
+        assert_eq!(artifact.tags, ["release-1.2"]);
+        assert!(artifact.has_tag("RELEASE-1.2"));
+        assert!(artifact.add_tag(" ").is_err());
+        assert!(artifact.add_tag("a,b").is_err());
+
+        assert!(artifact.remove_tag("Release-1.2"));
+        assert!(!artifact.remove_tag("release-1.2"));
+        assert!(artifact.tags.is_empty());
+    }
 }
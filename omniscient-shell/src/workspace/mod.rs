@@ -3,7 +3,11 @@
 pub mod selection;
 pub mod artifacts;
 pub mod retention;
+pub mod sweeper;
+pub mod watcher;
 
-pub use selection::Workspace;
-pub use artifacts::Artifact;
-pub use retention::{RetentionPolicy, PruneStrategy};
+pub use selection::{Workspace, ArtifactStorage};
+pub use artifacts::{Artifact, ArtifactKind};
+pub use retention::{RetentionPolicy, PruneStrategy, ArtifactPreview};
+pub use sweeper::{RetentionSweeper, SweepReason};
+pub use watcher::WorkspaceWatcher;
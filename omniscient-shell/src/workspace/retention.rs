@@ -2,12 +2,18 @@
 
 use anyhow::Result;
 use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::state::artifact_index::ArtifactIndex;
+use crate::utils::config::{combine_errors, RetentionConfig};
+use crate::workspace::artifacts::{Artifact, ArtifactKind};
 
 /// Retention policy
 #[derive(Debug, Clone)]
 pub struct RetentionPolicy {
-    pub always_persist: HashSet<String>,
-    pub ephemeral: HashSet<String>,
+    pub always_persist: HashSet<ArtifactKind>,
+    pub ephemeral: HashSet<ArtifactKind>,
     pub days: u32,
     pub max_mb: u32,
 }
@@ -15,21 +21,34 @@ pub struct RetentionPolicy {
 impl RetentionPolicy {
     pub fn new(always_persist: Vec<String>, ephemeral: Vec<String>, days: u32, max_mb: u32) -> Self {
         RetentionPolicy {
-            always_persist: always_persist.into_iter().collect(),
-            ephemeral: ephemeral.into_iter().collect(),
+            always_persist: always_persist.into_iter().map(ArtifactKind::from).collect(),
+            ephemeral: ephemeral.into_iter().map(ArtifactKind::from).collect(),
             days,
             max_mb,
         }
     }
 
+    /// Build a policy from config, rejecting reserved or ill-formed artifact kinds
+    pub fn from_config(config: &RetentionConfig) -> Result<Self> {
+        config.validate().map_err(combine_errors)?;
+        let parse_kinds = |kinds: &[String]| kinds.iter().map(|k| ArtifactKind::parse(k)).collect();
+
+        Ok(RetentionPolicy {
+            always_persist: parse_kinds(&config.always_persist),
+            ephemeral: parse_kinds(&config.ephemeral),
+            days: config.days,
+            max_mb: config.max_mb,
+        })
+    }
+
     /// Check if a kind should always persist
     pub fn should_persist(&self, kind: &str) -> bool {
-        self.always_persist.contains(kind)
+        self.always_persist.contains(&ArtifactKind::parse(kind))
     }
 
     /// Check if a kind is ephemeral
     pub fn is_ephemeral(&self, kind: &str) -> bool {
-        self.ephemeral.contains(kind)
+        self.ephemeral.contains(&ArtifactKind::parse(kind))
     }
 
     /// Get TTL in days for a kind
@@ -41,21 +60,111 @@ impl RetentionPolicy {
         }
     }
 
-    /// Prune artifacts based on strategy
-    pub async fn prune(&self, strategy: PruneStrategy) -> Result<Vec<String>> {
-        // Placeholder for pruning logic
-        // Real implementation would:
-        // 1. Query artifact index
-        // 2. Filter by strategy (size/time)
-        // 3. Exclude bookmarks
-        // 4. Delete files
-        // 5. Return pruned artifact IDs
-        
+    /// Preview what `prune` would remove, without deleting anything
+    pub async fn would_prune(
+        &self,
+        index: &ArtifactIndex,
+        workspace: &str,
+        strategy: PruneStrategy,
+    ) -> Result<Vec<ArtifactPreview>> {
+        let artifacts = index.list(workspace).await?;
+        let now = SystemTime::now();
+
+        Ok(self
+            .select_for_pruning(&artifacts, &strategy, now)
+            .into_iter()
+            .map(|artifact| ArtifactPreview {
+                id: artifact.id.clone(),
+                kind: artifact.kind.clone(),
+                path: artifact.path.clone(),
+                size_bytes: artifact.size_bytes,
+                age: artifact.age_at(now),
+            })
+            .collect())
+    }
+
+    /// Prune artifacts based on strategy, returning the pruned artifact IDs
+    pub async fn prune(
+        &self,
+        index: &ArtifactIndex,
+        workspace: &str,
+        strategy: PruneStrategy,
+    ) -> Result<Vec<String>> {
         tracing::info!("Pruning artifacts with strategy: {:?}", strategy);
-        Ok(vec![])
+
+        let artifacts = index.list(workspace).await?;
+        let selected = self.select_for_pruning(&artifacts, &strategy, SystemTime::now());
+
+        let mut pruned = Vec::with_capacity(selected.len());
+        for artifact in selected {
+            if let Err(e) = std::fs::remove_file(&artifact.path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::warn!("Failed to delete artifact {}: {}", artifact.path.display(), e);
+                    continue;
+                }
+            }
+            index.remove(&artifact.id).await?;
+            tracing::debug!("Pruned artifact: {} ({})", artifact.id, artifact.kind);
+            pruned.push(artifact.id.clone());
+        }
+
+        Ok(pruned)
+    }
+
+    /// Select artifacts to prune; shared by `prune` and `would_prune`
+    ///
+    /// Bookmarks and always-persist kinds are never selected. Size-based
+    /// pruning removes ephemeral kinds first, then oldest first.
+    fn select_for_pruning<'a>(
+        &self,
+        artifacts: &'a [Artifact],
+        strategy: &PruneStrategy,
+        now: SystemTime,
+    ) -> Vec<&'a Artifact> {
+        let mut selected: Vec<&Artifact> = Vec::new();
+
+        if matches!(strategy, PruneStrategy::ByAge | PruneStrategy::Both) {
+            selected.extend(artifacts.iter().filter(|a| a.is_expired_at(self, now)));
+        }
+
+        if matches!(strategy, PruneStrategy::BySize | PruneStrategy::Both) {
+            let max_bytes = self.max_mb as u64 * 1024 * 1024;
+            let mut total: u64 = artifacts
+                .iter()
+                .filter(|a| !selected.iter().any(|s| s.id == a.id))
+                .map(|a| a.size_bytes)
+                .sum();
+
+            let mut candidates: Vec<&Artifact> = artifacts
+                .iter()
+                .filter(|a| !a.should_persist(self))
+                .filter(|a| !selected.iter().any(|s| s.id == a.id))
+                .collect();
+            candidates.sort_by_key(|a| (!self.is_ephemeral(a.kind.as_str()), a.created_at));
+
+            for artifact in candidates {
+                if total <= max_bytes {
+                    break;
+                }
+                total -= artifact.size_bytes;
+                selected.push(artifact);
+            }
+        }
+
+        selected
     }
 }
 
+/// Artifact that would be removed by a prune
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactPreview {
+    pub id: String,
+    pub kind: ArtifactKind,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub age: Duration,
+}
+
 /// Pruning strategy
 #[derive(Debug, Clone)]
 pub enum PruneStrategy {
@@ -78,6 +187,69 @@ impl Default for RetentionPolicy {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::sqlite::SqliteStore;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    /// Seed an index with one expired ephemeral artifact, one expired bookmark,
+    /// one expired always-persist artifact and one fresh artifact
+    async fn seeded_index(dir: &TempDir) -> ArtifactIndex {
+        let index = ArtifactIndex::new(Arc::new(SqliteStore::in_memory().unwrap()));
+        let old = SystemTime::now() - Duration::from_secs(60 * 24 * 3600);
+
+        for (id, kind, created_at, bookmarked) in [
+            ("old-preview", "preview", old, false),
+            ("old-bookmark", "scratch", old, true),
+            ("old-diff", "diff", old, false),
+            ("new-preview", "preview", SystemTime::now(), false),
+        ] {
+            let path = dir.path().join(id);
+            std::fs::write(&path, b"artifact").unwrap();
+            let mut artifact = Artifact::new(id.to_string(), kind, path);
+            artifact.created_at = created_at;
+            artifact.bookmarked = bookmarked;
+            index.insert("ws", &artifact).await.unwrap();
+        }
+
+        index
+    }
+
+    #[tokio::test]
+    async fn test_would_prune_matches_prune() {
+        let dir = TempDir::new().unwrap();
+        let index = seeded_index(&dir).await;
+        let policy = RetentionPolicy::default();
+
+        let preview = policy.would_prune(&index, "ws", PruneStrategy::ByAge).await.unwrap();
+        let preview_ids: Vec<String> = preview.iter().map(|p| p.id.clone()).collect();
+        assert_eq!(preview_ids, vec!["old-preview".to_string()]);
+        assert_eq!(preview[0].kind, ArtifactKind::Preview);
+        assert!(preview[0].age >= Duration::from_secs(59 * 24 * 3600));
+
+        // Dry run deletes nothing
+        assert!(dir.path().join("old-preview").exists());
+        assert_eq!(index.list("ws").await.unwrap().len(), 4);
+
+        let pruned = policy.prune(&index, "ws", PruneStrategy::ByAge).await.unwrap();
+        assert_eq!(pruned, preview_ids);
+        assert!(!dir.path().join("old-preview").exists());
+        assert_eq!(index.list("ws").await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_would_prune_by_size() {
+        let dir = TempDir::new().unwrap();
+        let index = seeded_index(&dir).await;
+        let policy = RetentionPolicy {
+            max_mb: 0,
+            ..Default::default()
+        };
+
+        // Everything except bookmarks and always-persist kinds, oldest first
+        let preview = policy.would_prune(&index, "ws", PruneStrategy::BySize).await.unwrap();
+        let ids: Vec<&str> = preview.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["old-preview", "new-preview"]);
+    }
 
     #[test]
     fn test_retention_policy() {
@@ -92,6 +264,21 @@ mod tests {
         assert_eq!(policy.ttl("preview"), Some(30));
     }
 
+    #[test]
+    fn test_from_config_rejects_ill_formed_kinds() {
+        let mut config = crate::utils::config::Config::default().retention;
+        assert!(RetentionPolicy::from_config(&config).unwrap().is_ephemeral("preview"));
+
+        // A near miss is an error until it is allow-listed
+        config.ephemeral = vec!["preveiw".to_string(), "logs".to_string()];
+        assert!(RetentionPolicy::from_config(&config).is_err());
+        config.custom_kinds = vec!["preveiw".to_string(), "logs".to_string()];
+        assert!(RetentionPolicy::from_config(&config).unwrap().is_ephemeral("logs"));
+
+        config.ephemeral = vec!["Preview".to_string()];
+        assert!(RetentionPolicy::from_config(&config).is_err());
+    }
+
     #[test]
     fn test_custom_policy() {
         let policy = RetentionPolicy::new(
@@ -1,41 +1,163 @@
 //! Workspace selection and management
 
-use anyhow::{Context, Result};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::utils::config::{Config, WorkspaceConfig};
+use crate::utils::errors::{OmniError, OmniResult, RecoveryAction};
+use crate::workspace::artifacts::ArtifactKind;
+
+/// Where workspace artifacts are stored
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ArtifactStorage {
+    /// Inside the workspace at `<root>/.omniscient/`
+    #[default]
+    WorkspaceLocal,
+    /// Under a central root at `<root>/<workspace-hash>/`
+    Global(PathBuf),
+}
+
+impl ArtifactStorage {
+    pub fn from_config(config: &WorkspaceConfig) -> OmniResult<Self> {
+        match config.artifact_storage.as_str() {
+            "workspace" => Ok(ArtifactStorage::WorkspaceLocal),
+            "global" => {
+                let root = match &config.artifact_root {
+                    Some(root) => PathBuf::from(root),
+                    None => default_global_artifact_root(),
+                };
+                Ok(ArtifactStorage::Global(root))
+            }
+            other => Err(OmniError::config(
+                format!("Unknown artifact storage: {}", other),
+                Some("workspace.artifact_storage must be \"workspace\" or \"global\"".to_string()),
+                RecoveryAction::PromptUser("Fix workspace.artifact_storage in the config".to_string()),
+            )),
+        }
+    }
+}
+
+/// Default central artifact root (`~/.omniscient/artifacts`)
+pub fn default_global_artifact_root() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".omniscient").join("artifacts")
+}
+
+/// Stable identifier for a workspace root (FNV-1a of the path)
+pub fn workspace_hash(root: &Path) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in root.to_string_lossy().as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Workspace-local config layered over the user config, relative to the root
+pub const WORKSPACE_CONFIG_PATH: &str = ".omniscient/config.toml";
+
+/// Called with the effective config whenever selection changes it
+pub type ConfigListener = Box<dyn Fn(&Config) + Send + Sync>;
+
 /// Workspace provider
 pub struct Workspace {
     root: Arc<RwLock<Option<PathBuf>>>,
+    storage: ArtifactStorage,
+    base_config: Config,
+    effective_config: Arc<RwLock<Config>>,
+    config_listeners: Vec<ConfigListener>,
 }
 
 impl Workspace {
     pub fn new() -> Self {
+        Self::with_storage(ArtifactStorage::default())
+    }
+
+    /// Create a workspace provider with the given artifact storage
+    pub fn with_storage(storage: ArtifactStorage) -> Self {
         Workspace {
             root: Arc::new(RwLock::new(None)),
+            storage,
+            base_config: Config::default(),
+            effective_config: Arc::new(RwLock::new(Config::default())),
+            config_listeners: Vec::new(),
         }
     }
 
+    /// Use `config` as the user config that workspace configs layer over
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.effective_config = Arc::new(RwLock::new(config.clone()));
+        self.base_config = config;
+        self
+    }
+
+    /// Register a callback for effective config changes, e.g. to re-theme the dashboard
+    pub fn on_config_change(&mut self, listener: impl Fn(&Config) + Send + Sync + 'static) {
+        self.config_listeners.push(Box::new(listener));
+    }
+
+    /// Config for this session: the user config plus the selected workspace's overlay
+    pub async fn config(&self) -> Config {
+        self.effective_config.read().await.clone()
+    }
+
     /// Select a workspace (explicit selection required)
-    pub async fn select(&self, path: impl AsRef<Path>) -> Result<()> {
+    pub async fn select(&self, path: impl AsRef<Path>) -> OmniResult<()> {
         let path = path.as_ref();
         
         if !path.exists() {
-            anyhow::bail!("Workspace path does not exist: {}", path.display());
+            return Err(OmniError::workspace(
+                format!("Workspace path does not exist: {}", path.display()),
+                Some("Check the path for typos, or create the directory first".to_string()),
+                RecoveryAction::PromptUser("Select an existing directory".to_string()),
+            ));
         }
 
         if !path.is_dir() {
-            anyhow::bail!("Workspace path is not a directory: {}", path.display());
+            return Err(OmniError::workspace(
+                format!("Workspace path is not a directory: {}", path.display()),
+                Some("Select the directory that contains this file".to_string()),
+                RecoveryAction::PromptUser("Select a directory".to_string()),
+            ));
         }
 
-        let mut root = self.root.write().await;
-        *root = Some(path.to_path_buf());
+        let config = self.load_layered_config(path)?;
+
+        *self.root.write().await = Some(path.to_path_buf());
         
         tracing::info!("Workspace selected: {}", path.display());
+        self.set_config(config).await;
         Ok(())
     }
 
+    /// The base config with `<root>/.omniscient/config.toml` layered on top, if present
+    fn load_layered_config(&self, root: &Path) -> OmniResult<Config> {
+        let local = root.join(WORKSPACE_CONFIG_PATH);
+        if !local.is_file() {
+            return Ok(self.base_config.clone());
+        }
+
+        let invalid = |e: anyhow::Error| {
+            OmniError::config(
+                format!("Invalid workspace config {}: {:#}", local.display(), e),
+                Some("Workspace configs use the same keys as ~/.omniscient/config.toml".to_string()),
+                RecoveryAction::PromptUser(format!("Fix or remove {}", local.display())),
+            )
+        };
+        let contents = std::fs::read_to_string(&local).map_err(|e| invalid(e.into()))?;
+        let config = self.base_config.with_overlay(&contents).map_err(invalid)?;
+        tracing::info!("Loaded workspace config: {}", local.display());
+        Ok(config)
+    }
+
+    async fn set_config(&self, config: Config) {
+        *self.effective_config.write().await = config.clone();
+        for listener in &self.config_listeners {
+            listener(&config);
+        }
+    }
+
     /// Get current workspace root
     pub async fn root(&self) -> Option<PathBuf> {
         let root = self.root.read().await;
@@ -48,32 +170,52 @@ impl Workspace {
         root.is_some()
     }
 
-    /// Resolve artifact path within workspace
-    pub async fn resolve_artifact_path(&self, kind: &str, name: &str) -> Result<PathBuf> {
+    /// Identifier of the selected workspace, used to key the artifact index
+    pub async fn id(&self) -> Option<String> {
         let root = self.root.read().await;
-        let root = root.as_ref()
-            .ok_or_else(|| anyhow::anyhow!("No workspace selected. Use 'omni:workspace select <path>'"))?;
+        root.as_deref().map(workspace_hash)
+    }
+
+    /// Get the artifact storage mode
+    pub fn storage(&self) -> &ArtifactStorage {
+        &self.storage
+    }
+
+    /// Directory holding artifacts for the selected workspace
+    pub async fn artifact_root(&self) -> OmniResult<PathBuf> {
+        let root = self.root.read().await;
+        let root = root.as_ref().ok_or_else(|| {
+            OmniError::workspace(
+                "No workspace selected",
+                Some("Use 'omni:workspace select <path>'".to_string()),
+                RecoveryAction::PromptUser("Select a workspace".to_string()),
+            )
+        })?;
+
+        Ok(match &self.storage {
+            ArtifactStorage::WorkspaceLocal => root.join(".omniscient"),
+            ArtifactStorage::Global(global_root) => global_root.join(workspace_hash(root)),
+        })
+    }
 
-        // Create .omniscient directory in workspace
-        let omni_dir = root.join(".omniscient");
-        std::fs::create_dir_all(&omni_dir)
-            .with_context(|| format!("Failed to create .omniscient directory: {}", omni_dir.display()))?;
+    /// Resolve artifact path within workspace
+    pub async fn resolve_artifact_path(&self, kind: impl Into<ArtifactKind>, name: &str) -> OmniResult<PathBuf> {
+        let kind = kind.into();
+
+        // Create artifact root (.omniscient in workspace, or global)
+        let omni_dir = self.artifact_root().await?;
+        create_artifact_dir(&omni_dir, "artifact")?;
 
         // Create kind-specific subdirectory
-        let kind_dir = omni_dir.join(kind);
-        std::fs::create_dir_all(&kind_dir)
-            .with_context(|| format!("Failed to create {} directory: {}", kind, kind_dir.display()))?;
+        let kind_dir = omni_dir.join(kind.as_str());
+        create_artifact_dir(&kind_dir, &kind.to_string())?;
 
         Ok(kind_dir.join(name))
     }
 
     /// List all artifact types in workspace
-    pub async fn list_artifact_types(&self) -> Result<Vec<String>> {
-        let root = self.root.read().await;
-        let root = root.as_ref()
-            .ok_or_else(|| anyhow::anyhow!("No workspace selected"))?;
-
-        let omni_dir = root.join(".omniscient");
+    pub async fn list_artifact_types(&self) -> OmniResult<Vec<String>> {
+        let omni_dir = self.artifact_root().await?;
         if !omni_dir.exists() {
             return Ok(vec![]);
         }
@@ -93,12 +235,22 @@ impl Workspace {
 
     /// Clear workspace selection
     pub async fn clear(&self) {
-        let mut root = self.root.write().await;
-        *root = None;
+        *self.root.write().await = None;
         tracing::info!("Workspace selection cleared");
+        self.set_config(self.base_config.clone()).await;
     }
 }
 
+fn create_artifact_dir(dir: &Path, what: &str) -> OmniResult<()> {
+    std::fs::create_dir_all(dir).map_err(|e| {
+        OmniError::workspace(
+            format!("Failed to create {} directory {}: {}", what, dir.display(), e),
+            Some("Check that the directory is writable, or set workspace.artifact_storage = \"global\"".to_string()),
+            RecoveryAction::Retry,
+        )
+    })
+}
+
 impl Default for Workspace {
     fn default() -> Self {
         Self::new()
@@ -132,10 +284,103 @@ mod tests {
         assert!(path.to_string_lossy().contains(".omniscient/diff/test.diff"));
     }
 
+    #[tokio::test]
+    async fn test_global_artifact_storage() {
+        let global_root = TempDir::new().unwrap();
+        let workspace = Workspace::with_storage(ArtifactStorage::Global(global_root.path().to_path_buf()));
+        let temp_dir = TempDir::new().unwrap();
+        workspace.select(temp_dir.path()).await.unwrap();
+
+        let path = workspace.resolve_artifact_path("diff", "test.diff").await.unwrap();
+        let id = workspace.id().await.unwrap();
+        assert_eq!(path, global_root.path().join(&id).join("diff").join("test.diff"));
+        assert!(!temp_dir.path().join(".omniscient").exists());
+
+        assert_eq!(workspace.list_artifact_types().await.unwrap(), vec!["diff".to_string()]);
+    }
+
+    #[test]
+    fn test_storage_from_config() {
+        let mut config = crate::utils::config::Config::default().workspace;
+        assert_eq!(ArtifactStorage::from_config(&config).unwrap(), ArtifactStorage::WorkspaceLocal);
+
+        config.artifact_storage = "global".to_string();
+        config.artifact_root = Some("/srv/omni".to_string());
+        assert_eq!(
+            ArtifactStorage::from_config(&config).unwrap(),
+            ArtifactStorage::Global(PathBuf::from("/srv/omni"))
+        );
+
+        config.artifact_storage = "elsewhere".to_string();
+        assert!(ArtifactStorage::from_config(&config).is_err());
+    }
+
     #[tokio::test]
     async fn test_no_workspace_selected() {
         let workspace = Workspace::new();
         let result = workspace.resolve_artifact_path("diff", "test.diff").await;
         assert!(result.is_err());
+
+        let err = result.unwrap_err();
+        assert!(matches!(err, OmniError::Workspace { .. }));
+        assert!(err.hint().unwrap().contains("omni:workspace select"));
+        assert_eq!(
+            err.recovery_action(),
+            RecoveryAction::PromptUser("Select a workspace".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_select_errors_carry_hints() {
+        let workspace = Workspace::new();
+        let temp_dir = TempDir::new().unwrap();
+
+        let err = workspace.select(temp_dir.path().join("missing")).await.unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+        assert!(err.hint().is_some());
+        assert!(matches!(err.recovery_action(), RecoveryAction::PromptUser(_)));
+
+        let file = temp_dir.path().join("file.txt");
+        std::fs::write(&file, "x").unwrap();
+        let err = workspace.select(&file).await.unwrap_err();
+        assert!(err.to_string().contains("not a directory"));
+        assert!(err.hint().is_some());
+        assert!(!workspace.is_selected().await);
+
+        let mut config = crate::utils::config::Config::default().workspace;
+        config.artifact_storage = "elsewhere".to_string();
+        let err = ArtifactStorage::from_config(&config).unwrap_err();
+        assert!(matches!(err, OmniError::Config { .. }));
+        assert!(err.hint().unwrap().contains("global"));
+    }
+
+    #[tokio::test]
+    async fn test_workspace_config_overlays_theme() {
+        use std::sync::Mutex;
+
+        let mut workspace = Workspace::new().with_config(Config::default());
+        let themes = Arc::new(Mutex::new(Vec::new()));
+        let seen = themes.clone();
+        workspace.on_config_change(move |config| seen.lock().unwrap().push(config.theme.name.clone()));
+
+        let temp_dir = TempDir::new().unwrap();
+        let local = temp_dir.path().join(WORKSPACE_CONFIG_PATH);
+        std::fs::create_dir_all(local.parent().unwrap()).unwrap();
+        std::fs::write(&local, "[theme]\nname = \"Paper\"\nbackground = \"#ffffff\"\n").unwrap();
+
+        workspace.select(temp_dir.path()).await.unwrap();
+        let config = workspace.config().await;
+        assert_eq!(config.theme.name, "Paper");
+        assert_eq!(config.theme.background, "#ffffff");
+        assert_eq!(config.theme.accent, Config::default().theme.accent);
+
+        workspace.clear().await;
+        assert_eq!(workspace.config().await.theme.name, "NeoCyan");
+        assert_eq!(*themes.lock().unwrap(), vec!["Paper", "NeoCyan"]);
+
+        std::fs::write(&local, "[theme]\nname = 3\n").unwrap();
+        let err = workspace.select(temp_dir.path()).await.unwrap_err();
+        assert!(matches!(err, OmniError::Config { .. }));
+        assert!(!workspace.is_selected().await);
     }
 }
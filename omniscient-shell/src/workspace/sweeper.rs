@@ -0,0 +1,187 @@
+//! Scheduled background retention sweeps
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{interval_at, Instant, MissedTickBehavior};
+
+use crate::state::artifact_index::ArtifactIndex;
+use crate::utils::telemetry::TelemetryCollector;
+use crate::workspace::retention::{PruneStrategy, RetentionPolicy};
+
+/// How often to check whether the workspace exceeds `max_mb`
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Why a sweep was triggered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepReason {
+    /// The configured sweep interval elapsed
+    Scheduled,
+    /// Periodic check whether the workspace exceeds its size limit
+    SizeCheck,
+}
+
+/// Background task running retention sweeps until shut down
+pub struct RetentionSweeper {
+    shutdown: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+impl RetentionSweeper {
+    /// Spawn a sweeper calling `sweep` every `interval`, plus size checks
+    /// every `size_check_interval`
+    pub fn spawn<F, Fut>(interval: Duration, size_check_interval: Duration, mut sweep: F) -> Self
+    where
+        F: FnMut(SweepReason) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let (shutdown, mut shutdown_rx) = watch::channel(false);
+
+        let handle = tokio::spawn(async move {
+            let start = Instant::now();
+            let mut scheduled = interval_at(start + interval, interval);
+            let mut size_check = interval_at(start + size_check_interval, size_check_interval);
+            scheduled.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            size_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = shutdown_rx.changed() => break,
+                    _ = scheduled.tick() => sweep(SweepReason::Scheduled).await,
+                    _ = size_check.tick() => sweep(SweepReason::SizeCheck).await,
+                }
+            }
+
+            tracing::debug!("Retention sweeper stopped");
+        });
+
+        RetentionSweeper { shutdown, handle }
+    }
+
+    /// Spawn a sweeper pruning a workspace's artifacts per its retention policy
+    ///
+    /// Returns `None` when `interval_minutes` is 0 (sweeps disabled).
+    pub fn for_workspace(
+        policy: RetentionPolicy,
+        index: Arc<ArtifactIndex>,
+        workspace: String,
+        interval_minutes: u32,
+        telemetry: Option<Arc<TelemetryCollector>>,
+    ) -> Option<Self> {
+        if interval_minutes == 0 {
+            return None;
+        }
+
+        let interval = Duration::from_secs(interval_minutes as u64 * 60);
+        let size_check_interval = interval.min(SIZE_CHECK_INTERVAL);
+
+        Some(Self::spawn(interval, size_check_interval, move |reason| {
+            let policy = policy.clone();
+            let index = index.clone();
+            let workspace = workspace.clone();
+            let telemetry = telemetry.clone();
+            async move {
+                sweep_workspace(&policy, &index, &workspace, reason, telemetry.as_deref()).await;
+            }
+        }))
+    }
+
+    /// Stop the sweeper and wait for the task to exit
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.handle.await;
+    }
+}
+
+/// Run one sweep, logging and recording telemetry for what was removed
+async fn sweep_workspace(
+    policy: &RetentionPolicy,
+    index: &ArtifactIndex,
+    workspace: &str,
+    reason: SweepReason,
+    telemetry: Option<&TelemetryCollector>,
+) {
+    let strategy = match reason {
+        SweepReason::Scheduled => PruneStrategy::Both,
+        SweepReason::SizeCheck => {
+            let total_bytes: u64 = match index.list(workspace).await {
+                Ok(artifacts) => artifacts.iter().map(|a| a.size_bytes).sum(),
+                Err(e) => {
+                    tracing::warn!("Retention size check failed for {}: {}", workspace, e);
+                    return;
+                }
+            };
+            if total_bytes <= policy.max_mb as u64 * 1024 * 1024 {
+                return;
+            }
+            PruneStrategy::BySize
+        }
+    };
+
+    let result = policy.prune(index, workspace, strategy).await;
+    let success = result.is_ok();
+    let pruned = match result {
+        Ok(pruned) => {
+            if !pruned.is_empty() {
+                tracing::info!("Retention sweep ({:?}) removed {} artifact(s)", reason, pruned.len());
+            }
+            pruned.len()
+        }
+        Err(e) => {
+            tracing::warn!("Retention sweep ({:?}) failed: {}", reason, e);
+            0
+        }
+    };
+
+    if let Some(telemetry) = telemetry {
+        let mut metadata = HashMap::new();
+        metadata.insert("reason".to_string(), format!("{:?}", reason));
+        metadata.insert("pruned".to_string(), pruned.to_string());
+        let _ = telemetry.record_event("retention_sweep", None, metadata, success).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sweeps_at_configured_interval() {
+        let reasons = Arc::new(Mutex::new(Vec::new()));
+        let recorded = reasons.clone();
+
+        let sweeper = RetentionSweeper::spawn(
+            Duration::from_secs(3600),
+            Duration::from_secs(1800),
+            move |reason| {
+                recorded.lock().unwrap().push(reason);
+                async {}
+            },
+        );
+
+        tokio::time::sleep(Duration::from_secs(3599)).await;
+        assert_eq!(*reasons.lock().unwrap(), vec![SweepReason::SizeCheck]);
+
+        tokio::time::sleep(Duration::from_secs(2 * 3600)).await;
+        let scheduled = reasons.lock().unwrap().iter().filter(|r| **r == SweepReason::Scheduled).count();
+        assert_eq!(scheduled, 2);
+
+        sweeper.shutdown().await;
+        let count = reasons.lock().unwrap().len();
+        tokio::time::sleep(Duration::from_secs(3 * 3600)).await;
+        assert_eq!(reasons.lock().unwrap().len(), count);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_when_interval_zero() {
+        let index = Arc::new(ArtifactIndex::new(Arc::new(
+            crate::state::sqlite::SqliteStore::in_memory().unwrap(),
+        )));
+        let sweeper = RetentionSweeper::for_workspace(RetentionPolicy::default(), index, "ws".to_string(), 0, None);
+        assert!(sweeper.is_none());
+    }
+}
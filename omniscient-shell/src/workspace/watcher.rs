@@ -0,0 +1,113 @@
+//! Polls workspace files for changes, debouncing bursts of writes
+//!
+//! Editors and exporters often write a file several times in a row; a change
+//! is only reported once the file has stopped changing for the debounce
+//! period, so consumers regenerate once per burst.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::time::Instant;
+
+/// Quiet period after the last write before a change is reported
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What identifies a version of a file; None while it doesn't exist
+type FileStamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> FileStamp {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+struct WatchedFile {
+    stamp: FileStamp,
+    /// When the last unreported change was seen
+    changed_at: Option<Instant>,
+}
+
+/// Watches a set of files by polling their modification time and size
+pub struct WorkspaceWatcher {
+    debounce: Duration,
+    files: HashMap<PathBuf, WatchedFile>,
+}
+
+impl WorkspaceWatcher {
+    pub fn new(debounce: Duration) -> Self {
+        WorkspaceWatcher {
+            debounce,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Start watching `path`; its current state is the baseline
+    pub fn watch(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        let stamp = stamp(&path);
+        self.files.insert(path, WatchedFile { stamp, changed_at: None });
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    pub fn is_watching(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Files modified (or removed) since they were last reported, once they
+    /// have been quiet for the debounce period
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        let mut changed = Vec::new();
+        for (path, file) in &mut self.files {
+            let current = stamp(path);
+            if current != file.stamp {
+                file.stamp = current;
+                file.changed_at = Some(now);
+            }
+            if file.changed_at.is_some_and(|at| now.duration_since(at) >= self.debounce) {
+                file.changed_at = None;
+                changed.push(path.clone());
+            }
+        }
+        changed.sort();
+        changed
+    }
+}
+
+impl Default for WorkspaceWatcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEBOUNCE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_of_writes_reported_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("render.png");
+        std::fs::write(&path, b"v1").unwrap();
+        let mut watcher = WorkspaceWatcher::new(Duration::from_millis(300));
+        watcher.watch(&path);
+        assert!(watcher.poll().is_empty());
+
+        // Each write within the debounce period restarts it
+        for contents in [&b"v22"[..], b"v333", b"v4444"] {
+            std::fs::write(&path, contents).unwrap();
+            assert!(watcher.poll().is_empty());
+            tokio::time::advance(Duration::from_millis(200)).await;
+        }
+        tokio::time::advance(Duration::from_millis(100)).await;
+        assert_eq!(watcher.poll(), vec![path.clone()]);
+        assert!(watcher.poll().is_empty());
+
+        watcher.unwatch(&path);
+        std::fs::remove_file(&path).unwrap();
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(watcher.poll().is_empty());
+    }
+}